@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 use crate::at_commands::at_commands::AtCommandsContext;
-use crate::call_validation::{ChatContent, ChatMessage};
+use crate::call_validation::{ChatContent, ChatMessage, DiffChunk};
+use crate::diffs::diff_to_commit_context;
 use crate::global_context::{try_load_caps_quickly_if_not_present, GlobalContext};
 use crate::subchat::subchat_single;
 use std::sync::Arc;
@@ -146,6 +147,7 @@ Simplify age check logic for accessing permissions by using a single expression
 - Avoid wording: "Updated", "Modified", or "Changed" without explicitly stating *why*—focus on *intent*."#;
 const N_CTX: usize = 32000;
 const TEMPERATURE: f32 = 0.5;
+const COMMIT_CONTEXT_MAX_TOKENS: usize = 1000;
 
 fn remove_fencing(message: &String) -> String {
     let trimmed_message = message.trim();
@@ -238,6 +240,7 @@ pub async fn generate_commit_message_by_diff(
         None,
         None,
         None,
+        None,
     )
         .await
         .map_err(|e| format!("Error: {}", e))?;
@@ -257,6 +260,38 @@ pub async fn generate_commit_message_by_diff(
     Ok(remove_fencing(&commit_message))
 }
 
+fn chunks_to_diff_text(chunks: &Vec<DiffChunk>) -> String {
+    let mut diff = String::new();
+    for chunk in chunks.iter() {
+        diff.push_str(&format!("--- a/{}\n+++ b/{}\n", chunk.file_name, chunk.file_name_rename.as_ref().unwrap_or(&chunk.file_name)));
+        if chunk.file_action == "edit" {
+            diff.push_str(&format!("@@ -{} +{} @@\n", chunk.line1, chunk.line1));
+        }
+        for l in chunk.lines_remove.lines() {
+            diff.push_str(&format!("-{}\n", l));
+        }
+        for l in chunk.lines_add.lines() {
+            diff.push_str(&format!("+{}\n", l));
+        }
+    }
+    diff
+}
+
+/// For chunks that came out of the patch tool: the model gets a summary of the touched files and symbols
+/// on top of the chunks themselves, so it doesn't need the whole files to say what the change is about
+pub async fn generate_commit_message_by_chunks(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    chunks: &Vec<DiffChunk>,
+    commit_message_prompt: &Option<String>,
+) -> Result<String, String> {
+    if chunks.is_empty() {
+        return Err("The provided chunks are empty".to_string());
+    }
+    let summary = diff_to_commit_context(gcx.clone(), chunks, COMMIT_CONTEXT_MAX_TOKENS).await;
+    let diff = format!("{}\n{}", summary, chunks_to_diff_text(chunks));
+    generate_commit_message_by_diff(gcx, &diff, commit_message_prompt).await
+}
+
 pub async fn _generate_commit_message_for_projects(
     gcx: Arc<ARwLock<GlobalContext>>,
 ) -> Result<HashMap<PathBuf, String>, String> {
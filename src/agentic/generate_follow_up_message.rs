@@ -1,18 +1,29 @@
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{RwLock as ARwLock, Mutex as AMutex};
 use serde_json::Value;
 
 use crate::global_context::GlobalContext;
 use crate::at_commands::at_commands::AtCommandsContext;
-use crate::subchat::subchat_single;
+use crate::subchat::{subchat_single, SUBCHAT_TIMEOUT_ERROR};
 use crate::call_validation::ChatMessage;
 
+pub const FOLLOW_UPS_DEFAULT_MAX: usize = 3;
+const FOLLOW_UP_TIMEOUT: Duration = Duration::from_secs(30);
+const FOLLOW_UP_LONG_MESSAGE_CHARS: usize = 3000;
+const FOLLOW_UP_REPAIR_PROMPT: &str = "Your answer is not a valid JSON array. Output only the array, like [\"Follow up 1\", \"Follow up 2\"], no other text.";
+
 pub async fn generate_follow_up_message(
     mut messages: Vec<ChatMessage>,
     gcx: Arc<ARwLock<GlobalContext>>,
     model_name: &str,
     chat_id: &str,
+    max_follow_ups: usize,
 ) -> Result<Vec<String>, String> {
+    if max_follow_ups == 0 {
+        return Ok(vec![]);
+    }
     let last_assistant_msg_text;
     if let Some(last_assistant_msg) = messages.iter().rev().find(|m| m.role == "assistant").cloned() {
         // messages.clear();
@@ -22,24 +33,15 @@ pub async fn generate_follow_up_message(
         return Err(format!("The last message is not role=assistant"));
     }
 
-    // If the robot message is an open question, return empty list.
+    if surely_no_follow_ups(&last_assistant_msg_text) {
+        tracing::info!("no follow-ups, the assistant message is not a question");
+        return Ok(vec![]);
+    }
 
     messages = vec![
         ChatMessage::new(
             "system".to_string(),
-            concat!(
-                "Super simple job today, generate follow-ups! In the first message you will receive a question or statement generated by a robot.\n",
-                "Generate up to 3 most likely short follow-ups by the user to the robot message, in 3 words or less, like 'Go ahead' 'Looks fantastic!' 'Never mind' etc.\n",
-                "Put first the option that allows robot to continue.\n",
-                "All the follow-ups must mean different things, not 3 ways to say \"yes\".\n",
-                "If there are no simple answers possible, return empty list. If the is no question, return an empty list.\n",
-                "\n",
-                "Output must be this simple json:\n",
-                "\n",
-                "[\"Follow up 1\", \"Follow up 2\"]\n",
-                "\n",
-                "Don't write backquotes, just this format.\n",
-            ).to_string(),
+            follow_ups_system_prompt(max_follow_ups),
         ),
         ChatMessage::new(
             "user".to_string(),
@@ -47,43 +49,207 @@ pub async fn generate_follow_up_message(
         ),
     ];
 
-    let ccx = Arc::new(AMutex::new(AtCommandsContext::new(
-        gcx.clone(),
-        8000,
-        1,
-        false,
-        messages.clone(),
-        chat_id.to_string(),
-        false,
-    ).await));
-    let updated_messages: Vec<Vec<ChatMessage>> = subchat_single(
-        ccx.clone(),
-        model_name,
-        messages.clone(),
-        vec![],
-        None,
-        false,
-        Some(0.5),
-        None,
-        1,
-        None,
-        true,
-        None,
-        None,
-        None,
-    ).await?;
-    let response = updated_messages.into_iter().next().map(|x| x.into_iter().last().map(|last_m| {
-        last_m.content.content_text_only() })).flatten().ok_or("No commit message found".to_string())?;
-
-    tracing::info!("follow-up model says1 {:?}", messages);
-    tracing::info!("follow-up model says2 {:?}", response);
-
-    let parsed_response: Value = serde_json::from_str(&response).map_err(|e| e.to_string())?;
+    let retries = gcx.read().await.cmdline.follow_up_retries;
+    let ask_model = |bad_response: Option<String>| {
+        let gcx = gcx.clone();
+        let mut messages = messages.clone();
+        if let Some(bad_response) = bad_response {
+            messages.push(ChatMessage::new("assistant".to_string(), bad_response));
+            messages.push(ChatMessage::new("user".to_string(), FOLLOW_UP_REPAIR_PROMPT.to_string()));
+        }
+        let model_name = model_name.to_string();
+        let chat_id = chat_id.to_string();
+        async move {
+            let ccx = Arc::new(AMutex::new(AtCommandsContext::new(
+                gcx.clone(),
+                8000,
+                1,
+                false,
+                messages.clone(),
+                chat_id,
+                false,
+            ).await));
+            let updated_messages = subchat_single(
+                ccx.clone(),
+                &model_name,
+                messages.clone(),
+                vec![],
+                None,
+                false,
+                Some(0.5),
+                None,
+                1,
+                None,
+                true,
+                None,
+                None,
+                None,
+                Some(FOLLOW_UP_TIMEOUT),
+            ).await?;
+            let response = updated_messages.into_iter().next().map(|x| x.into_iter().last().map(|last_m| {
+                last_m.content.content_text_only() })).flatten().ok_or("No commit message found".to_string())?;
+            tracing::info!("follow-up model says {:?}", response);
+            Ok::<String, String>(response)
+        }
+    };
+    let mut follow_ups = with_follow_up_retries(retries, ask_model).await;
+    follow_ups.retain(|x| !x.trim().is_empty());
+    follow_ups.truncate(max_follow_ups);
+    Ok(follow_ups)
+}
+
+fn follow_ups_system_prompt(max_follow_ups: usize) -> String {
+    let (how_many, different) = if max_follow_ups == 1 {
+        ("the single most likely short follow-up".to_string(), "".to_string())
+    } else {
+        (
+            format!("up to {} most likely short follow-ups", max_follow_ups),
+            format!("All the follow-ups must mean different things, not {} ways to say \"yes\".\n", max_follow_ups),
+        )
+    };
+    format!(concat!(
+        "Super simple job today, generate follow-ups! In the first message you will receive a question or statement generated by a robot.\n",
+        "Generate {} by the user to the robot message, in 3 words or less, like 'Go ahead' 'Looks fantastic!' 'Never mind' etc.\n",
+        "Put first the option that allows robot to continue.\n",
+        "{}",
+        "If there are no simple answers possible, return empty list. If the is no question, return an empty list.\n",
+        "\n",
+        "Output must be this simple json:\n",
+        "\n",
+        "[\"Follow up 1\", \"Follow up 2\"]\n",
+        "\n",
+        "Don't write backquotes, just this format.\n",
+    ), how_many, different)
+}
+
+// Cheap check before asking the model: a message without a question that ends with code or is very long
+// needs no follow-ups. Anything else, including short statements, still goes to the model.
+fn surely_no_follow_ups(text: &str) -> bool {
+    if text.contains('?') {
+        return false;
+    }
+    text.trim_end().ends_with("```") || text.chars().count() > FOLLOW_UP_LONG_MESSAGE_CHARS
+}
+
+// Models like to wrap the array into ```json fences or say something around it
+fn strip_follow_ups_wrappers(response: &str) -> &str {
+    let response = response.trim();
+    match (response.find('['), response.rfind(']')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => response,
+    }
+}
+
+fn parse_follow_ups(response: &str) -> Result<Vec<String>, String> {
+    let parsed_response: Value = serde_json::from_str(strip_follow_ups_wrappers(response)).map_err(|e| e.to_string())?;
     let follow_ups = parsed_response.as_array()
         .ok_or("Invalid JSON format")?
         .iter()
         .map(|v| v.as_str().unwrap_or("").to_string())
         .collect();
-
     Ok(follow_ups)
 }
+
+// Flaky responses are asked again, giving up means no follow-ups rather than an error.
+// A response that doesn't parse gets one repair round-trip, ask_model receives it to show the model its mistake.
+async fn with_follow_up_retries<F, Fut>(retries: usize, mut ask_model: F) -> Vec<String>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    for attempt_n in 0..=retries {
+        match ask_model(None).await {
+            Ok(response) => match parse_follow_ups(&response) {
+                Ok(follow_ups) if !follow_ups.is_empty() => return follow_ups,
+                Ok(_) => tracing::warn!("follow-ups attempt {}: empty list", attempt_n + 1),
+                Err(e) => {
+                    tracing::warn!("follow-ups attempt {}: cannot parse {:?}: {}, asking to repair", attempt_n + 1, response, e);
+                    let repaired = ask_model(Some(response)).await.and_then(|r| {
+                        parse_follow_ups(&r).map_err(|e| format!("cannot parse the repaired {:?}: {}", r, e))
+                    });
+                    return match repaired {
+                        Ok(follow_ups) => follow_ups,
+                        Err(e) => {
+                            tracing::warn!("no follow-ups: {}", e);
+                            vec![]
+                        }
+                    };
+                }
+            },
+            Err(e) if e.starts_with(SUBCHAT_TIMEOUT_ERROR) => {
+                tracing::warn!("no follow-ups: {}", e);
+                return vec![];
+            }
+            Err(e) => tracing::warn!("follow-ups attempt {}: {}", attempt_n + 1, e),
+        }
+    }
+    vec![]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_follow_ups_retry_after_bad_response() {
+        let calls = AtomicUsize::new(0);
+        let follow_ups = with_follow_up_retries(2, |bad_response| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n == 0 {
+                    Ok("Sure! Here are follow-ups: Go ahead".to_string())
+                } else {
+                    assert_eq!(bad_response, Some("Sure! Here are follow-ups: Go ahead".to_string()));
+                    Ok("[\"Go ahead\", \"Never mind\"]".to_string())
+                }
+            }
+        }).await;
+        assert_eq!(follow_ups, vec!["Go ahead".to_string(), "Never mind".to_string()]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let calls = AtomicUsize::new(0);
+        let follow_ups = with_follow_up_retries(2, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok("[]".to_string()) }
+        }).await;
+        assert!(follow_ups.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicUsize::new(0);
+        let follow_ups = with_follow_up_retries(2, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok("I can't think of any follow-ups for the frog".to_string()) }
+        }).await;
+        assert!(follow_ups.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_no_follow_ups_for_code_dumps() {
+        assert!(surely_no_follow_ups("Here is the frog:\n```python\ndef jump():\n    pass\n```\n"));
+        assert!(surely_no_follow_ups(&"The frog jumps. ".repeat(300)));
+        assert!(!surely_no_follow_ups("Here is the frog:\n```python\ndef jump():\n    pass\n```\nShould I make it jump higher?"));
+        assert!(!surely_no_follow_ups("I have fed the frog."));
+    }
+
+    #[test]
+    fn test_follow_ups_prompt_mentions_max() {
+        let prompt = follow_ups_system_prompt(FOLLOW_UPS_DEFAULT_MAX);
+        assert!(prompt.contains("Generate up to 3 most likely short follow-ups by the user"));
+        assert!(prompt.contains("not 3 ways to say \"yes\""));
+        assert!(follow_ups_system_prompt(5).contains("up to 5 most likely"));
+        let prompt = follow_ups_system_prompt(1);
+        assert!(prompt.contains("Generate the single most likely short follow-up by the user"));
+        assert!(!prompt.contains("different things"));
+    }
+
+    #[test]
+    fn test_follow_ups_wrappers_are_stripped() {
+        let expected = vec!["Go ahead".to_string(), "Feed the frog".to_string()];
+        assert_eq!(parse_follow_ups("```json\n[\"Go ahead\", \"Feed the frog\"]\n```").unwrap(), expected);
+        assert_eq!(parse_follow_ups("Here you go: [\"Go ahead\", \"Feed the frog\"] hope it helps").unwrap(), expected);
+        assert!(parse_follow_ups("Go ahead").is_err());
+    }
+}
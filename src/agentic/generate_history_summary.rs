@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock as ARwLock, Mutex as AMutex};
+
+use crate::global_context::GlobalContext;
+use crate::at_commands::at_commands::AtCommandsContext;
+use crate::subchat::subchat_single;
+use crate::call_validation::ChatMessage;
+
+const HISTORY_SUMMARY_TIMEOUT: Duration = Duration::from_secs(60);
+const HISTORY_SUMMARY_MSG_MAX_CHARS: usize = 2000;
+pub const HISTORY_SUMMARY_MAX_TOKENS: usize = 1024;
+
+const HISTORY_SUMMARY_PROMPT: &str = r#"You will receive the beginning of a conversation between a user and an AI coding assistant, it doesn't fit into the context anymore.
+Summarize it so the assistant can continue the work without it. Keep:
+- decisions that were made and why, including rejected approaches;
+- every file path, symbol name and command that was mentioned, exactly as written;
+- what was done already and what is left to do.
+Skip greetings and the details of tool outputs that don't matter anymore. Write a compact bullet list, no introduction."#;
+
+fn history_transcript(messages: &Vec<ChatMessage>) -> String {
+    let mut transcript = String::new();
+    for msg in messages.iter() {
+        let text = msg.content.content_text_only().chars().take(HISTORY_SUMMARY_MSG_MAX_CHARS).collect::<String>();
+        transcript.push_str(&format!("{}: {}\n", msg.role, text));
+        for call in msg.tool_calls.iter().flatten() {
+            transcript.push_str(&format!("tool call {}({})\n", call.function.name, call.function.arguments));
+        }
+    }
+    transcript
+}
+
+pub async fn generate_history_summary(
+    dropped_messages: &Vec<ChatMessage>,
+    gcx: Arc<ARwLock<GlobalContext>>,
+    model_name: &str,
+    chat_id: &str,
+) -> Result<String, String> {
+    let messages = vec![
+        ChatMessage::new("system".to_string(), HISTORY_SUMMARY_PROMPT.to_string()),
+        ChatMessage::new("user".to_string(), history_transcript(dropped_messages)),
+    ];
+    let ccx = Arc::new(AMutex::new(AtCommandsContext::new(
+        gcx.clone(),
+        8000,
+        1,
+        false,
+        messages.clone(),
+        chat_id.to_string(),
+        false,
+    ).await));
+    let updated_messages = subchat_single(
+        ccx.clone(),
+        model_name,
+        messages,
+        vec![],
+        None,
+        false,
+        Some(0.0),
+        Some(HISTORY_SUMMARY_MAX_TOKENS),
+        1,
+        None,
+        false,
+        None,
+        None,
+        None,
+        Some(HISTORY_SUMMARY_TIMEOUT),
+    ).await?;
+    let summary = updated_messages.into_iter().next()
+        .and_then(|x| x.into_iter().last())
+        .map(|last_m| last_m.content.content_text_only())
+        .unwrap_or_default();
+    if summary.trim().is_empty() {
+        return Err("the model returned an empty history summary".to_string());
+    }
+    tracing::info!("summarized {} dropped messages into {} chars", dropped_messages.len(), summary.len());
+    Ok(summary)
+}
@@ -1,2 +1,3 @@
 pub mod generate_commit_message;
-pub mod generate_follow_up_message;
\ No newline at end of file
+pub mod generate_follow_up_message;
+pub mod generate_history_summary;
\ No newline at end of file
@@ -8,8 +8,11 @@ use serde_cbor;
 use sled::Db;
 use lazy_static::lazy_static;
 use regex::Regex;
+use sha2::{Digest, Sha256};
 
-use crate::ast::ast_structs::{AstDB, AstDefinition, AstCounters, AstErrorStats};
+use crate::ast::ast_structs::{AstDB, AstDefinition, AstCounters, AstErrorStats, AstParseCache, AstQuerySearchResult, CachedParse};
+use crate::ast::treesitter::structs::SymbolType;
+use crate::ast::treesitter::ast_instance_structs::ImportDeclaration;
 use crate::ast::ast_parse_anything::{parse_anything_and_add_file_path, filesystem_path_to_double_colon_path};
 use crate::fuzzy_search::fuzzy_search;
 
@@ -50,6 +53,8 @@ use crate::fuzzy_search::fuzzy_search;
 //   doc-cpath|alt_testsuite::cpp_goat_library 👉 src/ast/alt_testsuite/cpp_goat_library.h
 //             ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ file_global_path (means path up to the global scope of the file)
 //                                                ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ file filesystem path
+//   imports|src/ast/alt_testsuite/cpp_goat_main.cpp 👉 [ImportDeclaration { filepath_ref: Some(".../cpp_goat_library.h"), .. }]
+//           ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ file filesystem path, the imports are resolved to workspace files when indexed
 //
 // Other keys:
 //   counters|defs: 42
@@ -90,7 +95,8 @@ pub async fn ast_index_init(ast_permanent: String, ast_max_files: usize, want_pe
     let db: Arc<Db> = Arc::new(task::spawn_blocking(
         move || config.open().unwrap()
     ).await.unwrap());
-    db.clear().unwrap();
+    db.clear().unwrap();  // the default tree only, parses in the cache tree stay valid after a restart
+    let parse_cache_tree = db.open_tree("parse-cache").map_err(|e| tracing::error!("cannot open the AST parse cache: {}", e)).ok();
     tracing::info!("/starting AST");
     let ast_index = AstDB {
         sleddb: db,
@@ -98,6 +104,7 @@ pub async fn ast_index_init(ast_permanent: String, ast_max_files: usize, want_pe
         batch_counter: 0,
         counters_increase: HashMap::new(),
         ast_max_files,
+        parse_cache: AstParseCache::new(parse_cache_tree),
     };
     Arc::new(AMutex::new(ast_index))
 }
@@ -171,10 +178,35 @@ pub async fn doc_add(
     cpath: &String,
     text: &String,
     errors: &mut AstErrorStats,
-) -> Result<(Vec<Arc<AstDefinition>>, String), String>
+) -> Result<(Vec<Arc<AstDefinition>>, String, Vec<ImportDeclaration>), String>
 {
     let file_global_path = filesystem_path_to_double_colon_path(cpath);
-    let (defs, language) = parse_anything_and_add_file_path(&cpath, text, errors)?;   // errors mostly "no such parser" here
+    let cache_key = parse_cache_key(cpath, text);
+    let cached = ast_index.lock().await.parse_cache.get(&cache_key);
+    let (defs, language, imports) = match cached {
+        Some(cached) => {
+            for (line, message) in cached.errors.iter() {
+                errors.add_error(cpath.clone(), *line, message);
+            }
+            (cached.defs, cached.language, cached.imports)
+        }
+        None => {
+            let mut previous = ast_index.lock().await.parse_cache.take_tree(cpath);
+            let mut file_errors = AstErrorStats::default();
+            let parsed = parse_anything_and_add_file_path(&cpath, text, &mut previous, &mut file_errors);
+            if let Some(previous) = previous {
+                ast_index.lock().await.parse_cache.put_tree(cpath.clone(), previous);
+            }
+            let file_errors = file_errors.errors.into_iter().map(|e| (e.err_line, e.err_message)).collect::<Vec<_>>();
+            for (line, message) in file_errors.iter() {
+                errors.add_error(cpath.clone(), *line, message);
+            }
+            let (defs, language, imports) = parsed?;   // errors mostly "no such parser" here
+            let defs: Vec<Arc<AstDefinition>> = defs.into_iter().map(Arc::new).collect();
+            ast_index.lock().await.parse_cache.insert(cache_key, CachedParse { defs: defs.clone(), language: language.clone(), errors: file_errors, imports: imports.clone() });
+            (defs, language, imports)
+        }
+    };
     let db = ast_index.lock().await.sleddb.clone();
     let batch_arc = flush_sled_batch(ast_index.clone(), 1000).await;
     let mut batch = batch_arc.lock().await;
@@ -183,7 +215,7 @@ pub async fn doc_add(
     let mut unresolved_usages: i32 = 0;
     for definition in defs.iter() {
         assert!(definition.cpath == *cpath);
-        let serialized = serde_cbor::to_vec(&definition).unwrap();
+        let serialized = serde_cbor::to_vec(definition.as_ref()).unwrap();
         let official_path = definition.official_path.join("::");
         let d_key = format!("d|{}", official_path);
         debug_print!("writing {}", d_key);
@@ -227,7 +259,13 @@ pub async fn doc_add(
     _increase_counter(ast_index.clone(), "counters|defs", added_defs).await;
     _increase_counter(ast_index.clone(), "counters|usages", added_usages).await;
 
-    Ok((defs.into_iter().map(Arc::new).collect(), language))
+    Ok((defs, language, imports))
+}
+
+fn parse_cache_key(cpath: &String, text: &String) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{} {:x}", cpath, hasher.finalize())
 }
 
 pub async fn doc_remove(ast_index: Arc<AMutex<AstDB>>, cpath: &String)
@@ -284,6 +322,8 @@ pub async fn doc_remove(ast_index: Arc<AMutex<AstDB>>, cpath: &String)
     }
     let doc_resolved_key = format!("doc-resolved|{}", file_global_path.join("::"));
     batch.remove(doc_resolved_key.as_bytes());
+    let imports_key = format!("imports|{}", cpath);
+    batch.remove(imports_key.as_bytes());
     let doc_key = format!("doc-cpath|{}", file_global_path.join("::"));
     if db.get(doc_key.as_bytes()).unwrap().is_some() {
         _increase_counter(ast_index.clone(), "counters|docs", -1).await;
@@ -293,6 +333,51 @@ pub async fn doc_remove(ast_index: Arc<AMutex<AstDB>>, cpath: &String)
     _increase_counter(ast_index.clone(), "counters|usages", -deleted_usages).await;
 }
 
+pub async fn doc_imports_set(ast_index: Arc<AMutex<AstDB>>, cpath: &String, imports: &Vec<ImportDeclaration>)
+{
+    let batch_arc = flush_sled_batch(ast_index.clone(), 1000).await;
+    let mut batch = batch_arc.lock().await;
+    let imports_key = format!("imports|{}", cpath);
+    batch.insert(imports_key.as_bytes(), serde_cbor::to_vec(imports).unwrap());
+}
+
+pub async fn doc_imports(ast_index: Arc<AMutex<AstDB>>, cpath: &String) -> Vec<ImportDeclaration>
+{
+    let db = ast_index.lock().await.sleddb.clone();
+    let imports_key = format!("imports|{}", cpath);
+    match db.get(imports_key.as_bytes()) {
+        Ok(Some(value)) => serde_cbor::from_slice::<Vec<ImportDeclaration>>(&value).unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// Every indexed file with its imports
+pub async fn all_docs_imports(ast_index: Arc<AMutex<AstDB>>) -> Vec<(String, Vec<ImportDeclaration>)>
+{
+    let db = ast_index.lock().await.sleddb.clone();
+    let mut result = Vec::new();
+    let mut iter = db.scan_prefix("imports|");
+    while let Some(Ok((key, value))) = iter.next() {
+        let cpath = String::from_utf8_lossy(&key["imports|".len()..]).to_string();
+        match serde_cbor::from_slice::<Vec<ImportDeclaration>>(&value) {
+            Ok(imports) => result.push((cpath, imports)),
+            Err(e) => tracing::error!("failed to deserialize imports of {}: {:?}", cpath, e),
+        }
+    }
+    result
+}
+
+pub async fn indexed_docs(ast_index: Arc<AMutex<AstDB>>) -> Vec<String>
+{
+    let db = ast_index.lock().await.sleddb.clone();
+    let mut cpaths = Vec::new();
+    let mut iter = db.scan_prefix("doc-cpath|");
+    while let Some(Ok((_, value))) = iter.next() {
+        cpaths.push(String::from_utf8_lossy(&value).to_string());
+    }
+    cpaths
+}
+
 pub async fn doc_defs(ast_index: Arc<AMutex<AstDB>>, cpath: &String) -> Vec<Arc<AstDefinition>>
 {
     let to_search_prefix = filesystem_path_to_double_colon_path(cpath);
@@ -675,6 +760,60 @@ pub async fn usages(ast_index: Arc<AMutex<AstDB>>, full_official_path: String, l
     usages
 }
 
+const ENTRY_POINT_NAMES: &[&str] = &["main", "__main__", "__init__", "__new__", "__call__", "setUp", "tearDown"];
+
+fn looks_like_entry_point(name: &str) -> bool {
+    ENTRY_POINT_NAMES.contains(&name) ||
+        name.starts_with("test") ||
+        (name.starts_with("__") && name.ends_with("__"))  // python magic methods are called implicitly
+}
+
+/// Declarations of `kinds` (functions and classes if empty) nothing refers to, dead code candidates, only in files
+/// whose path contains `file_filter` if given. Only usages the AST resolved are counted: calls by name through
+/// reflection, dynamic dispatch or from another language are not seen, so treat the result as a list to review,
+/// not to delete blindly.
+/// This is what used to be `AstModule::find_unreferenced_declarations`: the sled index keeps no guids or
+/// SymbolInformation, so like the rest of the queries here it returns AstDefinition, with path, type and lines.
+pub async fn find_unreferenced_declarations(
+    ast_index: Arc<AMutex<AstDB>>,
+    kinds: &Vec<SymbolType>,
+    file_filter: Option<&str>,
+) -> Vec<Arc<AstDefinition>>
+{
+    let kinds = if kinds.is_empty() {
+        vec![SymbolType::FunctionDeclaration, SymbolType::StructDeclaration]
+    } else {
+        kinds.clone()
+    };
+    let db = ast_index.lock().await.sleddb.clone();
+    let mut referenced: HashSet<String> = HashSet::new();
+    let mut iter = db.scan_prefix("u|");
+    while let Some(Ok((key, _))) = iter.next() {
+        let key_string = String::from_utf8(key.to_vec()).unwrap();
+        if let Some((used, _where)) = key_string.strip_prefix("u|").unwrap().split_once(" ⚡ ") {
+            referenced.insert(used.trim().to_string());
+        }
+    }
+    let mut result = vec![];
+    let mut iter = db.scan_prefix("d|");
+    while let Some(Ok((_, value))) = iter.next() {
+        let definition = match serde_cbor::from_slice::<AstDefinition>(&value) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        if !kinds.contains(&definition.symbol_type) ||
+            file_filter.map(|f| !definition.cpath.contains(f)).unwrap_or(false) ||
+            looks_like_entry_point(&definition.name()) ||
+            referenced.contains(&definition.path())
+        {
+            continue;
+        }
+        result.push(Arc::new(definition));
+    }
+    result.sort_by(|a, b| a.cpath.cmp(&b.cpath).then(a.decl_line1.cmp(&b.decl_line1)));
+    result
+}
+
 pub async fn definitions(ast_index: Arc<AMutex<AstDB>>, double_colon_path: &str) -> Vec<Arc<AstDefinition>>
 {
     let db = ast_index.lock().await.sleddb.clone();
@@ -714,6 +853,46 @@ pub async fn definitions(ast_index: Arc<AMutex<AstDB>>, double_colon_path: &str)
     defs
 }
 
+/// Classes derived from the class at double_colon_path, directly or via up to max_depth levels of inheritance,
+/// together with the level they were found at. A class derived from several of them is listed once, at the nearest level.
+pub async fn implementations(ast_index: Arc<AMutex<AstDB>>, double_colon_path: &str, max_depth: usize) -> Vec<(Arc<AstDefinition>, usize)>
+{
+    // Data example:
+    // classes|cpp🔎Animal ⚡ alt_testsuite::cpp_goat_library::Goat 👉 "cpp🔎Goat"
+    let bases = definitions(ast_index.clone(), double_colon_path).await;
+    let db = ast_index.lock().await.sleddb.clone();
+    let mut seen: HashSet<String> = bases.iter().map(|d| d.path()).collect();
+    let mut klasses: Vec<String> = bases.iter().filter(|d| !d.this_is_a_class.is_empty()).map(|d| d.this_is_a_class.clone()).collect();
+    let mut result = Vec::new();
+    for depth in 1..=max_depth {
+        let mut derived_klasses = Vec::new();
+        for klass in klasses.iter() {
+            let t_prefix = format!("classes|{} ⚡ ", klass);
+            let mut iter = db.scan_prefix(&t_prefix);
+            while let Some(Ok((key, value))) = iter.next() {
+                let key_string = String::from_utf8(key.to_vec()).unwrap();
+                let derived_path = key_string.strip_prefix(&t_prefix).unwrap_or_default().trim().to_string();
+                if !seen.insert(derived_path.clone()) {
+                    continue;
+                }
+                let d_key = format!("d|{}", derived_path);
+                if let Ok(Some(d_value)) = db.get(d_key.as_bytes()) {
+                    match serde_cbor::from_slice::<AstDefinition>(&d_value) {
+                        Ok(definition) => result.push((Arc::new(definition), depth)),
+                        Err(e) => tracing::error!("failed to deserialize value for {}: {:?}", d_key, e),
+                    }
+                }
+                derived_klasses.push(String::from_utf8(value.to_vec()).unwrap().trim().to_string());
+            }
+        }
+        if derived_klasses.is_empty() {
+            break;
+        }
+        klasses = derived_klasses;
+    }
+    result
+}
+
 #[allow(dead_code)]
 pub async fn type_hierarchy(ast_index: Arc<AMutex<AstDB>>, language: String, subtree_of: String) -> String
 {
@@ -779,6 +958,49 @@ pub async fn type_hierarchy(ast_index: Arc<AMutex<AstDB>>, language: String, sub
     result
 }
 
+const NAME_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Definitions whose name (the last part of the path) matches the regex, in path order, symbol_types empty means any type.
+/// Patterns that compile into something huge, like `\w{1000}{1000}`, are refused instead of eating the memory.
+pub async fn search_by_name_regex(
+    ast_index: Arc<AMutex<AstDB>>,
+    pattern: &str,
+    symbol_types: &Vec<SymbolType>,
+    top_n: usize,
+) -> Result<AstQuerySearchResult, String>
+{
+    let re = regex::RegexBuilder::new(pattern)
+        .size_limit(NAME_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(NAME_REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("bad regex {:?}: {}", pattern, e))?;
+    let db = ast_index.lock().await.sleddb.clone();
+    let mut result = Vec::new();
+    let mut iter = db.scan_prefix("d|");
+    while let Some(Ok((key, value))) = iter.next() {
+        let key_string = String::from_utf8(key.to_vec()).unwrap();
+        let name = key_string.rsplit("::").next().unwrap_or_default();
+        if !re.is_match(name) {
+            continue;
+        }
+        match serde_cbor::from_slice::<AstDefinition>(&value) {
+            Ok(definition) => {
+                if symbol_types.is_empty() || symbol_types.contains(&definition.symbol_type) {
+                    result.push(Arc::new(definition));
+                }
+            }
+            Err(e) => tracing::error!("failed to deserialize value for {}: {:?}", key_string, e),
+        }
+        if result.len() >= top_n {
+            break;
+        }
+    }
+    Ok(AstQuerySearchResult {
+        query_text: pattern.to_string(),
+        search_results: result,
+    })
+}
+
 pub async fn definition_paths_fuzzy(ast_index: Arc<AMutex<AstDB>>, pattern: &str, top_n: usize, max_candidates_to_consider: usize) -> Vec<String> {
     let db = ast_index.lock().await.sleddb.clone();
     let mut candidates = HashSet::new();
@@ -862,6 +1084,7 @@ pub async fn dump_database(ast_index: Arc<AMutex<AstDB>>) -> usize
 mod tests {
     use super::*;
     use std::fs;
+    use itertools::Itertools;
     use tracing_subscriber;
     use std::io::stderr;
     use tracing_subscriber::fmt::format;
@@ -920,6 +1143,18 @@ mod tests {
             type_hierarchy(ast_index.clone(), language.to_string(), format!("{}🔎Animal", language)).await
         );
 
+        let animal_path = format!("{}_goat_library::Animal", language);
+        let implementations_of = |path: String, max_depth: usize| {
+            let ast_index = ast_index.clone();
+            async move {
+                implementations(ast_index, &path, max_depth).await.iter().map(|(d, depth)| (d.name(), *depth)).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(implementations_of(animal_path.clone(), 5).await, vec![("Goat".to_string(), 1), ("CosmicGoat".to_string(), 2)]);
+        assert_eq!(implementations_of(animal_path.clone(), 1).await, vec![("Goat".to_string(), 1)]);
+        assert_eq!(implementations_of(format!("{}_goat_main::CosmicJustice", language), 5).await, vec![("CosmicGoat".to_string(), 1)]);
+        assert!(implementations_of(format!("{}_goat_main::CosmicGoat", language), 5).await.is_empty());
+
         // Goat::Goat() is a C++ constructor
         let goat_def = definitions(ast_index.clone(), goat_location).await;
         let mut goat_def_str = String::new();
@@ -970,6 +1205,115 @@ mod tests {
         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
     }
 
+    #[tokio::test]
+    async fn test_find_unreferenced_declarations() {
+        init_tracing();
+        let ast_index = ast_index_init("".to_string(), 10, false).await;
+        let tmp = tempfile::Builder::new().prefix("frog_dead_code").tempdir().unwrap();
+        let cpath = tmp.path().join("pond.py").to_string_lossy().to_string();
+        let text = "def jump():\n    return 1\n\ndef croak():\n    return 2\n\ndef main():\n    jump()\n";
+        let mut errstats: AstErrorStats = AstErrorStats::default();
+        doc_add(ast_index.clone(), &cpath, &text.to_string(), &mut errstats).await.unwrap();
+        let mut ucx: ConnectUsageContext = connect_usages_look_if_full_reset_needed(ast_index.clone()).await;
+        while connect_usages(ast_index.clone(), &mut ucx).await {}
+        flush_sled_batch(ast_index.clone(), 0).await;
+
+        let unreferenced = find_unreferenced_declarations(ast_index.clone(), &vec![], None).await;
+
+        let names = unreferenced.iter().map(|d| d.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["croak".to_string()]);
+        let elsewhere = find_unreferenced_declarations(ast_index.clone(), &vec![], Some("no_such_pond")).await;
+        assert!(elsewhere.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_file_is_not_parsed_again() {
+        init_tracing();
+        let ast_index = ast_index_init("".to_string(), 10, false).await;
+        let cpath = "/tmp/frog_pond_cache/pond.py".to_string();
+        let text = "def jump():\n    return 1\n\ndef croak():\n    jump()\n".to_string();
+        let mut errstats: AstErrorStats = AstErrorStats::default();
+
+        let (defs1, _, _) = doc_add(ast_index.clone(), &cpath, &text, &mut errstats).await.unwrap();
+        doc_remove(ast_index.clone(), &cpath).await;
+        let (defs2, language, _) = doc_add(ast_index.clone(), &cpath, &text, &mut errstats).await.unwrap();
+        assert_eq!(ast_index.lock().await.parse_cache.hits, 1);
+        assert_eq!(language, "python");
+        assert!(defs1.iter().zip(defs2.iter()).all(|(a, b)| Arc::ptr_eq(a, b)));
+        flush_sled_batch(ast_index.clone(), 0).await;
+        assert_eq!(doc_defs(ast_index.clone(), &cpath).await.len(), defs1.len());
+
+        doc_remove(ast_index.clone(), &cpath).await;
+        let changed = text.replace("croak", "ribbit");
+        let (defs3, _, _) = doc_add(ast_index.clone(), &cpath, &changed, &mut errstats).await.unwrap();
+        assert_eq!(ast_index.lock().await.parse_cache.hits, 1);
+        assert!(defs3.iter().any(|d| d.name() == "ribbit"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_cache_survives_restart_with_its_errors() {
+        init_tracing();
+        let tmp = tempfile::Builder::new().prefix("frog_parse_cache").tempdir().unwrap();
+        let ast_permanent = tmp.path().join("ast").to_string_lossy().to_string();
+        let cpath = tmp.path().join("pond.js").to_string_lossy().to_string();
+        let text = "import { ribbit } from './toad';\n\nfunction jump() {\n    return 1;\n}\n\nfunction croak() {\n    return jump();\n}\n\njump();\n".to_string();
+        let error_lines = |errstats: &AstErrorStats| errstats.errors.iter().map(|e| (e.err_cpath.clone(), e.err_line, e.err_message.clone())).collect::<Vec<_>>();
+
+        let ast_index = ast_index_init(ast_permanent.clone(), 10, false).await;
+        let mut errstats_parsed = AstErrorStats::default();
+        let (defs_parsed, _, imports_parsed) = doc_add(ast_index.clone(), &cpath, &text, &mut errstats_parsed).await.unwrap();
+        assert_eq!(ast_index.lock().await.parse_cache.hits, 0);
+        flush_sled_batch(ast_index.clone(), 0).await;
+        let db = ast_index.lock().await.sleddb.clone();
+        drop(ast_index);
+        db.flush().unwrap();
+        drop(db);
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+
+        let ast_index = ast_index_init(ast_permanent.clone(), 10, false).await;
+        let mut errstats_cached = AstErrorStats::default();
+        let (defs_cached, language, imports_cached) = doc_add(ast_index.clone(), &cpath, &text, &mut errstats_cached).await.unwrap();
+        assert_eq!(ast_index.lock().await.parse_cache.hits, 1);
+        assert_eq!(language, "javascript");
+        assert_eq!(defs_cached.iter().map(|d| d.path()).collect::<Vec<_>>(), defs_parsed.iter().map(|d| d.path()).collect::<Vec<_>>());
+        assert_eq!(error_lines(&errstats_cached), error_lines(&errstats_parsed));
+        assert!(!imports_parsed.is_empty());
+        assert_eq!(imports_cached, imports_parsed);
+        flush_sled_batch(ast_index.clone(), 0).await;
+        assert_eq!(doc_defs(ast_index.clone(), &cpath).await.len(), defs_parsed.len());
+
+        // a changed file replaces the old parse instead of piling up next to it
+        let changed = text.replace("croak", "ribbit");
+        doc_remove(ast_index.clone(), &cpath).await;
+        doc_add(ast_index.clone(), &cpath, &changed, &mut AstErrorStats::default()).await.unwrap();
+        let cache_tree = ast_index.lock().await.sleddb.open_tree("parse-cache").unwrap();
+        assert_eq!(cache_tree.scan_prefix(format!("{} ", cpath).as_bytes()).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_name_regex() {
+        init_tracing();
+        let ast_index = ast_index_init("".to_string(), 10, false).await;
+        let cpath = "/tmp/frog_regex/pond.py".to_string();
+        let text = "class FrogController:\n    def jump(self):\n        pass\n\nclass ControllerOfToads:\n    pass\n\ndef ToadController():\n    pass\n";
+        let mut errstats: AstErrorStats = AstErrorStats::default();
+        doc_add(ast_index.clone(), &cpath, &text.to_string(), &mut errstats).await.unwrap();
+        flush_sled_batch(ast_index.clone(), 0).await;
+
+        let found = search_by_name_regex(ast_index.clone(), ".*Controller$", &vec![], 10).await.unwrap();
+        assert_eq!(found.query_text, ".*Controller$");
+        let found = found.search_results;
+        let names = found.iter().map(|d| d.name()).sorted().collect::<Vec<_>>();
+        assert_eq!(names, vec!["FrogController", "ToadController"]);
+        let frog = found.iter().find(|d| d.name() == "FrogController").unwrap();
+        assert_eq!((frog.cpath.as_str(), frog.full_line1(), frog.full_line2()), ("/tmp/frog_regex/pond.py", 1, 3));
+
+        let classes = search_by_name_regex(ast_index.clone(), "Controller", &vec![SymbolType::StructDeclaration], 10).await.unwrap().search_results;
+        assert_eq!(classes.iter().map(|d| d.name()).sorted().collect::<Vec<_>>(), vec!["ControllerOfToads", "FrogController"]);
+        assert_eq!(search_by_name_regex(ast_index.clone(), "Controller", &vec![], 1).await.unwrap().search_results.len(), 1);
+        assert!(search_by_name_regex(ast_index.clone(), "\\w{1000}{1000}", &vec![], 10).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_ast_db_cpp() {
         init_tracing();
@@ -9,7 +9,10 @@ use crate::files_in_workspace::Document;
 use crate::global_context::GlobalContext;
 
 use crate::ast::ast_structs::{AstDB, AstStatus, AstCounters, AstErrorStats};
-use crate::ast::ast_db::{ast_index_init, fetch_counters, doc_add, doc_remove, flush_sled_batch, ConnectUsageContext, connect_usages, connect_usages_look_if_full_reset_needed};
+use crate::ast::ast_db::{ast_index_init, fetch_counters, doc_add, doc_remove, doc_imports_set, flush_sled_batch, ConnectUsageContext, connect_usages, connect_usages_look_if_full_reset_needed};
+use crate::ast::import_graph::{resolve_imports, resolve_stored_imports_again, WorkspaceModules};
+use crate::ast::treesitter::language_id::LanguageId;
+use crate::ast::treesitter::parsers::{ast_languages_from_cmdline, is_ast_language_allowed};
 
 
 pub struct AstIndexService {
@@ -27,12 +30,16 @@ async fn ast_indexer_thread(
     let mut reported_connect_stats = true;
     let mut stats_parsed_cnt = 0;
     let mut stats_symbols_cnt = 0;
+    let mut stats_cache_hits0 = 0;
     let mut stats_t0 = std::time::Instant::now();
     let mut stats_update_ts = std::time::Instant::now() - std::time::Duration::from_millis(1000);
     let mut stats_failure_reasons: IndexMap<String, usize> = IndexMap::new();
     let mut stats_success_languages: IndexMap<String, usize> = IndexMap::new();
     let mut stats_parsing_errors = AstErrorStats::default();
     let mut ast_max_files_hit = false;
+    let mut workspace_modules: Option<WorkspaceModules> = None;  // to resolve imports, built once and then follows the files that come through here
+    let mut workspace_modules_changed = false;
+    let mut ast_languages_allowed: Vec<LanguageId> = vec![];
     let (ast_index, ast_status, ast_sleeping_point) = {
         let ast_service_locked = ast_service.lock().await;
         (
@@ -62,9 +69,6 @@ async fn ast_indexer_thread(
         if let Some(cpath) = cpath {
             reported_parse_stats = false;
             reported_connect_stats = false;
-            if stats_parsed_cnt == 0 {
-                stats_t0 = std::time::Instant::now();
-            }
             let gcx = match gcx_weak.upgrade() {
                 Some(x) => x,
                 None => {
@@ -72,23 +76,37 @@ async fn ast_indexer_thread(
                     break;
                 }
             };
+            if stats_parsed_cnt == 0 {
+                stats_t0 = std::time::Instant::now();
+                stats_cache_hits0 = ast_index.lock().await.parse_cache.hits;
+                // main() already refused to start with an unknown language
+                ast_languages_allowed = ast_languages_from_cmdline(&gcx.read().await.cmdline.ast_languages).unwrap_or_default();
+            }
+            if workspace_modules.is_none() {
+                let workspace_files = gcx.read().await.documents_state.workspace_files.lock().unwrap().clone();
+                workspace_modules = Some(WorkspaceModules::new(workspace_files.iter()));
+            }
+            let workspace = workspace_modules.as_mut().unwrap();
             let mut doc = Document { doc_path: cpath.clone().into(), doc_text: None };
 
             doc_remove(ast_index.clone(), &cpath).await;
 
             match crate::files_in_workspace::get_file_text_from_memory_or_disk(gcx.clone(), &doc.doc_path).await {
                 Ok(file_text) => {
+                    workspace_modules_changed |= workspace.add_file(&doc.doc_path);
                     doc.update_text(&file_text);
                     let mut error_message: Option<String> = None;
-                    match doc.does_text_look_good() {
+                    match doc.does_text_look_good().and_then(|_| is_ast_language_allowed(&doc.doc_path, &ast_languages_allowed)) {
                         Ok(_) => {
                             let start_time = std::time::Instant::now();
                             match doc_add(ast_index.clone(), &cpath, &file_text, &mut stats_parsing_errors).await {
-                                Ok((defs, language)) => {
+                                Ok((defs, language, imports)) => {
                                     let elapsed = start_time.elapsed().as_secs_f32();
                                     if elapsed > 0.1 {
                                         tracing::info!("{}/{} doc_add {:.3?}s {}", stats_parsed_cnt, (stats_parsed_cnt+left_todo_count), elapsed, crate::nicer_logs::last_n_chars(&cpath, 40));
                                     }
+                                    let imports = resolve_imports(&doc.doc_path, imports, workspace);
+                                    doc_imports_set(ast_index.clone(), &cpath, &imports).await;
                                     stats_parsed_cnt += 1;
                                     stats_symbols_cnt += defs.len();
                                     *stats_success_languages.entry(language).or_insert(0) += 1;
@@ -107,6 +125,7 @@ async fn ast_indexer_thread(
                     }
                 }
                 Err(_e) => {
+                    workspace_modules_changed |= workspace.remove_file(&doc.doc_path);
                     tracing::info!("deleting from index {} because cannot read it", crate::nicer_logs::last_n_chars(&cpath, 30));
                     *stats_failure_reasons.entry("cannot read file".to_string()).or_insert(0) += 1;
                 }
@@ -137,6 +156,18 @@ async fn ast_indexer_thread(
 
         flush_sled_batch(ast_index.clone(), 0).await;  // otherwise bad stats
 
+        if workspace_modules_changed {
+            // files imported before they existed, or imported and then deleted
+            if let Some(workspace) = workspace_modules.as_ref() {
+                let changed_cnt = resolve_stored_imports_again(ast_index.clone(), workspace).await;
+                if changed_cnt > 0 {
+                    info!("imports of {} files point somewhere else now", changed_cnt);
+                    flush_sled_batch(ast_index.clone(), 0).await;
+                }
+            }
+            workspace_modules_changed = false;
+        }
+
         if !reported_parse_stats {
             if !stats_parsing_errors.errors.is_empty() {
                 let error_count = stats_parsing_errors.errors_counter;
@@ -152,9 +183,11 @@ async fn ast_indexer_thread(
                 stats_parsing_errors = AstErrorStats::default();
             }
             if stats_parsed_cnt + stats_symbols_cnt > 0 {
-                info!("AST finished parsing, got {} symbols by processing {} files in {:>.3}s",
+                let stats_cache_hits = ast_index.lock().await.parse_cache.hits - stats_cache_hits0;
+                info!("AST finished parsing, got {} symbols by processing {} files ({} unchanged, parsing skipped) in {:>.3}s",
                     stats_symbols_cnt,
                     stats_parsed_cnt,
+                    stats_cache_hits,
                     stats_t0.elapsed().as_secs_f64()
                 );
             }
@@ -6,9 +6,9 @@ use std::path::Path;
 use sha2::{Sha256, Digest};
 
 use crate::ast::ast_structs::{AstDefinition, AstUsage, AstErrorStats};
-use crate::ast::treesitter::parsers::get_ast_parser_by_filename;
+use crate::ast::treesitter::parsers::{get_ast_parser_by_filename, text_delta_edit, PreviousParse};
 use crate::ast::treesitter::structs::SymbolType;
-use crate::ast::treesitter::ast_instance_structs::{VariableUsage, VariableDefinition, AstSymbolInstance, FunctionDeclaration, StructDeclaration, FunctionCall, AstSymbolInstanceArc};
+use crate::ast::treesitter::ast_instance_structs::{VariableUsage, VariableDefinition, AstSymbolInstance, FunctionDeclaration, StructDeclaration, FunctionCall, AstSymbolInstanceArc, ImportDeclaration};
 use crate::ast::parse_common::line12mid_from_ranges;
 
 
@@ -332,22 +332,35 @@ fn _usage_or_typeof_caller_colon_colon_usage(
     }
 }
 
+// previous: the tree of the previous version of this file if there is one, replaced with the tree of this version
+// returns definitions, language and the import declarations of the file
 pub fn parse_anything(
     cpath: &str,
     text: &str,
+    previous: &mut Option<PreviousParse>,
     errors: &mut AstErrorStats,
-) -> Result<(Vec<AstDefinition>, String), String>
+) -> Result<(Vec<AstDefinition>, String, Vec<ImportDeclaration>), String>
 {
     let path = PathBuf::from(cpath);
     let (mut parser, language_id) = get_ast_parser_by_filename(&path).map_err(|err| err.message)?;
     let language = language_id.to_string();
+    let old_tree = previous.take().map(|mut prev| {
+        prev.tree.edit(&text_delta_edit(&prev.text, text));
+        prev.tree
+    });
     if language == "python" {
-        let mut cx = crate::ast::parse_python::py_parse(text);
-        return Ok((cx.ap.export_defs(cpath), "python".to_string()));
+        let (mut cx, tree) = crate::ast::parse_python::py_parse_with_old_tree(text, old_tree.as_ref());
+        *previous = Some(PreviousParse { text: text.to_string(), tree });
+        let mut imports = std::mem::take(&mut cx.imports);
+        for import in imports.iter_mut() {
+            import.ast_fields.file_path = path.clone();
+        }
+        return Ok((cx.ap.export_defs(cpath), "python".to_string(), imports));
     }
     let file_global_path = vec!["file".to_string()];
 
-    let symbols = parser.parse(text, &path);
+    let (symbols, tree) = parser.parse_with_old_tree(text, &path, old_tree.as_ref());
+    *previous = Some(PreviousParse { text: text.to_string(), tree });
     if symbols.len() > TOO_MANY_SYMBOLS_IN_FILE {
         return Err(format!("more than {} symbols, generated?", TOO_MANY_SYMBOLS_IN_FILE));
     }
@@ -360,6 +373,7 @@ pub fn parse_anything(
         file_global_path,
         language,
     };
+    let mut imports: Vec<ImportDeclaration> = vec![];
 
     for symbol in symbols {
         let symbol_arc_clone = symbol.clone();
@@ -425,9 +439,13 @@ pub fn parse_anything(
                     errors.add_error("".to_string(), symbol.full_range().start_point.row + 1, "nameless decl");
                 }
             }
+            SymbolType::ImportDeclaration => {
+                if let Some(import) = symbol.as_any().downcast_ref::<ImportDeclaration>() {
+                    imports.push(import.clone());
+                }
+            }
             SymbolType::Module |
             SymbolType::CommentDefinition |
-            SymbolType::ImportDeclaration |
             SymbolType::FunctionCall |
             SymbolType::VariableUsage => {
                 // do nothing
@@ -490,7 +508,7 @@ pub fn parse_anything(
     let mut sorted_definitions: Vec<(Uuid, AstDefinition)> = pcx.definitions.into_iter().collect();
     sorted_definitions.sort_by(|a, b| a.1.official_path.cmp(&b.1.official_path));
     let definitions: IndexMap<Uuid, AstDefinition> = IndexMap::from_iter(sorted_definitions);
-    Ok((definitions.into_values().collect(), pcx.language))
+    Ok((definitions.into_values().collect(), pcx.language, imports))
 }
 
 pub fn filesystem_path_to_double_colon_path(cpath: &str) -> Vec<String> {
@@ -531,13 +549,14 @@ pub fn filesystem_path_to_double_colon_path(cpath: &str) -> Vec<String> {
 pub fn parse_anything_and_add_file_path(
     cpath: &str,
     text: &str,
+    previous: &mut Option<PreviousParse>,
     errstats: &mut AstErrorStats,
-) -> Result<(Vec<AstDefinition>, String), String>
+) -> Result<(Vec<AstDefinition>, String, Vec<ImportDeclaration>), String>
 {
     let file_global_path = filesystem_path_to_double_colon_path(cpath);
     let file_global_path_str = file_global_path.join("::");
     let errors_count_before = errstats.errors.len();
-    let (mut definitions, language) = parse_anything(cpath, text, errstats)?;
+    let (mut definitions, language, imports) = parse_anything(cpath, text, previous, errstats)?;
     for error in errstats.errors.iter_mut().skip(errors_count_before) {
         error.err_cpath = cpath.to_string();
     }
@@ -567,7 +586,7 @@ pub fn parse_anything_and_add_file_path(
             }
         }
     }
-    Ok((definitions, language))
+    Ok((definitions, language, imports))
 }
 
 
@@ -617,7 +636,7 @@ mod tests {
         let mut errstats = AstErrorStats::default();
         let absfn1 = std::fs::canonicalize(input_file).unwrap();
         let text = _read_file(absfn1.to_str().unwrap());
-        let (definitions, _language) = parse_anything(absfn1.to_str().unwrap(), &text, &mut errstats).unwrap();
+        let (definitions, _language, _imports) = parse_anything(absfn1.to_str().unwrap(), &text, &mut None, &mut errstats).unwrap();
         let mut defs_str = String::new();
         for d in definitions.iter() {
             defs_str.push_str(&format!("{:?}\n", d));
@@ -633,6 +652,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_python_reparse_keeps_the_tree() {
+        let mut errstats = AstErrorStats::default();
+        let text = "def jump(frog):\n    return frog.legs * 2\n\ndef croak():\n    print('croak')\n";
+        let edited = text.replace("def croak", "def ribbit");
+        let mut previous = None;
+        parse_anything("/pond/frog.py", text, &mut previous, &mut errstats).unwrap();
+        assert_eq!(previous.as_ref().map(|p| p.text.as_str()), Some(text));
+
+        let (incremental, _, _) = parse_anything("/pond/frog.py", &edited, &mut previous, &mut errstats).unwrap();
+        let (full, _, _) = parse_anything("/pond/frog.py", &edited, &mut None, &mut errstats).unwrap();
+        assert_eq!(previous.as_ref().map(|p| p.text.as_str()), Some(edited.as_str()));
+        assert_eq!(format!("{:?}", incremental), format!("{:?}", full));
+        assert!(incremental.iter().any(|d| d.name() == "ribbit"));
+    }
+
     #[test]
     fn test_ast_parse_cpp_library() {
         _run_parse_test(
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::fmt;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex as AMutex, Notify as ANotify};
 pub use crate::ast::treesitter::structs::SymbolType;
+use crate::ast::treesitter::parsers::PreviousParse;
+use crate::ast::treesitter::ast_instance_structs::ImportDeclaration;
 
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -57,12 +60,19 @@ impl AstDefinition {
     }
 }
 
+#[derive(Serialize)]
+pub struct AstQuerySearchResult {
+    pub query_text: String,
+    pub search_results: Vec<Arc<AstDefinition>>,  // each has cpath and line ranges, so editors can jump there
+}
+
 pub struct AstDB {
     pub sleddb: Arc<sled::Db>,
     pub sledbatch: Arc<AMutex<sled::Batch>>,
     pub batch_counter: usize,
     pub counters_increase: HashMap<String, i32>,
     pub ast_max_files: usize,
+    pub parse_cache: AstParseCache,
 }
 
 #[derive(Serialize, Clone)]
@@ -86,6 +96,112 @@ pub struct AstCounters {
 }
 
 
+const PARSE_CACHE_MAX_FILES: usize = 1000;
+
+// Everything a parse of one file gives, errors included, so a cache hit reports the same errors as parsing would
+#[derive(Clone)]
+pub struct CachedParse {
+    pub defs: Vec<Arc<AstDefinition>>,
+    pub language: String,
+    pub errors: Vec<(usize, String)>,  // line, message
+    pub imports: Vec<ImportDeclaration>,
+}
+
+// Parse results keyed by file path + content hash: an unchanged file (saved without changes, enqueued again
+// by a workspace rescan, or indexed again after a restart) is written to the index again without parsing it.
+// The most recently used ones are kept in memory, all of them in a sled tree of the AST db that survives the
+// restart, the latest parse for each file.
+// Trees are kept for the documents open in the IDE, which are the documents being typed in, so the
+// next keystroke reparses only the changed part.
+#[derive(Default)]
+pub struct AstParseCache {
+    entries: IndexMap<String, CachedParse>,  // the last one is the most recently used
+    persistent: Option<sled::Tree>,
+    trees: HashMap<String, PreviousParse>,
+    open_documents: HashSet<String>,
+    pub hits: usize,
+}
+
+impl AstParseCache {
+    pub fn new(persistent: Option<sled::Tree>) -> Self {
+        AstParseCache { persistent, ..Default::default() }
+    }
+
+    pub fn get(&mut self, key: &String) -> Option<CachedParse> {
+        let value = match self.entries.shift_remove(key) {
+            Some(value) => value,
+            None => self.persistent_get(key)?,
+        };
+        self.entries.insert(key.clone(), value.clone());
+        self.hits += 1;
+        Some(value)
+    }
+
+    pub fn insert(&mut self, key: String, parsed: CachedParse) {
+        self.persistent_insert(&key, &parsed);
+        self.entries.shift_remove(&key);
+        self.entries.insert(key, parsed);
+        while self.entries.len() > PARSE_CACHE_MAX_FILES {
+            self.entries.shift_remove_index(0);
+        }
+    }
+
+    fn persistent_get(&self, key: &String) -> Option<CachedParse> {
+        let value = self.persistent.as_ref()?.get(key.as_bytes()).ok()??;
+        match serde_cbor::from_slice::<(Vec<AstDefinition>, String, Vec<(usize, String)>, Vec<ImportDeclaration>)>(&value) {
+            Ok((defs, language, errors, imports)) => Some(CachedParse { defs: defs.into_iter().map(Arc::new).collect(), language, errors, imports }),
+            Err(e) => {
+                tracing::error!("parse cache entry {} is broken: {}", key, e);
+                None
+            }
+        }
+    }
+
+    // keys are "cpath sha256", the parses of the older versions of the file go away
+    fn persistent_insert(&self, key: &String, parsed: &CachedParse) {
+        let tree = match &self.persistent {
+            Some(tree) => tree,
+            None => return,
+        };
+        if let Some((cpath, _)) = key.rsplit_once(' ') {
+            let stale = tree.scan_prefix(format!("{} ", cpath).as_bytes()).keys()
+                .filter_map(|k| k.ok())
+                .filter(|k| String::from_utf8_lossy(k).rsplit_once(' ').map_or(false, |(p, _)| p == cpath))
+                .collect::<Vec<_>>();
+            for k in stale {
+                let _ = tree.remove(k);
+            }
+        }
+        let defs = parsed.defs.iter().map(|d| d.as_ref()).collect::<Vec<_>>();
+        match serde_cbor::to_vec(&(defs, &parsed.language, &parsed.errors, &parsed.imports)) {
+            Ok(value) => if let Err(e) = tree.insert(key.as_bytes(), value) {
+                tracing::error!("cannot save parse cache entry {}: {}", key, e);
+            },
+            Err(e) => tracing::error!("cannot serialize parse cache entry {}: {}", key, e),
+        }
+    }
+
+    pub fn document_opened(&mut self, cpath: String) {
+        self.open_documents.insert(cpath);
+    }
+
+    pub fn document_closed(&mut self, cpath: &String) {
+        self.open_documents.remove(cpath);
+        self.trees.remove(cpath);
+    }
+
+    pub fn take_tree(&mut self, cpath: &String) -> Option<PreviousParse> {
+        self.trees.remove(cpath)
+    }
+
+    pub fn put_tree(&mut self, cpath: String, previous: PreviousParse) {
+        if self.open_documents.contains(&cpath) {
+            self.trees.insert(cpath, previous);
+        }
+    }
+}
+
+
 const TOO_MANY_ERRORS: usize = 1000;
 
 pub struct AstError {
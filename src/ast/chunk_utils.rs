@@ -149,13 +149,62 @@ pub fn get_chunks(text: &String,
     chunks.into_iter().filter(|c|!c.window_text.is_empty()).collect()
 }
 
+fn split_result_from_lines(chunk: &SplitResult, lines: &[&str], start_line: u64) -> SplitResult {
+    let text = lines.join("\n");
+    SplitResult {
+        file_path: chunk.file_path.clone(),
+        window_text_hash: official_text_hashing_function(&text),
+        window_text: text,
+        start_line,
+        end_line: start_line + lines.len().saturating_sub(1) as u64,
+        symbol_path: chunk.symbol_path.clone(),
+    }
+}
+
+// get_chunks() counts tokens per line, the joined text has extra tokens for newlines and might not fit,
+// token-dense symbols are force-split here counting the whole window with the tokenizer
+pub fn enforce_tokens_limit(
+    chunks: Vec<SplitResult>,
+    tokenizer: Option<Arc<StdRwLock<Tokenizer>>>,
+    tokens_limit: usize,
+) -> Vec<SplitResult> {
+    let mut result = vec![];
+    for chunk in chunks {
+        if tokens_limit == 0 || count_tokens(tokenizer.clone(), &chunk.window_text) <= tokens_limit {
+            result.push(chunk);
+            continue;
+        }
+        let lines = chunk.window_text.split("\n").collect::<Vec<_>>();
+        let mut window_start = 0;
+        while window_start < lines.len() {
+            let mut window_end = window_start + 1;
+            while window_end < lines.len()
+                && count_tokens(tokenizer.clone(), &lines[window_start..window_end + 1].join("\n")) <= tokens_limit {
+                window_end += 1;
+            }
+            let start_line = chunk.start_line + window_start as u64;
+            let window = split_result_from_lines(&chunk, &lines[window_start..window_end], start_line);
+            if count_tokens(tokenizer.clone(), &window.window_text) > tokens_limit {
+                // a single line that doesn't fit
+                for piece in split_line_if_needed(&window.window_text, tokenizer.clone(), tokens_limit) {
+                    result.push(split_result_from_lines(&chunk, &[piece.as_str()], start_line));
+                }
+            } else {
+                result.push(window);
+            }
+            window_start = window_end;
+        }
+    }
+    result.into_iter().filter(|c| !c.window_text.is_empty()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
     use std::str::FromStr;
     use std::sync::{Arc, RwLock as StdRwLock};
 
-    use crate::ast::chunk_utils::get_chunks;
+    use crate::ast::chunk_utils::{enforce_tokens_limit, get_chunks};
     use crate::ast::count_tokens;
     // use crate::vecdb::vdb_structs::SplitResult;
 
@@ -213,4 +262,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn token_dense_symbol_is_split_to_fit_context() {
+        let tokenizer = Arc::new(StdRwLock::new(tokenizers::Tokenizer::from_str(DUMMY_TOKENIZER).unwrap()));
+        let token_limit = 64;
+        let dense_symbol = (0..40).map(|i| format!("    x{} = [{}]*{}", i, i, i)).collect::<Vec<_>>().join("\n");
+        let dense_symbol = format!("def dense():\n{}", dense_symbol);
+        let chunks = get_chunks(
+            &dense_symbol,
+            &PathBuf::from_str("/tmp/test.py").unwrap(),
+            &"dense".to_string(),
+            (10, 51),
+            Some(tokenizer.clone()),
+            token_limit, 0, false);
+
+        let chunks = enforce_tokens_limit(chunks, Some(tokenizer.clone()), token_limit);
+
+        assert!(chunks.len() > 1);
+        for chunk in chunks.iter() {
+            let tok_n = count_tokens(Some(tokenizer.clone()), &chunk.window_text);
+            assert!(tok_n <= token_limit, "chunk {}-{} has {} tokens > {}", chunk.start_line, chunk.end_line, tok_n, token_limit);
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use tower_lsp::lsp_types;
+use uuid::Uuid;
+
+use crate::ast::treesitter::ast_instance_structs::SymbolInformation;
+use crate::ast::treesitter::parsers::get_ast_parser_by_filename;
+use crate::ast::treesitter::structs::SymbolType;
+
+
+// Outline of one file, the same shape as LSP textDocument/documentSymbol wants: declarations nested
+// into the declarations they are in, usages and imports are not part of it.
+
+#[derive(Debug, Clone)]
+pub struct DocumentSymbol {
+    pub info: SymbolInformation,
+    pub children: Vec<DocumentSymbol>,
+}
+
+fn is_outline_symbol(symbol_type: &SymbolType) -> bool {
+    matches!(symbol_type,
+        SymbolType::StructDeclaration |
+        SymbolType::TypeAlias |
+        SymbolType::ClassFieldDeclaration |
+        SymbolType::VariableDefinition |
+        SymbolType::FunctionDeclaration
+    )
+}
+
+fn build_outline(symbols: Vec<SymbolInformation>) -> Vec<DocumentSymbol> {
+    let by_guid: HashMap<Uuid, SymbolInformation> = symbols.into_iter().map(|s| (s.guid.clone(), s)).collect();
+    // the nearest parent that is in the outline itself, usages and calls in between are skipped
+    let outline_parent = |s: &SymbolInformation| -> Option<Uuid> {
+        let mut parent_guid = s.parent_guid.clone();
+        while let Some(parent) = by_guid.get(&parent_guid) {
+            if is_outline_symbol(&parent.symbol_type) {
+                return Some(parent.guid.clone());
+            }
+            parent_guid = parent.parent_guid.clone();
+        }
+        None
+    };
+    let mut children_of: HashMap<Option<Uuid>, Vec<SymbolInformation>> = HashMap::new();
+    for s in by_guid.values() {
+        if !is_outline_symbol(&s.symbol_type) || s.name.is_empty() {
+            continue;
+        }
+        let parent_guid = outline_parent(s);
+        // local variables are not interesting in an outline
+        let parent_is_function = parent_guid.as_ref().and_then(|g| by_guid.get(g)).map(|p| p.symbol_type == SymbolType::FunctionDeclaration).unwrap_or(false);
+        if s.symbol_type == SymbolType::VariableDefinition && parent_is_function {
+            continue;
+        }
+        children_of.entry(parent_guid).or_default().push(s.clone());
+    }
+
+    fn nest(parent_guid: Option<Uuid>, children_of: &mut HashMap<Option<Uuid>, Vec<SymbolInformation>>) -> Vec<DocumentSymbol> {
+        let children = children_of.remove(&parent_guid).unwrap_or_default();
+        children.into_iter()
+            .sorted_by_key(|s| s.full_range.start_byte)
+            .map(|info| {
+                let children = nest(Some(info.guid.clone()), children_of);
+                DocumentSymbol { info, children }
+            })
+            .collect()
+    }
+    nest(None, &mut children_of)
+}
+
+pub fn document_symbols(file_path: &PathBuf, text: &str) -> Result<Vec<DocumentSymbol>, String> {
+    let (mut parser, _language_id) = get_ast_parser_by_filename(file_path).map_err(|e| e.message)?;
+    let symbols = parser.parse(text, file_path).iter().map(|s| s.read().symbol_info_struct()).collect::<Vec<_>>();
+    Ok(build_outline(symbols))
+}
+
+fn lsp_symbol_kind(kind: u8) -> lsp_types::SymbolKind {
+    serde_json::from_value(serde_json::json!(kind)).unwrap_or(lsp_types::SymbolKind::NULL)
+}
+
+pub fn to_lsp_document_symbol(symbol: DocumentSymbol, parent: Option<&SymbolInformation>) -> lsp_types::DocumentSymbol {
+    // columns are in bytes, the same as for ASCII in UTF-16
+    let range = |r: &tree_sitter::Range| lsp_types::Range::new(
+        lsp_types::Position::new(r.start_point.row as u32, r.start_point.column as u32),
+        lsp_types::Position::new(r.end_point.row as u32, r.end_point.column as u32),
+    );
+    // editors reject a selection range outside of the symbol range
+    let within = |inner: &tree_sitter::Range, outer: &tree_sitter::Range| inner.start_byte >= outer.start_byte && inner.end_byte <= outer.end_byte;
+    let DocumentSymbol { info, children } = symbol;
+    #[allow(deprecated)]
+    lsp_types::DocumentSymbol {
+        name: info.name.clone(),
+        detail: None,
+        kind: lsp_symbol_kind(info.to_lsp_symbol_kind_with_parent(parent)),
+        tags: None,
+        deprecated: None,
+        range: range(&info.full_range),
+        selection_range: range(if within(&info.declaration_range, &info.full_range) { &info.declaration_range } else { &info.full_range }),
+        children: Some(children.into_iter().map(|child| to_lsp_document_symbol(child, Some(&info))).collect()),
+    }
+}
+
+pub fn lsp_document_symbols(file_path: &PathBuf, text: &str) -> Result<Vec<lsp_types::DocumentSymbol>, String> {
+    Ok(document_symbols(file_path, text)?.into_iter().map(|symbol| to_lsp_document_symbol(symbol, None)).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline_names(outline: &Vec<DocumentSymbol>, indent: usize, out: &mut Vec<String>) {
+        for s in outline {
+            out.push(format!("{:indent$}{} {}", "", s.info.symbol_type, s.info.name, indent = indent));
+            outline_names(&s.children, indent + 2, out);
+        }
+    }
+
+    #[test]
+    fn test_document_symbols_nested_and_ordered() {
+        let text = "class Frog:\n    def __init__(self, x):\n        self.x = x\n        tmp = 1\n\n    def jump(self):\n        croak()\n\ndef croak():\n    pass\n\nPOND_SIZE = 10\n";
+        let outline = document_symbols(&PathBuf::from("/tmp/frog_outline/frog.py"), text).unwrap();
+        let mut names = vec![];
+        outline_names(&outline, 0, &mut names);
+        assert_eq!(names[0], "StructDeclaration Frog");
+        assert!(names.contains(&"  FunctionDeclaration jump".to_string()));
+        assert!(!names.iter().any(|n| n.ends_with(" tmp")), "{:?}", names);
+        let top_level = outline.iter().map(|s| s.info.name.clone()).collect::<Vec<_>>();
+        assert_eq!(top_level, vec!["Frog", "croak", "POND_SIZE"]);
+
+        let frog = &outline[0];
+        assert!(frog.info.declaration_range.start_byte >= frog.info.full_range.start_byte);
+        assert!(frog.info.declaration_range.end_byte <= frog.info.full_range.end_byte);
+        let jump = frog.children.iter().find(|s| s.info.name == "jump").unwrap();
+        assert_eq!(jump.info.full_range.start_point.row, 5);
+    }
+
+    #[test]
+    fn test_lsp_document_symbols_match_the_parse() {
+        let text = "class Frog:\n    def __init__(self, x):\n        self.x = x\n\n    def jump(self):\n        croak()\n\ndef croak():\n    pass\n";
+        let path = PathBuf::from("/tmp/frog_outline/frog.py");
+        let outline = document_symbols(&path, text).unwrap();
+        let lsp_outline = lsp_document_symbols(&path, text).unwrap();
+
+        assert_eq!(lsp_outline.iter().map(|s| (s.name.as_str(), s.kind)).collect::<Vec<_>>(), vec![("Frog", lsp_types::SymbolKind::CLASS), ("croak", lsp_types::SymbolKind::FUNCTION)]);
+        let frog = &lsp_outline[0];
+        assert_eq!((frog.name.as_str(), frog.kind), ("Frog", lsp_types::SymbolKind::CLASS));
+        assert_eq!(frog.range.start, lsp_types::Position::new(0, 0));
+        assert_eq!(frog.range.end.line as usize, outline[0].info.full_range.end_point.row);
+        assert!(frog.selection_range.start >= frog.range.start && frog.selection_range.end <= frog.range.end);
+
+        let methods = frog.children.as_ref().unwrap();
+        let method_names = methods.iter().filter(|m| m.kind == lsp_types::SymbolKind::METHOD).map(|m| m.name.clone()).collect::<Vec<_>>();
+        assert_eq!(method_names, vec!["__init__", "jump"]);
+        let jump = methods.iter().find(|m| m.name == "jump").unwrap();
+        assert_eq!((jump.range.start.line, jump.range.end.line), (4, 5));
+        assert!(jump.children.as_ref().unwrap().is_empty());
+    }
+}
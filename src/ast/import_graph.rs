@@ -0,0 +1,444 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex as AMutex, RwLock as ARwLock};
+
+use crate::ast::ast_db::{all_docs_imports, doc_imports_set};
+use crate::ast::ast_structs::AstDB;
+use crate::ast::treesitter::ast_instance_structs::{ImportDeclaration, ImportType};
+use crate::ast::treesitter::language_id::LanguageId;
+use crate::global_context::GlobalContext;
+
+
+// Relative imports are resolved the way the language does it: python "from .toad import croak" is ./toad.py or
+// ./toad/__init__.py, js/ts "./util" is ./util.ts ./util.js ./util/index.ts and so on.
+// Everything else is best-effort: the path components of an import are matched against the tail of workspace
+// file paths, "a::b::C" or "a.b.C" may end up in a/b.rs a/b/mod.rs a/b.py a/b/__init__.py
+
+const PACKAGE_FILE_STEMS: [&str; 3] = ["mod", "__init__", "index"];
+const JS_EXTENSIONS: [&str; 7] = ["ts", "tsx", "d.ts", "js", "jsx", "mjs", "cjs"];
+
+pub struct WorkspaceModules {
+    files: HashSet<PathBuf>,
+    modules: HashMap<PathBuf, Vec<PathBuf>>,
+    modules_by_name: HashMap<String, Vec<PathBuf>>,  // "toad" -> /pond/toad /swamp/toad, to match imports without a scan
+}
+
+impl WorkspaceModules {
+    pub fn new<'a>(files: impl Iterator<Item = &'a PathBuf>) -> Self {
+        let mut workspace = WorkspaceModules { files: HashSet::new(), modules: HashMap::new(), modules_by_name: HashMap::new() };
+        for path in files {
+            workspace.add_file(path);
+        }
+        workspace
+    }
+
+    /// False if the file was already there
+    pub fn add_file(&mut self, path: &PathBuf) -> bool {
+        if !self.files.insert(path.clone()) {
+            return false;
+        }
+        let module_path = module_path_of_file(path);
+        let files = self.modules.entry(module_path.clone()).or_default();
+        if files.is_empty() {
+            self.modules_by_name.entry(module_name(&module_path)).or_default().push(module_path);
+        }
+        files.push(path.clone());
+        true
+    }
+
+    /// False if there was no such file
+    pub fn remove_file(&mut self, path: &PathBuf) -> bool {
+        if !self.files.remove(path) {
+            return false;
+        }
+        let module_path = module_path_of_file(path);
+        let module_is_gone = match self.modules.get_mut(&module_path) {
+            Some(files) => {
+                files.retain(|f| f != path);
+                files.is_empty()
+            }
+            None => false,
+        };
+        if module_is_gone {
+            self.modules.remove(&module_path);
+            let name = module_name(&module_path);
+            if let Some(same_name) = self.modules_by_name.get_mut(&name) {
+                same_name.retain(|m| *m != module_path);
+                if same_name.is_empty() {
+                    self.modules_by_name.remove(&name);
+                }
+            }
+        }
+        true
+    }
+}
+
+fn module_name(module_path: &Path) -> String {
+    module_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn module_path_of_file(path: &Path) -> PathBuf {
+    let without_ext = path.with_extension("");
+    match without_ext.file_name().and_then(|n| n.to_str()) {
+        Some(stem) if PACKAGE_FILE_STEMS.contains(&stem) => without_ext.parent().map(|p| p.to_path_buf()).unwrap_or(without_ext),
+        _ => without_ext,
+    }
+}
+
+/// Leading "." and ".." components turned into a directory relative to the importer, None for non-relative imports
+fn relative_import_anchor<'a>(importer: &PathBuf, components: &'a Vec<String>) -> Option<(PathBuf, &'a [String])> {
+    let mut anchor = importer.parent()?.to_path_buf();
+    let mut skip = 0;
+    for c in components.iter() {
+        match c.as_str() {
+            "." => {}
+            ".." => anchor = anchor.parent()?.to_path_buf(),
+            _ => break,
+        }
+        skip += 1;
+    }
+    if skip == 0 {
+        return None;
+    }
+    Some((anchor, &components[skip..]))
+}
+
+fn resolve_python_relative_import(importer: &PathBuf, components: &Vec<String>, workspace: &WorkspaceModules) -> Option<PathBuf> {
+    let (anchor, rest) = relative_import_anchor(importer, components)?;
+    // "from .toad import croak" is [".", "toad", "croak"], croak might be a submodule or a name inside toad.py
+    for n in (0..=rest.len()).rev() {
+        let module_dir = rest[..n].iter().fold(anchor.clone(), |p, c| p.join(c));
+        let mut candidates = vec![module_dir.join("__init__.py")];
+        if n > 0 {
+            candidates.insert(0, module_dir.with_extension("py"));
+        }
+        if let Some(found) = candidates.into_iter().find(|c| workspace.files.contains(c)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn resolve_js_relative_import(importer: &PathBuf, components: &Vec<String>, workspace: &WorkspaceModules) -> Option<PathBuf> {
+    let (anchor, rest) = relative_import_anchor(importer, components)?;
+    // "import { croak } from './util'" is [".", "util", "croak"], the imported names follow the module path
+    for n in (1..=rest.len()).rev() {
+        let module_path = rest[..n].iter().fold(anchor.clone(), |p, c| p.join(c));
+        if workspace.files.contains(&module_path) {
+            return Some(module_path);
+        }
+        let mut candidates = vec![];
+        for ext in JS_EXTENSIONS.iter() {
+            candidates.push(PathBuf::from(format!("{}.{}", module_path.to_string_lossy(), ext)));
+            // "./util.js" written in typescript sources points to util.ts
+            if module_path.extension().is_some() {
+                candidates.push(module_path.with_extension(ext));
+            }
+        }
+        for ext in JS_EXTENSIONS.iter() {
+            candidates.push(module_path.join(format!("index.{}", ext)));
+        }
+        if let Some(found) = candidates.into_iter().find(|c| workspace.files.contains(c)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// The workspace file an import points to, using the rules of the language the import is written in
+pub fn resolve_import(
+    import: &ImportDeclaration,
+    from_file: &PathBuf,
+    workspace: &WorkspaceModules,
+) -> Option<PathBuf> {
+    let language_specific = match import.ast_fields.language {
+        LanguageId::Python => resolve_python_relative_import(from_file, &import.path_components, workspace),
+        LanguageId::JavaScript | LanguageId::TypeScript | LanguageId::TypeScriptReact => {
+            resolve_js_relative_import(from_file, &import.path_components, workspace)
+        }
+        _ => None,
+    };
+    language_specific.or_else(|| resolve_import_by_module_path(from_file, &import.path_components, workspace))
+}
+
+fn resolve_import_by_module_path(
+    importer: &PathBuf,
+    components: &Vec<String>,
+    workspace: &WorkspaceModules,
+) -> Option<PathBuf> {
+    let importer_dir = importer.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let mut anchor: Option<PathBuf> = None;
+    let mut rest: Vec<&String> = vec![];
+    for (i, c) in components.iter().enumerate() {
+        match c.as_str() {
+            "." | "self" if i == 0 => anchor = Some(importer_dir.clone()),
+            ".." | "super" if i == 0 => anchor = importer_dir.parent().map(|p| p.to_path_buf()),
+            "crate" if i == 0 => {}
+            _ => rest.push(c),
+        }
+    }
+
+    // the longest prefix wins, "pond::frog::Frog" is pond/frog.rs and not pond.rs
+    for n in (1..=rest.len()).rev() {
+        let tail: PathBuf = rest[..n].iter().map(|c| c.as_str()).collect();
+        let candidates: Vec<&PathBuf> = match &anchor {
+            Some(anchor) => workspace.modules.get(&anchor.join(&tail)).map(|files| files.iter().collect()).unwrap_or_default(),
+            None => workspace.modules_by_name.get(rest[n - 1].as_str()).into_iter()
+                .flatten()
+                .filter(|module_path| module_path.ends_with(&tail))
+                .flat_map(|module_path| workspace.modules.get(module_path).into_iter().flatten())
+                .collect(),
+        };
+        // among several same-named modules the one closest to the importer
+        let best = candidates.into_iter()
+            .filter(|f| *f != importer)
+            .max_by_key(|f| (f.components().zip(importer.components()).take_while(|(a, b)| a == b).count(), std::cmp::Reverse(f.as_os_str().len())));
+        if best.is_some() {
+            return best.cloned();
+        }
+    }
+    None
+}
+
+/// Import declarations of a file worth following, with `filepath_ref` set to the workspace file each one points to.
+/// The imports come from the parse the AST indexer already did, it stores the result per file, see `doc_imports()`.
+pub fn resolve_imports(path: &PathBuf, imports: Vec<ImportDeclaration>, workspace: &WorkspaceModules) -> Vec<ImportDeclaration> {
+    let mut resolved = vec![];
+    for mut import in imports {
+        if import.import_type == ImportType::System || import.path_components.is_empty() {
+            continue;
+        }
+        import.filepath_ref = resolve_import(&import, path, workspace);
+        resolved.push(import);
+    }
+    resolved
+}
+
+/// After files appear or go away, imports stored earlier might point somewhere else. Returns how many files changed.
+pub async fn resolve_stored_imports_again(ast_index: Arc<AMutex<AstDB>>, workspace: &WorkspaceModules) -> usize {
+    let mut changed = 0;
+    for (cpath, imports) in all_docs_imports(ast_index.clone()).await {
+        let path = PathBuf::from(&cpath);
+        let mut updated = imports.clone();
+        for import in updated.iter_mut() {
+            import.filepath_ref = resolve_import(import, &path, workspace);
+        }
+        if updated != imports {
+            doc_imports_set(ast_index.clone(), &cpath, &updated).await;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+fn import_graph_of_resolved(files_imports: Vec<(PathBuf, Vec<ImportDeclaration>)>) -> HashMap<PathBuf, Vec<PathBuf>> {
+    let mut graph: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for (path, imports) in files_imports {
+        let mut targets: Vec<PathBuf> = vec![];
+        for import in imports {
+            if let Some(target) = import.filepath_ref {
+                if !targets.contains(&target) && target != path {
+                    targets.push(target);
+                }
+            }
+        }
+        graph.insert(path, targets);
+    }
+    graph
+}
+
+// Tarjan's strongly connected components, a file can be in several cycles and all of them end up in its component.
+// Iterative, long import chains would overflow the stack of a recursive version.
+fn cycles_in_graph(graph: &HashMap<PathBuf, Vec<PathBuf>>) -> Vec<Vec<PathBuf>> {
+    let mut nodes: Vec<&PathBuf> = graph.keys().collect();
+    nodes.sort();
+    let mut index_of: HashMap<&PathBuf, usize> = HashMap::new();
+    let mut lowlink: HashMap<&PathBuf, usize> = HashMap::new();
+    let mut on_stack: HashSet<&PathBuf> = HashSet::new();
+    let mut stack: Vec<&PathBuf> = vec![];
+    let mut components = vec![];
+    for root in nodes {
+        if index_of.contains_key(root) {
+            continue;
+        }
+        let mut work: Vec<(&PathBuf, usize)> = vec![(root, 0)];  // a node and the next of its imports to follow
+        while let Some((node, edge_idx)) = work.pop() {
+            if edge_idx == 0 {
+                index_of.insert(node, index_of.len());
+                lowlink.insert(node, index_of[node]);
+                stack.push(node);
+                on_stack.insert(node);
+            }
+            if let Some(next) = graph.get(node).and_then(|targets| targets.get(edge_idx)) {
+                work.push((node, edge_idx + 1));
+                if !index_of.contains_key(next) {
+                    work.push((next, 0));
+                } else if on_stack.contains(next) {
+                    lowlink.insert(node, lowlink[node].min(index_of[next]));
+                }
+                continue;
+            }
+            if lowlink[node] == index_of[node] {
+                let mut component = vec![];
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(member);
+                    component.push(member.clone());
+                    if member == node {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    component.sort();
+                    components.push(component);
+                }
+            }
+            if let Some((parent, _)) = work.last() {
+                lowlink.insert(*parent, lowlink[*parent].min(lowlink[node]));
+            }
+        }
+    }
+    components.sort();
+    components
+}
+
+/// Import cycles of the workspace, from the imports the AST indexer has resolved
+pub async fn workspace_import_cycles(gcx: Arc<ARwLock<GlobalContext>>) -> Result<Vec<Vec<PathBuf>>, String> {
+    let ast_service = gcx.read().await.ast_service.clone().ok_or("ast module is turned off".to_string())?;
+    let ast_index = ast_service.lock().await.ast_index.clone();
+    let files_imports = all_docs_imports(ast_index).await;
+    tokio::task::spawn_blocking(move || {
+        let graph = import_graph_of_resolved(files_imports.into_iter().map(|(cpath, imports)| (PathBuf::from(cpath), imports)).collect());
+        cycles_in_graph(&graph)
+    }).await.map_err(|e| format!("import cycles search failed: {}", e))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ast_structs::AstErrorStats;
+
+    fn file_imports_resolved(path: &PathBuf, text: &str, workspace: &WorkspaceModules) -> Vec<ImportDeclaration> {
+        let (_, _, imports) = crate::ast::ast_parse_anything::parse_anything(&path.to_string_lossy(), text, &mut None, &mut AstErrorStats::default()).unwrap();
+        resolve_imports(path, imports, workspace)
+    }
+
+    fn find_import_cycles(files: &Vec<(PathBuf, String)>) -> Vec<Vec<PathBuf>> {
+        let workspace = WorkspaceModules::new(files.iter().map(|(path, _)| path));
+        cycles_in_graph(&import_graph_of_resolved(files.iter()
+            .map(|(path, text)| (path.clone(), file_imports_resolved(path, text, &workspace)))
+            .collect()))
+    }
+
+    #[test]
+    fn test_find_import_cycles_two_files() {
+        let files = vec![
+            (PathBuf::from("/pond/frog.py"), "from pond.toad import croak\n\ndef jump():\n    croak()\n".to_string()),
+            (PathBuf::from("/pond/toad.py"), "import os\nfrom pond.frog import jump\n\ndef croak():\n    jump()\n".to_string()),
+            (PathBuf::from("/pond/lily.py"), "from pond.frog import jump\n".to_string()),
+        ];
+        let cycles = find_import_cycles(&files);
+        assert_eq!(cycles, vec![vec![PathBuf::from("/pond/frog.py"), PathBuf::from("/pond/toad.py")]]);
+    }
+
+    #[test]
+    fn test_find_import_cycles_sharing_a_file() {
+        let files = vec![
+            (PathBuf::from("/pond/frog.py"), "from pond.toad import croak\n".to_string()),
+            (PathBuf::from("/pond/toad.py"), "from pond.frog import jump\nfrom pond.lily import float\n".to_string()),
+            (PathBuf::from("/pond/lily.py"), "from pond.toad import croak\n".to_string()),
+            (PathBuf::from("/pond/newt.py"), "from pond.frog import jump\n".to_string()),
+            (PathBuf::from("/swamp/heron.py"), "from swamp.reed import hide\n".to_string()),
+            (PathBuf::from("/swamp/reed.py"), "from swamp.heron import hunt\n".to_string()),
+        ];
+        let cycles = find_import_cycles(&files);
+        assert_eq!(cycles, vec![
+            vec![PathBuf::from("/pond/frog.py"), PathBuf::from("/pond/lily.py"), PathBuf::from("/pond/toad.py")],
+            vec![PathBuf::from("/swamp/heron.py"), PathBuf::from("/swamp/reed.py")],
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_resolved_imports_stored_in_index() {
+        let ast_index = crate::ast::ast_db::ast_index_init("".to_string(), 10, false).await;
+        let files = vec![
+            (PathBuf::from("/pond/frog.py"), "from .toad import croak\n".to_string()),
+            (PathBuf::from("/pond/toad.py"), "from .frog import jump\n".to_string()),
+        ];
+        let workspace = WorkspaceModules::new(files.iter().map(|(path, _)| path));
+        for (path, text) in files.iter() {
+            let imports = file_imports_resolved(path, text, &workspace);
+            crate::ast::ast_db::doc_imports_set(ast_index.clone(), &path.to_string_lossy().to_string(), &imports).await;
+        }
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+
+        let frog_imports = crate::ast::ast_db::doc_imports(ast_index.clone(), &"/pond/frog.py".to_string()).await;
+        assert_eq!(frog_imports.iter().map(|x| x.filepath_ref.clone()).collect::<Vec<_>>(), vec![Some(PathBuf::from("/pond/toad.py"))]);
+        let graph = import_graph_of_resolved(all_docs_imports(ast_index.clone()).await.into_iter().map(|(cpath, imports)| (PathBuf::from(cpath), imports)).collect());
+        assert_eq!(cycles_in_graph(&graph), vec![vec![PathBuf::from("/pond/frog.py"), PathBuf::from("/pond/toad.py")]]);
+
+        crate::ast::ast_db::doc_remove(ast_index.clone(), &"/pond/frog.py".to_string()).await;
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        assert!(crate::ast::ast_db::doc_imports(ast_index.clone(), &"/pond/frog.py".to_string()).await.is_empty());
+    }
+
+    async fn stored_targets(ast_index: Arc<AMutex<AstDB>>, cpath: &str) -> Vec<Option<PathBuf>> {
+        let imports = crate::ast::ast_db::doc_imports(ast_index, &cpath.to_string()).await;
+        imports.into_iter().map(|x| x.filepath_ref).collect()
+    }
+
+    #[tokio::test]
+    async fn test_stored_imports_follow_workspace_changes() {
+        let ast_index = crate::ast::ast_db::ast_index_init("".to_string(), 10, false).await;
+        let frog = PathBuf::from("/pond/frog.py");
+        let toad = PathBuf::from("/pond/toad.py");
+        let mut workspace = WorkspaceModules::new(vec![frog.clone()].iter());
+        let imports = file_imports_resolved(&frog, "from .toad import croak\nfrom pond.lily import float\n", &workspace);
+        crate::ast::ast_db::doc_imports_set(ast_index.clone(), &"/pond/frog.py".to_string(), &imports).await;
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        assert_eq!(stored_targets(ast_index.clone(), "/pond/frog.py").await, vec![None, None]);
+
+        assert!(workspace.add_file(&toad));
+        assert!(workspace.add_file(&PathBuf::from("/pond/lily/__init__.py")));
+        assert!(!workspace.add_file(&toad));
+        assert_eq!(resolve_stored_imports_again(ast_index.clone(), &workspace).await, 1);
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        assert_eq!(stored_targets(ast_index.clone(), "/pond/frog.py").await, vec![Some(toad.clone()), Some(PathBuf::from("/pond/lily/__init__.py"))]);
+
+        assert!(workspace.remove_file(&toad));
+        assert!(!workspace.remove_file(&toad));
+        assert_eq!(resolve_stored_imports_again(ast_index.clone(), &workspace).await, 1);
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        assert_eq!(stored_targets(ast_index.clone(), "/pond/frog.py").await, vec![None, Some(PathBuf::from("/pond/lily/__init__.py"))]);
+        assert_eq!(resolve_stored_imports_again(ast_index.clone(), &workspace).await, 0);
+    }
+
+    fn resolved_targets(path: &str, text: &str, workspace_files: &Vec<&str>) -> Vec<Option<PathBuf>> {
+        let files: Vec<PathBuf> = workspace_files.iter().map(PathBuf::from).collect();
+        let workspace = WorkspaceModules::new(files.iter());
+        file_imports_resolved(&PathBuf::from(path), text, &workspace).into_iter().map(|x| x.filepath_ref).collect()
+    }
+
+    #[test]
+    fn test_resolve_python_relative_import() {
+        let workspace_files = vec!["/pond/frog.py", "/pond/toad.py", "/swamp/toad.py", "/pond/lily/__init__.py", "/pond/lily/pad.py"];
+        let targets = resolved_targets(
+            "/pond/lily/pad.py",
+            "from ..toad import croak\nfrom . import Leaf\n",
+            &workspace_files,
+        );
+        assert_eq!(targets, vec![Some(PathBuf::from("/pond/toad.py")), Some(PathBuf::from("/pond/lily/__init__.py"))]);
+    }
+
+    #[test]
+    fn test_resolve_typescript_relative_import() {
+        let workspace_files = vec!["/pond/app.ts", "/pond/util.ts", "/swamp/util.ts", "/pond/frogs/index.ts"];
+        let targets = resolved_targets(
+            "/pond/app.ts",
+            "import { croak } from './util';\nimport { Frog } from './frogs';\n",
+            &workspace_files,
+        );
+        assert_eq!(targets, vec![Some(PathBuf::from("/pond/util.ts")), Some(PathBuf::from("/pond/frogs/index.ts"))]);
+    }
+}
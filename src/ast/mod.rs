@@ -14,6 +14,8 @@ pub mod ast_structs;
 pub mod ast_parse_anything;
 pub mod ast_indexer_thread;
 pub mod ast_db;
+pub mod import_graph;
+pub mod document_symbols;
 
 pub mod linters;
 
@@ -21,6 +23,8 @@ pub mod linters;
 pub mod file_splitter;
 #[cfg(feature="vecdb")]
 pub mod chunk_utils;
+#[cfg(feature="vecdb")]
+pub mod notebook;
 
 pub mod parse_python;
 pub mod parse_common;
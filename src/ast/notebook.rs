@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use ropey::Rope;
+use serde_json::Value;
+
+use crate::files_in_workspace::Document;
+use crate::vecdb::vdb_structs::SplitResult;
+
+
+pub fn is_notebook(path: &PathBuf) -> bool {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase() == "ipynb").unwrap_or(false)
+}
+
+/// Cells of a .ipynb joined into a plain source file in the notebook language, so it can go through
+/// the usual splitter. Every line remembers where it came from, to map chunks back to the notebook.
+pub struct NotebookSourceView {
+    pub text: String,
+    pub extension: String,
+    // for each line of `text`: (cell index, line in the .ipynb file), None for separators
+    pub line_origins: Vec<Option<(usize, usize)>>,
+}
+
+fn language_extension(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "python" | "python3" => "py",
+        "javascript" => "js",
+        "typescript" => "ts",
+        "java" => "java",
+        "rust" => "rs",
+        "c++" | "cpp" => "cpp",
+        "c" => "c",
+        "r" => "r",
+        "julia" => "jl",
+        "scala" => "scala",
+        _ => "txt",
+    }
+}
+
+fn comment_prefix(extension: &str) -> &'static str {
+    match extension {
+        "js" | "ts" | "java" | "rs" | "cpp" | "c" | "scala" => "//",
+        _ => "#",
+    }
+}
+
+fn cell_source_lines(cell: &Value) -> Vec<String> {
+    let source = match cell.get("source") {
+        Some(Value::Array(parts)) => parts.iter().filter_map(|p| p.as_str()).collect::<String>(),
+        Some(Value::String(s)) => s.clone(),
+        _ => String::new(),
+    };
+    source.lines().map(|l| l.to_string()).collect()
+}
+
+// nbformat writes one source line per json line: `"source": [` followed by the lines. Returns
+// the json line of every source line of every cell, all on the `"source"` line if it's a one-liner.
+fn source_lines_in_json(notebook_text: &str, cells: &Vec<Value>) -> Vec<Vec<usize>> {
+    let json_lines = notebook_text.lines().collect::<Vec<_>>();
+    let mut search_from = 0;
+    let mut result = vec![];
+    for cell in cells.iter() {
+        let lines_cnt = cell_source_lines(cell).len();
+        let source_row = (search_from..json_lines.len()).find(|&i| json_lines[i].trim_start().starts_with("\"source\""));
+        match source_row {
+            Some(row) if json_lines[row].trim_end().ends_with('[') => {
+                result.push((0..lines_cnt).map(|k| row + 1 + k).collect());
+                search_from = row + 1;
+            }
+            Some(row) => {
+                result.push(vec![row; lines_cnt]);
+                search_from = row + 1;
+            }
+            None => {
+                result.push(vec![search_from.saturating_sub(1); lines_cnt]);
+            }
+        }
+    }
+    result
+}
+
+pub fn notebook_source_view(notebook_text: &str, include_markdown: bool) -> Result<NotebookSourceView, String> {
+    let notebook: Value = serde_json::from_str(notebook_text).map_err(|e| format!("cannot parse notebook: {}", e))?;
+    let cells = notebook.get("cells").and_then(|c| c.as_array()).cloned().ok_or("notebook has no cells".to_string())?;
+    let language = notebook.pointer("/metadata/kernelspec/language")
+        .or_else(|| notebook.pointer("/metadata/language_info/name"))
+        .and_then(|l| l.as_str())
+        .unwrap_or("python");
+    let extension = language_extension(language).to_string();
+    let comment = comment_prefix(&extension);
+    let json_rows = source_lines_in_json(notebook_text, &cells);
+
+    let mut lines: Vec<String> = vec![];
+    let mut line_origins = vec![];
+    for (cell_idx, cell) in cells.iter().enumerate() {
+        let cell_type = cell.get("cell_type").and_then(|t| t.as_str()).unwrap_or("");
+        let is_code = cell_type == "code";
+        if !is_code && !(include_markdown && cell_type == "markdown") {
+            continue;
+        }
+        lines.push(format!("{} %% cell {} [{}]", comment, cell_idx, cell_type));
+        line_origins.push(None);
+        for (line_idx, line) in cell_source_lines(cell).into_iter().enumerate() {
+            let line = if is_code { line } else { format!("{} {}", comment, line) };
+            // ipython magics are not valid code, keep them searchable as comments
+            let line = if is_code && (line.starts_with('%') || line.starts_with('!')) { format!("{} {}", comment, line) } else { line };
+            lines.push(line);
+            line_origins.push(json_rows[cell_idx].get(line_idx).map(|row| (cell_idx, *row)));
+        }
+        lines.push("".to_string());
+        line_origins.push(None);
+    }
+    Ok(NotebookSourceView { text: lines.join("\n"), extension, line_origins })
+}
+
+impl NotebookSourceView {
+    pub fn as_document(&self, notebook_path: &PathBuf) -> Document {
+        Document {
+            doc_path: notebook_path.with_extension(&self.extension),
+            doc_text: Some(Rope::from_str(&self.text)),
+        }
+    }
+
+    /// Chunks of the view pointing to the notebook file, lines are lines of the .ipynb and the symbol path starts with the cell.
+    pub fn chunks_to_notebook(&self, chunks: Vec<SplitResult>, notebook_path: &PathBuf) -> Vec<SplitResult> {
+        chunks.into_iter().filter_map(|chunk| {
+            let last = (chunk.end_line as usize).min(self.line_origins.len().saturating_sub(1));
+            let origins = self.line_origins.get(chunk.start_line as usize..=last)?
+                .iter().filter_map(|x| *x).collect::<Vec<_>>();
+            let (cell_idx, start_line) = *origins.first()?;
+            let (_, end_line) = *origins.last()?;
+            let symbol_path = if chunk.symbol_path.is_empty() {
+                format!("cell{}", cell_idx)
+            } else {
+                format!("cell{}::{}", cell_idx, chunk.symbol_path)
+            };
+            Some(SplitResult {
+                file_path: notebook_path.clone(),
+                start_line: start_line as u64,
+                end_line: end_line as u64,
+                symbol_path,
+                ..chunk
+            })
+        }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::file_splitter::split_with_parser;
+    use crate::ast::treesitter::parsers::get_ast_parser_by_filename;
+
+    const FROG_NOTEBOOK: &str = r#"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": [
+    "# Frog pond simulation"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {},
+   "outputs": [],
+   "source": [
+    "%matplotlib inline\n",
+    "import numpy as np"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 2,
+   "metadata": {},
+   "outputs": [],
+   "source": [
+    "def bounce_off_banks(x, vx, pond_width):\n",
+    "    if x < 0 or x > pond_width:\n",
+    "        vx = -vx\n",
+    "    return np.clip(x, 0, pond_width), vx"
+   ]
+  }
+ ],
+ "metadata": {
+  "kernelspec": {
+   "display_name": "Python 3",
+   "language": "python",
+   "name": "python3"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"#;
+
+    #[test]
+    fn test_notebook_code_cells_indexed_as_python() {
+        let notebook_path = PathBuf::from("pond").join("frog.ipynb");
+        let view = notebook_source_view(FROG_NOTEBOOK, true).unwrap();
+        assert_eq!(view.extension, "py");
+        assert!(view.text.contains("# %matplotlib inline"));
+        assert!(view.text.contains("# # Frog pond simulation"));
+
+        let view_doc = view.as_document(&notebook_path);
+        let (mut parser, language) = get_ast_parser_by_filename(&view_doc.doc_path).unwrap();
+        let chunks = split_with_parser(&view_doc, &mut parser, language, None, 512).unwrap();
+        let chunks = view.chunks_to_notebook(chunks, &notebook_path);
+
+        let func_chunk = chunks.iter().find(|c| c.window_text.contains("def bounce_off_banks")).unwrap();
+        assert_eq!(func_chunk.file_path, notebook_path);
+        assert!(func_chunk.symbol_path.starts_with("cell2::"), "{}", func_chunk.symbol_path);
+        assert!(func_chunk.symbol_path.ends_with("bounce_off_banks"), "{}", func_chunk.symbol_path);
+        let json_lines = FROG_NOTEBOOK.lines().collect::<Vec<_>>();
+        assert!(json_lines[func_chunk.start_line as usize].contains("def bounce_off_banks"));
+        assert!(json_lines[func_chunk.end_line as usize].contains("return np.clip"));
+    }
+}
@@ -1,9 +1,12 @@
 use indexmap::IndexMap;
-use tree_sitter::{Node, Parser};
+use tree_sitter::{Node, Parser, Tree};
 use tree_sitter_python::language;
 
 use crate::ast::ast_structs::{AstDefinition, AstUsage, AstErrorStats};
 use crate::ast::treesitter::structs::SymbolType;
+use crate::ast::treesitter::ast_instance_structs::{ImportDeclaration, ImportType};
+use crate::ast::treesitter::language_id::LanguageId;
+use crate::ast::treesitter::parsers::python::PYTHON_MODULES;
 use crate::ast::parse_common::{ContextAnyParser, Thing, any_child_of_type, type_deindex, type_deindex_n, type_call, type_zerolevel_comma_split};
 
 const DEBUG: bool = false;
@@ -16,6 +19,7 @@ const DEBUG: bool = false;
 
 pub struct ContextPy {
     pub ap: ContextAnyParser,
+    pub imports: Vec<ImportDeclaration>,
 }
 
 fn debug_helper(cx: &ContextPy, args: std::fmt::Arguments) {
@@ -149,6 +153,32 @@ fn py_import_save<'a>(cx: &mut ContextPy, path: &Vec<String>, dotted_from: Strin
     cx.ap.alias.insert(save_as, p.join("::"));
 }
 
+// "from ..toad import croak" is ["..", "toad", "croak"], the same path components the treesitter python parser produces
+fn py_import_declaration<'a>(cx: &mut ContextPy, node: &Node<'a>, dotted_from: &str, import_what: &str, import_as: &str)
+{
+    let leading_dots = dotted_from.chars().take_while(|c| *c == '.').count();
+    let mut path_components: Vec<String> = match leading_dots {
+        0 => vec![],
+        1 => vec![".".to_string()],
+        n => vec!["..".to_string(); n - 1],
+    };
+    path_components.extend(dotted_from.split(".").chain(import_what.split(".")).map(|x| x.trim().to_string()).filter(|x| !x.is_empty()));
+    let mut import = ImportDeclaration::default();
+    import.import_type = match path_components.first() {
+        Some(first) if PYTHON_MODULES.contains(&first.as_str()) => ImportType::System,
+        _ if leading_dots > 0 => ImportType::UserModule,
+        _ => ImportType::Unknown,
+    };
+    import.ast_fields.language = LanguageId::Python;
+    import.ast_fields.full_range = node.range();
+    import.ast_fields.name = import_as.to_string();
+    if import_as != import_what {
+        import.alias = Some(import_as.to_string());
+    }
+    import.path_components = path_components;
+    cx.imports.push(import);
+}
+
 fn py_import<'a>(cx: &mut ContextPy, node: &Node<'a>, path: &Vec<String>)
 {
     let mut dotted_from = String::new();
@@ -162,11 +192,15 @@ fn py_import<'a>(cx: &mut ContextPy, node: &Node<'a>, path: &Vec<String>)
             "from" => { from_clause = true; },
             "dotted_name" => {
                 if just_do_it {
+                    py_import_declaration(cx, node, &dotted_from, &child_text, &child_text);
                     py_import_save(cx, path, dotted_from.clone(), child_text.clone(), child_text.clone());
                 } else if from_clause {
                     dotted_from = child_text.clone();
                 }
             },
+            "relative_import" if from_clause && !just_do_it => {
+                dotted_from = child_text.clone();
+            },
             "aliased_import" => {
                 let mut import_what = String::new();
                 for i in 0..child.child_count() {
@@ -175,7 +209,10 @@ fn py_import<'a>(cx: &mut ContextPy, node: &Node<'a>, path: &Vec<String>)
                     match subch.kind() {
                         "dotted_name" => { import_what = subch_text; },
                         "as" => { },
-                        "identifier" => { py_import_save(cx, path, dotted_from.clone(), import_what.clone(), subch_text); },
+                        "identifier" => {
+                            py_import_declaration(cx, node, &dotted_from, &import_what, &subch_text);
+                            py_import_save(cx, path, dotted_from.clone(), import_what.clone(), subch_text);
+                        },
                         _ => {
                             let msg = cx.ap.error_report(&child, format!("aliased_import syntax"));
                             debug!(cx, "{}", msg);
@@ -824,14 +861,21 @@ fn py_make_cx(code: &str) -> ContextPy
             alias: IndexMap::new(),
             star_imports: vec![],
         },
+        imports: vec![],
     };
     cx
 }
 
 pub fn py_parse(code: &str) -> ContextPy
+{
+    py_parse_with_old_tree(code, None).0
+}
+
+// old_tree must already have the changes applied using Tree::edit(), see text_delta_edit()
+pub fn py_parse_with_old_tree(code: &str, old_tree: Option<&Tree>) -> (ContextPy, Tree)
 {
     let mut cx = py_make_cx(code);
-    let tree = cx.ap.sitter.parse(code, None).unwrap();
+    let tree = cx.ap.sitter.parse(code, old_tree).unwrap();
     let path = vec!["root".to_string()];
     let mut pass_n = 1;
     loop {
@@ -843,6 +887,7 @@ pub fn py_parse(code: &str) -> ContextPy
         }
         cx.ap.usages.clear();
         cx.ap.errs = AstErrorStats::default();
+        cx.imports.clear();
         pass_n += 1;
     }
     cx.ap.defs.insert("root".to_string(), AstDefinition {
@@ -858,7 +903,7 @@ pub fn py_parse(code: &str) -> ContextPy
         body_line1: 0,
         body_line2: 0,
     });
-    return cx;
+    return (cx, tree);
 }
 
 
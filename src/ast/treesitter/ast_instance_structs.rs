@@ -199,6 +199,31 @@ impl SymbolInformation {
         let content = fs::read_to_string(&self.file_path)?;
         self.get_declaration_content(&content)
     }
+
+    // numeric values of LSP SymbolKind, see https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#symbolKind
+    pub fn to_lsp_symbol_kind(&self) -> u8 {
+        match self.symbol_type {
+            SymbolType::Module => 2,                 // Module
+            SymbolType::ImportDeclaration => 2,      // Module
+            SymbolType::StructDeclaration => 5,      // Class
+            SymbolType::ClassFieldDeclaration => 8,  // Field
+            SymbolType::FunctionDeclaration => 12,   // Function
+            SymbolType::FunctionCall => 12,          // Function
+            SymbolType::VariableDefinition => 13,    // Variable
+            SymbolType::VariableUsage => 13,         // Variable
+            SymbolType::CommentDefinition => 15,     // String
+            SymbolType::TypeAlias => 26,             // TypeParameter
+            SymbolType::Unknown => 21,               // Null
+        }
+    }
+
+    // SymbolType doesn't distinguish methods from free functions, the parent does
+    pub fn to_lsp_symbol_kind_with_parent(&self, parent: Option<&SymbolInformation>) -> u8 {
+        match (&self.symbol_type, parent.map(|p| &p.symbol_type)) {
+            (SymbolType::FunctionDeclaration, Some(SymbolType::StructDeclaration)) => 6,  // Method
+            _ => self.to_lsp_symbol_kind(),
+        }
+    }
 }
 
 impl Default for AstSymbolFields {
@@ -1196,3 +1221,41 @@ impl AstSymbolInstance for VariableUsage {
         SymbolType::VariableUsage
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, symbol_type: SymbolType) -> SymbolInformation {
+        let empty_range = Range { start_byte: 0, end_byte: 0, start_point: Point::default(), end_point: Point::default() };
+        SymbolInformation {
+            guid: Uuid::default(),
+            name: name.to_string(),
+            parent_guid: Uuid::default(),
+            linked_decl_guid: Uuid::default(),
+            caller_guid: Uuid::default(),
+            symbol_type,
+            symbol_path: "".to_string(),
+            language: LanguageId::Python,
+            file_path: PathBuf::from("frog.py"),
+            namespace: "".to_string(),
+            is_error: false,
+            full_range: empty_range,
+            declaration_range: empty_range,
+            definition_range: empty_range,
+        }
+    }
+
+    #[test]
+    fn test_lsp_symbol_kind() {
+        let frog = symbol("Frog", SymbolType::StructDeclaration);
+        let jump = symbol("jump", SymbolType::FunctionDeclaration);
+        let standalone = symbol("standalone_jumping_function", SymbolType::FunctionDeclaration);
+
+        assert_eq!(frog.to_lsp_symbol_kind(), 5);  // Class
+        assert_eq!(jump.to_lsp_symbol_kind_with_parent(Some(&frog)), 6);  // Method
+        assert_eq!(standalone.to_lsp_symbol_kind(), 12);  // Function
+        assert_eq!(standalone.to_lsp_symbol_kind_with_parent(None), 12);
+    }
+}
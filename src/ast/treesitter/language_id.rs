@@ -139,6 +139,8 @@ impl From<Language> for LanguageId {
             Self::Java
         } else if value == tree_sitter_javascript::language() {
             Self::JavaScript
+        } else if value == tree_sitter_go::language() {
+            Self::Go
         } else if value == tree_sitter_rust::language() {
             Self::Rust
         } else if value == tree_sitter_typescript::language_typescript() {
@@ -2,6 +2,7 @@ use std::fmt::Display;
 use std::path::PathBuf;
 
 use tracing::error;
+use tree_sitter::{InputEdit, Point, Tree};
 
 use crate::ast::treesitter::ast_instance_structs::AstSymbolInstanceArc;
 use crate::ast::treesitter::language_id::LanguageId;
@@ -16,6 +17,7 @@ mod java;
 mod cpp;
 mod ts;
 mod js;
+mod go;
 
 
 #[derive(Debug, PartialEq, Eq)]
@@ -23,8 +25,72 @@ pub struct ParserError {
     pub message: String,
 }
 
+// From --ast-languages, empty means every language that has a parser
+pub fn ast_languages_from_cmdline(languages: &Vec<String>) -> Result<Vec<LanguageId>, String> {
+    let mut allowed = vec![];
+    for name in languages.iter() {
+        let language_id = LanguageId::from(name.trim().to_lowercase().as_str());
+        if language_id == LanguageId::Unknown {
+            return Err(format!("unknown language {:?} in --ast-languages", name));
+        }
+        allowed.push(language_id);
+    }
+    Ok(allowed)
+}
+
+// Files of other languages are not AST-indexed, they still can go to vecdb
+pub fn is_ast_language_allowed(filename: &PathBuf, allowed: &Vec<LanguageId>) -> Result<(), String> {
+    match get_language_id_by_filename(filename) {
+        Some(language_id) if !allowed.is_empty() && !allowed.contains(&language_id) => {
+            let suffix = filename.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+            Err(format!("not supported {}, {} is not in --ast-languages", suffix, language_id))
+        }
+        _ => Ok(()),
+    }
+}
+
 pub trait AstLanguageParser: Send {
-    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc>;
+    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
+        self.parse_with_old_tree(code, path, None).0
+    }
+
+    // old_tree must already have the changes applied using Tree::edit(), see text_delta_edit()
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree);
+}
+
+// The tree of the previous version of a document, to parse the next version incrementally
+pub struct PreviousParse {
+    pub text: String,
+    pub tree: Tree,
+}
+
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text.as_bytes()[..byte];
+    let line_start = before.iter().rposition(|b| *b == b'\n').map(|pos| pos + 1).unwrap_or(0);
+    Point::new(before.iter().filter(|b| **b == b'\n').count(), byte - line_start)
+}
+
+/// The change from old_text to new_text as a single edit: everything between the common prefix and the common suffix
+pub fn text_delta_edit(old_text: &str, new_text: &str) -> InputEdit {
+    let (old_bytes, new_bytes) = (old_text.as_bytes(), new_text.as_bytes());
+    let mut prefix = old_bytes.iter().zip(new_bytes.iter()).take_while(|(a, b)| a == b).count();
+    while !old_text.is_char_boundary(prefix) || !new_text.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let max_suffix = old_bytes.len().min(new_bytes.len()) - prefix;
+    let mut suffix = old_bytes.iter().rev().zip(new_bytes.iter().rev()).take(max_suffix).take_while(|(a, b)| a == b).count();
+    while !old_text.is_char_boundary(old_bytes.len() - suffix) || !new_text.is_char_boundary(new_bytes.len() - suffix) {
+        suffix -= 1;
+    }
+    let (old_end, new_end) = (old_bytes.len() - suffix, new_bytes.len() - suffix);
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old_text, prefix),
+        old_end_position: point_at(old_text, old_end),
+        new_end_position: point_at(new_text, new_end),
+    }
 }
 
 fn internal_error<E: Display>(err: E) -> ParserError {
@@ -61,6 +127,10 @@ pub(crate) fn get_ast_parser(language_id: LanguageId) -> Result<Box<dyn AstLangu
             let parser = js::JSParser::new()?;
             Ok(Box::new(parser))
         }
+        LanguageId::Go => {
+            let parser = go::GoParser::new()?;
+            Ok(Box::new(parser))
+        }
         LanguageId::TypeScriptReact => {
             let parser = ts::TSParser::new()?; //quick fix untill we have a dedicated parser for TypeScriptReact
             Ok(Box::new(parser))
@@ -93,6 +163,7 @@ pub fn get_language_id_by_filename(filename: &PathBuf) -> Option<LanguageId> {
         "java" => Some(LanguageId::Java),
         "js" | "jsx" => Some(LanguageId::JavaScript),
         "rs" => Some(LanguageId::Rust),
+        "go" => Some(LanguageId::Go),
         "ts" => Some(LanguageId::TypeScript),
         "tsx" => Some(LanguageId::TypeScriptReact),
         _ => None
@@ -0,0 +1,743 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::string::ToString;
+use std::sync::Arc;
+
+#[cfg(test)]
+use itertools::Itertools;
+
+use parking_lot::RwLock;
+use similar::DiffableStr;
+use tree_sitter::{Node, Parser, Range, Tree};
+use tree_sitter_go::language;
+use uuid::Uuid;
+
+use crate::ast::treesitter::ast_instance_structs::{AstSymbolFields, AstSymbolInstanceArc, ClassFieldDeclaration, CommentDefinition, FunctionArg, FunctionCall, FunctionDeclaration, ImportDeclaration, ImportType, StructDeclaration, TypeAlias, TypeDef, VariableDefinition, VariableUsage};
+use crate::ast::treesitter::language_id::LanguageId;
+use crate::ast::treesitter::parsers::{AstLanguageParser, internal_error, ParserError};
+use crate::ast::treesitter::parsers::utils::{CandidateInfo, get_guid};
+
+pub(crate) struct GoParser {
+    pub parser: Parser,
+    // methods are declared outside of their struct, they are moved under it after parsing if the struct is in the same file
+    receivers: HashMap<Uuid, String>,
+}
+
+static GO_KEYWORDS: [&str; 25] = [
+    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for",
+    "func", "go", "goto", "if", "import", "interface", "map", "package", "range", "return",
+    "select", "struct", "switch", "type", "var",
+];
+
+fn pod_type(text: String) -> TypeDef {
+    TypeDef {
+        name: None,
+        inference_info: Some(text),
+        inference_info_guid: None,
+        is_pod: true,
+        namespace: "".to_string(),
+        guid: None,
+        nested_types: vec![],
+    }
+}
+
+fn named_type(name: String) -> TypeDef {
+    TypeDef {
+        name: Some(name),
+        inference_info: None,
+        inference_info_guid: None,
+        is_pod: false,
+        namespace: "".to_string(),
+        guid: None,
+        nested_types: vec![],
+    }
+}
+
+static GO_POD_TYPES: [&str; 20] = [
+    "bool", "byte", "complex64", "complex128", "error", "float32", "float64", "int", "int8", "int16",
+    "int32", "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64", "uintptr",
+];
+
+pub fn parse_type(parent: &Node, code: &str) -> Option<TypeDef> {
+    let kind = parent.kind();
+    let text = code.slice(parent.byte_range()).to_string();
+    match kind {
+        "type_identifier" | "identifier" => {
+            if GO_POD_TYPES.contains(&text.as_str()) {
+                return Some(pod_type(text));
+            }
+            return Some(named_type(text));
+        }
+        "qualified_type" => {
+            let mut decl = named_type(text);
+            if let Some(name) = parent.child_by_field_name("name") {
+                decl.name = Some(code.slice(name.byte_range()).to_string());
+            }
+            if let Some(package) = parent.child_by_field_name("package") {
+                decl.namespace = code.slice(package.byte_range()).to_string();
+            }
+            return Some(decl);
+        }
+        "pointer_type" | "parenthesized_type" | "type_elem" => {
+            for i in 0..parent.child_count() {
+                let child = parent.child(i).unwrap();
+                if let Some(dtype) = parse_type(&child, code) {
+                    return Some(dtype);
+                }
+            }
+        }
+        "slice_type" | "array_type" | "implicit_length_array_type" | "channel_type" => {
+            let mut decl = named_type(if kind == "channel_type" { "chan".to_string() } else { "[]".to_string() });
+            let element = parent.child_by_field_name("element").or(parent.child_by_field_name("value"));
+            if let Some(element) = element {
+                if let Some(dtype) = parse_type(&element, code) {
+                    decl.nested_types.push(dtype);
+                }
+            }
+            return Some(decl);
+        }
+        "map_type" => {
+            let mut decl = named_type("map".to_string());
+            for field in ["key", "value"] {
+                if let Some(node) = parent.child_by_field_name(field) {
+                    if let Some(dtype) = parse_type(&node, code) {
+                        decl.nested_types.push(dtype);
+                    }
+                }
+            }
+            return Some(decl);
+        }
+        "generic_type" => {
+            let mut decl = named_type(text);
+            if let Some(type_node) = parent.child_by_field_name("type") {
+                if let Some(dtype) = parse_type(&type_node, code) {
+                    decl.name = dtype.name;
+                    decl.namespace = dtype.namespace;
+                }
+            }
+            if let Some(arguments) = parent.child_by_field_name("type_arguments") {
+                for i in 0..arguments.child_count() {
+                    let child = arguments.child(i).unwrap();
+                    if let Some(dtype) = parse_type(&child, code) {
+                        decl.nested_types.push(dtype);
+                    }
+                }
+            }
+            return Some(decl);
+        }
+        "function_type" | "struct_type" | "interface_type" => {
+            return Some(pod_type(text));
+        }
+        &_ => {}
+    }
+    None
+}
+
+fn parse_function_args(parameters: &Node, code: &str) -> Vec<FunctionArg> {
+    let mut args = vec![];
+    for i in 0..parameters.child_count() {
+        let child = parameters.child(i).unwrap();
+        if !["parameter_declaration", "variadic_parameter_declaration"].contains(&child.kind()) {
+            continue;
+        }
+        let type_ = child.child_by_field_name("type").and_then(|t| parse_type(&t, code));
+        let mut cursor = child.walk();
+        let names = child.children_by_field_name("name", &mut cursor).collect::<Vec<_>>();
+        if names.is_empty() {
+            args.push(FunctionArg { name: "".to_string(), type_: type_.clone() });
+        }
+        for name in names {
+            args.push(FunctionArg { name: code.slice(name.byte_range()).to_string(), type_: type_.clone() });
+        }
+    }
+    args
+}
+
+fn receiver_type_name(receiver: &Node, code: &str) -> Option<String> {
+    for i in 0..receiver.child_count() {
+        let child = receiver.child(i).unwrap();
+        if child.kind() == "parameter_declaration" {
+            let type_node = child.child_by_field_name("type")?;
+            return parse_type(&type_node, code).and_then(|t| t.name);
+        }
+    }
+    None
+}
+
+// "type Frog struct {" is declaration, "{ ... }" is definition
+fn body_start<'a>(type_node: &Node<'a>) -> Option<Node<'a>> {
+    for i in 0..type_node.child_count() {
+        let child = type_node.child(i).unwrap();
+        if ["field_declaration_list", "{"].contains(&child.kind()) {
+            return Some(child);
+        }
+    }
+    None
+}
+
+
+impl GoParser {
+    pub fn new() -> Result<GoParser, ParserError> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language())
+            .map_err(internal_error)?;
+        Ok(GoParser { parser, receivers: HashMap::new() })
+    }
+
+    fn parse_type_declaration<'a>(&mut self, info: &CandidateInfo<'a>, code: &str, candidates: &mut VecDeque<CandidateInfo<'a>>) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = vec![];
+        symbols.extend(self.find_error_usages(&info.node, code, &info.ast_fields.file_path, &info.parent_guid));
+        let specs = (0..info.node.child_count())
+            .map(|i| info.node.child(i).unwrap())
+            .filter(|c| ["type_spec", "type_alias"].contains(&c.kind()))
+            .collect::<Vec<_>>();
+        // a single "type Frog struct {}" covers the keyword as well, grouped declarations only have their specs
+        let single_spec = specs.len() == 1;
+        for spec in specs {
+            let full_range = if single_spec { info.node.range() } else { spec.range() };
+            let name = spec.child_by_field_name("name").map(|n| code.slice(n.byte_range()).to_string()).unwrap_or_default();
+            let type_node = match spec.child_by_field_name("type") {
+                Some(type_node) => type_node,
+                None => continue,
+            };
+            if spec.kind() == "type_spec" && ["struct_type", "interface_type"].contains(&type_node.kind()) {
+                let mut decl = StructDeclaration::default();
+                decl.ast_fields.language = info.ast_fields.language;
+                decl.ast_fields.full_range = full_range;
+                decl.ast_fields.declaration_range = full_range;
+                decl.ast_fields.definition_range = full_range;
+                decl.ast_fields.file_path = info.ast_fields.file_path.clone();
+                decl.ast_fields.parent_guid = Some(info.parent_guid.clone());
+                decl.ast_fields.guid = get_guid();
+                decl.ast_fields.is_error = info.ast_fields.is_error;
+                decl.ast_fields.name = name;
+                if let Some(type_parameters) = spec.child_by_field_name("type_parameters") {
+                    for i in 0..type_parameters.child_count() {
+                        let child = type_parameters.child(i).unwrap();
+                        if let Some(name) = child.child_by_field_name("name") {
+                            decl.template_types.push(named_type(code.slice(name.byte_range()).to_string()));
+                        }
+                    }
+                }
+                if let Some(body) = body_start(&type_node) {
+                    decl.ast_fields.definition_range = Range {
+                        start_byte: body.start_byte(),
+                        end_byte: type_node.end_byte(),
+                        start_point: body.start_position(),
+                        end_point: type_node.end_position(),
+                    };
+                    decl.ast_fields.declaration_range = Range {
+                        start_byte: full_range.start_byte,
+                        end_byte: body.start_byte(),
+                        start_point: full_range.start_point,
+                        end_point: body.start_position(),
+                    };
+                }
+                let members = if type_node.kind() == "struct_type" { body_start(&type_node).unwrap_or(type_node) } else { type_node };
+                for i in 0..members.child_count() {
+                    let child = members.child(i).unwrap();
+                    candidates.push_back(CandidateInfo {
+                        ast_fields: decl.ast_fields.clone(),
+                        node: child,
+                        parent_guid: decl.ast_fields.guid.clone(),
+                    });
+                }
+                symbols.push(Arc::new(RwLock::new(Box::new(decl))));
+            } else {
+                let mut type_alias = TypeAlias::default();
+                type_alias.ast_fields.name = name;
+                type_alias.ast_fields.language = info.ast_fields.language;
+                type_alias.ast_fields.full_range = full_range;
+                type_alias.ast_fields.file_path = info.ast_fields.file_path.clone();
+                type_alias.ast_fields.parent_guid = Some(info.parent_guid.clone());
+                type_alias.ast_fields.guid = get_guid();
+                type_alias.ast_fields.is_error = info.ast_fields.is_error;
+                if let Some(dtype) = parse_type(&type_node, code) {
+                    type_alias.types.push(dtype);
+                }
+                symbols.push(Arc::new(RwLock::new(Box::new(type_alias))));
+            }
+        }
+        symbols
+    }
+
+    fn parse_field_declaration<'a>(&mut self, info: &CandidateInfo<'a>, code: &str) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = vec![];
+        symbols.extend(self.find_error_usages(&info.node, code, &info.ast_fields.file_path, &info.parent_guid));
+        let type_node = info.node.child_by_field_name("type");
+        let dtype = type_node.and_then(|t| parse_type(&t, code)).unwrap_or_default();
+        let mut cursor = info.node.walk();
+        let mut names = info.node.children_by_field_name("name", &mut cursor)
+            .map(|n| code.slice(n.byte_range()).to_string())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            // embedded field, named after its type
+            if let Some(name) = dtype.name.clone() {
+                names.push(name);
+            }
+        }
+        for name in names {
+            let mut decl = ClassFieldDeclaration::default();
+            decl.ast_fields.language = info.ast_fields.language;
+            decl.ast_fields.full_range = info.node.range();
+            decl.ast_fields.declaration_range = info.node.range();
+            decl.ast_fields.file_path = info.ast_fields.file_path.clone();
+            decl.ast_fields.parent_guid = Some(info.parent_guid.clone());
+            decl.ast_fields.guid = get_guid();
+            decl.ast_fields.is_error = info.ast_fields.is_error;
+            decl.ast_fields.name = name;
+            decl.type_ = dtype.clone();
+            symbols.push(Arc::new(RwLock::new(Box::new(decl))));
+        }
+        symbols
+    }
+
+    fn parse_variable_definition<'a>(&mut self, info: &CandidateInfo<'a>, code: &str, candidates: &mut VecDeque<CandidateInfo<'a>>) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = vec![];
+        symbols.extend(self.find_error_usages(&info.node, code, &info.ast_fields.file_path, &info.parent_guid));
+
+        // var_spec and const_spec have name/type/value, short_var_declaration has left/right
+        let is_short = info.node.kind() == "short_var_declaration";
+        let mut dtype = info.node.child_by_field_name("type").and_then(|t| parse_type(&t, code)).unwrap_or_default();
+        let value = info.node.child_by_field_name(if is_short { "right" } else { "value" });
+        if let Some(value) = value {
+            dtype.inference_info = Some(code.slice(value.byte_range()).to_string());
+            candidates.push_back(CandidateInfo {
+                ast_fields: info.ast_fields.clone(),
+                node: value,
+                parent_guid: info.parent_guid.clone(),
+            });
+        }
+
+        let mut name_nodes = vec![];
+        if is_short {
+            if let Some(left) = info.node.child_by_field_name("left") {
+                for i in 0..left.child_count() {
+                    let child = left.child(i).unwrap();
+                    if child.kind() == "identifier" {
+                        name_nodes.push(child);
+                    }
+                }
+            }
+        } else {
+            let mut cursor = info.node.walk();
+            name_nodes.extend(info.node.children_by_field_name("name", &mut cursor));
+        }
+        for name in name_nodes {
+            let name = code.slice(name.byte_range()).to_string();
+            if name == "_" {
+                continue;
+            }
+            let mut decl = VariableDefinition::default();
+            decl.ast_fields.language = info.ast_fields.language;
+            decl.ast_fields.full_range = info.node.range();
+            decl.ast_fields.file_path = info.ast_fields.file_path.clone();
+            decl.ast_fields.parent_guid = Some(info.parent_guid.clone());
+            decl.ast_fields.guid = get_guid();
+            decl.ast_fields.is_error = info.ast_fields.is_error;
+            decl.ast_fields.name = name;
+            decl.type_ = dtype.clone();
+            symbols.push(Arc::new(RwLock::new(Box::new(decl))));
+        }
+        symbols
+    }
+
+    fn parse_import_declaration<'a>(&mut self, info: &CandidateInfo<'a>, code: &str) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = vec![];
+        let mut specs = vec![];
+        for i in 0..info.node.child_count() {
+            let child = info.node.child(i).unwrap();
+            match child.kind() {
+                "import_spec" => specs.push(child),
+                "import_spec_list" => {
+                    for j in 0..child.child_count() {
+                        let spec = child.child(j).unwrap();
+                        if spec.kind() == "import_spec" {
+                            specs.push(spec);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for spec in specs {
+            let mut def = ImportDeclaration::default();
+            def.ast_fields.language = info.ast_fields.language;
+            def.ast_fields.full_range = spec.range();
+            def.ast_fields.file_path = info.ast_fields.file_path.clone();
+            def.ast_fields.parent_guid = Some(info.parent_guid.clone());
+            def.ast_fields.guid = get_guid();
+            if let Some(path) = spec.child_by_field_name("path") {
+                let path = code.slice(path.byte_range()).to_string();
+                def.path_components = path.trim_matches(|c| c == '"' || c == '`').split("/").map(|x| x.to_string()).collect();
+            }
+            if let Some(first) = def.path_components.first() {
+                // standard library packages have no domain in their path: "fmt", "net/http"
+                if !first.contains(".") {
+                    def.import_type = ImportType::System;
+                }
+            }
+            def.ast_fields.name = def.path_components.last().cloned().unwrap_or_default();
+            if let Some(alias) = spec.child_by_field_name("name") {
+                def.alias = Some(code.slice(alias.byte_range()).to_string());
+            }
+            symbols.push(Arc::new(RwLock::new(Box::new(def))));
+        }
+        symbols
+    }
+
+    fn parse_usages_<'a>(&mut self, info: &CandidateInfo<'a>, code: &str, candidates: &mut VecDeque<CandidateInfo<'a>>) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = vec![];
+        let kind = info.node.kind();
+        match kind {
+            "type_declaration" => {
+                symbols.extend(self.parse_type_declaration(info, code, candidates));
+            }
+            "function_declaration" | "method_declaration" | "method_elem" | "method_spec" => {
+                symbols.extend(self.parse_function_declaration(info, code, candidates));
+            }
+            "field_declaration" => {
+                symbols.extend(self.parse_field_declaration(info, code));
+            }
+            "var_spec" | "const_spec" | "short_var_declaration" => {
+                symbols.extend(self.parse_variable_definition(info, code, candidates));
+            }
+            "call_expression" | "composite_literal" => {
+                symbols.extend(self.parse_call_expression(info, code, candidates));
+            }
+            "import_declaration" => {
+                symbols.extend(self.parse_import_declaration(info, code));
+            }
+            "identifier" => {
+                let mut usage = VariableUsage::default();
+                usage.ast_fields.name = code.slice(info.node.byte_range()).to_string();
+                usage.ast_fields.language = info.ast_fields.language;
+                usage.ast_fields.full_range = info.node.range();
+                usage.ast_fields.file_path = info.ast_fields.file_path.clone();
+                usage.ast_fields.parent_guid = Some(info.parent_guid.clone());
+                usage.ast_fields.guid = get_guid();
+                usage.ast_fields.is_error = info.ast_fields.is_error;
+                if let Some(caller_guid) = info.ast_fields.caller_guid.clone() {
+                    usage.ast_fields.guid = caller_guid;
+                }
+                symbols.push(Arc::new(RwLock::new(Box::new(usage))));
+            }
+            "selector_expression" => {
+                let operand = info.node.child_by_field_name("operand").unwrap();
+                let field = info.node.child_by_field_name("field").unwrap();
+                let mut usage = VariableUsage::default();
+                usage.ast_fields.name = code.slice(field.byte_range()).to_string();
+                usage.ast_fields.language = info.ast_fields.language;
+                usage.ast_fields.full_range = info.node.range();
+                usage.ast_fields.file_path = info.ast_fields.file_path.clone();
+                usage.ast_fields.guid = get_guid();
+                usage.ast_fields.parent_guid = Some(info.parent_guid.clone());
+                usage.ast_fields.caller_guid = Some(get_guid());
+                usage.ast_fields.is_error = info.ast_fields.is_error;
+                if let Some(caller_guid) = info.ast_fields.caller_guid.clone() {
+                    usage.ast_fields.guid = caller_guid;
+                }
+                candidates.push_back(CandidateInfo {
+                    ast_fields: usage.ast_fields.clone(),
+                    node: operand,
+                    parent_guid: info.parent_guid.clone(),
+                });
+                symbols.push(Arc::new(RwLock::new(Box::new(usage))));
+            }
+            "comment" => {
+                let mut def = CommentDefinition::default();
+                def.ast_fields.language = info.ast_fields.language;
+                def.ast_fields.full_range = info.node.range();
+                def.ast_fields.file_path = info.ast_fields.file_path.clone();
+                def.ast_fields.parent_guid = Some(info.parent_guid.clone());
+                def.ast_fields.guid = get_guid();
+                def.ast_fields.is_error = info.ast_fields.is_error;
+                symbols.push(Arc::new(RwLock::new(Box::new(def))));
+            }
+            "ERROR" => {
+                let mut ast = info.ast_fields.clone();
+                ast.is_error = true;
+
+                for i in 0..info.node.child_count() {
+                    let child = info.node.child(i).unwrap();
+                    candidates.push_back(CandidateInfo {
+                        ast_fields: ast.clone(),
+                        node: child,
+                        parent_guid: info.parent_guid.clone(),
+                    });
+                }
+            }
+            "package_clause" | "field_identifier" | "type_identifier" | "package_identifier" => {}
+            _ => {
+                for i in 0..info.node.child_count() {
+                    let child = info.node.child(i).unwrap();
+                    candidates.push_back(CandidateInfo {
+                        ast_fields: info.ast_fields.clone(),
+                        node: child,
+                        parent_guid: info.parent_guid.clone(),
+                    })
+                }
+            }
+        }
+        symbols
+    }
+
+    fn find_error_usages(&mut self, parent: &Node, code: &str, path: &PathBuf, parent_guid: &Uuid) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = Default::default();
+        for i in 0..parent.child_count() {
+            let child = parent.child(i).unwrap();
+            if child.kind() == "ERROR" {
+                symbols.extend(self.parse_error_usages(&child, code, path, parent_guid));
+            }
+        }
+        symbols
+    }
+
+    fn parse_error_usages(&mut self, parent: &Node, code: &str, path: &PathBuf, parent_guid: &Uuid) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = Default::default();
+        match parent.kind() {
+            "identifier" => {
+                let name = code.slice(parent.byte_range()).to_string();
+                if GO_KEYWORDS.contains(&name.as_str()) {
+                    return symbols;
+                }
+
+                let mut usage = VariableUsage::default();
+                usage.ast_fields.name = name;
+                usage.ast_fields.language = LanguageId::Go;
+                usage.ast_fields.full_range = parent.range();
+                usage.ast_fields.file_path = path.clone();
+                usage.ast_fields.parent_guid = Some(parent_guid.clone());
+                usage.ast_fields.guid = get_guid();
+                usage.ast_fields.is_error = true;
+                symbols.push(Arc::new(RwLock::new(Box::new(usage))));
+            }
+            "selector_expression" => {
+                let operand = parent.child_by_field_name("operand").unwrap();
+                let usages = self.parse_error_usages(&operand, code, path, parent_guid);
+                let field = parent.child_by_field_name("field").unwrap();
+                let mut usage = VariableUsage::default();
+                usage.ast_fields.name = code.slice(field.byte_range()).to_string();
+                usage.ast_fields.language = LanguageId::Go;
+                usage.ast_fields.full_range = parent.range();
+                usage.ast_fields.file_path = path.clone();
+                usage.ast_fields.guid = get_guid();
+                usage.ast_fields.parent_guid = Some(parent_guid.clone());
+                usage.ast_fields.is_error = true;
+                if let Some(last) = usages.last() {
+                    usage.ast_fields.caller_guid = last.read().fields().parent_guid.clone();
+                }
+                symbols.extend(usages);
+                if !GO_KEYWORDS.contains(&usage.ast_fields.name.as_str()) {
+                    symbols.push(Arc::new(RwLock::new(Box::new(usage))));
+                }
+            }
+            &_ => {
+                for i in 0..parent.child_count() {
+                    let child = parent.child(i).unwrap();
+                    symbols.extend(self.parse_error_usages(&child, code, path, parent_guid));
+                }
+            }
+        }
+
+        symbols
+    }
+
+    pub fn parse_function_declaration<'a>(&mut self, info: &CandidateInfo<'a>, code: &str, candidates: &mut VecDeque<CandidateInfo<'a>>) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = Default::default();
+        let mut decl = FunctionDeclaration::default();
+        decl.ast_fields.language = info.ast_fields.language;
+        decl.ast_fields.full_range = info.node.range();
+        decl.ast_fields.declaration_range = info.node.range();
+        decl.ast_fields.definition_range = info.node.range();
+        decl.ast_fields.file_path = info.ast_fields.file_path.clone();
+        decl.ast_fields.parent_guid = Some(info.parent_guid.clone());
+        decl.ast_fields.is_error = info.ast_fields.is_error;
+        decl.ast_fields.guid = get_guid();
+
+        symbols.extend(self.find_error_usages(&info.node, code, &info.ast_fields.file_path, &decl.ast_fields.guid));
+
+        if let Some(name_node) = info.node.child_by_field_name("name") {
+            decl.ast_fields.name = code.slice(name_node.byte_range()).to_string();
+        }
+        if let Some(receiver) = info.node.child_by_field_name("receiver") {
+            if let Some(type_name) = receiver_type_name(&receiver, code) {
+                self.receivers.insert(decl.ast_fields.guid.clone(), type_name);
+            }
+        }
+        if let Some(type_parameters) = info.node.child_by_field_name("type_parameters") {
+            for i in 0..type_parameters.child_count() {
+                let child = type_parameters.child(i).unwrap();
+                if let Some(name) = child.child_by_field_name("name") {
+                    decl.template_types.push(named_type(code.slice(name.byte_range()).to_string()));
+                }
+            }
+        }
+        if let Some(parameters_node) = info.node.child_by_field_name("parameters") {
+            symbols.extend(self.find_error_usages(&parameters_node, code, &info.ast_fields.file_path, &decl.ast_fields.guid));
+            decl.args = parse_function_args(&parameters_node, code);
+        }
+        if let Some(result) = info.node.child_by_field_name("result") {
+            decl.return_type = if result.kind() == "parameter_list" {
+                // multiple results (Frog, error) are kept as a tuple-like type
+                let mut dtype = pod_type(code.slice(result.byte_range()).to_string());
+                dtype.nested_types = parse_function_args(&result, code).into_iter().filter_map(|a| a.type_).collect();
+                Some(dtype)
+            } else {
+                parse_type(&result, code)
+            };
+        }
+
+        if let Some(body_node) = info.node.child_by_field_name("body") {
+            decl.ast_fields.definition_range = body_node.range();
+            decl.ast_fields.declaration_range = Range {
+                start_byte: decl.ast_fields.full_range.start_byte,
+                end_byte: decl.ast_fields.definition_range.start_byte,
+                start_point: decl.ast_fields.full_range.start_point,
+                end_point: decl.ast_fields.definition_range.start_point,
+            };
+            candidates.push_back(CandidateInfo {
+                ast_fields: decl.ast_fields.clone(),
+                node: body_node,
+                parent_guid: decl.ast_fields.guid.clone(),
+            });
+        } else {
+            decl.ast_fields.declaration_range = decl.ast_fields.full_range;
+        }
+
+        symbols.push(Arc::new(RwLock::new(Box::new(decl))));
+        symbols
+    }
+
+    pub fn parse_call_expression<'a>(&mut self, info: &CandidateInfo<'a>, code: &str, candidates: &mut VecDeque<CandidateInfo<'a>>) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = Default::default();
+        let mut decl = FunctionCall::default();
+        decl.ast_fields.language = info.ast_fields.language;
+        decl.ast_fields.full_range = info.node.range();
+        decl.ast_fields.file_path = info.ast_fields.file_path.clone();
+        decl.ast_fields.parent_guid = Some(info.parent_guid.clone());
+        decl.ast_fields.guid = get_guid();
+        decl.ast_fields.is_error = info.ast_fields.is_error;
+        if let Some(caller_guid) = info.ast_fields.caller_guid.clone() {
+            decl.ast_fields.guid = caller_guid;
+        }
+        decl.ast_fields.caller_guid = Some(get_guid());
+
+        symbols.extend(self.find_error_usages(&info.node, code, &info.ast_fields.file_path, &info.parent_guid));
+
+        let mut object: Option<Node> = None;
+        if let Some(function) = info.node.child_by_field_name("function") {
+            match function.kind() {
+                "identifier" => {
+                    decl.ast_fields.name = code.slice(function.byte_range()).to_string();
+                }
+                "selector_expression" => {
+                    if let Some(field) = function.child_by_field_name("field") {
+                        decl.ast_fields.name = code.slice(field.byte_range()).to_string();
+                    }
+                    object = function.child_by_field_name("operand");
+                }
+                _ => {
+                    // func literal called in place and similar, nothing to name
+                    candidates.push_back(CandidateInfo {
+                        ast_fields: info.ast_fields.clone(),
+                        node: function,
+                        parent_guid: info.parent_guid.clone(),
+                    });
+                }
+            }
+        }
+        if let Some(type_) = info.node.child_by_field_name("type") {
+            // Frog{Name: "Kermit"} constructs a struct, similar to a constructor call
+            if let Some(dtype) = parse_type(&type_, code) {
+                decl.ast_fields.name = dtype.name.unwrap_or_else(|| code.slice(type_.byte_range()).to_string());
+                decl.ast_fields.namespace = dtype.namespace;
+            }
+        }
+        let arguments = info.node.child_by_field_name("arguments").or(info.node.child_by_field_name("body"));
+        if let Some(arguments) = arguments {
+            symbols.extend(self.find_error_usages(&arguments, code, &info.ast_fields.file_path, &info.parent_guid));
+            let mut new_ast_fields = info.ast_fields.clone();
+            new_ast_fields.caller_guid = None;
+            for i in 0..arguments.child_count() {
+                let child = arguments.child(i).unwrap();
+                candidates.push_back(CandidateInfo {
+                    ast_fields: new_ast_fields.clone(),
+                    node: child,
+                    parent_guid: info.parent_guid.clone(),
+                });
+            }
+        }
+        if let Some(object) = object {
+            candidates.push_back(CandidateInfo {
+                ast_fields: decl.ast_fields.clone(),
+                node: object,
+                parent_guid: info.parent_guid.clone(),
+            });
+        }
+
+        symbols.push(Arc::new(RwLock::new(Box::new(decl))));
+        symbols
+    }
+
+    fn parse_(&mut self, parent: &Node, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
+        let mut symbols: Vec<AstSymbolInstanceArc> = Default::default();
+        let mut ast_fields = AstSymbolFields::default();
+        ast_fields.file_path = path.clone();
+        ast_fields.is_error = false;
+        ast_fields.language = LanguageId::Go;
+        self.receivers.clear();
+
+        let mut candidates = VecDeque::from(vec![CandidateInfo {
+            ast_fields,
+            node: parent.clone(),
+            parent_guid: get_guid(),
+        }]);
+        while let Some(candidate) = candidates.pop_front() {
+            let symbols_l = self.parse_usages_(&candidate, code, &mut candidates);
+            symbols.extend(symbols_l);
+        }
+
+        let struct_guids = symbols.iter()
+            .filter(|s| s.read().as_any().downcast_ref::<StructDeclaration>().is_some())
+            .map(|s| (s.read().name().to_string(), s.read().guid().clone()))
+            .collect::<HashMap<_, _>>();
+        for symbol in symbols.iter_mut() {
+            let guid = symbol.read().guid().clone();
+            if let Some(struct_guid) = self.receivers.get(&guid).and_then(|type_name| struct_guids.get(type_name)) {
+                symbol.write().fields_mut().parent_guid = Some(struct_guid.clone());
+            }
+        }
+
+        let guid_to_symbol_map = symbols.iter()
+            .map(|s| (s.clone().read().guid().clone(), s.clone())).collect::<HashMap<_, _>>();
+        for symbol in symbols.iter_mut() {
+            let guid = symbol.read().guid().clone();
+            if let Some(parent_guid) = symbol.read().parent_guid() {
+                if let Some(parent) = guid_to_symbol_map.get(parent_guid) {
+                    parent.write().fields_mut().childs_guid.push(guid);
+                }
+            }
+        }
+
+        #[cfg(test)]
+        for symbol in symbols.iter_mut() {
+            let mut sym = symbol.write();
+            sym.fields_mut().childs_guid = sym.fields_mut().childs_guid.iter()
+                .sorted_by_key(|x| {
+                    guid_to_symbol_map.get(*x).unwrap().read().full_range().start_byte
+                }).map(|x| x.clone()).collect();
+        }
+
+        symbols
+    }
+}
+
+impl AstLanguageParser for GoParser {
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree) {
+        let tree = self.parser.parse(code, old_tree).unwrap();
+        let symbols = self.parse_(&tree.root_node(), code, path);
+        (symbols, tree)
+    }
+}
@@ -8,7 +8,7 @@ use itertools::Itertools;
 
 use parking_lot::RwLock;
 use similar::DiffableStr;
-use tree_sitter::{Node, Parser, Range};
+use tree_sitter::{Node, Parser, Range, Tree};
 use tree_sitter_java::language;
 use uuid::Uuid;
 
@@ -794,9 +794,9 @@ impl JavaParser {
 }
 
 impl AstLanguageParser for JavaParser {
-    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
-        let tree = self.parser.parse(code, None).unwrap();
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree) {
+        let tree = self.parser.parse(code, old_tree).unwrap();
         let symbols = self.parse_(&tree.root_node(), code, path);
-        symbols
+        (symbols, tree)
     }
 }
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 
 use similar::DiffableStr;
-use tree_sitter::{Node, Parser, Range};
+use tree_sitter::{Node, Parser, Range, Tree};
 use tree_sitter_javascript::language;
 use uuid::Uuid;
 
@@ -788,10 +788,10 @@ impl JSParser {
 }
 
 impl AstLanguageParser for JSParser {
-    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
-        let tree = self.parser.parse(code, None).unwrap();
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree) {
+        let tree = self.parser.parse(code, old_tree).unwrap();
         let symbols = self.parse_(&tree.root_node(), code, path);
-        symbols
+        (symbols, tree)
     }
 }
 
@@ -7,7 +7,7 @@ use std::sync::Arc;
 use itertools::Itertools;
 use parking_lot::RwLock;
 use similar::DiffableStr;
-use tree_sitter::{Node, Parser, Point, Range};
+use tree_sitter::{Node, Parser, Point, Range, Tree};
 use tree_sitter_python::language;
 use uuid::Uuid;
 
@@ -18,7 +18,7 @@ use crate::ast::treesitter::parsers::utils::{CandidateInfo, get_children_guids,
 use crate::ast::treesitter::skeletonizer::SkeletonFormatter;
 use crate::ast::treesitter::structs::SymbolType;
 
-static PYTHON_MODULES: [&str; 203] = [
+pub(crate) static PYTHON_MODULES: [&str; 203] = [
     "abc", "aifc", "argparse", "array", "asynchat", "asyncio", "asyncore", "atexit", "audioop",
     "base64", "bdb", "binascii", "binhex", "bisect", "builtins", "bz2", "calendar", "cgi", "cgitb",
     "chunk", "cmath", "cmd", "code", "codecs", "codeop", "collections", "colorsys", "compileall",
@@ -942,9 +942,9 @@ impl SkeletonFormatter for PythonSkeletonFormatter {
 }
 
 impl AstLanguageParser for PythonParser {
-    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
-        let tree = self.parser.parse(code, None).unwrap();
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree) {
+        let tree = self.parser.parse(code, old_tree).unwrap();
         let symbols = self.parse_(&tree.root_node(), code, path);
-        symbols
+        (symbols, tree)
     }
 }
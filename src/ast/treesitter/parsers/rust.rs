@@ -4,7 +4,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 
 use similar::DiffableStr;
-use tree_sitter::{Node, Parser, Point, Range};
+use tree_sitter::{Node, Parser, Point, Range, Tree};
 use tree_sitter_rust::language;
 use uuid::Uuid;
 
@@ -1004,10 +1004,10 @@ impl RustParser {
 }
 
 impl AstLanguageParser for RustParser {
-    fn parse(&mut self, code: &str, path: &PathBuf) -> Vec<AstSymbolInstanceArc> {
-        let tree = self.parser.parse(code, None).unwrap();
+    fn parse_with_old_tree(&mut self, code: &str, path: &PathBuf, old_tree: Option<&Tree>) -> (Vec<AstSymbolInstanceArc>, Tree) {
+        let tree = self.parser.parse(code, old_tree).unwrap();
         let parent_guid = get_guid();
         let symbols = self.parse_block(&tree.root_node(), code, path, &parent_guid, false);
-        symbols
+        (symbols, tree)
     }
 }
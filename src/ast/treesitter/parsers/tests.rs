@@ -22,6 +22,7 @@ mod java;
 mod cpp;
 mod ts;
 mod js;
+mod go;
 
 pub(crate) fn print(symbols: &Vec<AstSymbolInstanceArc>, code: &str) {
     let guid_to_symbol_map = symbols.iter()
@@ -280,3 +281,18 @@ pub(crate) fn base_declaration_formatter_test(lang: &LanguageId,
     let ref_decls: HashSet<Decl> = HashSet::from_iter(ref_decls.iter().cloned());
     assert_eq!(decls, ref_decls);
 }
+
+#[test]
+fn test_ast_languages_allow_list() {
+    use crate::ast::treesitter::parsers::{ast_languages_from_cmdline, is_ast_language_allowed};
+    let only_python = ast_languages_from_cmdline(&vec!["Python".to_string()]).unwrap();
+    assert_eq!(only_python, vec![LanguageId::Python]);
+    assert!(ast_languages_from_cmdline(&vec!["frogscript".to_string()]).is_err());
+    let frog_py = PathBuf::from("tests").join("emergency_frog_situation").join("frog.py");
+    let frog_java = PathBuf::from("tests").join("emergency_frog_situation").join("Frog.java");
+
+    assert!(is_ast_language_allowed(&frog_py, &only_python).is_ok());
+    let err = is_ast_language_allowed(&frog_java, &only_python).unwrap_err();
+    assert!(err.contains("--ast-languages"), "{}", err);
+    assert!(is_ast_language_allowed(&frog_java, &vec![]).is_ok());
+}
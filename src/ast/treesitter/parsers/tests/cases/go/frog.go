@@ -0,0 +1,25 @@
+package pond
+
+import "strings"
+
+// Frog lives in the pond and catches insects
+type Frog struct {
+	Name string
+	X    float64
+	Y    float64
+}
+
+// NewFrog makes a frog sitting at the origin
+func NewFrog(name string) *Frog {
+	return &Frog{Name: name}
+}
+
+// Jump moves the frog by dx, dy
+func (f *Frog) Jump(dx float64, dy float64) {
+	f.X += dx
+	f.Y += dy
+}
+
+func (f *Frog) Croak(times int) string {
+	return strings.Repeat("ribbit ", times)
+}
@@ -0,0 +1,42 @@
+package main
+
+import (
+	"fmt"
+	pond "github.com/frogs/pond"
+)
+
+const MaxJump = 10
+
+var (
+	swamp, marsh = "swamp", "marsh"
+)
+
+type Meters float64
+
+type Croaker interface {
+	Croak(times int) string
+}
+
+type Frog struct {
+	Name   string
+	Height Meters
+	pond.Habitat
+}
+
+func NewFrog(name string) *Frog {
+	return &Frog{Name: name}
+}
+
+func (f *Frog) Jump(height Meters) (Meters, error) {
+	if height > MaxJump {
+		return 0, fmt.Errorf("too high")
+	}
+	f.Height = height
+	return f.Height, nil
+}
+
+func main() {
+	frog := NewFrog("Kermit")
+	height, _ := frog.Jump(3)
+	fmt.Println(swamp, marsh, height)
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::canonicalize;
+    use std::path::PathBuf;
+
+    use crate::ast::treesitter::ast_instance_structs::{AstSymbolInstanceArc, ImportDeclaration, ImportType};
+    use crate::ast::treesitter::language_id::LanguageId;
+    use crate::ast::treesitter::parsers::AstLanguageParser;
+    use crate::ast::treesitter::parsers::go::GoParser;
+    use crate::ast::treesitter::parsers::tests::{base_declaration_formatter_test, base_parser_test, base_skeletonizer_test};
+    use crate::ast::treesitter::structs::SymbolType;
+
+    const MAIN_GO_CODE: &str = include_str!("cases/go/main.go");
+    const MAIN_GO_SYMBOLS: &str = include_str!("cases/go/main.go.json");
+
+    const FROG_GO_CODE: &str = include_str!("cases/go/frog.go");
+    const FROG_GO_SKELETON: &str = include_str!("cases/go/frog.go.skeleton");
+    const FROG_GO_DECLS: &str = include_str!("cases/go/frog.go.decl_json");
+
+    fn find(symbols: &Vec<AstSymbolInstanceArc>, symbol_type: SymbolType, name: &str) -> AstSymbolInstanceArc {
+        let found = symbols.iter()
+            .filter(|s| s.read().symbol_type() == symbol_type && s.read().name() == name)
+            .collect::<Vec<_>>();
+        assert_eq!(found.len(), 1, "{} {}", symbol_type, name);
+        found[0].clone()
+    }
+
+    #[test]
+    fn parser_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(GoParser::new().expect("GoParser::new"));
+        let path = PathBuf::from("file:///main.go");
+        let symbols = parser.parse(MAIN_GO_CODE, &path);
+
+        let frog = find(&symbols, SymbolType::StructDeclaration, "Frog");
+        let frog_guid = frog.read().guid().clone();
+        assert_eq!(frog.read().full_range().start_point.row, 19);
+        assert_eq!(frog.read().full_range().end_point.row, 23);
+        assert_eq!(frog.read().declaration_range().end_point.row, 19);
+        assert_eq!(frog.read().definition_range().start_point.row, 19);
+        for field in ["Name", "Height", "Habitat"] {
+            let field = find(&symbols, SymbolType::ClassFieldDeclaration, field);
+            assert_eq!(field.read().parent_guid().clone(), Some(frog_guid.clone()));
+        }
+
+        // methods are declared outside of the struct but belong to it
+        let jump = find(&symbols, SymbolType::FunctionDeclaration, "Jump");
+        assert_eq!(jump.read().parent_guid().clone(), Some(frog_guid.clone()));
+        assert!(frog.read().childs_guid().contains(jump.read().guid()));
+        assert_eq!(jump.read().full_range().start_point.row, 29);
+        assert_eq!(jump.read().full_range().end_point.row, 35);
+        assert_eq!(jump.read().declaration_range().end_point.row, 29);
+        assert_eq!(jump.read().definition_range().start_point.row, 29);
+        assert_eq!(jump.read().definition_range().end_point.row, 35);
+
+        let new_frog = find(&symbols, SymbolType::FunctionDeclaration, "NewFrog");
+        assert_ne!(new_frog.read().parent_guid().clone(), Some(frog_guid.clone()));
+        assert_eq!(new_frog.read().full_range().start_point.row, 25);
+
+        let croaker = find(&symbols, SymbolType::StructDeclaration, "Croaker");
+        let croak = find(&symbols, SymbolType::FunctionDeclaration, "Croak");
+        assert_eq!(croak.read().parent_guid().clone(), Some(croaker.read().guid().clone()));
+        find(&symbols, SymbolType::TypeAlias, "Meters");
+
+        for var in ["MaxJump", "swamp", "marsh", "frog", "height"] {
+            find(&symbols, SymbolType::VariableDefinition, var);
+        }
+        for call in ["NewFrog", "Jump", "Println", "Errorf", "Frog"] {
+            find(&symbols, SymbolType::FunctionCall, call);
+        }
+
+        let imports = symbols.iter()
+            .filter(|s| s.read().symbol_type() == SymbolType::ImportDeclaration)
+            .map(|s| {
+                let s = s.read();
+                let import = s.as_any().downcast_ref::<ImportDeclaration>().unwrap();
+                (import.path_components.join("/"), import.alias.clone(), import.import_type.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(imports.len(), 2);
+        assert!(imports.contains(&("fmt".to_string(), None, ImportType::System)));
+        assert!(imports.iter().any(|(path, alias, import_type)| path == "github.com/frogs/pond" && alias == &Some("pond".to_string()) && *import_type != ImportType::System));
+    }
+
+    #[test]
+    fn parser_fixture_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(GoParser::new().expect("GoParser::new"));
+        let path = PathBuf::from("file:///main.go");
+        base_parser_test(&mut parser, &path, MAIN_GO_CODE, MAIN_GO_SYMBOLS);
+    }
+
+    #[test]
+    fn skeletonizer_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(GoParser::new().expect("GoParser::new"));
+        let file = canonicalize(PathBuf::from(file!())).unwrap().parent().unwrap().join("cases/go/frog.go");
+        assert!(file.exists());
+
+        base_skeletonizer_test(&LanguageId::Go, &mut parser, &file, FROG_GO_CODE, FROG_GO_SKELETON);
+    }
+
+    #[test]
+    fn declaration_formatter_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(GoParser::new().expect("GoParser::new"));
+        let file = canonicalize(PathBuf::from(file!())).unwrap().parent().unwrap().join("cases/go/frog.go");
+        assert!(file.exists());
+        base_declaration_formatter_test(&LanguageId::Go, &mut parser, &file, FROG_GO_CODE, FROG_GO_DECLS);
+    }
+}
@@ -3,8 +3,9 @@ mod tests {
     use std::fs::canonicalize;
     use std::path::PathBuf;
 
+    use crate::ast::treesitter::ast_instance_structs::AstSymbolInstanceArc;
     use crate::ast::treesitter::language_id::LanguageId;
-    use crate::ast::treesitter::parsers::AstLanguageParser;
+    use crate::ast::treesitter::parsers::{text_delta_edit, AstLanguageParser};
     use crate::ast::treesitter::parsers::rust::RustParser;
     use crate::ast::treesitter::parsers::tests::{base_declaration_formatter_test, base_parser_test, base_skeletonizer_test};
 
@@ -22,6 +23,67 @@ mod tests {
         base_parser_test(&mut parser, &path, MAIN_RS_CODE, MAIN_RS_SYMBOLS);
     }
 
+    fn big_file_and_small_edit() -> (String, String) {
+        let code = (0..200).map(|i| format!("mod frog{} {{\n{}\n}}\n", i, MAIN_RS_CODE)).collect::<String>();
+        let middle = code.len() / 2;
+        let at = middle + code[middle..].find("fn ").unwrap() + 3;
+        let edited = format!("{}ribbit_{}", &code[..at], &code[at..]);
+        (code, edited)
+    }
+
+    #[test]
+    fn text_delta_edit_test() {
+        let edit = text_delta_edit("fn jump() {}\n// 🐸\nfn croak() {}\n", "fn jump() {}\n// 🐸🐸\nfn croak() {}\n");
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (20, 20, 24));
+        assert_eq!((edit.start_position.row, edit.start_position.column), (1, 7));
+        assert_eq!((edit.new_end_position.row, edit.new_end_position.column), (1, 11));
+        let edit = text_delta_edit("frog", "frog");
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (4, 4, 4));
+    }
+
+    #[test]
+    fn incremental_parser_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(RustParser::new().expect("RustParser::new"));
+        let path = PathBuf::from("file:///frogs.rs");
+        let (code, edited) = big_file_and_small_edit();
+
+        let (_, mut old_tree) = parser.parse_with_old_tree(&code, &path, None);
+        old_tree.edit(&text_delta_edit(&code, &edited));
+        let (symbols, tree) = parser.parse_with_old_tree(&edited, &path, Some(&old_tree));
+        let (full_symbols, full_tree) = parser.parse_with_old_tree(&edited, &path, None);
+
+        assert_eq!(tree.root_node().to_sexp(), full_tree.root_node().to_sexp());
+        let names = |symbols: &Vec<AstSymbolInstanceArc>| symbols.iter()
+            .map(|s| (s.read().name().to_string(), s.read().full_range().start_byte))
+            .collect::<Vec<_>>();
+        assert_eq!(names(&symbols), names(&full_symbols));
+        assert!(symbols.iter().any(|s| s.read().name().starts_with("ribbit_")));
+    }
+
+    #[ignore]
+    #[test]
+    fn incremental_parser_speed_test() {
+        let mut parser: Box<dyn AstLanguageParser> = Box::new(RustParser::new().expect("RustParser::new"));
+        let path = PathBuf::from("file:///frogs.rs");
+        let (code, edited) = big_file_and_small_edit();
+        let (_, tree) = parser.parse_with_old_tree(&code, &path, None);
+
+        let t0 = std::time::Instant::now();
+        for _ in 0..10 {
+            parser.parse_with_old_tree(&edited, &path, None);
+        }
+        let full_time = t0.elapsed();
+        let t1 = std::time::Instant::now();
+        for _ in 0..10 {
+            let mut old_tree = tree.clone();
+            old_tree.edit(&text_delta_edit(&code, &edited));
+            parser.parse_with_old_tree(&edited, &path, Some(&old_tree));
+        }
+        let incremental_time = t1.elapsed();
+
+        assert!(incremental_time < full_time, "incremental {:?} full {:?}", incremental_time, full_time);
+    }
+
     #[test]
     fn skeletonizer_test() {
         let mut parser: Box<dyn AstLanguageParser> = Box::new(RustParser::new().expect("RustParser::new"));
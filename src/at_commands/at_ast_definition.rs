@@ -35,6 +35,21 @@ impl AtParamSymbolPathQuery {
 //     score
 // }
 
+const DEFINITIONS_PAGE_SIZE: usize = 20;
+
+// Optional second argument, a number of results to skip: `@definition MyClass 20` shows the second page
+pub fn offset_from_args(args: &Vec<AtCommandMember>) -> Option<(AtCommandMember, usize)> {
+    let arg = args.get(1)?;
+    arg.text.parse::<usize>().ok().map(|offset| (arg.clone(), offset))
+}
+
+pub fn next_page_note(cmd_name: &str, symbol: &str, offset: usize, shown: usize, total: usize) -> Option<String> {
+    if offset + shown >= total {
+        return None;
+    }
+    Some(format!("...and {} more, use `{} {} {}` for the next page", total - offset - shown, cmd_name, symbol, offset + shown))
+}
+
 pub struct AtAstDefinition {
     pub params: Vec<Arc<AMutex<dyn AtParam>>>,
 }
@@ -108,15 +123,24 @@ impl AtCommand for AtAstDefinition {
             },
         };
 
+        let offset_mb = offset_from_args(args);
         correct_at_arg(ccx.clone(), self.params[0].clone(), &mut arg_symbol).await;
         args.clear();
         args.push(arg_symbol.clone());
+        let offset = match offset_mb {
+            Some((arg_offset, offset)) => {
+                args.push(arg_offset);
+                offset
+            }
+            None => 0,
+        };
 
         let gcx = ccx.lock().await.global_context.clone();
         let ast_service_opt = gcx.read().await.ast_service.clone();
         if let Some(ast_service) = ast_service_opt {
             let ast_index = ast_service.lock().await.ast_index.clone();
-            let defs: Vec<Arc<crate::ast::ast_structs::AstDefinition>> = crate::ast::ast_db::definitions(ast_index, arg_symbol.text.as_str()).await;
+            let all_defs: Vec<Arc<crate::ast::ast_structs::AstDefinition>> = crate::ast::ast_db::definitions(ast_index, arg_symbol.text.as_str()).await;
+            let defs = all_defs.iter().skip(offset).take(DEFINITIONS_PAGE_SIZE).cloned().collect::<Vec<_>>();
             let file_paths = defs.iter().map(|x| x.cpath.clone()).collect::<Vec<_>>();
             let short_file_paths = crate::files_correction::shortify_paths(gcx.clone(), &file_paths).await;
 
@@ -126,9 +150,15 @@ impl AtCommand for AtAstDefinition {
                 } else {
                     format!("`{}` (defined in {})", &arg_symbol.text, path0)
                 }
+            } else if offset > 0 && !all_defs.is_empty() {
+                format!("`{}` has only {} definitions, nothing left after {}", &arg_symbol.text, all_defs.len(), offset)
             } else {
                 format!("`{}` (definition not found in the AST tree)", &arg_symbol.text)
             };
+            let text = match next_page_note("@definition", &arg_symbol.text, offset, defs.len(), all_defs.len()) {
+                Some(note) => format!("{}\n{}", text, note),
+                None => text,
+            };
 
             let mut result = vec![];
             for (res, cpath) in defs.iter().zip(file_paths.iter()) {
@@ -152,3 +182,23 @@ impl AtCommand for AtAstDefinition {
         vec!["ast".to_string()]
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_page_note() {
+        assert_eq!(next_page_note("@references", "Frog::jump", 0, 20, 20), None);
+        assert_eq!(
+            next_page_note("@references", "Frog::jump", 0, 20, 45),
+            Some("...and 25 more, use `@references Frog::jump 20` for the next page".to_string()),
+        );
+        assert_eq!(
+            next_page_note("@references", "Frog::jump", 20, 20, 45),
+            Some("...and 5 more, use `@references Frog::jump 40` for the next page".to_string()),
+        );
+        assert_eq!(next_page_note("@references", "Frog::jump", 40, 5, 45), None);
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex as AMutex;
+
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::call_validation::{ContextFile, ContextEnum};
+use crate::at_commands::execute_at::{AtCommandMember, correct_at_arg};
+use crate::at_commands::at_ast_definition::AtParamSymbolPathQuery;
+
+
+const IMPLEMENTATIONS_MAX_DEPTH: usize = 5;
+const IMPLEMENTATIONS_LIMIT: usize = 20;
+
+pub struct AtAstImplementations {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtAstImplementations {
+    pub fn new() -> Self {
+        AtAstImplementations {
+            params: vec![
+                Arc::new(AMutex::new(AtParamSymbolPathQuery::new()))
+            ],
+        }
+    }
+}
+
+
+#[async_trait]
+impl AtCommand for AtAstImplementations {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        let mut arg_symbol = match args.get(0) {
+            Some(x) => x.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("no symbol path".to_string());
+                args.clear();
+                return Err("no symbol path".to_string());
+            },
+        };
+
+        correct_at_arg(ccx.clone(), self.params[0].clone(), &mut arg_symbol).await;
+        args.clear();
+        args.push(arg_symbol.clone());
+
+        let gcx = ccx.lock().await.global_context.clone();
+        let ast_service_opt = gcx.read().await.ast_service.clone();
+
+        if let Some(ast_service) = ast_service_opt {
+            let ast_index = ast_service.lock().await.ast_index.clone();
+            let implementations = crate::ast::ast_db::implementations(ast_index.clone(), arg_symbol.text.as_str(), IMPLEMENTATIONS_MAX_DEPTH).await;
+            let mut messages = vec![format!("symbol `{}` has {} implementations", arg_symbol.text, implementations.len())];
+
+            let all_results = implementations.iter().take(IMPLEMENTATIONS_LIMIT).map(|(def, depth)| {
+                ContextFile {
+                    file_name: def.cpath.clone(),
+                    file_content: "".to_string(),
+                    line1: def.full_line1(),
+                    line2: def.full_line2(),
+                    symbols: vec![def.path_drop0()],
+                    gradient_type: -1,
+                    usefulness: 100.0 - 10.0 * (*depth as f32 - 1.0),
+                }
+            }).collect::<Vec<_>>();
+            if implementations.len() > IMPLEMENTATIONS_LIMIT {
+                messages.push(format!("...and {} more implementations", implementations.len() - IMPLEMENTATIONS_LIMIT));
+            }
+
+            Ok((all_results.into_iter().map(|x| ContextEnum::ContextFile(x)).collect::<Vec<ContextEnum>>(), messages.join("\n")))
+        } else {
+            Err("attempt to use @implementations with no ast turned on".to_string())
+        }
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec!["ast".to_string()]
+    }
+}
@@ -6,7 +6,7 @@ use tokio::sync::Mutex as AMutex;
 use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
 use crate::call_validation::{ContextFile, ContextEnum};
 use crate::at_commands::execute_at::{AtCommandMember, correct_at_arg};
-use crate::at_commands::at_ast_definition::AtParamSymbolPathQuery;
+use crate::at_commands::at_ast_definition::{AtParamSymbolPathQuery, next_page_note, offset_from_args};
 
 
 pub struct AtAstReference {
@@ -46,9 +46,17 @@ impl AtCommand for AtAstReference {
             },
         };
 
+        let offset_mb = offset_from_args(args);
         correct_at_arg(ccx.clone(), self.params[0].clone(), &mut arg_symbol).await;
         args.clear();
         args.push(arg_symbol.clone());
+        let offset = match offset_mb {
+            Some((arg_offset, offset)) => {
+                args.push(arg_offset);
+                offset
+            }
+            None => 0,
+        };
 
         let gcx = ccx.lock().await.global_context.clone();
         let ast_service_opt = gcx.read().await.ast_service.clone();
@@ -62,7 +70,7 @@ impl AtCommand for AtAstReference {
             const USAGES_LIMIT: usize = 20;
 
             if let Some(def) = defs.get(0) {
-                let usages: Vec<(Arc<crate::ast::ast_structs::AstDefinition>, usize)> = crate::ast::ast_db::usages(ast_index.clone(), def.path(), 100).await;
+                let usages: Vec<(Arc<crate::ast::ast_structs::AstDefinition>, usize)> = crate::ast::ast_db::usages(ast_index.clone(), def.path(), offset + 100).await;
                 let usage_count = usages.len();
 
                 let text = format!(
@@ -72,7 +80,8 @@ impl AtCommand for AtAstReference {
                 );
                 messages.push(text);
 
-                for (usedin, uline) in usages.iter().take(USAGES_LIMIT) {
+                let page = usages.iter().skip(offset).take(USAGES_LIMIT).collect::<Vec<_>>();
+                for (usedin, uline) in page.iter() {
                     all_results.push(ContextFile {
                         file_name: usedin.cpath.clone(),
                         file_content: "".to_string(),
@@ -83,8 +92,8 @@ impl AtCommand for AtAstReference {
                         usefulness: 100.0,
                     });
                 }
-                if usage_count > USAGES_LIMIT {
-                    messages.push(format!("...and {} more usages", usage_count - USAGES_LIMIT));
+                if let Some(note) = next_page_note("@references", &arg_symbol.text, offset, page.len(), usage_count) {
+                    messages.push(note);
                 }
             } else {
                 messages.push("No definitions found for the symbol".to_string());
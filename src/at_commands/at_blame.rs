@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex as AMutex;
+
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::at_commands::at_file::AtParamFilePath;
+use crate::at_commands::execute_at::AtCommandMember;
+use crate::call_validation::{ChatMessage, ContextEnum};
+use crate::files_correction::parse_file_line_ref;
+use crate::git::operations::{git_blame_lines, BlameLine};
+
+const BLAME_MAX_LINES: usize = 200;
+
+
+pub struct AtBlame {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtBlame {
+    pub fn new() -> Self {
+        AtBlame {
+            params: vec![
+                Arc::new(AMutex::new(AtParamFilePath::new()))
+            ],
+        }
+    }
+}
+
+fn format_blame(file_ref: &str, blame: &Vec<BlameLine>, truncated: bool) -> String {
+    let mut text = format!("git blame {}:\n", file_ref);
+    for b in blame.iter() {
+        text.push_str(&format!("{:>5} {} {} ({}) | {}\n", b.line_n, b.commit_short, b.author, b.summary, b.text));
+    }
+    if truncated {
+        text.push_str(&format!("...only the first {} lines are shown\n", BLAME_MAX_LINES));
+    }
+    text
+}
+
+#[async_trait]
+impl AtCommand for AtBlame {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        let arg0 = match args.iter().filter(|x| !x.text.trim().is_empty()).next() {
+            Some(x) => x.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("no file provided".to_string());
+                args.clear();
+                return Err("Cannot execute @blame: no file provided".to_string());
+            }
+        };
+        args.clear();
+        args.push(arg0.clone());
+
+        let gcx = ccx.lock().await.global_context.clone();
+        let (path, range) = parse_file_line_ref(&arg0.text, gcx.clone()).await.map_err(|e| {
+            cmd.ok = false;
+            cmd.reason = Some(e.clone());
+            format!("Cannot execute @blame: {}", e)
+        })?;
+        let (line1, line2) = range.unwrap_or((1, usize::MAX));
+        let truncated = line2.saturating_sub(line1) >= BLAME_MAX_LINES;
+        let line2 = line2.min(line1 + BLAME_MAX_LINES - 1);
+
+        let text = match tokio::task::spawn_blocking(move || git_blame_lines(&path, line1, line2)).await {
+            Ok(Ok(blame)) => format_blame(&arg0.text, &blame, truncated && blame.len() == BLAME_MAX_LINES),
+            Ok(Err(e)) => format!("No blame for {}: {}", arg0.text, e),
+            Err(e) => format!("No blame for {}: {}", arg0.text, e),
+        };
+        Ok((vec![ContextEnum::ChatMessage(ChatMessage::new("plain_text".to_string(), text))], "".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository;
+    use crate::git::commit_file_for_tests;
+
+    #[test]
+    fn test_blame_names_committing_author() {
+        let tmp = tempfile::Builder::new().prefix("frog_blame").tempdir().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let file_path = tmp.path().join("frog.py");
+        commit_file_for_tests(&repo, "frog.py", "def jump():\n    print(\"jump\")\n\ndef croak():\n    print(\"croak\")\n", "Teach the frog to croak");
+
+        let blame = git_blame_lines(&file_path, 4, 5).unwrap();
+        let text = format_blame("frog.py:4-5", &blame, false);
+
+        assert_eq!(blame.iter().map(|b| b.line_n).collect::<Vec<_>>(), vec![4, 5]);
+        assert!(blame.iter().all(|b| b.author == "Frog Author" && b.summary == "Teach the frog to croak"), "{}", text);
+        assert!(text.contains("print(\"croak\")"), "{}", text);
+
+        let outside = tempfile::Builder::new().prefix("frog_no_git").tempdir().unwrap();
+        std::fs::write(outside.path().join("toad.py"), "print(\"ribbit\")\n").unwrap();
+        assert!(git_blame_lines(&outside.path().join("toad.py"), 1, 1).is_err());
+    }
+}
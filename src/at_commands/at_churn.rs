@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex as AMutex;
+
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::at_commands::at_file::AtParamFilePath;
+use crate::at_commands::execute_at::AtCommandMember;
+use crate::call_validation::{ChatMessage, ContextEnum};
+use crate::files_correction::parse_file_line_ref;
+use crate::git::churn::symbol_churn;
+
+const CHURN_MAX_SYMBOLS: usize = 30;
+
+
+pub struct AtChurn {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtChurn {
+    pub fn new() -> Self {
+        AtChurn {
+            params: vec![
+                Arc::new(AMutex::new(AtParamFilePath::new()))
+            ],
+        }
+    }
+}
+
+fn format_churn(file_ref: &str, churn: &Vec<(String, usize)>) -> String {
+    if churn.is_empty() {
+        return format!("No symbol of {} was changed in git history\n", file_ref);
+    }
+    let mut text = format!("commits that touched each symbol of {}:\n", file_ref);
+    for (path, commits_n) in churn.iter().take(CHURN_MAX_SYMBOLS) {
+        text.push_str(&format!("{:>5} {}\n", commits_n, path));
+    }
+    if churn.len() > CHURN_MAX_SYMBOLS {
+        text.push_str(&format!("...and {} less changed symbols\n", churn.len() - CHURN_MAX_SYMBOLS));
+    }
+    text
+}
+
+#[async_trait]
+impl AtCommand for AtChurn {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        let arg0 = match args.iter().filter(|x| !x.text.trim().is_empty()).next() {
+            Some(x) => x.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("no file provided".to_string());
+                args.clear();
+                return Err("Cannot execute @churn: no file provided".to_string());
+            }
+        };
+        args.clear();
+        args.push(arg0.clone());
+
+        let gcx = ccx.lock().await.global_context.clone();
+        let (path, _) = parse_file_line_ref(&arg0.text, gcx.clone()).await.map_err(|e| {
+            cmd.ok = false;
+            cmd.reason = Some(e.clone());
+            format!("Cannot execute @churn: {}", e)
+        })?;
+
+        let text = match symbol_churn(gcx.clone(), &path).await {
+            Ok(churn) => format_churn(&arg0.text, &churn),
+            Err(e) => format!("No churn for {}: {}", arg0.text, e),
+        };
+        Ok((vec![ContextEnum::ChatMessage(ChatMessage::new("plain_text".to_string(), text))], "".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_churn_most_changed_first_and_truncated() {
+        let mut churn = vec![("Frog::jump".to_string(), 7), ("Frog::croak".to_string(), 2)];
+        assert_eq!(format_churn("frog.py", &churn), "commits that touched each symbol of frog.py:\n    7 Frog::jump\n    2 Frog::croak\n");
+
+        churn.extend((0..CHURN_MAX_SYMBOLS).map(|i| (format!("tadpole{}", i), 1)));
+        let text = format_churn("frog.py", &churn);
+        assert_eq!(text.lines().count(), CHURN_MAX_SYMBOLS + 2);
+        assert!(text.ends_with("...and 2 less changed symbols\n"), "{}", text);
+
+        assert_eq!(format_churn("toad.py", &vec![]), "No symbol of toad.py was changed in git history\n");
+    }
+}
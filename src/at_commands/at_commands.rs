@@ -13,7 +13,13 @@ use crate::global_context::GlobalContext;
 use crate::at_commands::at_file::AtFile;
 use crate::at_commands::at_ast_definition::AtAstDefinition;
 use crate::at_commands::at_ast_reference::AtAstReference;
+use crate::at_commands::at_ast_implementations::AtAstImplementations;
 use crate::at_commands::at_tree::AtTree;
+use crate::at_commands::at_test_files::AtTestFiles;
+use crate::at_commands::at_blame::AtBlame;
+use crate::at_commands::at_churn::AtChurn;
+use crate::at_commands::at_grep::AtGrep;
+use crate::at_commands::at_explain_symbol::AtExplainSymbol;
 use crate::at_commands::at_web::AtWeb;
 use crate::at_commands::execute_at::AtCommandMember;
 
@@ -95,8 +101,14 @@ pub async fn at_commands_dict(gcx: Arc<ARwLock<GlobalContext>>) -> HashMap<Strin
         // ("@file-search".to_string(), Arc::new(AMutex::new(Box::new(AtFileSearch::new()) as Box<dyn AtCommand + Send>))),
         ("@definition".to_string(), Arc::new(AMutex::new(Box::new(AtAstDefinition::new()) as Box<dyn AtCommand + Send>))),
         ("@references".to_string(), Arc::new(AMutex::new(Box::new(AtAstReference::new()) as Box<dyn AtCommand + Send>))),
+        ("@implementations".to_string(), Arc::new(AMutex::new(Box::new(AtAstImplementations::new()) as Box<dyn AtCommand + Send>))),
+        ("@explain-symbol".to_string(), Arc::new(AMutex::new(Box::new(AtExplainSymbol::new()) as Box<dyn AtCommand + Send>))),
         // ("@local-notes-to-self".to_string(), Arc::new(AMutex::new(Box::new(AtLocalNotesToSelf::new()) as Box<dyn AtCommand + Send>))),
         ("@tree".to_string(), Arc::new(AMutex::new(Box::new(AtTree::new()) as Box<dyn AtCommand + Send>))),
+        ("@test-files".to_string(), Arc::new(AMutex::new(Box::new(AtTestFiles::new()) as Box<dyn AtCommand + Send>))),
+        ("@blame".to_string(), Arc::new(AMutex::new(Box::new(AtBlame::new()) as Box<dyn AtCommand + Send>))),
+        ("@churn".to_string(), Arc::new(AMutex::new(Box::new(AtChurn::new()) as Box<dyn AtCommand + Send>))),
+        ("@grep".to_string(), Arc::new(AMutex::new(Box::new(AtGrep::new()) as Box<dyn AtCommand + Send>))),
         // ("@diff".to_string(), Arc::new(AMutex::new(Box::new(AtDiff::new()) as Box<dyn AtCommand + Send>))),
         // ("@diff-rev".to_string(), Arc::new(AMutex::new(Box::new(AtDiffRev::new()) as Box<dyn AtCommand + Send>))),
         ("@web".to_string(), Arc::new(AMutex::new(Box::new(AtWeb::new()) as Box<dyn AtCommand + Send>))),
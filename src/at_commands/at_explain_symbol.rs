@@ -0,0 +1,297 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex as AMutex;
+
+use tokio::sync::RwLock as ARwLock;
+
+use crate::ast::ast_structs::AstDB;
+use crate::ast::treesitter::language_id::LanguageId;
+use crate::ast::treesitter::parsers::get_language_id_by_filename;
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::at_commands::at_ast_definition::AtParamSymbolPathQuery;
+use crate::at_commands::execute_at::{AtCommandMember, correct_at_arg};
+use crate::call_validation::{ChatMessage, ContextEnum};
+use crate::files_in_workspace::get_file_text_from_memory_or_disk;
+use crate::global_context::GlobalContext;
+
+const EXPLAIN_MAX_DEFINITION_LINES: usize = 60;
+const EXPLAIN_MAX_REFERENCES: usize = 10;
+
+
+pub struct AtExplainSymbol {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtExplainSymbol {
+    pub fn new() -> Self {
+        AtExplainSymbol {
+            params: vec![
+                Arc::new(AMutex::new(AtParamSymbolPathQuery::new()))
+            ],
+        }
+    }
+}
+
+// `#` starts a comment in python, but it's `#include` in C++, so only the markers of the file's language count
+fn comment_markers(language: Option<LanguageId>) -> &'static [&'static str] {
+    match language {
+        Some(LanguageId::Python) | Some(LanguageId::Bash) | Some(LanguageId::Ruby) | Some(LanguageId::R) => &["#"],
+        Some(LanguageId::Lua) | Some(LanguageId::Sql) => &["--"],
+        Some(LanguageId::Php) => &["///", "//", "#", "/**", "*/", "*"],
+        Some(_) => &["///", "//!", "//", "/**", "*/", "*"],
+        None => &["///", "//!", "//", "#", "/**", "*/", "*", "--"],
+    }
+}
+
+fn strip_comment_marker<'a>(line: &'a str, markers: &[&str]) -> Option<&'a str> {
+    let line = line.trim();
+    for marker in markers {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return Some(rest.trim_end_matches("*/").trim());
+        }
+    }
+    None
+}
+
+// Comment lines right above the declaration, or a python-style string literal right after it. Lines are 1-based.
+fn extract_docstring(lines: &Vec<&str>, decl_line1: usize, decl_line2: usize, language: Option<LanguageId>) -> String {
+    let markers = comment_markers(language);
+    let mut above = vec![];
+    for line in lines.iter().take(decl_line1.saturating_sub(1)).rev() {
+        if line.trim().starts_with("#[") || line.trim().starts_with("@") {
+            continue;  // attributes and decorators sit between the comment and the declaration
+        }
+        match strip_comment_marker(line, markers) {
+            Some(text) => above.push(text),
+            None => break,
+        }
+    }
+    above.reverse();
+    let above = above.join("\n").trim().to_string();
+    if !above.is_empty() {
+        return above;
+    }
+
+    let mut below = vec![];
+    let mut quote: Option<&str> = None;
+    for line in lines.iter().skip(decl_line2) {
+        let trimmed = line.trim();
+        match quote {
+            None => {
+                quote = ["\"\"\"", "'''"].into_iter().find(|q| trimmed.starts_with(*q));
+                let q = match quote {
+                    Some(q) => q,
+                    None => break,
+                };
+                let rest = &trimmed[q.len()..];
+                if let Some(end) = rest.find(q) {
+                    below.push(&rest[..end]);
+                    break;
+                }
+                below.push(rest);
+            }
+            Some(q) => {
+                if let Some(end) = trimmed.find(q) {
+                    below.push(&trimmed[..end]);
+                    break;
+                }
+                below.push(trimmed);
+            }
+        }
+    }
+    below.join("\n").trim().to_string()
+}
+
+fn format_explanation(
+    symbol: &str,
+    definition_file: &str,
+    definition_lines: (usize, usize),
+    definition_text: &str,
+    docstring: &str,
+    references: &Vec<(String, usize, String)>,
+    references_total: usize,
+) -> String {
+    let mut out = format!("# Symbol `{}`\n\n", symbol);
+    out.push_str(&format!("## Definition\n{}:{}-{}\n```\n{}\n```\n\n", definition_file, definition_lines.0, definition_lines.1, definition_text.trim_end()));
+    out.push_str("## Docstring\n");
+    if docstring.is_empty() {
+        out.push_str("(no docstring)\n\n");
+    } else {
+        out.push_str(&format!("{}\n\n", docstring));
+    }
+    out.push_str(&format!("## References ({} total)\n", references_total));
+    if references.is_empty() {
+        out.push_str("(no references found)\n");
+    }
+    for (file_name, line, code) in references.iter() {
+        out.push_str(&format!("{}:{}  {}\n", file_name, line, code.trim()));
+    }
+    if references_total > references.len() {
+        out.push_str(&format!("...and {} more\n", references_total - references.len()));
+    }
+    out
+}
+
+async fn explain_symbol(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    ast_index: Arc<AMutex<AstDB>>,
+    symbol: &str,
+) -> Result<(Vec<ContextEnum>, String), String> {
+    let defs = crate::ast::ast_db::definitions(ast_index.clone(), symbol).await;
+
+    let def = match defs.len() {
+        0 => {
+            return Ok((vec![], format!("`{}` (definition not found in the AST tree)", symbol)));
+        }
+        1 => defs[0].clone(),
+        _ => {
+            let file_paths = defs.iter().map(|x| x.cpath.clone()).collect::<Vec<_>>();
+            let short_file_paths = crate::files_correction::shortify_paths(gcx.clone(), &file_paths).await;
+            let mut text = format!("`{}` is ambiguous, use one of the full names:\n", symbol);
+            for (d, short_path) in defs.iter().zip(short_file_paths.iter()) {
+                text.push_str(&format!("{} ({}:{})\n", d.path_drop0(), short_path, d.full_line1()));
+            }
+            return Ok((vec![ContextEnum::ChatMessage(ChatMessage::new("plain_text".to_string(), text))], "".to_string()));
+        }
+    };
+
+    let def_path = PathBuf::from(&def.cpath);
+    let text = get_file_text_from_memory_or_disk(gcx.clone(), &def_path).await?;
+    let lines: Vec<&str> = text.lines().collect();
+    // long bodies don't help, the signature is enough
+    let (line1, line2) = if def.full_line2() + 1 - def.full_line1() > EXPLAIN_MAX_DEFINITION_LINES {
+        (def.decl_line1, def.decl_line2)
+    } else {
+        (def.full_line1(), def.full_line2())
+    };
+    let definition_text = lines.iter().skip(line1 - 1).take(line2 + 1 - line1).cloned().collect::<Vec<_>>().join("\n");
+    let docstring = extract_docstring(&lines, def.decl_line1, def.decl_line2, get_language_id_by_filename(&def_path));
+
+    let usages = crate::ast::ast_db::usages(ast_index.clone(), def.path(), 100).await;
+    let usage_paths = usages.iter().take(EXPLAIN_MAX_REFERENCES).map(|(usedin, _)| usedin.cpath.clone()).collect::<Vec<_>>();
+    let short_usage_paths = crate::files_correction::shortify_paths(gcx.clone(), &usage_paths).await;
+    let mut references = vec![];
+    for ((usedin, uline), short_path) in usages.iter().zip(short_usage_paths.into_iter()) {
+        let code = get_file_text_from_memory_or_disk(gcx.clone(), &PathBuf::from(&usedin.cpath)).await
+            .ok()
+            .and_then(|t| t.lines().nth(uline.saturating_sub(1)).map(|l| l.to_string()))
+            .unwrap_or_default();
+        references.push((short_path, *uline, code));
+    }
+
+    let short_def_path = crate::files_correction::shortify_paths(gcx.clone(), &vec![def.cpath.clone()]).await
+        .pop().unwrap_or(def.cpath.clone());
+    let explanation = format_explanation(&def.path_drop0(), &short_def_path, (line1, line2), &definition_text, &docstring, &references, usages.len());
+    Ok((vec![ContextEnum::ChatMessage(ChatMessage::new("plain_text".to_string(), explanation))], "".to_string()))
+}
+
+#[async_trait]
+impl AtCommand for AtExplainSymbol {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        let mut arg_symbol = match args.get(0) {
+            Some(x) => x.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("parameter is missing".to_string());
+                args.clear();
+                return Err("parameter `symbol` is missing".to_string());
+            },
+        };
+
+        correct_at_arg(ccx.clone(), self.params[0].clone(), &mut arg_symbol).await;
+        args.clear();
+        args.push(arg_symbol.clone());
+
+        let gcx = ccx.lock().await.global_context.clone();
+        let ast_service = gcx.read().await.ast_service.clone()
+            .ok_or("attempt to use @explain-symbol with no ast turned on".to_string())?;
+        let ast_index = ast_service.lock().await.ast_index.clone();
+        explain_symbol(gcx.clone(), ast_index, &arg_symbol.text).await
+    }
+
+    fn depends_on(&self) -> Vec<String> {
+        vec!["ast".to_string()]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explanation_has_definition_docstring_and_references() {
+        let text = "import pond\n\n# Makes the frog jump.\n# Returns the height.\ndef jump(frog):\n    return frog.legs * 2\n";
+        let lines: Vec<&str> = text.lines().collect();
+        let docstring = extract_docstring(&lines, 5, 5, Some(LanguageId::Python));
+        assert_eq!(docstring, "Makes the frog jump.\nReturns the height.");
+
+        let cpp_text = "#include \"pond.h\"\nint jump(Frog frog);\n";
+        let cpp_lines: Vec<&str> = cpp_text.lines().collect();
+        assert_eq!(extract_docstring(&cpp_lines, 2, 2, Some(LanguageId::Cpp)), "");
+
+        let python_text = "def croak():\n    \"\"\"Say something\n    froggy.\"\"\"\n    print(\"croak\")\n";
+        let python_lines: Vec<&str> = python_text.lines().collect();
+        assert_eq!(extract_docstring(&python_lines, 1, 1, Some(LanguageId::Python)), "Say something\nfroggy.");
+
+        let definition_text = lines[4..6].join("\n");
+        let references = vec![
+            ("pond.py".to_string(), 12, "    h = jump(frog)".to_string()),
+            ("tests/test_frog.py".to_string(), 3, "assert jump(frog) == 8".to_string()),
+        ];
+        let explanation = format_explanation("frog::jump", "frog.py", (5, 6), &definition_text, &docstring, &references, 3);
+
+        assert!(explanation.contains("## Definition\nfrog.py:5-6\n```\ndef jump(frog):\n    return frog.legs * 2\n```"));
+        assert!(explanation.contains("## Docstring\nMakes the frog jump.\nReturns the height."));
+        assert!(explanation.contains("## References (3 total)\npond.py:12  h = jump(frog)\ntests/test_frog.py:3  assert jump(frog) == 8\n...and 1 more\n"));
+    }
+
+    #[tokio::test]
+    async fn test_explain_symbol_from_indexed_files() {
+        use crate::ast::ast_db::{ast_index_init, connect_usages, connect_usages_look_if_full_reset_needed, doc_add, flush_sled_batch};
+        use crate::ast::ast_structs::AstErrorStats;
+
+        let workspace = tempfile::Builder::new().prefix("frog_explain").tempdir().unwrap();
+        let frog_path = workspace.path().join("frog.py");
+        let pond_path = workspace.path().join("pond.py");
+        std::fs::write(&frog_path, "# Makes the frog jump.\n# Returns the height.\ndef jump(height):\n    return height * 2\n").unwrap();
+        std::fs::write(&pond_path, "from frog import jump\n\ndef hop():\n    return jump(3)\n").unwrap();
+        let gcx_dir = tempfile::Builder::new().prefix("frog_explain_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(gcx_dir.path(), vec![frog_path.clone(), pond_path.clone()]).await;
+
+        let ast_index = ast_index_init("".to_string(), 10, false).await;
+        let mut errstats = AstErrorStats::default();
+        for path in [&frog_path, &pond_path] {
+            let cpath = path.to_string_lossy().to_string();
+            let text = std::fs::read_to_string(path).unwrap();
+            doc_add(ast_index.clone(), &cpath, &text, &mut errstats).await.unwrap();
+        }
+        let mut ucx = connect_usages_look_if_full_reset_needed(ast_index.clone()).await;
+        while connect_usages(ast_index.clone(), &mut ucx).await {}
+        flush_sled_batch(ast_index.clone(), 0).await;
+
+        let (messages, _) = explain_symbol(gcx.clone(), ast_index.clone(), "frog::jump").await.unwrap();
+        let explanation = match messages.first() {
+            Some(ContextEnum::ChatMessage(msg)) => msg.content.content_text_only(),
+            _ => panic!("expected a plain text message"),
+        };
+        assert!(explanation.contains("```\ndef jump(height):\n    return height * 2\n```"), "{}", explanation);
+        assert!(explanation.contains("## Docstring\nMakes the frog jump.\nReturns the height.\n"), "{}", explanation);
+        assert!(explanation.contains("pond.py:4  return jump(3)"), "{}", explanation);
+
+        let (messages, text) = explain_symbol(gcx.clone(), ast_index.clone(), "frog::croak").await.unwrap();
+        assert!(messages.is_empty());
+        assert!(text.contains("definition not found"));
+    }
+}
@@ -95,6 +95,15 @@ fn gradient_type_from_range_kind(range: &Option<ColonLinesRange>) -> i32 {
     }
 }
 
+// Line numbers past the end of the file point to the last line, an inverted range is an error
+fn clamp_colon_range(colon: &ColonLinesRange, lines_cnt: usize) -> Result<(usize, usize), String> {
+    if colon.kind == RangeKind::Range && colon.line1 > colon.line2 {
+        return Err(format!("inverted line range {}, the first line should not be after the last one", range_print(colon)));
+    }
+    let clamp = |line: usize| if line == 0 { 0 } else { line.min(lines_cnt.max(1)) };
+    Ok((clamp(colon.line1), clamp(colon.line2)))
+}
+
 fn put_colon_back_to_arg(value: &mut String, colon: &Option<ColonLinesRange>) {
     if let Some(colon) = colon {
         value.push_str(":");
@@ -249,8 +258,8 @@ pub async fn context_file_from_file_path(
     let file_content = get_file_text_from_memory_or_disk(gcx.clone(), &PathBuf::from(&file_path_no_colon)).await?;
 
     if let Some(colon) = &colon_kind_mb {
-        line1 = colon.line1;
-        line2 = colon.line2;
+        (line1, line2) = clamp_colon_range(colon, file_content.lines().count())
+            .map_err(|e| format!("{}: {}", file_path_no_colon, e))?;
     }
     if line1 == 0 && line2 == 0 {
         line2 = file_content.lines().count();
@@ -360,4 +369,15 @@ mod tests {
             assert_eq!(result, None);
         }
     }
+
+    #[test]
+    fn test_file_range_clamped_to_file() {
+        let range = |kind, line1, line2| ColonLinesRange { kind, line1, line2 };
+        assert_eq!(clamp_colon_range(&range(RangeKind::Range, 10, 20), 100), Ok((10, 20)));
+        assert_eq!(clamp_colon_range(&range(RangeKind::Range, 90, 120), 100), Ok((90, 100)));
+        assert_eq!(clamp_colon_range(&range(RangeKind::Range, 150, 170), 100), Ok((100, 100)));
+        assert_eq!(clamp_colon_range(&range(RangeKind::GradToCursorTwoSided, 250, 0), 100), Ok((100, 0)));
+        assert_eq!(clamp_colon_range(&range(RangeKind::GradToCursorPrefix, 0, 15), 100), Ok((0, 15)));
+        assert!(clamp_colon_range(&range(RangeKind::Range, 20, 10), 100).is_err());
+    }
 }
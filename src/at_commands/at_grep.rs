@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use regex::Regex;
+use tokio::sync::Mutex as AMutex;
+
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::at_commands::at_file::return_one_candidate_or_a_good_error;
+use crate::at_commands::execute_at::AtCommandMember;
+use crate::call_validation::{ContextEnum, ContextFile};
+use crate::files_correction::{correct_to_nearest_dir_path, get_project_dirs};
+use crate::files_in_workspace::get_file_text_from_memory_or_disk;
+
+const GREP_CONTEXT_LINES: usize = 2;
+const GREP_MAX_MATCHES: usize = 50;
+const GREP_MAX_BYTES: usize = 32_000;
+
+
+pub struct AtGrep {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtGrep {
+    pub fn new() -> Self {
+        AtGrep {
+            params: vec![],
+        }
+    }
+}
+
+// Matching lines with GREP_CONTEXT_LINES around them, as 1-based inclusive ranges, overlapping ranges merged.
+// Each range comes with how many lines matched inside it, stops after max_matches.
+fn grep_text(text: &str, re: &Regex, max_matches: usize) -> Vec<(usize, usize, usize)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut ranges: Vec<(usize, usize, usize)> = vec![];
+    let mut matches_cnt = 0;
+    for (idx, line) in lines.iter().enumerate() {
+        if matches_cnt >= max_matches {
+            break;
+        }
+        if !re.is_match(line) {
+            continue;
+        }
+        matches_cnt += 1;
+        let line1 = (idx + 1).saturating_sub(GREP_CONTEXT_LINES).max(1);
+        let line2 = (idx + 1 + GREP_CONTEXT_LINES).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if line1 <= last.1 + 1 => {
+                last.1 = line2;
+                last.2 += 1;
+            }
+            _ => ranges.push((line1, line2, 1)),
+        }
+    }
+    ranges
+}
+
+fn range_bytes(text: &str, line1: usize, line2: usize) -> usize {
+    text.lines().skip(line1 - 1).take(line2 + 1 - line1).map(|l| l.len() + 1).sum()
+}
+
+#[async_trait]
+impl AtCommand for AtGrep {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        // @grep pattern [dir] [glob]
+        *args = args.iter().take_while(|arg| arg.text != "\n").take(3).cloned().collect();
+        let pattern = match args.get(0) {
+            Some(x) => x.text.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("parameter is missing".to_string());
+                args.clear();
+                return Err("parameter `pattern` is missing".to_string());
+            }
+        };
+        let re = Regex::new(&pattern).map_err(|e| {
+            cmd.ok = false;
+            cmd.reason = Some(format!("bad regex: {}", e));
+            args.clear();
+            format!("Cannot execute @grep, bad regex `{}`: {}", pattern, e)
+        })?;
+        let file_glob = match args.iter().skip(1).find(|x| x.text.contains('*')) {
+            Some(x) => Some(glob::Pattern::new(&x.text).map_err(|e| {
+                cmd.ok = false;
+                cmd.reason = Some(format!("bad glob: {}", e));
+                args.clear();
+                format!("Cannot execute @grep, bad glob `{}`: {}", x.text, e)
+            })?),
+            None => None,
+        };
+
+        let gcx = ccx.lock().await.global_context.clone();
+        let project_dirs = get_project_dirs(gcx.clone()).await;
+        let start_dir = match args.iter().skip(1).find(|x| !x.text.contains('*')).map(|x| x.text.clone()) {
+            Some(dir) => {
+                let candidates = correct_to_nearest_dir_path(gcx.clone(), &dir, false, 10).await;
+                let candidate = return_one_candidate_or_a_good_error(gcx.clone(), &dir, &candidates, &project_dirs, true).await.map_err(|e| {
+                    cmd.ok = false;
+                    cmd.reason = Some(e.clone());
+                    args.clear();
+                    e
+                })?;
+                Some(PathBuf::from(candidate))
+            }
+            None => None,
+        };
+
+        let workspace_files = gcx.read().await.documents_state.workspace_files.lock().unwrap().clone();
+        let mut context_files = vec![];
+        let (mut files_cnt, mut matches_cnt, mut bytes_cnt) = (0, 0, 0);
+        let mut truncated = false;
+        for path in workspace_files.iter() {
+            if start_dir.as_ref().map_or(false, |d| !path.starts_with(d)) {
+                continue;
+            }
+            if file_glob.as_ref().map_or(false, |g| !g.matches_path(path) && !path.file_name().map_or(false, |n| g.matches(&n.to_string_lossy()))) {
+                continue;
+            }
+            let text = match get_file_text_from_memory_or_disk(gcx.clone(), path).await {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+            if text.contains('\0') {
+                continue;  // binary
+            }
+            let ranges = grep_text(&text, &re, GREP_MAX_MATCHES - matches_cnt);
+            if ranges.is_empty() {
+                continue;
+            }
+            // only the matches of attached ranges are counted, so the message agrees with what the model sees
+            let mut file_attached = false;
+            for (line1, line2, range_matches_cnt) in ranges {
+                bytes_cnt += range_bytes(&text, line1, line2);
+                if bytes_cnt > GREP_MAX_BYTES {
+                    truncated = true;
+                    break;
+                }
+                file_attached = true;
+                matches_cnt += range_matches_cnt;
+                context_files.push(ContextFile {
+                    file_name: path.to_string_lossy().to_string(),
+                    file_content: "".to_string(),
+                    line1,
+                    line2,
+                    symbols: vec![],
+                    gradient_type: -1,
+                    usefulness: 100.0,
+                });
+            }
+            if file_attached {
+                files_cnt += 1;
+            }
+            if truncated || matches_cnt >= GREP_MAX_MATCHES {
+                truncated = true;
+                break;
+            }
+        }
+
+        args.clear();
+        let text = if context_files.is_empty() {
+            format!("`{}` not found", pattern)
+        } else if truncated {
+            format!("`{}` found in {} files, only the first {} matches are attached", pattern, files_cnt, matches_cnt)
+        } else {
+            format!("`{}` found in {} files, {} matches", pattern, files_cnt, matches_cnt)
+        };
+        Ok((context_files.into_iter().map(|x| ContextEnum::ContextFile(x)).collect::<Vec<ContextEnum>>(), text))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_text_merges_context_and_caps_matches() {
+        let text = "def jump():\n    pass\n\n\n\n\ndef croak():\n    ribbit()\n    ribbit()\n\ndef swim():\n    ribbit()\n";
+        let re = Regex::new(r"ribbit\(\)").unwrap();
+
+        assert_eq!(grep_text(text, &re, 50), vec![(6, 12, 3)]);
+        assert_eq!(grep_text(text, &Regex::new("def").unwrap(), 2), vec![(1, 3, 1), (5, 9, 1)]);
+        assert_eq!(range_bytes(text, 1, 3), "def jump():\n    pass\n\n".len());
+    }
+
+    async fn grep_in_pond(gcx: Arc<tokio::sync::RwLock<crate::global_context::GlobalContext>>, words: &[&str]) -> (Result<(Vec<ContextEnum>, String), String>, AtCommandMember, Vec<AtCommandMember>) {
+        let ccx = Arc::new(AMutex::new(AtCommandsContext::new(gcx, 4096, 5, false, vec![], "frog-chat".to_string(), false).await));
+        let mut cmd = AtCommandMember::new("cmd".to_string(), "@grep".to_string(), 0, 5);
+        let mut args = words.iter().map(|w| AtCommandMember::new("arg".to_string(), w.to_string(), 0, 0)).collect::<Vec<_>>();
+        let result = AtGrep::new().at_execute(ccx, &mut cmd, &mut args).await;
+        (result, cmd, args)
+    }
+
+    #[tokio::test]
+    async fn test_grep_at_execute_filters_caps_and_clears_args() {
+        let pond = tempfile::Builder::new().prefix("frog_grep").tempdir().unwrap();
+        let frog_py = pond.path().join("frog.py");
+        let notes_txt = pond.path().join("notes.txt");
+        let tadpole_bin = pond.path().join("tadpole.bin");
+        let lily_py = pond.path().join("lily.py");
+        std::fs::write(&frog_py, "def croak():\n    ribbit()\n").unwrap();
+        std::fs::write(&notes_txt, "ribbit is what frogs say\n").unwrap();
+        std::fs::write(&tadpole_bin, "ribbit\0\0").unwrap();
+        // every match is a separate range of a few long lines, GREP_MAX_BYTES runs out before GREP_MAX_MATCHES
+        let long_line = "x".repeat(2_000);
+        let lily_text = (0..40).map(|i| format!("{}\n{}\nribbit {}\n{}\n{}\n\n", long_line, long_line, i, long_line, long_line)).collect::<String>();
+        std::fs::write(&lily_py, &lily_text).unwrap();
+        let gcx_dir = tempfile::Builder::new().prefix("frog_grep_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(gcx_dir.path(), vec![frog_py.clone(), notes_txt.clone(), tadpole_bin.clone(), lily_py.clone()]).await;
+
+        let (result, _, args) = grep_in_pond(gcx.clone(), &["ribbit\\(\\)", "*.py"]).await;
+        let (context, text) = result.unwrap();
+        assert!(args.is_empty());
+        assert_eq!(text, "`ribbit\\(\\)` found in 1 files, 1 matches");
+        assert!(matches!(context.as_slice(), [ContextEnum::ContextFile(cf)] if cf.file_name.ends_with("frog.py") && (cf.line1, cf.line2) == (1, 2)));
+
+        let (result, _, _) = grep_in_pond(gcx.clone(), &["is what frogs"]).await;
+        let (context, _) = result.unwrap();
+        assert!(matches!(context.as_slice(), [ContextEnum::ContextFile(cf)] if cf.file_name.ends_with("notes.txt")));
+
+        let (result, _, _) = grep_in_pond(gcx.clone(), &["ribbit \\d+", "lily.*"]).await;
+        let (context, text) = result.unwrap();
+        let attached_matches = context.len();
+        assert!(attached_matches > 0 && attached_matches < 40);
+        assert_eq!(text, format!("`ribbit \\d+` found in 1 files, only the first {} matches are attached", attached_matches));
+
+        let (result, _, _) = grep_in_pond(gcx.clone(), &["ribbit", "*.bin"]).await;
+        let (context, text) = result.unwrap();
+        assert!(context.is_empty(), "binary files are skipped");
+        assert_eq!(text, "`ribbit` not found");
+
+        let (result, cmd, args) = grep_in_pond(gcx.clone(), &["ribbit", "[*"]).await;
+        assert!(result.is_err());
+        assert!(!cmd.ok);
+        assert!(args.is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use crate::at_commands::at_commands::{vec_context_file_to_context_tools, AtComma
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::Mutex as AMutex;
+use tokio::sync::RwLock as ARwLock;
 use tracing::info;
 use crate::nicer_logs::last_n_chars;
 
@@ -9,7 +10,10 @@ use crate::at_commands::execute_at::AtCommandMember;
 use crate::call_validation::{ContextEnum, ContextFile};
 use crate::caps::get_custom_embedding_api_key;
 use crate::vecdb;
-use crate::vecdb::vdb_structs::VecdbSearch;
+use crate::files_correction::{get_active_workspace_folder, get_project_dirs};
+use crate::global_context::GlobalContext;
+use crate::vecdb::vdb_lance::scope_filter_in_namespace;
+use crate::vecdb::vdb_structs::{filter_by_test_scope, VecdbNamespace, VecdbSearch, VecdbTestScope};
 
 
 pub fn text_on_clip(query: &String, from_tool_call: bool) -> String {
@@ -59,29 +63,51 @@ fn results2message(results: &Vec<vecdb::vdb_structs::VecdbRecord>) -> Vec<Contex
     vector_of_context_file
 }
 
+pub async fn active_vecdb_namespace(gcx: Arc<ARwLock<GlobalContext>>) -> VecdbNamespace {
+    match get_active_workspace_folder(gcx).await {
+        Some(folder) => VecdbNamespace::Project(folder),
+        None => VecdbNamespace::AllProjects,
+    }
+}
+
 pub async fn execute_at_search(
     ccx: Arc<AMutex<AtCommandsContext>>,
     query: &String,
     vecdb_scope_filter_mb: Option<String>,
+    test_scope: VecdbTestScope,
+    all_projects: bool,
 ) -> Result<Vec<ContextFile>, String> {
     let (gcx, top_n) = {
         let ccx_locked = ccx.lock().await;
         (ccx_locked.global_context.clone(), ccx_locked.top_n)
     };
+    // a filter for a particular dir or file is already precise, otherwise the search stays in the active project
+    let vecdb_scope_filter_mb = if vecdb_scope_filter_mb.is_none() && !all_projects {
+        scope_filter_in_namespace(None, &active_vecdb_namespace(gcx.clone()).await)
+    } else {
+        vecdb_scope_filter_mb
+    };
+    let test_scope = if test_scope == VecdbTestScope::Exclude && !gcx.read().await.cmdline.vecdb_separate_test_scope {
+        VecdbTestScope::Include  // tests are excluded by default only if they were asked to live in a separate scope
+    } else {
+        test_scope
+    };
 
     let api_key = get_custom_embedding_api_key(gcx.clone()).await;
     if let Err(err) = api_key {
         return Err(err.message);
     }
     let api_key = api_key.unwrap();
+    let project_dirs = get_project_dirs(gcx.clone()).await;
 
     let vec_db = gcx.read().await.vec_db.clone();
     let r = match *vec_db.lock().await {
         Some(ref db) => {
             let top_n_twice_as_big = top_n * 2;  // top_n will be cut at postprocessing stage, and we really care about top_n files, not pieces
+            let top_n_search = if test_scope == VecdbTestScope::Include { top_n_twice_as_big } else { top_n_twice_as_big * 2 };  // some get filtered out below
             // TODO: this code sucks, release lock, don't hold anything during the search
-            let search_result = db.vecdb_search(query.clone(), top_n_twice_as_big, vecdb_scope_filter_mb, &api_key).await?;
-            let results = search_result.results.clone();
+            let search_result = db.vecdb_search(query.clone(), top_n_search, vecdb_scope_filter_mb, vec![], None, None, &api_key).await?;
+            let results = filter_by_test_scope(search_result.results.clone(), test_scope, &project_dirs);
             return Ok(results2message(&results));
         }
         None => Err("VecDB is not active. Possible reasons: VecDB is turned off in settings, or perhaps a vectorization model is not available.".to_string())
@@ -112,7 +138,7 @@ impl AtCommand for AtSearch {
             return Err("Cannot execute search: query is empty.".to_string());
         }
 
-        let vector_of_context_file = execute_at_search(ccx.clone(), &query, None).await?;
+        let vector_of_context_file = execute_at_search(ccx.clone(), &query, None, VecdbTestScope::Exclude, false).await?;
         let text = text_on_clip(&query, false);
         Ok((vec_context_file_to_context_tools(vector_of_context_file), text))
     }
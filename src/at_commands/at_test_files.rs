@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex as AMutex;
+
+use crate::at_commands::at_commands::{AtCommand, AtCommandsContext, AtParam};
+use crate::at_commands::at_file::{file_repair_candidates, AtParamFilePath};
+use crate::at_commands::execute_at::{AtCommandMember, correct_at_arg};
+use crate::call_validation::{ChatMessage, ContextEnum};
+use crate::file_filter::is_test_file;
+use crate::files_correction::{get_project_dirs, shortify_paths};
+
+const NAMING_MATCH_SCORE: f32 = 10.0;
+const NAMING_PARTIAL_SCORE: f32 = 3.0;
+const REFERENCES_SCORE_LIMIT: usize = 5;
+const DEFS_TO_CHECK_LIMIT: usize = 50;
+
+
+pub struct AtTestFiles {
+    pub params: Vec<Arc<AMutex<dyn AtParam>>>,
+}
+
+impl AtTestFiles {
+    pub fn new() -> Self {
+        AtTestFiles {
+            params: vec![
+                Arc::new(AMutex::new(AtParamFilePath::new()))
+            ],
+        }
+    }
+}
+
+fn lowercase_stem(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase()
+}
+
+fn naming_score(source_stem: &str, candidate: &Path) -> f32 {
+    let stem = lowercase_stem(candidate);
+    let conventions = [
+        format!("test_{}", source_stem),
+        format!("tests_{}", source_stem),
+        format!("test{}", source_stem),
+        format!("{}_test", source_stem),
+        format!("{}_tests", source_stem),
+        format!("{}test", source_stem),
+        format!("{}tests", source_stem),
+        format!("{}.test", source_stem),
+        format!("{}.spec", source_stem),
+        format!("{}_spec", source_stem),
+    ];
+    if conventions.contains(&stem) {
+        NAMING_MATCH_SCORE
+    } else if stem.contains(source_stem) {
+        NAMING_PARTIAL_SCORE
+    } else {
+        0.0
+    }
+}
+
+pub fn rank_test_file_candidates(
+    source_path: &PathBuf,
+    workspace_files: &Vec<PathBuf>,
+    project_dirs: &[PathBuf],
+    references_per_file: &HashMap<PathBuf, usize>,
+) -> Vec<(PathBuf, f32)> {
+    let source_stem = lowercase_stem(source_path);
+    let mut ranked = workspace_files.iter()
+        .filter(|f| *f != source_path && is_test_file(f, project_dirs))
+        .filter_map(|f| {
+            let references = references_per_file.get(f).cloned().unwrap_or(0).min(REFERENCES_SCORE_LIMIT);
+            let score = naming_score(&source_stem, f) + references as f32;
+            if score > 0.0 { Some((f.clone(), score)) } else { None }
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+#[async_trait]
+impl AtCommand for AtTestFiles {
+    fn params(&self) -> &Vec<Arc<AMutex<dyn AtParam>>> {
+        &self.params
+    }
+
+    async fn at_execute(
+        &self,
+        ccx: Arc<AMutex<AtCommandsContext>>,
+        cmd: &mut AtCommandMember,
+        args: &mut Vec<AtCommandMember>,
+    ) -> Result<(Vec<ContextEnum>, String), String> {
+        let mut arg0 = match args.iter().filter(|x| !x.text.trim().is_empty()).next() {
+            Some(x) => x.clone(),
+            None => {
+                cmd.ok = false;
+                cmd.reason = Some("no file provided".to_string());
+                args.clear();
+                return Err("Cannot execute @test-files: no file provided".to_string());
+            }
+        };
+        correct_at_arg(ccx.clone(), self.params[0].clone(), &mut arg0).await;
+        args.clear();
+        args.push(arg0.clone());
+
+        let (gcx, top_n) = {
+            let ccx_lock = ccx.lock().await;
+            (ccx_lock.global_context.clone(), ccx_lock.top_n)
+        };
+
+        let candidates = file_repair_candidates(gcx.clone(), &arg0.text, top_n, false).await;
+        let source_path = match candidates.get(0) {
+            Some(c) => PathBuf::from(c),
+            None => return Err(format!("cannot find {:?}", arg0.text)),
+        };
+
+        let mut references_per_file: HashMap<PathBuf, usize> = HashMap::new();
+        let ast_service_opt = gcx.read().await.ast_service.clone();
+        if let Some(ast_service) = ast_service_opt {
+            let ast_index = ast_service.lock().await.ast_index.clone();
+            let defs = crate::ast::ast_db::doc_defs(ast_index.clone(), &source_path.to_string_lossy().to_string()).await;
+            for def in defs.iter().take(DEFS_TO_CHECK_LIMIT) {
+                for (usedin, _) in crate::ast::ast_db::usages(ast_index.clone(), def.path(), 100).await {
+                    *references_per_file.entry(PathBuf::from(&usedin.cpath)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let workspace_files = gcx.read().await.documents_state.workspace_files.lock().unwrap().clone();
+        let project_dirs = get_project_dirs(gcx.clone()).await;
+        let ranked = rank_test_file_candidates(&source_path, &workspace_files, &project_dirs, &references_per_file);
+        let ranked_paths = ranked.iter().take(top_n.max(1)).map(|(p, _)| p.to_string_lossy().to_string()).collect::<Vec<_>>();
+        let shortified = shortify_paths(gcx.clone(), &ranked_paths).await;
+
+        let text = if shortified.is_empty() {
+            format!("No test files found for {}", arg0.text)
+        } else {
+            let mut text = format!("Test files for {}, most relevant first:\n", arg0.text);
+            for ((_, score), path) in ranked.iter().zip(shortified.iter()) {
+                text.push_str(&format!("{} (score {:.1})\n", path, score));
+            }
+            text
+        };
+        Ok((vec![ContextEnum::ChatMessage(ChatMessage::new("plain_text".to_string(), text))], "".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_test_files_finds_test_frog() {
+        let source = PathBuf::from("tests").join("emergency_frog_situation").join("frog.py");
+        let workspace_files = vec![
+            source.clone(),
+            PathBuf::from("tests").join("emergency_frog_situation").join("work_day.py"),
+            PathBuf::from("tests").join("test_work_day.py"),
+            PathBuf::from("tests").join("test_frog_jumps.py"),
+            PathBuf::from("tests").join("test_frog.py"),
+        ];
+        let references = HashMap::from([
+            (PathBuf::from("tests").join("test_work_day.py"), 2),
+        ]);
+
+        let ranked = rank_test_file_candidates(&source, &workspace_files, &[], &references);
+
+        assert_eq!(ranked[0].0, PathBuf::from("tests").join("test_frog.py"));
+        assert!(!ranked.iter().any(|(p, _)| *p == source));
+    }
+
+    #[test]
+    fn test_rank_test_files_in_a_project_under_tests_dir() {
+        let project = PathBuf::from("/home/frog/tests/pond");
+        let source = project.join("frog.py");
+        let workspace_files = vec![source.clone(), project.join("frog_utils.py"), project.join("tests").join("test_frog.py")];
+
+        let ranked = rank_test_file_candidates(&source, &workspace_files, &[project.clone()], &HashMap::new());
+
+        assert_eq!(ranked.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>(), vec![project.join("tests").join("test_frog.py")]);
+    }
+}
@@ -170,15 +170,20 @@ fn _print_files_tree(
     result
 }
 
+const TREE_MAX_NODES: usize = 1000;  // lines in the output, a huge monorepo fits char_limit badly anyway
+
+// The deepest tree that fits both the char limit and the node limit, but never deeper than max_depth
 fn _print_files_tree_with_budget(
     tree: Vec<PathsHolderNodeArc>,
     char_limit: usize,
+    max_nodes: usize,
+    max_depth: Option<usize>,
     ast_db: Option<Arc<AMutex<AstDB>>>,
 ) -> String {
     let mut good_enough = String::new();
-    for maxdepth in 1..20 {
+    for maxdepth in 1..=max_depth.unwrap_or(19).min(19) {
         let bigger_tree_str = _print_files_tree(&tree, ast_db.clone(), maxdepth);
-        if bigger_tree_str.len() > char_limit {
+        if bigger_tree_str.len() > char_limit || bigger_tree_str.lines().count() > max_nodes {
             break;
         }
         good_enough = bigger_tree_str;
@@ -190,6 +195,7 @@ pub async fn print_files_tree_with_budget(
     ccx: Arc<AMutex<AtCommandsContext>>,
     tree: Vec<PathsHolderNodeArc>,
     use_ast: bool,
+    max_depth: Option<usize>,
 ) -> Result<String, String> {
     let (gcx, tokens_for_rag) = {
         let ccx_locked = ccx.lock().await;
@@ -205,9 +211,9 @@ pub async fn print_files_tree_with_budget(
     match ast_module_option {
         Some(ast_module) => {
             let ast_db: Option<Arc<AMutex<AstDB>>> = Some(ast_module.lock().await.ast_index.clone());
-            Ok(_print_files_tree_with_budget(tree, char_limit, ast_db.clone()))
+            Ok(_print_files_tree_with_budget(tree, char_limit, TREE_MAX_NODES, max_depth, ast_db.clone()))
         }
-        None => Ok(_print_files_tree_with_budget(tree, char_limit, None)),
+        None => Ok(_print_files_tree_with_budget(tree, char_limit, TREE_MAX_NODES, max_depth, None)),
     }
 }
 
@@ -232,9 +238,11 @@ impl AtCommand for AtTree {
             .collect();
         tracing::info!("tree: project_dirs={:?} file paths {} filtered project dirs only => {} paths", project_dirs, paths_from_anywhere_len, filtered_paths.len());
 
-        *args = args.iter().take_while(|arg| arg.text != "\n" || arg.text == "--ast").take(2).cloned().collect();
+        // @tree [path] [depth] [--ast]
+        *args = args.iter().take_while(|arg| arg.text != "\n" || arg.text == "--ast").take(3).cloned().collect();
+        let max_depth = args.iter().find_map(|x| x.text.parse::<usize>().ok());
 
-        let tree = match args.iter().find(|x| x.text != "--ast") {
+        let tree = match args.iter().find(|x| x.text != "--ast" && x.text.parse::<usize>().is_err()) {
             None => construct_tree_out_of_flat_list_of_paths(&filtered_paths),
             Some(arg) => {
                 let path = arg.text.clone();
@@ -254,7 +262,7 @@ impl AtCommand for AtTree {
 
         let use_ast = args.iter().any(|x| x.text == "--ast");
 
-        let tree = print_files_tree_with_budget(ccx.clone(), tree, use_ast).await.map_err(|err| {
+        let tree = print_files_tree_with_budget(ccx.clone(), tree, use_ast, max_depth).await.map_err(|err| {
             warn!("{}", err);
             err
         })?;
@@ -271,3 +279,29 @@ impl AtCommand for AtTree {
         Ok((vec![context], "".to_string()))
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_files_tree_depth_and_node_limits() {
+        let paths = vec![
+            PathBuf::from("pond/frog.py"),
+            PathBuf::from("pond/lily/pad.py"),
+            PathBuf::from("pond/lily/flower.py"),
+            PathBuf::from("pond/toad.py"),
+        ];
+        let tree = construct_tree_out_of_flat_list_of_paths(&paths);
+
+        let full = _print_files_tree_with_budget(tree.clone(), 10000, 1000, None, None);
+        assert_eq!(full, "pond/\n  frog.py\n  lily/\n    pad.py\n    flower.py\n  toad.py\n");
+
+        let shallow = _print_files_tree_with_budget(tree.clone(), 10000, 1000, Some(1), None);
+        assert_eq!(shallow, "pond/\n  frog.py\n  lily/\n    ...0 subdirs, 2 files...\n  toad.py\n");
+
+        let capped = _print_files_tree_with_budget(tree.clone(), 10000, 5, None, None);
+        assert_eq!(capped, shallow);
+    }
+}
@@ -6,7 +6,7 @@ use tokenizers::Tokenizer;
 use tracing::{info, warn};
 
 use crate::at_commands::at_commands::{AtCommandsContext, AtParam, filter_only_context_file_from_context_tool};
-use crate::call_validation::{ChatContent, ChatMessage, ContextEnum};
+use crate::call_validation::{ChatContent, ChatMessage, ContextEnum, ContextFile};
 use crate::http::http_post_json;
 use crate::http::routers::v1::at_commands::{CommandExecutePost, CommandExecuteResponse};
 use crate::integrations::docker::docker_container_manager::docker_container_get_host_lsp_port_to_connect;
@@ -95,7 +95,15 @@ pub async fn run_at_commands_locally(
         // TODO: reduce context_limit by tokens(messages_exec_output)
 
         if context_limit > MIN_RAG_CONTEXT_LIMIT {
+            let (gcx, mut pp_settings, pp_skeleton) = {
+                let ccx_locked = ccx.lock().await;
+                (ccx_locked.global_context.clone(), ccx_locked.postprocess_parameters.clone(), ccx_locked.pp_skeleton)
+            };
             let mut context_file_pp = filter_only_context_file_from_context_tool(&messages_exec_output);
+            let dropped_note_mb = drop_not_useful_context_files(&mut context_file_pp, pp_settings.min_usefulness);
+            if let Some(note) = &dropped_note_mb {
+                plain_text_messages.push(note);
+            }
             let (tokens_limit_plain, mut tokens_limit_files) = {
                 if context_file_pp.is_empty() {
                     (context_limit, 0)
@@ -120,10 +128,6 @@ pub async fn run_at_commands_locally(
             }
             tokens_limit_files += non_used_plain;
             info!("tokens_limit_files {}", tokens_limit_files);
-            let (gcx, mut pp_settings, pp_skeleton) = {
-                let ccx_locked = ccx.lock().await;
-                (ccx_locked.global_context.clone(), ccx_locked.postprocess_parameters.clone(), ccx_locked.pp_skeleton)
-            };
             pp_settings.max_files_n = top_n;
             if pp_skeleton {
                 pp_settings.take_floor = 50.0;
@@ -164,6 +168,21 @@ pub async fn run_at_commands_locally(
     return (rebuilt_messages, user_msg_starts, any_context_produced)
 }
 
+/// Drops context files below `min_usefulness`, returns a note for the model saying how many were dropped.
+fn drop_not_useful_context_files(context_files: &mut Vec<ContextFile>, min_usefulness: f32) -> Option<ChatMessage> {
+    let before = context_files.len();
+    context_files.retain(|cf| cf.usefulness >= min_usefulness);
+    let dropped = before - context_files.len();
+    if dropped == 0 {
+        return None;
+    }
+    info!("dropped {} context files with usefulness below {:.1}", dropped, min_usefulness);
+    Some(ChatMessage::new(
+        "plain_text".to_string(),
+        format!("{} more context files were found, but not included because they are not useful enough (usefulness below {:.1}).", dropped, min_usefulness),
+    ))
+}
+
 pub async fn run_at_commands_remotely(
     ccx: Arc<AMutex<AtCommandsContext>>,
     model_name: &str,
@@ -319,6 +338,35 @@ pub fn parse_words_from_line(line: &String) -> Vec<(String, usize, usize)> {
 mod tests {
     use super::*;
 
+    fn frog_context_file(file_name: &str, usefulness: f32) -> ContextFile {
+        ContextFile {
+            file_name: file_name.to_string(),
+            file_content: "".to_string(),
+            line1: 1,
+            line2: 10,
+            symbols: vec![],
+            gradient_type: -1,
+            usefulness,
+        }
+    }
+
+    #[test]
+    fn test_drop_not_useful_context_files() {
+        let mut context_files = vec![
+            frog_context_file("frog.py", 90.0),
+            frog_context_file("pond.py", 10.0),
+            frog_context_file("lily.py", 50.0),
+            frog_context_file("mud.py", 25.0),
+        ];
+        assert!(drop_not_useful_context_files(&mut context_files, 0.0).is_none());
+        assert_eq!(context_files.len(), 4);
+
+        let note = drop_not_useful_context_files(&mut context_files, 30.0).unwrap();
+        assert_eq!(context_files.iter().map(|cf| cf.file_name.as_str()).collect::<Vec<_>>(), vec!["frog.py", "lily.py"]);
+        assert_eq!(note.role, "plain_text");
+        assert!(note.content.content_text_only().starts_with("2 more context files"), "{}", note.content.content_text_only());
+    }
+
     #[test]
     fn test_parse_words_from_line_with_link() {
         let line = "Check out this link: https://doc.rust-lang.org/book/ch03-04-comments.html".to_string();
@@ -1,10 +1,16 @@
 pub mod execute_at;
 pub mod at_ast_definition;
 pub mod at_ast_reference;
+pub mod at_ast_implementations;
 pub mod at_commands;
 pub mod at_file;
 pub mod at_web;
 pub mod at_tree;
+pub mod at_test_files;
+pub mod at_blame;
+pub mod at_churn;
+pub mod at_grep;
+pub mod at_explain_symbol;
 
 #[cfg(feature="vecdb")]
 pub mod at_search;
@@ -210,6 +210,8 @@ pub struct ChatPost {
     pub meta: ChatMeta,
     #[serde(default)]
     pub style: Option<String>,
+    #[serde(default)]
+    pub summarize_dropped_history: bool,  // replace messages that don't fit into the context with their summary instead of dropping
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -270,6 +272,8 @@ pub struct DiffChunk {
     #[serde(default = "default_true", skip_serializing)]
     pub is_file: bool,
     pub application_details: String,
+    #[serde(default)]
+    pub confidence: Option<u8>,  // 0..100 how sure whoever produced the chunk is, None is as sure as an exact match
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -284,6 +288,7 @@ pub struct PostprocessSettings {
     pub close_small_gaps: bool,
     pub take_floor: f32,                 // take/dont value
     pub max_files_n: usize,              // don't produce more than n files in output
+    pub min_usefulness: f32,             // drop context files less useful than this before expanding them, 0.0 keeps all
 }
 
 impl Default for PostprocessSettings {
@@ -303,6 +308,7 @@ impl PostprocessSettings {
             comments_propagate_up_coef: 0.99,
             take_floor: 0.0,
             max_files_n: 0,
+            min_usefulness: 0.0,
         }
     }
 }
@@ -144,6 +144,10 @@ pub struct CodeAssistantCaps {
     #[serde(default)]
     pub embedding_n_ctx: usize,
     #[serde(default)]
+    pub embedding_distance_reject: Option<f32>,  // search results further than this are dropped, scale depends on the model
+    #[serde(default)]
+    pub embedding_distance_metric: String,  // "cosine" (default), "dot" or "l2", whatever the embedding model was trained for
+    #[serde(default)]
     pub running_models: Vec<String>,  // check there if a model is available or not, not in other places
     #[serde(default)]
     pub caps_version: i64,  // need to reload if it increases on server, that happens when server configuration changes
@@ -211,7 +215,10 @@ fn load_caps_from_buf(
     }
     r1.telemetry_basic_dest = relative_to_full_url(&caps_url, &r1.telemetry_basic_dest)?;
     r1.telemetry_basic_retrieve_my_own = relative_to_full_url(&caps_url, &r1.telemetry_basic_retrieve_my_own)?;
-    r1.endpoint_embeddings_template = relative_to_full_url(&caps_url, &r1.endpoint_embeddings_template)?;
+    if r1.endpoint_embeddings_style.to_lowercase() != "local" {
+        // for local embeddings it's a path to the model directory
+        r1.endpoint_embeddings_template = relative_to_full_url(&caps_url, &r1.endpoint_embeddings_template)?;
+    }
     r1.tokenizer_path_template = relative_to_full_url(&caps_url, &r1.tokenizer_path_template)?;
     if r1.embedding_n_ctx == 0 {
         r1.embedding_n_ctx = 512;
@@ -1,17 +1,24 @@
+use std::io::Read;
 use std::mem;
 use std::path::PathBuf;
 use std::sync::Arc;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 use tokio::sync::RwLock as ARwLock;
+use tokio::sync::Mutex as AMutex;
 use hashbrown::{HashMap, HashSet};
-use tracing::info;
+use indexmap::IndexMap;
+use tracing::{info, warn};
+use crate::ast::ast_structs::{AstDB, AstDefinition, SymbolType};
 use crate::at_commands::at_file::{file_repair_candidates, return_one_candidate_or_a_good_error};
 use crate::call_validation::DiffChunk;
 use crate::files_correction::{get_project_dirs, correct_to_nearest_dir_path};
 use crate::global_context::GlobalContext;
 
 const DEBUG: usize = 0;
+const PREVIEW_MAX_FUZZY_N: usize = 10;
+const BINARY_CHECK_BYTES: usize = 8192;
 
 
 #[derive(Clone, Debug, Default)]
@@ -36,9 +43,20 @@ pub struct ApplyDiffResult {
     pub file_name_add: Option<String>,
 }
 
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FilePreview {
+    pub path: String,
+    pub before_text: String,
+    pub after_text: String,
+    pub applied: bool,
+    pub reason: Option<String>,
+    pub unified_diff: String,  // of the edit chunks that apply, empty for other file actions
+}
+
 #[derive(Serialize, Debug)]
 pub struct ApplyDiffUnwrapped {
     pub chunk_id: usize,
+    pub stable_id: String,  // chunk_id() of the chunk, survives re-parsing the same model output
     pub applied: bool,
     pub can_unapply: bool,
     pub success: bool,
@@ -70,6 +88,99 @@ fn validate_chunk(chunk: &DiffChunk) -> Result<(), String> {
     Ok(())
 }
 
+fn is_binary_file(path: &PathBuf) -> Result<bool, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("failed to open file {}: {}", path.display(), e))?;
+    let mut head = Vec::with_capacity(BINARY_CHECK_BYTES);
+    file.take(BINARY_CHECK_BYTES as u64).read_to_end(&mut head)
+        .map_err(|e| format!("failed to read file {}: {}", path.display(), e))?;
+    Ok(head.contains(&0))
+}
+
+fn has_control_chars(text: &str) -> bool {
+    text.chars().any(|c| c.is_control() && !['\n', '\r', '\t'].contains(&c))
+}
+
+// Leading components both paths share don't count, otherwise a long common workspace prefix makes any two files look similar
+fn tail_similarity(path_str: &str, candidate: &str) -> f64 {
+    let p_components = PathBuf::from(path_str).components().collect::<Vec<_>>();
+    let c_components = PathBuf::from(candidate).components().collect::<Vec<_>>();
+    let tail = &c_components[c_components.len().saturating_sub(p_components.len())..];
+    let common = p_components.iter().zip(tail.iter()).take_while(|(a, b)| a == b).count();
+    let p_rest = p_components[common..].iter().collect::<PathBuf>();
+    let c_rest = tail[common..].iter().collect::<PathBuf>();
+    strsim::normalized_levenshtein(&p_rest.to_string_lossy(), &c_rest.to_string_lossy())
+}
+
+/// The only candidate similar enough to the path the model wrote, comparing as many trailing components
+/// as the path has. None if nothing is close or two candidates are, better to error than to patch a wrong file.
+fn confident_path_typo_correction(path_str: &str, candidates: &Vec<String>, min_similarity: f64) -> Option<String> {
+    let mut similar = candidates.iter()
+        .filter(|c| tail_similarity(path_str, c) >= min_similarity)
+        .cloned()
+        .collect::<Vec<_>>();
+    similar.dedup();
+    if similar.len() == 1 { similar.pop() } else { None }
+}
+
+const MISSING_FILE_FUZZY_MIN_SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingFilePolicy {
+    Reject,
+    Add,
+    Fuzzy,
+}
+
+impl std::str::FromStr for MissingFilePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "reject" => Ok(MissingFilePolicy::Reject),
+            "add" => Ok(MissingFilePolicy::Add),
+            "fuzzy" => Ok(MissingFilePolicy::Fuzzy),
+            _ => Err(format!("unknown --patch-missing-file value {:?}, must be one of reject, add, fuzzy", value)),
+        }
+    }
+}
+
+/// An `edit` of a file that doesn't exist: `Add` turns it into an `add` of the added lines (the removed
+/// lines never existed), `Fuzzy` moves it to the nearest of `fuzzy_candidates` if that one is similar enough
+/// and clearly the nearest, two equally near files is an error.
+fn edit_of_missing_file(chunk: &mut DiffChunk, policy: MissingFilePolicy, fuzzy_candidates: &Vec<String>) -> Result<(), String> {
+    match policy {
+        MissingFilePolicy::Reject => Err(format!("cannot edit {:?}: the file doesn't exist", chunk.file_name)),
+        MissingFilePolicy::Add => {
+            info!("patch edits {:?} that doesn't exist, adding it instead", chunk.file_name);
+            chunk.file_action = "add".to_string();
+            chunk.lines_remove = String::new();
+            chunk.line1 = 1;
+            chunk.line2 = 1;
+            Ok(())
+        }
+        MissingFilePolicy::Fuzzy => {
+            let mut candidates = fuzzy_candidates.clone();
+            candidates.sort();
+            candidates.dedup();
+            let mut scored = candidates.iter()
+                .map(|c| (tail_similarity(&chunk.file_name, c), c))
+                .filter(|(similarity, _)| *similarity >= MISSING_FILE_FUZZY_MIN_SIMILARITY)
+                .collect::<Vec<_>>();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let nearest = match scored.as_slice() {
+                [] => return Err(format!("cannot edit {:?}: the file doesn't exist and no similar file found", chunk.file_name)),
+                [(best, first), (second, other), ..] if best == second => {
+                    return Err(format!("cannot edit {:?}: the file doesn't exist, {:?} and {:?} are equally similar", chunk.file_name, first, other));
+                }
+                [(_, nearest), ..] => *nearest,
+            };
+            info!("patch edits {:?} that doesn't exist, corrected to the nearest file {:?}", chunk.file_name, nearest);
+            chunk.file_name = nearest.clone();
+            Ok(())
+        }
+    }
+}
+
 pub async fn correct_and_validate_chunks(
     gcx: Arc<ARwLock<GlobalContext>>,
     chunks: &mut Vec<DiffChunk>,
@@ -88,7 +199,15 @@ pub async fn correct_and_validate_chunks(
             // has extension -> is_file; no extension and lines_add/remove are !empty -> file; else -> dir
             let is_file = path.extension().is_some() || (path.extension().is_some() && (!chunk.lines_add.is_empty() || !chunk.lines_remove.is_empty()));
             if is_file {
-                let candidates = file_repair_candidates(gcx.clone(), path_str, 10, false).await;
+                let mut candidates = file_repair_candidates(gcx.clone(), path_str, 10, false).await;
+                let typo_similarity = gcx.read().await.cmdline.patch_path_typo_similarity;
+                if candidates.is_empty() && typo_similarity > 0.0 {
+                    let fuzzy_candidates = file_repair_candidates(gcx.clone(), path_str, 10, true).await;
+                    if let Some(corrected) = confident_path_typo_correction(path_str, &fuzzy_candidates, typo_similarity) {
+                        info!("patch path {:?} doesn't exist, corrected the typo to {:?}", path_str, corrected);
+                        candidates = vec![corrected];
+                    }
+                }
                 let candidate = return_one_candidate_or_a_good_error(gcx.clone(), path_str, &candidates, &get_project_dirs(gcx.clone()).await, false).await?;
                 Ok((candidate, true))
             } else {
@@ -99,7 +218,23 @@ pub async fn correct_and_validate_chunks(
         }
     }
 
+    let missing_file_policy = gcx.read().await.cmdline.patch_missing_file;
     for c in chunks.iter_mut() {
+        if c.file_action == "edit" && missing_file_policy != MissingFilePolicy::Reject
+            && PathBuf::from(&c.file_name).extension().is_some() && !PathBuf::from(&c.file_name).exists()
+            && file_repair_candidates(gcx.clone(), &c.file_name, 10, false).await.is_empty()
+        {
+            let fuzzy_candidates = match missing_file_policy {
+                MissingFilePolicy::Fuzzy => file_repair_candidates(gcx.clone(), &c.file_name, 10, true).await,
+                _ => vec![],
+            };
+            edit_of_missing_file(c, missing_file_policy, &fuzzy_candidates)?;
+            if c.file_action == "add" && PathBuf::from(&c.file_name).is_relative() {
+                if let Some(project_dir) = get_project_dirs(gcx.clone()).await.first() {
+                    c.file_name = project_dir.join(&c.file_name).to_string_lossy().to_string();
+                }
+            }
+        }
         if c.file_action == "add" {
             c.is_file = PathBuf::from(&c.file_name).extension().is_some() || !c.lines_add.is_empty();
         } else if c.file_action == "rename" {
@@ -111,12 +246,23 @@ pub async fn correct_and_validate_chunks(
             c.is_file = is_file;
             c.file_name = true_file_path;
         }
+        if c.file_action == "edit" && c.is_file && is_binary_file(&PathBuf::from(&c.file_name))? {
+            return Err(format!("cannot patch binary file {}", c.file_name));
+        }
+        if c.file_action == "add" && has_control_chars(&c.lines_add) {
+            warn!("added file {} contains control characters, it might be binary garbage", c.file_name);
+        }
         validate_chunk(c).map_err(|e| format!("error validating chunk {:?}:\n{}", c, e))?;
     }
 
     Ok(())
 }
 
+/// Chunks confident enough to apply without asking, and the rest for the user to review, order is kept in both
+pub fn partition_chunks_by_confidence(chunks: Vec<DiffChunk>, min_confidence: u8) -> (Vec<DiffChunk>, Vec<DiffChunk>) {
+    chunks.into_iter().partition(|c| c.confidence.unwrap_or(100) >= min_confidence)
+}
+
 fn find_chunk_matches(chunk_lines_remove: &Vec<DiffLine>, orig_lines: &Vec<&DiffLine>) -> Result<Vec<Vec<usize>>, String> {
     let chunk_len = chunk_lines_remove.len();
     let orig_len = orig_lines.len();
@@ -463,6 +609,155 @@ pub fn apply_diff_chunks_to_text(
     (results, outputs)
 }
 
+pub fn chunk_side_by_side(chunk: &DiffChunk) -> Vec<(Option<String>, Option<String>)> {
+    fn flush_pending(
+        pending_remove: &mut Vec<String>,
+        pending_add: &mut Vec<String>,
+        rows: &mut Vec<(Option<String>, Option<String>)>,
+    ) {
+        let rows_n = pending_remove.len().max(pending_add.len());
+        let mut removed = pending_remove.drain(..);
+        let mut added = pending_add.drain(..);
+        for _ in 0..rows_n {
+            rows.push((removed.next(), added.next()));
+        }
+    }
+
+    let mut diffs = diff::lines(&chunk.lines_remove, &chunk.lines_add);
+    // diff::lines yields an extra empty line when both texts end with '\n'
+    if chunk.lines_remove.ends_with('\n') && chunk.lines_add.ends_with('\n') {
+        if let Some(diff::Result::Both("", "")) = diffs.last() {
+            diffs.pop();
+        }
+    }
+
+    let mut rows = vec![];
+    let mut pending_remove = vec![];
+    let mut pending_add = vec![];
+    for d in diffs {
+        match d {
+            diff::Result::Left(l) => pending_remove.push(l.to_string()),
+            diff::Result::Right(r) => pending_add.push(r.to_string()),
+            diff::Result::Both(l, r) => {
+                flush_pending(&mut pending_remove, &mut pending_add, &mut rows);
+                rows.push((Some(l.to_string()), Some(r.to_string())));
+            }
+        }
+    }
+    flush_pending(&mut pending_remove, &mut pending_add, &mut rows);
+    rows
+}
+
+/// Short id of a chunk that stays the same when the same model output is parsed again, application_details is not part of it.
+pub fn chunk_id(chunk: &DiffChunk) -> String {
+    let mut hasher = Sha256::new();
+    for field in [
+        chunk.file_name.as_str(),
+        chunk.file_action.as_str(),
+        chunk.line1.to_string().as_str(),
+        chunk.line2.to_string().as_str(),
+        chunk.lines_remove.as_str(),
+        chunk.lines_add.as_str(),
+        chunk.file_name_rename.as_deref().unwrap_or(""),
+    ] {
+        // length first, so "ab"+"c" and "a"+"bc" hash differently
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The first chunk of `file_name` whose [line1, line2) covers `line`, a chunk that only adds lines covers
+/// the line it inserts before, so the cursor there can accept it too.
+pub fn chunk_at_line<'a>(chunks: &'a Vec<DiffChunk>, file_name: &str, line: usize) -> Option<&'a DiffChunk> {
+    chunks.iter().find(|c| {
+        c.file_name == file_name && (c.line1..c.line2.max(c.line1 + 1)).contains(&line)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffContextMode {
+    Minimal,       // just enough context to make each hunk unique in the file
+    Fixed(usize),
+}
+
+fn count_occurrences(file_lines: &[&str], block: &[&str]) -> usize {
+    if block.is_empty() || block.len() > file_lines.len() {
+        return 0;
+    }
+    file_lines.windows(block.len()).filter(|w| *w == block).count()
+}
+
+/// How many context lines around `file_lines[start..start+len]` are needed for the
+/// old side of the hunk to occur only once in the file
+pub fn minimal_context_lines(file_lines: &[&str], start: usize, len: usize) -> usize {
+    let end = (start + len).min(file_lines.len());
+    let max_n = start.max(file_lines.len() - end);
+    let min_n = if end == start { 1 } else { 0 };  // pure insertion needs at least one line to anchor
+    for n in min_n..=max_n {
+        let from = start.saturating_sub(n);
+        let to = (end + n).min(file_lines.len());
+        if count_occurrences(file_lines, &file_lines[from..to]) == 1 {
+            return n;
+        }
+    }
+    max_n
+}
+
+/// Renders edit chunks of a single file as a unified diff against `file_text`
+pub fn chunks_to_unified_diff(file_text: &str, chunks: &Vec<DiffChunk>, context_mode: DiffContextMode) -> String {
+    let file_lines = file_text.lines().collect::<Vec<_>>();
+    let mut edits = chunks.iter().filter(|c| c.file_action == "edit").collect::<Vec<_>>();
+    edits.sort_by_key(|c| (c.line1, c.line2));
+    let file_name = match edits.first() {
+        Some(c) => c.file_name.clone(),
+        None => return String::new(),
+    };
+    let ranges = edits.iter()
+        .map(|c| {
+            let start = (c.line1 - 1).min(file_lines.len());
+            (start, (c.line2 - 1).clamp(start, file_lines.len()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", file_name, file_name);
+    let mut offset: isize = 0;
+    let mut prev_end = 0;
+    for (idx, (c, (start, end))) in edits.iter().zip(ranges.iter()).enumerate() {
+        let (start, end) = (*start, *end);
+        let n = match context_mode {
+            DiffContextMode::Minimal => minimal_context_lines(&file_lines, start, end - start),
+            DiffContextMode::Fixed(n) => n,
+        };
+        // context never runs into neighbouring hunks, so hunks don't overlap
+        let next_start = ranges.get(idx + 1).map(|r| r.0).unwrap_or(file_lines.len());
+        let from = start.saturating_sub(n).max(prev_end);
+        let to = (end + n).min(next_start);
+        let added = c.lines_add.lines().collect::<Vec<_>>();
+
+        let old_len = to - from;
+        let new_len = old_len - (end - start) + added.len();
+        let old_start = if old_len == 0 { from } else { from + 1 };
+        let new_start = if new_len == 0 { from as isize + offset } else { from as isize + 1 + offset };
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_len, new_start, new_len));
+        for l in &file_lines[from..start] {
+            out.push_str(&format!(" {}\n", l));
+        }
+        for l in &file_lines[start..end] {
+            out.push_str(&format!("-{}\n", l));
+        }
+        for l in added.iter() {
+            out.push_str(&format!("+{}\n", l));
+        }
+        for l in &file_lines[end..to] {
+            out.push_str(&format!(" {}\n", l));
+        }
+        offset += added.len() as isize - (end - start) as isize;
+        prev_end = to;
+    }
+    out
+}
+
 pub async fn read_files_n_apply_diff_chunks(
     gcx: Arc<ARwLock<GlobalContext>>,
     chunks: &Vec<DiffChunk>,
@@ -537,6 +832,85 @@ pub async fn read_files_n_apply_diff_chunks(
     (results, outputs)
 }
 
+fn preview_file_chunks(
+    file_name: &String,
+    before_text: &String,
+    chunks: Vec<(usize, &DiffChunk)>,
+    context_mode: DiffContextMode,
+) -> FilePreview {
+    let (results, outputs) = apply_diff_chunks_to_text(before_text, chunks.clone(), vec![], PREVIEW_MAX_FUZZY_N);
+    let mut errors = outputs.iter()
+        .filter_map(|(id, out)| match out {
+            ApplyDiffOutput::Err(e) => Some((*id, e.clone())),
+            ApplyDiffOutput::Ok() => None,
+        })
+        .collect::<Vec<_>>();
+    errors.sort_by_key(|(id, _)| *id);
+    let chunks_ok = chunks.iter()
+        .filter(|(id, _)| outputs.get(id) == Some(&ApplyDiffOutput::Ok()))
+        .map(|(_, c)| (*c).clone())
+        .collect::<Vec<_>>();
+
+    let after_text = match results.first() {
+        Some(r) if r.file_name_delete.is_some() && r.file_name_add.is_none() => String::new(),
+        Some(r) => r.file_text.clone().unwrap_or(before_text.clone()),
+        None => before_text.clone(),
+    };
+    FilePreview {
+        path: file_name.clone(),
+        before_text: before_text.clone(),
+        after_text,
+        applied: errors.is_empty(),
+        reason: if errors.is_empty() {
+            None
+        } else {
+            Some(errors.iter().map(|(id, e)| format!("chunk #{}: {}", id, e)).collect::<Vec<_>>().join("\n"))
+        },
+        unified_diff: chunks_to_unified_diff(before_text, &chunks_ok, context_mode),
+    }
+}
+
+/// Computes what applying `chunks` would do to each file, without writing anything to disk.
+pub async fn preview_diff_chunks(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    chunks: &Vec<DiffChunk>,
+    context_mode: DiffContextMode,
+) -> Vec<FilePreview> {
+    let mut file_groups: Vec<(String, Vec<(usize, &DiffChunk)>)> = vec![];
+    for (idx, c) in chunks.iter().enumerate() {
+        let key = if c.file_action == "edit" { c.file_name.clone() } else { format!("{}:{}", c.file_action, idx) };
+        match file_groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push((idx, c)),
+            None => file_groups.push((key, vec![(idx, c)])),
+        }
+    }
+
+    let mut previews = vec![];
+    for (_, group) in file_groups {
+        let file_name = group[0].1.file_name.clone();
+        let before_text = if group[0].1.file_action == "add" {
+            String::new()
+        } else {
+            match crate::files_in_workspace::get_file_text_from_memory_or_disk(gcx.clone(), &PathBuf::from(&file_name)).await {
+                Ok(t) => t.to_string(),
+                Err(e) => {
+                    previews.push(FilePreview {
+                        path: file_name,
+                        before_text: String::new(),
+                        after_text: String::new(),
+                        applied: false,
+                        reason: Some(format!("Failed to read file: {}", e)),
+                        unified_diff: String::new(),
+                    });
+                    continue;
+                }
+            }
+        };
+        previews.push(preview_file_chunks(&file_name, &before_text, group, context_mode));
+    }
+    previews
+}
+
 pub fn unwrap_diff_apply_outputs(
     outputs: HashMap<usize, ApplyDiffOutput>,
     chunks_default: Vec<DiffChunk>
@@ -545,11 +919,13 @@ pub fn unwrap_diff_apply_outputs(
     let other_actions = vec!["add", "remove", "rename"];
 
     for (chunk_id, c) in chunks_default.into_iter().enumerate() {
+        let stable_id = self::chunk_id(&c);
         if let Some(res) = outputs.get(&chunk_id) {
             if let ApplyDiffOutput::Ok() = res {
                 let can_unapply = !other_actions.contains(&c.file_action.as_str());
                 out_results.push(ApplyDiffUnwrapped {
                     chunk_id,
+                    stable_id,
                     applied: true,
                     can_unapply,
                     success: true,
@@ -559,6 +935,7 @@ pub fn unwrap_diff_apply_outputs(
             else if let ApplyDiffOutput::Err(e) = res {
                 out_results.push(ApplyDiffUnwrapped {
                     chunk_id,
+                    stable_id,
                     applied: false,
                     can_unapply: false,
                     success: false,
@@ -568,6 +945,7 @@ pub fn unwrap_diff_apply_outputs(
         } else {
             out_results.push(ApplyDiffUnwrapped {
                 chunk_id,
+                stable_id,
                 applied: false,
                 can_unapply: false,
                 success: true,
@@ -577,3 +955,541 @@ pub fn unwrap_diff_apply_outputs(
     }
     out_results
 }
+
+fn innermost_enclosing_def(defs: &Vec<Arc<AstDefinition>>, line1: usize, line2: usize) -> Option<Arc<AstDefinition>> {
+    defs.iter()
+        .filter(|d| matches!(d.symbol_type, SymbolType::FunctionDeclaration | SymbolType::StructDeclaration))
+        .filter(|d| d.full_line1() <= line1 && line2 <= d.full_line2())
+        .min_by_key(|d| d.full_line2() - d.full_line1())
+        .cloned()
+}
+
+/// Name of the innermost function or class containing the chunk, e.g. `frog::Frog::bounce_off_banks`
+pub async fn enclosing_symbol_for_chunk(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    chunk: &DiffChunk,
+) -> Option<String> {
+    let ast_service = gcx.read().await.ast_service.clone()?;
+    let ast_index = ast_service.lock().await.ast_index.clone();
+    let defs = crate::ast::ast_db::doc_defs(ast_index, &chunk.file_name).await;
+    let last_line = chunk.line2.saturating_sub(1).max(chunk.line1);  // line2 is exclusive
+    innermost_enclosing_def(&defs, chunk.line1, last_line).map(|d| d.path_drop0())
+}
+
+/// Compact summary of what the chunks change, to feed a commit message model instead of the full context:
+/// files changed, lines added and removed per file, and the functions or classes touched
+pub async fn diff_to_commit_context(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    chunks: &Vec<DiffChunk>,
+    max_tokens: usize,
+) -> String {
+    let ast_index_mb = match gcx.read().await.ast_service.clone() {
+        Some(ast_service) => Some(ast_service.lock().await.ast_index.clone()),
+        None => None,
+    };
+    commit_context_with_ast(ast_index_mb, chunks, max_tokens).await
+}
+
+async fn commit_context_with_ast(
+    ast_index_mb: Option<Arc<AMutex<AstDB>>>,
+    chunks: &Vec<DiffChunk>,
+    max_tokens: usize,
+) -> String {
+    // file_name -> (file_action, added, removed, symbols)
+    let mut per_file: IndexMap<String, (String, usize, usize, Vec<String>)> = IndexMap::new();
+    let mut defs_cache: HashMap<String, Vec<Arc<AstDefinition>>> = HashMap::new();
+    for chunk in chunks.iter() {
+        let symbol_mb = match &ast_index_mb {
+            Some(ast_index) if chunk.file_action == "edit" => {
+                if !defs_cache.contains_key(&chunk.file_name) {
+                    defs_cache.insert(chunk.file_name.clone(), crate::ast::ast_db::doc_defs(ast_index.clone(), &chunk.file_name).await);
+                }
+                let last_line = chunk.line2.saturating_sub(1).max(chunk.line1);
+                innermost_enclosing_def(&defs_cache[&chunk.file_name], chunk.line1, last_line).map(|d| d.path_drop0())
+            }
+            _ => None,
+        };
+        let entry = per_file.entry(chunk.file_name.clone()).or_insert_with(|| (chunk.file_action.clone(), 0, 0, vec![]));
+        entry.1 += chunk.lines_add.lines().count();
+        entry.2 += chunk.lines_remove.lines().count();
+        if let Some(symbol) = symbol_mb {
+            if !entry.3.contains(&symbol) {
+                entry.3.push(symbol);
+            }
+        }
+    }
+
+    let (total_added, total_removed) = per_file.values().fold((0, 0), |(a, r), f| (a + f.1, r + f.2));
+    let mut summary = format!("{} file(s) changed, +{} -{}\n", per_file.len(), total_added, total_removed);
+    let max_chars = max_tokens * 4;  // rough estimate, good enough for a budget
+    for (i, (file_name, (file_action, added, removed, symbols))) in per_file.iter().enumerate() {
+        let mut line = format!("{} ({}) +{} -{}", file_name, file_action, added, removed);
+        if !symbols.is_empty() {
+            line.push_str(&format!(": {}", symbols.join(", ")));
+        }
+        line.push('\n');
+        if summary.len() + line.len() > max_chars {
+            summary.push_str(&format!("...and {} more file(s)\n", per_file.len() - i));
+            break;
+        }
+        summary.push_str(&line);
+    }
+    summary
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some_pair(left: &str, right: &str) -> (Option<String>, Option<String>) {
+        (Some(left.to_string()), Some(right.to_string()))
+    }
+
+    #[test]
+    fn test_path_typo_corrected_and_applied() {
+        let workspace = tempfile::Builder::new().prefix("frog_typo").tempdir().unwrap();
+        let frog_dir = workspace.path().join("tests").join("emergency_frog_situation");
+        std::fs::create_dir_all(&frog_dir).unwrap();
+        let frog_py = frog_dir.join("frog.py").to_string_lossy().to_string();
+        let toad_py = frog_dir.join("toad.py").to_string_lossy().to_string();
+        std::fs::write(&frog_py, "def croak():\n    print(\"croak\")\n").unwrap();
+        let candidates = vec![frog_py.clone(), toad_py.clone()];
+
+        assert_eq!(confident_path_typo_correction("tests/emergancy_frog_situation/frog.py", &candidates, 0.9), Some(frog_py.clone()));
+        assert_eq!(confident_path_typo_correction("tests/emergancy_frog_situation/frog.py", &candidates, 0.999), None);
+        // frog.py and toad.py are both two edits away from froad.py
+        assert_eq!(confident_path_typo_correction("froad.py", &candidates, 0.7), None);
+
+        let mut chunk = DiffChunk {
+            file_name: "tests/emergancy_frog_situation/frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1: 2,
+            line2: 3,
+            lines_remove: "    print(\"croak\")\n".to_string(),
+            lines_add: "    print(\"ribbit\")\n".to_string(),
+            ..Default::default()
+        };
+        chunk.file_name = confident_path_typo_correction(&chunk.file_name, &candidates, 0.9).unwrap();
+        let file_text = std::fs::read_to_string(&chunk.file_name).unwrap();
+        let (results, outputs) = apply_diff_chunks_to_text(&file_text, vec![(0, &chunk)], vec![], 1);
+        assert_eq!(outputs.get(&0), Some(&ApplyDiffOutput::Ok()));
+        assert_eq!(results[0].file_text.clone().unwrap(), "def croak():\n    print(\"ribbit\")\n");
+    }
+
+    fn edit_of_missing_frog() -> (tempfile::TempDir, String, DiffChunk) {
+        let workspace = tempfile::Builder::new().prefix("frog_missing").tempdir().unwrap();
+        let frog_py = workspace.path().join("frog.py").to_string_lossy().to_string();
+        std::fs::write(&frog_py, "def croak():\n    print(\"croak\")\n").unwrap();
+        let chunk = DiffChunk {
+            file_name: workspace.path().join("frogs.py").to_string_lossy().to_string(),
+            file_action: "edit".to_string(),
+            line1: 2,
+            line2: 3,
+            lines_remove: "    print(\"croak\")\n".to_string(),
+            lines_add: "    print(\"ribbit\")\n".to_string(),
+            ..Default::default()
+        };
+        (workspace, frog_py, chunk)
+    }
+
+    #[test]
+    fn test_edit_of_missing_file_rejected() {
+        let (_workspace, frog_py, mut chunk) = edit_of_missing_frog();
+        let before = chunk.clone();
+        assert_eq!("reject".parse::<MissingFilePolicy>(), Ok(MissingFilePolicy::Reject));
+        assert!("jump".parse::<MissingFilePolicy>().is_err());
+        {
+            use structopt::StructOpt;
+            let cmdline = crate::global_context::CommandLine::from_iter_safe(["refact-lsp", "--patch-missing-file", "fuzzy"]).unwrap();
+            assert_eq!(cmdline.patch_missing_file, MissingFilePolicy::Fuzzy);
+            assert!(crate::global_context::CommandLine::from_iter_safe(["refact-lsp", "--patch-missing-file", "jump"]).is_err());
+        }
+        assert!(edit_of_missing_file(&mut chunk, MissingFilePolicy::Reject, &vec![frog_py]).is_err());
+        assert_eq!(chunk, before);
+    }
+
+    #[test]
+    fn test_edit_of_missing_file_added() {
+        let (_workspace, _frog_py, mut chunk) = edit_of_missing_frog();
+        let frogs_py = chunk.file_name.clone();
+        edit_of_missing_file(&mut chunk, MissingFilePolicy::Add, &vec![]).unwrap();
+        assert_eq!(chunk.file_action, "add");
+        assert_eq!(chunk.file_name, frogs_py);
+        assert!(chunk.lines_remove.is_empty());
+        validate_chunk(&chunk).unwrap();
+        let (results, outputs) = apply_diff_chunks_to_text(&"".to_string(), vec![(0, &chunk)], vec![], 1);
+        assert_eq!(outputs.get(&0), Some(&ApplyDiffOutput::Ok()));
+        assert_eq!(results[0].file_name_add, Some(frogs_py));
+        assert_eq!(results[0].file_text.clone().unwrap(), "    print(\"ribbit\")\n");
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_missing_file_needs_a_clear_nearest_file() {
+        let (workspace, frog_py, chunk) = edit_of_missing_frog();
+        let workspace_dir = crate::files_correction::canonical_path(&workspace.path().to_string_lossy().to_string());
+        let frog_py = crate::files_correction::canonical_path(&frog_py).to_string_lossy().to_string();
+        for name in ["toad_a.py", "toad_b.py"] {
+            std::fs::write(workspace_dir.join(name), "def croak():\n    print(\"croak\")\n").unwrap();
+        }
+        let files = ["frog.py", "toad_a.py", "toad_b.py"].iter().map(|n| workspace_dir.join(n)).collect();
+        let gcx = crate::global_context::create_global_context_for_tests(&workspace_dir, files).await;
+        gcx.write().await.cmdline.patch_missing_file = MissingFilePolicy::Fuzzy;
+        let edit_of = |name: &str| DiffChunk { file_name: workspace_dir.join(name).to_string_lossy().to_string(), ..chunk.clone() };
+
+        let mut chunks = vec![edit_of("frogs.py")];
+        correct_and_validate_chunks(gcx.clone(), &mut chunks).await.unwrap();
+        assert_eq!(chunks[0].file_name, frog_py);
+        assert_eq!(chunks[0].file_action, "edit");
+
+        // nothing is similar to lizard.py, toad_c.py is one letter away from two files
+        for name in ["lizard.py", "toad_c.py"] {
+            let mut chunks = vec![edit_of(name)];
+            let err = correct_and_validate_chunks(gcx.clone(), &mut chunks).await.unwrap_err();
+            assert!(err.contains("doesn't exist"), "unexpected error for {}: {}", name, err);
+        }
+    }
+
+    #[test]
+    fn test_edit_of_missing_file_fuzzy_corrected() {
+        let (workspace, frog_py, mut chunk) = edit_of_missing_frog();
+        let toad_py = workspace.path().join("toad.py").to_string_lossy().to_string();
+        assert!(edit_of_missing_file(&mut chunk.clone(), MissingFilePolicy::Fuzzy, &vec![]).is_err());
+        edit_of_missing_file(&mut chunk, MissingFilePolicy::Fuzzy, &vec![toad_py, frog_py.clone()]).unwrap();
+        assert_eq!(chunk.file_action, "edit");
+        assert_eq!(chunk.file_name, frog_py);
+        let file_text = std::fs::read_to_string(&chunk.file_name).unwrap();
+        let (results, outputs) = apply_diff_chunks_to_text(&file_text, vec![(0, &chunk)], vec![], 1);
+        assert_eq!(outputs.get(&0), Some(&ApplyDiffOutput::Ok()));
+        assert_eq!(results[0].file_text.clone().unwrap(), "def croak():\n    print(\"ribbit\")\n");
+    }
+
+    #[test]
+    fn test_partition_chunks_by_confidence() {
+        let chunk = |line1: usize, confidence: Option<u8>| DiffChunk {
+            file_name: "frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1,
+            line2: line1 + 1,
+            lines_remove: "croak\n".to_string(),
+            lines_add: "ribbit\n".to_string(),
+            confidence,
+            ..Default::default()
+        };
+        let chunks = vec![chunk(1, Some(95)), chunk(5, Some(40)), chunk(9, None), chunk(13, Some(70)), chunk(17, Some(69))];
+
+        let (auto, ask) = partition_chunks_by_confidence(chunks.clone(), 70);
+        assert_eq!(auto.iter().map(|c| c.line1).collect::<Vec<_>>(), vec![1, 9, 13]);
+        assert_eq!(ask.iter().map(|c| c.line1).collect::<Vec<_>>(), vec![5, 17]);
+
+        let (auto, ask) = partition_chunks_by_confidence(chunks.clone(), 0);
+        assert_eq!((auto.len(), ask.len()), (5, 0));
+        let (auto, ask) = partition_chunks_by_confidence(chunks, 100);
+        assert_eq!(auto.iter().map(|c| c.line1).collect::<Vec<_>>(), vec![9]);
+        assert_eq!(ask.len(), 4);
+    }
+
+    #[test]
+    fn test_chunk_at_line() {
+        let chunk = |file_name: &str, line1: usize, line2: usize| DiffChunk {
+            file_name: file_name.to_string(),
+            file_action: "edit".to_string(),
+            line1,
+            line2,
+            ..Default::default()
+        };
+        let chunks = vec![chunk("/pond/frog.py", 3, 6), chunk("/pond/frog.py", 5, 8), chunk("/pond/toad.py", 1, 20), chunk("/pond/frog.py", 10, 10)];
+
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 3), Some(&chunks[0]));
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 5), Some(&chunks[0]));
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 7), Some(&chunks[1]));
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 8), None);
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 2), None);
+        assert_eq!(chunk_at_line(&chunks, "/pond/frog.py", 10), Some(&chunks[3]));
+        assert_eq!(chunk_at_line(&chunks, "/pond/lily.py", 4), None);
+    }
+
+    #[test]
+    fn test_chunk_side_by_side_aligns_changed_lines() {
+        let chunk = DiffChunk {
+            file_name: "frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1: 1,
+            line2: 4,
+            lines_remove: "def jump():\n    x = 1\n    return x\n".to_string(),
+            lines_add: "def jump():\n    x = 2\n    return x * 2\n".to_string(),
+            ..Default::default()
+        };
+
+        let rows = chunk_side_by_side(&chunk);
+
+        assert_eq!(rows, vec![
+            some_pair("def jump():", "def jump():"),
+            some_pair("    x = 1", "    x = 2"),
+            some_pair("    return x", "    return x * 2"),
+        ]);
+    }
+
+    #[test]
+    fn test_preview_file_chunks_reports_failed_chunk() {
+        let file_text = "a\nb\nc\n".to_string();
+        let good_chunk = DiffChunk {
+            file_name: "frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1: 2,
+            line2: 3,
+            lines_remove: "b\n".to_string(),
+            lines_add: "B\n".to_string(),
+            ..Default::default()
+        };
+        let bad_chunk = DiffChunk {
+            lines_remove: "z\n".to_string(),
+            lines_add: "Z\n".to_string(),
+            ..good_chunk.clone()
+        };
+
+        let preview = preview_file_chunks(
+            &"frog.py".to_string(), &file_text, vec![(0, &good_chunk), (1, &bad_chunk)], DiffContextMode::Fixed(1),
+        );
+
+        assert_eq!(preview.before_text, file_text);
+        assert_eq!(preview.after_text, "a\nB\nc\n");
+        assert!(!preview.applied);
+        let reason = preview.reason.unwrap();
+        assert!(reason.starts_with("chunk #1:"), "unexpected reason: {}", reason);
+        assert!(!reason.contains("chunk #0"));
+        assert_eq!(preview.unified_diff, "--- a/frog.py\n+++ b/frog.py\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+    }
+
+    #[tokio::test]
+    async fn test_preview_diff_chunks_never_writes() {
+        let workspace = tempfile::Builder::new().prefix("frog_preview").tempdir().unwrap();
+        let workspace_dir = crate::files_correction::canonical_path(&workspace.path().to_string_lossy().to_string());
+        let frog_py = workspace_dir.join("frog.py").to_string_lossy().to_string();
+        std::fs::write(&frog_py, "a\nb\nc\n").unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(&workspace_dir, vec![PathBuf::from(&frog_py)]).await;
+        let good_chunk = DiffChunk {
+            file_name: frog_py.clone(),
+            file_action: "edit".to_string(),
+            line1: 2,
+            line2: 3,
+            lines_remove: "b\n".to_string(),
+            lines_add: "B\n".to_string(),
+            ..Default::default()
+        };
+        let bad_chunk = DiffChunk {
+            lines_remove: "z\n".to_string(),
+            lines_add: "Z\n".to_string(),
+            ..good_chunk.clone()
+        };
+        let lily_pad = DiffChunk {
+            file_name: workspace_dir.join("lily_pad.py").to_string_lossy().to_string(),
+            file_action: "add".to_string(),
+            line1: 1,
+            line2: 1,
+            lines_add: "class LilyPad:\n    pass\n".to_string(),
+            ..Default::default()
+        };
+
+        let previews = preview_diff_chunks(gcx.clone(), &vec![good_chunk, bad_chunk, lily_pad.clone()], DiffContextMode::Minimal).await;
+
+        assert_eq!(previews.len(), 2);
+        assert_eq!(previews[0].path, frog_py);
+        assert_eq!(previews[0].before_text, "a\nb\nc\n");
+        assert_eq!(previews[0].after_text, "a\nB\nc\n");
+        assert!(!previews[0].applied);
+        let reason = previews[0].reason.clone().unwrap();
+        assert!(reason.starts_with("chunk #1:") && !reason.contains("chunk #0"), "unexpected reason: {}", reason);
+        assert_eq!(previews[0].unified_diff, format!("--- a/{}\n+++ b/{}\n@@ -2,1 +2,1 @@\n-b\n+B\n", frog_py, frog_py));
+        assert_eq!(previews[1].path, lily_pad.file_name);
+        assert_eq!(previews[1].after_text, "class LilyPad:\n    pass\n");
+        assert!(previews[1].applied);
+        assert!(previews[1].unified_diff.is_empty());
+        assert_eq!(std::fs::read_to_string(&frog_py).unwrap(), "a\nb\nc\n");
+        assert!(!PathBuf::from(&lily_pad.file_name).exists());
+    }
+
+    #[test]
+    fn test_chunk_side_by_side_unbalanced() {
+        let chunk = DiffChunk {
+            file_name: "frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1: 1,
+            line2: 2,
+            lines_remove: "a\n".to_string(),
+            lines_add: "b\nc\n".to_string(),
+            ..Default::default()
+        };
+
+        let rows = chunk_side_by_side(&chunk);
+
+        assert_eq!(rows, vec![
+            some_pair("a", "b"),
+            (None, Some("c".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_binary_file_detection() {
+        use std::io::Write;
+        let mut binary = tempfile::NamedTempFile::new().unwrap();
+        binary.write_all(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").unwrap();
+        let mut text = tempfile::NamedTempFile::new().unwrap();
+        text.write_all("def jump():\n    return 1\n".as_bytes()).unwrap();
+
+        assert!(is_binary_file(&binary.path().to_path_buf()).unwrap());
+        assert!(!is_binary_file(&text.path().to_path_buf()).unwrap());
+        assert!(has_control_chars("frog\x07\n"));
+        assert!(!has_control_chars("frog\tjump\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_enclosing_symbol_inside_bounce_off_banks() {
+        let cpath = "tests/emergency_frog_situation/frog.py".to_string();
+        let text = std::fs::read_to_string(&cpath).unwrap();
+        let workspace = tempfile::Builder::new().prefix("frog_enclosing").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(workspace.path(), vec![]).await;
+        let ast_service = crate::ast::ast_indexer_thread::ast_service_init("".to_string(), 10).await;
+        let ast_index = ast_service.lock().await.ast_index.clone();
+        gcx.write().await.ast_service = Some(ast_service);
+        let mut errstats = crate::ast::ast_structs::AstErrorStats::default();
+        crate::ast::ast_db::doc_add(ast_index.clone(), &cpath, &text, &mut errstats).await.unwrap();
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        let chunk_at = |line1: usize, line2: usize| DiffChunk {
+            file_name: cpath.clone(),
+            file_action: "edit".to_string(),
+            line1,
+            line2,
+            ..Default::default()
+        };
+
+        // `elif self.x > pond_width:` and the line below it
+        let symbol = enclosing_symbol_for_chunk(gcx.clone(), &chunk_at(15, 17)).await.unwrap();
+
+        assert!(symbol.ends_with("Frog::bounce_off_banks"), "unexpected symbol: {}", symbol);
+        assert_eq!(enclosing_symbol_for_chunk(gcx.clone(), &chunk_at(1, 3)).await, None);
+        gcx.write().await.ast_service = None;
+        assert_eq!(enclosing_symbol_for_chunk(gcx.clone(), &chunk_at(15, 17)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_commit_context_lists_files_and_symbols() {
+        let cpath = "tests/emergency_frog_situation/frog.py".to_string();
+        let text = std::fs::read_to_string(&cpath).unwrap();
+        let ast_index = crate::ast::ast_db::ast_index_init("".to_string(), 10, false).await;
+        let mut errstats = crate::ast::ast_structs::AstErrorStats::default();
+        crate::ast::ast_db::doc_add(ast_index.clone(), &cpath, &text, &mut errstats).await.unwrap();
+        crate::ast::ast_db::flush_sled_batch(ast_index.clone(), 0).await;
+        let chunks = vec![
+            DiffChunk {
+                file_name: cpath.clone(),
+                file_action: "edit".to_string(),
+                line1: 15,
+                line2: 16,
+                lines_remove: "        elif self.x > pond_width:\n".to_string(),
+                lines_add: "        elif self.x >= pond_width:\n".to_string(),
+                ..Default::default()
+            },
+            DiffChunk {
+                file_name: cpath.clone(),
+                file_action: "edit".to_string(),
+                line1: 22,
+                line2: 23,
+                lines_remove: "        self.x += self.vx * DT\n".to_string(),
+                lines_add: "        # leap\n        self.x += self.vx * DT\n".to_string(),
+                ..Default::default()
+            },
+            DiffChunk {
+                file_name: "tests/emergency_frog_situation/lily_pad.py".to_string(),
+                file_action: "add".to_string(),
+                lines_add: "class LilyPad:\n    pass\n".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let summary = commit_context_with_ast(Some(ast_index.clone()), &chunks, 1000).await;
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "2 file(s) changed, +5 -2");
+        assert!(lines[1].starts_with(&format!("{} (edit) +3 -2: ", cpath)), "unexpected line: {}", lines[1]);
+        assert!(lines[1].contains("Frog::bounce_off_banks") && lines[1].contains("Frog::jump"), "unexpected line: {}", lines[1]);
+        assert_eq!(lines[2], "tests/emergency_frog_situation/lily_pad.py (add) +2 -0");
+
+        let tiny = commit_context_with_ast(Some(ast_index), &chunks, 10).await;
+        assert!(tiny.ends_with("...and 2 more file(s)\n"), "unexpected summary: {}", tiny);
+    }
+
+    fn apply_unified_diff_by_search(file_text: &str, unified_diff: &str) -> String {
+        let mut lines = file_text.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        for hunk in unified_diff.split("\n@@").skip(1) {
+            let body = hunk.lines().skip(1).collect::<Vec<_>>();
+            let old_side = body.iter().filter(|l| !l.starts_with('+')).map(|l| l[1..].to_string()).collect::<Vec<_>>();
+            let new_side = body.iter().filter(|l| !l.starts_with('-')).map(|l| l[1..].to_string()).collect::<Vec<_>>();
+            let positions = (0..=lines.len() - old_side.len())
+                .filter(|&i| lines[i..i + old_side.len()] == old_side[..])
+                .collect::<Vec<_>>();
+            assert_eq!(positions.len(), 1, "hunk is ambiguous or not found:\n{}", hunk);
+            let _ = lines.splice(positions[0]..positions[0] + old_side.len(), new_side);
+        }
+        lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+
+    #[test]
+    fn test_unified_diff_minimal_context() {
+        let file_text = std::fs::read_to_string("tests/emergency_frog_situation/frog.py").unwrap();
+        let chunks = vec![
+            DiffChunk {
+                file_name: "frog.py".to_string(),
+                file_action: "edit".to_string(),
+                line1: 14,
+                line2: 15,
+                lines_remove: "            self.vx = np.abs(self.vx)\n".to_string(),
+                lines_add: "            self.vx = 2 * np.abs(self.vx)\n".to_string(),
+                ..Default::default()
+            },
+            DiffChunk {
+                file_name: "frog.py".to_string(),
+                file_action: "edit".to_string(),
+                line1: 31,
+                line2: 32,
+                lines_remove: "            print(\"croak\")\n".to_string(),
+                lines_add: "            print(\"ribbit\")\n            print(\"croak\")\n".to_string(),
+                ..Default::default()
+            },
+        ];
+        let (results, _) = apply_diff_chunks_to_text(&file_text, chunks.iter().enumerate().collect(), vec![], 1);
+        let expected = results[0].file_text.clone().unwrap();
+        let context_lines_n = |diff: &str| diff.lines().filter(|l| l.starts_with(' ')).count();
+
+        let minimal = chunks_to_unified_diff(&file_text, &chunks, DiffContextMode::Minimal);
+        let fixed = chunks_to_unified_diff(&file_text, &chunks, DiffContextMode::Fixed(3));
+
+        assert!(context_lines_n(&minimal) < context_lines_n(&fixed), "minimal:\n{}\nfixed:\n{}", minimal, fixed);
+        assert_eq!(apply_unified_diff_by_search(&file_text, &minimal), expected);
+        assert_eq!(apply_unified_diff_by_search(&file_text, &fixed), expected);
+    }
+
+    #[test]
+    fn test_chunk_id_stable_across_parses() {
+        use std::io::Write;
+        use crate::tools::tool_patch_aux::search_replace_parser::parse_search_replace;
+        let mut file = tempfile::Builder::new().prefix("frog").suffix(".py").tempfile().unwrap();
+        file.write_all(b"def jump():\n    x = 1\n    return x\n").unwrap();
+        let path = file.path().to_path_buf();
+        let model_output = "<<<<<<<< SEARCH\n    x = 1\n========\n    x = 2\n>>>>>>>> REPLACE\n";
+
+        let first = parse_search_replace(model_output, &path).unwrap();
+        let second = parse_search_replace(model_output, &path).unwrap();
+        let other_body = parse_search_replace(&model_output.replace("x = 2", "x = 3"), &path).unwrap();
+
+        assert_eq!(chunk_id(&first[0]), chunk_id(&second[0]));
+        assert_eq!(chunk_id(&first[0]).len(), 16);
+        assert_ne!(chunk_id(&first[0]), chunk_id(&other_body[0]));
+        let mut with_details = first[0].clone();
+        with_details.application_details = "applied".to_string();
+        assert_eq!(chunk_id(&first[0]), chunk_id(&with_details));
+
+        let unwrapped = unwrap_diff_apply_outputs(HashMap::new(), second.clone());
+        assert_eq!(unwrapped[0].chunk_id, 0);
+        assert_eq!(unwrapped[0].stable_id, chunk_id(&first[0]));
+    }
+}
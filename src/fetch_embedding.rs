@@ -17,6 +17,10 @@ pub async fn get_embedding(
     match endpoint_embeddings_style.to_lowercase().as_str() {
         "hf" => get_embedding_hf_style(client, text, endpoint_template, model_name, api_key).await,
         "openai" => get_embedding_openai_style(client, text, endpoint_template, model_name, api_key).await,
+        #[cfg(feature="local-embeddings")]
+        "local" => crate::local_embeddings::get_embedding_local_style(text, endpoint_template).await,
+        #[cfg(not(feature="local-embeddings"))]
+        "local" => Err("endpoint_embeddings_style \"local\" requires the binary built with --features local-embeddings".to_string()),
         _ => {
             error!("Invalid endpoint_embeddings_style: {}", endpoint_embeddings_style);
             Err("Invalid endpoint_embeddings_style".to_string())
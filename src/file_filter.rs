@@ -1,10 +1,52 @@
+use std::collections::HashMap;
 use std::fs;
 #[cfg(not(windows))]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::info;
+
+use crate::global_context::CommandLine;
 
 const LARGE_FILE_SIZE_THRESHOLD: u64 = 180*1024; // 180k files (180k is ~0.2% of all files on our dataset)
 const SMALL_FILE_SIZE_THRESHOLD: u64 = 5;        // 5 Bytes
+const REFACTIGNORE_FILE_NAME: &str = ".refactignore";
+pub const DEFAULT_MAX_FILE_SIZE_BYTES: u64 = 10*1024*1024;
+
+/// Which files to skip according to the command line, take it from `gcx.read().await.cmdline`
+#[derive(Debug, Clone)]
+pub struct FileFilterSettings {
+    pub max_file_size_bytes: u64,   // unlike LARGE_FILE_SIZE_THRESHOLD it applies even with ignore_size_thresholds
+    pub extra_blacklisted_dirs: Vec<String>,   // --blacklist-dir, in addition to BLACKLISTED_DIRS
+}
+
+impl FileFilterSettings {
+    pub fn from_cmdline(cmdline: &CommandLine) -> Self {
+        FileFilterSettings {
+            max_file_size_bytes: cmdline.max_file_size_bytes,
+            extra_blacklisted_dirs: cmdline.blacklist_dir.clone(),
+        }
+    }
+}
+
+impl Default for FileFilterSettings {
+    fn default() -> Self {
+        FileFilterSettings {
+            max_file_size_bytes: DEFAULT_MAX_FILE_SIZE_BYTES,
+            extra_blacklisted_dirs: vec![],
+        }
+    }
+}
+
+pub fn check_file_size(path: &Path, limit: u64) -> Result<(), String> {
+    let file_size = fs::metadata(path)
+        .map_err(|e| format!("cannot read metadata of {}: {}", path.display(), e))?
+        .len();
+    if file_size > limit {
+        return Err(format!("file {} is too large, {} bytes, the limit is {} bytes (--max-file-size-bytes)", path.display(), file_size, limit));
+    }
+    Ok(())
+}
 
 pub const SOURCE_FILE_EXTENSIONS: &[&str] = &[
     "c", "cpp", "cc", "h", "hpp", "cs", "java", "py", "rb", "go", "rs", "swift",
@@ -24,7 +66,18 @@ pub(crate) const BLACKLISTED_DIRS: &[&str] = &[
     "_trajectories", ".gradle"
 ];
 
-pub fn is_valid_file(path: &PathBuf, allow_hidden_folders: bool, ignore_size_thresholds: bool) -> Result<(), Box<dyn std::error::Error>> {
+pub fn is_blacklisted_dir_name(name: &str, settings: &FileFilterSettings) -> bool {
+    BLACKLISTED_DIRS.contains(&name) || settings.extra_blacklisted_dirs.iter().any(|d| d == name)
+}
+
+/// Built-in blacklisted dir names followed by the ones from the command line.
+pub fn blacklisted_dir_names(settings: &FileFilterSettings) -> Vec<String> {
+    let mut names = BLACKLISTED_DIRS.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+    names.extend(settings.extra_blacklisted_dirs.iter().cloned());
+    names
+}
+
+pub fn is_valid_file(path: &PathBuf, allow_hidden_folders: bool, ignore_size_thresholds: bool, settings: &FileFilterSettings) -> Result<(), Box<dyn std::error::Error>> {
     if !path.is_file() {
         return Err("Path is not a file".into());
     }
@@ -39,6 +92,9 @@ pub fn is_valid_file(path: &PathBuf, allow_hidden_folders: bool, ignore_size_thr
 
     if let Ok(metadata) = fs::metadata(path) {
         let file_size = metadata.len();
+        if file_size > settings.max_file_size_bytes {
+            return Err("File size is above --max-file-size-bytes".into());
+        }
         if !ignore_size_thresholds && file_size < SMALL_FILE_SIZE_THRESHOLD {
             return Err("File size is too small".into());
         }
@@ -58,12 +114,40 @@ pub fn is_valid_file(path: &PathBuf, allow_hidden_folders: bool, ignore_size_thr
     Ok(())
 }
 
-pub fn is_this_inside_blacklisted_dir(path: &PathBuf) -> bool {
+const TEST_DIR_NAMES: [&str; 5] = ["test", "tests", "__tests__", "spec", "specs"];
+
+// By directory (tests/, __tests__/, spec/) or by naming convention: test_frog.py, frog_test.go,
+// frog.spec.ts, FrogTest.java, frog_spec.rb. Words like latest or inspect don't count.
+// Only directories inside the project count, a project checked out into ~/tests/pond is not all tests.
+pub fn is_test_file(path: &Path, project_dirs: &[PathBuf]) -> bool {
+    let inside_project = project_dirs.iter()
+        .filter_map(|dir| path.strip_prefix(dir).ok())
+        .min_by_key(|relative| relative.components().count())
+        .unwrap_or(path);
+    let in_test_dir = inside_project.parent().map_or(false, |dir| dir.components()
+        .any(|c| TEST_DIR_NAMES.contains(&c.as_os_str().to_string_lossy().to_lowercase().as_str())));
+    if in_test_dir {
+        return true;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let parts = name.split('.').collect::<Vec<_>>();
+    if parts.len() > 2 && parts[1..parts.len() - 1].iter().any(|p| *p == "test" || *p == "spec") {
+        return true;
+    }
+    let stem = parts[0];
+    let stem_lower = stem.to_lowercase();
+    stem_lower == "test" || stem_lower == "tests"
+        || stem_lower.starts_with("test_")
+        || stem_lower.ends_with("_test") || stem_lower.ends_with("_tests") || stem_lower.ends_with("_spec")
+        || stem.ends_with("Test") || stem.ends_with("Tests")
+}
+
+pub fn is_this_inside_blacklisted_dir(path: &PathBuf, settings: &FileFilterSettings) -> bool {
     let mut path = path.clone();
     while path.parent().is_some() {
         path = path.parent().unwrap().to_path_buf();
         if let Some(file_name) = path.file_name() {
-            if BLACKLISTED_DIRS.contains(&file_name.to_str().unwrap_or_default()) {
+            if is_blacklisted_dir_name(file_name.to_str().unwrap_or_default(), settings) {
                 return true;
             }
             if let Some(file_name_str) = file_name.to_str() {
@@ -76,3 +160,156 @@ pub fn is_this_inside_blacklisted_dir(path: &PathBuf) -> bool {
     false
 }
 
+
+/// `.refactignore` files (gitignore syntax) found between a workspace root and the files under it.
+/// Patterns are relative to the folder containing the ignore file, the deepest file that
+/// has an opinion about a path wins, same as nested .gitignore files.
+pub struct RefactIgnore {
+    root: PathBuf,
+    matchers: HashMap<PathBuf, Option<Gitignore>>,
+    ignored_dirs: HashMap<PathBuf, bool>,
+}
+
+impl RefactIgnore {
+    pub fn new(root: &PathBuf) -> Self {
+        RefactIgnore {
+            root: root.clone(),
+            matchers: HashMap::new(),
+            ignored_dirs: HashMap::new(),
+        }
+    }
+
+    fn matcher_for_dir(&mut self, dir: &Path) -> Option<&Gitignore> {
+        self.matchers.entry(dir.to_path_buf()).or_insert_with(|| {
+            let ignore_file = dir.join(REFACTIGNORE_FILE_NAME);
+            if !ignore_file.is_file() {
+                return None;
+            }
+            let mut builder = GitignoreBuilder::new(dir);
+            if let Some(e) = builder.add(&ignore_file) {
+                info!("problem reading {}: {}", ignore_file.display(), e);
+            }
+            builder.build().map_err(|e| info!("cannot use {}: {}", ignore_file.display(), e)).ok()
+        }).as_ref()
+    }
+
+    fn matched_by_own_patterns(&mut self, path: &Path, is_dir: bool) -> bool {
+        let dirs = path.ancestors().skip(1)
+            .take_while(|d| d.starts_with(&self.root))
+            .map(|d| d.to_path_buf())
+            .collect::<Vec<_>>();
+        for dir in dirs {
+            if let Some(matcher) = self.matcher_for_dir(&dir) {
+                let m = matcher.matched(path, is_dir);
+                if m.is_ignore() {
+                    return true;
+                }
+                if m.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn is_dir_ignored(&mut self, dir: &Path) -> bool {
+        if dir == self.root.as_path() || !dir.starts_with(&self.root) {
+            return false;
+        }
+        if let Some(ignored) = self.ignored_dirs.get(dir) {
+            return *ignored;
+        }
+        let ignored = dir.parent().map(|p| self.is_dir_ignored(p)).unwrap_or(false)
+            || self.matched_by_own_patterns(dir, true);
+        self.ignored_dirs.insert(dir.to_path_buf(), ignored);
+        ignored
+    }
+
+    pub fn is_file_ignored(&mut self, path: &Path) -> bool {
+        if !path.starts_with(&self.root) {
+            return false;
+        }
+        path.parent().map(|p| self.is_dir_ignored(p)).unwrap_or(false)
+            || self.matched_by_own_patterns(path, false)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_test_file_by_dir_and_naming_convention() {
+        for path in [
+            "/pond/tests/frog.rs", "/pond/src/__tests__/frog.js", "/pond/test_frog.py", "/pond/frog_test.go",
+            "/pond/frog.spec.ts", "/pond/frog.test.jsx", "/pond/src/FrogTest.java", "/pond/FrogTests.cs", "/pond/frog_spec.rb",
+        ] {
+            assert!(is_test_file(Path::new(path), &[]), "{} should be a test", path);
+        }
+        for path in [
+            "/pond/latest.py", "/pond/contest.rs", "/pond/inspect.py", "/pond/spectral.py", "/pond/src/Contest.java",
+            "/pond/testament/frog.py", "/pond/frog.py",
+        ] {
+            assert!(!is_test_file(Path::new(path), &[]), "{} should not be a test", path);
+        }
+    }
+
+    #[test]
+    fn test_is_test_file_looks_inside_the_project_only() {
+        let project_dirs = vec![PathBuf::from("/home/frog/tests/pond"), PathBuf::from("/home/frog/swamp")];
+        assert!(!is_test_file(Path::new("/home/frog/tests/pond/frog.py"), &project_dirs));
+        assert!(!is_test_file(Path::new("/home/frog/tests/pond/src/lily.rs"), &project_dirs));
+        assert!(is_test_file(Path::new("/home/frog/tests/pond/tests/frog.py"), &project_dirs));
+        assert!(is_test_file(Path::new("/home/frog/tests/pond/test_frog.py"), &project_dirs));
+        assert!(is_test_file(Path::new("/home/frog/swamp/spec/heron.rb"), &project_dirs));
+        // outside of any project the whole path counts
+        assert!(is_test_file(Path::new("/home/frog/tests/newt.py"), &project_dirs));
+    }
+
+    #[test]
+    fn test_check_file_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("frog.log");
+        fs::write(&path, "croak\n".repeat(100)).unwrap();
+
+        assert!(check_file_size(&path, 600).is_ok());
+        let err = check_file_size(&path, 599).unwrap_err();
+        assert!(err.contains("600 bytes") && err.contains("599"), "unexpected error: {}", err);
+        assert!(check_file_size(&tmp.path().join("no_such_frog.py"), 600).is_err());
+    }
+
+    fn touch(root: &Path, rel: &str) -> PathBuf {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "print('frog')\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_refactignore_nested_files_compose() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+        fs::write(root.join(REFACTIGNORE_FILE_NAME), "generated/\n*.log\n").unwrap();
+        fs::create_dir_all(root.join("pond")).unwrap();
+        fs::write(root.join("pond").join(REFACTIGNORE_FILE_NAME), "/tadpoles.py\n!keep.log\n").unwrap();
+        let frog = touch(&root, "frog.py");
+        let generated = touch(&root, "generated/schema.py");
+        let deep_generated = touch(&root, "pond/generated/lily.py");
+        let log = touch(&root, "pond/jumps.log");
+        let kept_log = touch(&root, "pond/keep.log");
+        let tadpoles = touch(&root, "pond/tadpoles.py");
+        let root_tadpoles = touch(&root, "tadpoles.py");
+
+        let mut refactignore = RefactIgnore::new(&root);
+
+        assert!(!refactignore.is_file_ignored(&frog));
+        assert!(refactignore.is_dir_ignored(&root.join("generated")));
+        assert!(refactignore.is_file_ignored(&generated));
+        assert!(refactignore.is_file_ignored(&deep_generated));
+        assert!(refactignore.is_file_ignored(&log));
+        assert!(!refactignore.is_file_ignored(&kept_log));
+        assert!(refactignore.is_file_ignored(&tadpoles));
+        assert!(!refactignore.is_file_ignored(&root_tadpoles));
+    }
+}
@@ -1,14 +1,14 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
-use std::path::{Component, PathBuf};
+use std::path::{Component, PathBuf, Prefix, PrefixComponent};
 use serde::Deserialize;
 use tokio::sync::RwLock as ARwLock;
 use tracing::info;
 
 use crate::files_in_workspace::detect_vcs_for_a_file_path;
 use crate::global_context::GlobalContext;
-use crate::fuzzy_search::fuzzy_search;
+use crate::fuzzy_search::{fuzzy_search, fuzzy_search_indexed, TrigramIndex};
 
 
 pub async fn paths_from_anywhere(global_context: Arc<ARwLock<GlobalContext>>) -> Vec<PathBuf> {
@@ -27,62 +27,137 @@ pub async fn paths_from_anywhere(global_context: Arc<ARwLock<GlobalContext>>) ->
     paths_from_anywhere.collect::<Vec<PathBuf>>()
 }
 
+fn path_suffixes(path_str: &String) -> Vec<String> {
+    let mut suffixes = vec![path_str.clone()];
+    // chop off directory names one by one
+    let mut index = 0;
+    while let Some(slashpos) = path_str[index .. ].find(|c| c == '/' || c == '\\') {
+        let absolute_slashpos = index + slashpos;
+        index = absolute_slashpos + 1;
+        let slashpos_to_end = &path_str[index .. ];
+        if !slashpos_to_end.is_empty() {
+            suffixes.push(slashpos_to_end.to_string());
+        }
+    }
+    suffixes
+}
+
+// Find the shortest unique suffix for the path, that is at least the path from workspace root
+fn shortest_unique_suffix(
+    path: &PathBuf,
+    cache_correction: &HashMap<String, HashSet<String>>,
+    workspace_folders: &Vec<PathBuf>,
+) -> String {
+    let workspace_components_len = workspace_folders.iter()
+        .filter_map(|workspace_dir| {
+            if path.starts_with(workspace_dir) {
+                Some(workspace_dir.components().count())
+            } else {
+                None
+            }
+        })
+        .max()
+        .unwrap_or(0);
+
+    let path_is_dir = path.to_string_lossy().ends_with(std::path::MAIN_SEPARATOR);
+    let mut current_suffix = PathBuf::new();
+    let path_components_count = path.components().count();
+    for component in path.components().rev() {
+        if !current_suffix.as_os_str().is_empty() || path_is_dir {
+            current_suffix = PathBuf::from(component.as_os_str()).join(&current_suffix);
+        } else {
+            current_suffix = PathBuf::from(component.as_os_str());
+        }
+        let suffix = current_suffix.to_string_lossy().into_owned();
+        if cache_correction.get(suffix.as_str()).map_or(0, |v| v.len()) == 1 &&
+            current_suffix.components().count() + workspace_components_len >= path_components_count {
+            return suffix;
+        }
+    }
+    path.to_string_lossy().into_owned()
+}
+
 fn make_cache(paths: &Vec<PathBuf>, workspace_folders: &Vec<PathBuf>) -> (
     HashMap<String, HashSet<String>>, HashSet<String>, usize
 ) {
     let mut cache_correction = HashMap::<String, HashSet<String>>::new();
-    let mut cnt = 0;
 
     for path in paths {
         let path_str = path.to_str().unwrap_or_default().to_string();
+        for suffix in path_suffixes(&path_str) {
+            cache_correction.entry(suffix).or_insert_with(HashSet::new).insert(path_str.clone());
+        }
+    }
 
-        cache_correction.entry(path_str.clone()).or_insert_with(HashSet::new).insert(path_str.clone());
-        // chop off directory names one by one
-        let mut index = 0;
-        while let Some(slashpos) = path_str[index .. ].find(|c| c == '/' || c == '\\') {
-            let absolute_slashpos = index + slashpos;
-            index = absolute_slashpos + 1;
-            let slashpos_to_end = &path_str[index .. ];
-            if !slashpos_to_end.is_empty() {
-                cache_correction.entry(slashpos_to_end.to_string()).or_insert_with(HashSet::new).insert(path_str.clone());
+    let cache_shortened: HashSet<String> = paths.iter()
+        .map(|path| shortest_unique_suffix(path, &cache_correction, workspace_folders))
+        .collect();
+    let cnt = paths.len();
+
+    (cache_correction, cache_shortened, cnt)
+}
+
+// Paths sharing at least one suffix with `path_str` might need a different shortened form now
+fn reshorten_paths_sharing_suffixes(
+    cache_correction: &HashMap<String, HashSet<String>>,
+    cache_shortened: &mut HashSet<String>,
+    fuzzy_index: &mut TrigramIndex,
+    affected: &HashSet<String>,
+    workspace_folders: &Vec<PathBuf>,
+) {
+    for p in affected.iter() {
+        for suffix in path_suffixes(p) {
+            if cache_shortened.remove(&suffix) {
+                fuzzy_index.remove(&suffix);
             }
         }
     }
+    for p in affected.iter() {
+        let shortened = shortest_unique_suffix(&PathBuf::from(p), cache_correction, workspace_folders);
+        fuzzy_index.add(&shortened);
+        cache_shortened.insert(shortened);
+    }
+}
 
-    // Find the shortest unique suffix for each path, that is at least the path from workspace root
-    let cache_shortened: HashSet<String> = paths.iter().map(|path| {
-        let workspace_components_len = workspace_folders.iter()
-            .filter_map(|workspace_dir| {
-                if path.starts_with(workspace_dir) {
-                    Some(workspace_dir.components().count())
-                } else {
-                    None
-                }
-            })
-            .max()
-            .unwrap_or(0);
+fn cache_add_path(
+    cache_correction: &mut HashMap<String, HashSet<String>>,
+    cache_shortened: &mut HashSet<String>,
+    fuzzy_index: &mut TrigramIndex,
+    path: &PathBuf,
+    workspace_folders: &Vec<PathBuf>,
+) {
+    let path_str = path.to_str().unwrap_or_default().to_string();
+    let mut affected = HashSet::new();
+    for suffix in path_suffixes(&path_str) {
+        let paths = cache_correction.entry(suffix).or_insert_with(HashSet::new);
+        paths.insert(path_str.clone());
+        affected.extend(paths.iter().cloned());
+    }
+    reshorten_paths_sharing_suffixes(cache_correction, cache_shortened, fuzzy_index, &affected, workspace_folders);
+}
 
-        let path_is_dir = path.to_string_lossy().ends_with(std::path::MAIN_SEPARATOR);
-        let mut current_suffix = PathBuf::new();
-        let path_components_count = path.components().count();
-        for component in path.components().rev() {
-            if !current_suffix.as_os_str().is_empty() || path_is_dir {
-                current_suffix = PathBuf::from(component.as_os_str()).join(&current_suffix);
-            } else {
-                current_suffix = PathBuf::from(component.as_os_str());
-            }
-            let suffix = current_suffix.to_string_lossy().into_owned();
-            if cache_correction.get(suffix.as_str()).map_or(0, |v| v.len()) == 1 &&
-                current_suffix.components().count() + workspace_components_len >= path_components_count {
-                cnt += 1;
-                return suffix;
+fn cache_remove_path(
+    cache_correction: &mut HashMap<String, HashSet<String>>,
+    cache_shortened: &mut HashSet<String>,
+    fuzzy_index: &mut TrigramIndex,
+    path: &PathBuf,
+    workspace_folders: &Vec<PathBuf>,
+) {
+    let path_str = path.to_str().unwrap_or_default().to_string();
+    let mut affected = HashSet::new();
+    for suffix in path_suffixes(&path_str) {
+        if let Some(paths) = cache_correction.get_mut(&suffix) {
+            paths.remove(&path_str);
+            affected.extend(paths.iter().cloned());
+            if paths.is_empty() {
+                cache_correction.remove(&suffix);
             }
         }
-        cnt += 1;
-        path.to_string_lossy().into_owned()
-    }).collect();
-
-    (cache_correction, cache_shortened, cnt)
+        if cache_shortened.remove(&suffix) {
+            fuzzy_index.remove(&suffix);
+        }
+    }
+    reshorten_paths_sharing_suffixes(cache_correction, cache_shortened, fuzzy_index, &affected, workspace_folders);
 }
 
 pub async fn files_cache_rebuild_as_needed(global_context: Arc<ARwLock<GlobalContext>>) -> (Arc<HashMap<String, HashSet<String>>>, Arc<HashSet<String>>) {
@@ -104,6 +179,7 @@ pub async fn files_cache_rebuild_as_needed(global_context: Arc<ARwLock<GlobalCon
         let paths_from_anywhere = paths_from_anywhere(global_context.clone()).await;
         let workspace_folders = get_project_dirs(global_context.clone()).await;
         let (cache_correction, cache_shortened, cnt) = make_cache(&paths_from_anywhere, &workspace_folders);
+        let fuzzy_index = TrigramIndex::new(cache_shortened.iter());
 
         info!("rebuild completed in {:.3}s, {} URLs => cache_correction.len is now {}", start_time.elapsed().as_secs_f64(), cnt, cache_correction.len());
         cache_correction_arc = Arc::new(cache_correction);
@@ -112,6 +188,7 @@ pub async fn files_cache_rebuild_as_needed(global_context: Arc<ARwLock<GlobalCon
             let mut cx = global_context.write().await;
             cx.documents_state.cache_correction = cache_correction_arc.clone();
             cx.documents_state.cache_shortened = cache_shortened_arc.clone();
+            cx.documents_state.cache_fuzzy_index = Arc::new(fuzzy_index);
         }
         *cache_dirty_ref = 0.0;
     }
@@ -120,6 +197,46 @@ pub async fn files_cache_rebuild_as_needed(global_context: Arc<ARwLock<GlobalCon
 }
 
 
+async fn files_cache_update_path(gcx: Arc<ARwLock<GlobalContext>>, path: &PathBuf, add: bool) {
+    let cache_dirty_arc = gcx.read().await.documents_state.cache_dirty.clone();
+    // same lock as the full rebuild, so updates and rebuilds don't interleave
+    let cache_dirty_ref = cache_dirty_arc.lock().await;
+    if *cache_dirty_ref > 0.0 {
+        return;  // full rebuild is pending anyway, it will see this path
+    }
+    let workspace_folders = get_project_dirs(gcx.clone()).await;
+    let t0 = Instant::now();
+    let mut cx = gcx.write().await;
+    if !add {
+        let documents_state = &cx.documents_state;
+        let still_known = documents_state.memory_document_map.contains_key(path) ||
+            documents_state.workspace_files.lock().unwrap().contains(path) ||
+            documents_state.jsonl_files.lock().unwrap().contains(path);
+        if still_known {
+            return;
+        }
+    }
+    let documents_state = &mut cx.documents_state;
+    // clones only if someone still holds the old version
+    let cache_correction = Arc::make_mut(&mut documents_state.cache_correction);
+    let cache_shortened = Arc::make_mut(&mut documents_state.cache_shortened);
+    let fuzzy_index = Arc::make_mut(&mut documents_state.cache_fuzzy_index);
+    if add {
+        cache_add_path(cache_correction, cache_shortened, fuzzy_index, path, &workspace_folders);
+    } else {
+        cache_remove_path(cache_correction, cache_shortened, fuzzy_index, path, &workspace_folders);
+    }
+    info!("files cache {} {} in {:.3}s", if add { "added" } else { "removed" }, crate::nicer_logs::last_n_chars(&path.to_string_lossy().to_string(), 30), t0.elapsed().as_secs_f64());
+}
+
+pub async fn files_cache_add_path(gcx: Arc<ARwLock<GlobalContext>>, path: &PathBuf) {
+    files_cache_update_path(gcx, path, true).await
+}
+
+pub async fn files_cache_remove_path(gcx: Arc<ARwLock<GlobalContext>>, path: &PathBuf) {
+    files_cache_update_path(gcx, path, false).await
+}
+
 fn winpath_normalize(p: &str) -> PathBuf {
     // horrible_path//..\project1\project1/1.cpp
     // everything should become an absolute \\?\ path on windows
@@ -201,7 +318,7 @@ pub async fn correct_to_nearest_filename(
         return vec![fixed.to_string_lossy().to_string()];
     }
 
-    let (cache_correction_arc, cache_fuzzy_arc) = files_cache_rebuild_as_needed(gcx.clone()).await;
+    let (cache_correction_arc, _) = files_cache_rebuild_as_needed(gcx.clone()).await;
     // it's dangerous to use cache_correction_arc without a mutex, but should be fine as long as it's read-only
     // (another thread never writes to the map itself, it can only replace the arc with a different map)
 
@@ -212,8 +329,9 @@ pub async fn correct_to_nearest_filename(
     }
 
     if fuzzy {
-        info!("fuzzy search {:?}, cache_fuzzy_arc.len={}", correction_candidate, cache_fuzzy_arc.len());
-        return fuzzy_search(correction_candidate, cache_fuzzy_arc.iter().cloned(), top_n, &['/', '\\']);
+        let fuzzy_index = gcx.read().await.documents_state.cache_fuzzy_index.clone();
+        info!("fuzzy search {:?}, fuzzy_index.len={}", correction_candidate, fuzzy_index.len());
+        return fuzzy_search_indexed(correction_candidate, &fuzzy_index, top_n, &['/', '\\']);
     }
 
     return vec![];
@@ -268,6 +386,52 @@ pub async fn correct_to_nearest_dir_path(
     vec![]
 }
 
+fn split_file_line_ref(s: &str) -> Result<(String, Option<(usize, usize)>), String> {
+    let s = s.trim();
+    let (file, suffix) = match s.rsplit_once(':') {
+        // only digits and dashes after the colon make it a line reference, otherwise it's part of the path (C:\...)
+        Some((file, suffix)) if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit() || c == '-') => (file, suffix),
+        _ => return Ok((s.to_string(), None)),
+    };
+    let parse_line = |x: &str| -> Result<usize, String> {
+        match x.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("invalid line number {:?} in {:?}, lines start from 1", x, s)),
+        }
+    };
+    let range = match suffix.split_once('-') {
+        None => {
+            let line = parse_line(suffix)?;
+            (line, line)
+        }
+        Some((line1, line2)) => {
+            let (line1, line2) = (parse_line(line1)?, parse_line(line2)?);
+            if line1 > line2 {
+                return Err(format!("invalid range in {:?}: {} > {}", s, line1, line2));
+            }
+            (line1, line2)
+        }
+    };
+    Ok((file.to_string(), Some(range)))
+}
+
+/// Resolves `file.py`, `file.py:10` or `file.py:10-20` into a full path and an inclusive 1-based line range.
+pub async fn parse_file_line_ref(s: &str, gcx: Arc<ARwLock<GlobalContext>>) -> Result<(PathBuf, Option<(usize, usize)>), String> {
+    let (file, range) = split_file_line_ref(s)?;
+    if file.is_empty() {
+        return Err(format!("no file name in {:?}", s));
+    }
+    let candidates = correct_to_nearest_filename(gcx.clone(), &file, false, 10).await;
+    match candidates.len() {
+        0 => Err(format!("cannot find file {:?}", file)),
+        1 => Ok((PathBuf::from(&candidates[0]), range)),
+        _ => {
+            let shortified = shortify_paths(gcx.clone(), &candidates).await;
+            Err(format!("ambiguous file name {:?}, could be:\n{}", file, shortified.join("\n")))
+        }
+    }
+}
+
 pub async fn get_project_dirs(gcx: Arc<ARwLock<GlobalContext>>) -> Vec<PathBuf> {
     let workspace_folders = gcx.read().await.documents_state.workspace_folders.clone();
     let workspace_folders_locked = workspace_folders.lock().unwrap();
@@ -382,6 +546,18 @@ fn absolute(path: &std::path::Path) -> std::io::Result<PathBuf> {
     Ok(normalized)
 }
 
+// Windows compares drive letters case-insensitively and the IDE may send C:\ while the file system gives c:\,
+// also canonicalize() gives \\?\c:\ for existing files but a removed file can only be made absolute, that gives c:\.
+// All of them become \\?\c:\ so the same file is always the same key.
+fn normalize_path_prefix(prefix: &PrefixComponent) -> String {
+    match prefix.kind() {
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            format!(r"\\?\{}:", (letter as char).to_ascii_lowercase())
+        }
+        _ => prefix.as_os_str().to_string_lossy().to_string().to_lowercase(),
+    }
+}
+
 pub fn canonical_path(s: &str) -> PathBuf {
     let mut res = match PathBuf::from(s).canonicalize() {
         Ok(x) => x,
@@ -395,10 +571,7 @@ pub fn canonical_path(s: &str) -> PathBuf {
         .components()
         .map(|x| match x {
             Component::Normal(c) => c.to_string_lossy().to_string(),
-            Component::Prefix(c) => {
-                let lowercase_prefix = c.as_os_str().to_string_lossy().to_string().to_lowercase();
-                lowercase_prefix
-            },
+            Component::Prefix(c) => normalize_path_prefix(&c),
             _ => x.as_os_str().to_string_lossy().to_string(),
         })
         .collect();
@@ -422,6 +595,29 @@ pub fn deserialize_path<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Re
 mod tests {
     use super::*;
 
+    #[cfg(windows)]
+    #[test]
+    fn test_uppercase_drive_letter_matches_known_file() {
+        let workspace_files = vec![canonical_path(r"\\?\c:\frog_pond\lily.py")];
+        // a removed file can't be canonicalized, and the watcher or the IDE may report it with an uppercase drive letter
+        assert!(workspace_files.contains(&canonical_path(r"C:\frog_pond\lily.py")));
+        assert!(workspace_files.contains(&canonical_path(r"c:/frog_pond/lily.py")));
+        assert!(!workspace_files.contains(&canonical_path(r"D:\frog_pond\lily.py")));
+    }
+
+    #[test]
+    fn test_split_file_line_ref() {
+        assert_eq!(split_file_line_ref("foo.py"), Ok(("foo.py".to_string(), None)));
+        assert_eq!(split_file_line_ref("foo.py:10"), Ok(("foo.py".to_string(), Some((10, 10)))));
+        assert_eq!(split_file_line_ref("foo.py:10-20"), Ok(("foo.py".to_string(), Some((10, 20)))));
+        assert_eq!(split_file_line_ref("C:\\frog\\foo.py:3"), Ok(("C:\\frog\\foo.py".to_string(), Some((3, 3)))));
+        assert_eq!(split_file_line_ref("C:\\frog\\foo.py"), Ok(("C:\\frog\\foo.py".to_string(), None)));
+        assert!(split_file_line_ref("foo.py:20-10").is_err());
+        assert!(split_file_line_ref("foo.py:10-").is_err());
+        assert!(split_file_line_ref("foo.py:0").is_err());
+        assert!(split_file_line_ref("foo.py:1-2-3").is_err());
+    }
+
     #[test]
     fn test_make_cache() {
         // Arrange
@@ -529,6 +725,75 @@ mod tests {
         assert_eq!(cache_shortened_result.len(), cnt);
     }
 
+    #[test]
+    fn test_incremental_cache_matches_full_rebuild() {
+        // Arrange
+        let workspace_folders = vec![
+            PathBuf::from("home").join("user").join("repo1"),
+            PathBuf::from("home").join("user").join("repo2"),
+        ];
+        let paths = vec![
+            PathBuf::from("home").join("user").join("repo1").join("dir").join("file.ext"),
+            PathBuf::from("home").join("user").join("repo1").join("this_file.ext"),
+            PathBuf::from("home").join("user").join("repo2").join("dir2"),
+        ];
+        let new_path = PathBuf::from("home").join("user").join("repo2").join("dir").join("file.ext");
+        let (mut cache_correction, mut cache_shortened, _) = make_cache(&paths, &workspace_folders);
+        let mut fuzzy_index = TrigramIndex::new(cache_shortened.iter());
+
+        // Act
+        cache_add_path(&mut cache_correction, &mut cache_shortened, &mut fuzzy_index, &new_path, &workspace_folders);
+
+        // Assert
+        let mut all_paths = paths.clone();
+        all_paths.push(new_path.clone());
+        let (expected_correction, expected_shortened, _) = make_cache(&all_paths, &workspace_folders);
+        assert_eq!(cache_correction, expected_correction);
+        assert_eq!(cache_shortened, expected_shortened);
+        assert_eq!(fuzzy_index.iter().cloned().collect::<HashSet<_>>(), expected_shortened);
+
+        cache_remove_path(&mut cache_correction, &mut cache_shortened, &mut fuzzy_index, &new_path, &workspace_folders);
+        let (expected_correction, expected_shortened, _) = make_cache(&paths, &workspace_folders);
+        assert_eq!(cache_correction, expected_correction);
+        assert_eq!(cache_shortened, expected_shortened);
+        assert_eq!(fuzzy_index.iter().cloned().collect::<HashSet<_>>(), expected_shortened);
+    }
+
+    // cicd works with virtual machine, this test is slow
+    #[cfg(not(all(target_arch = "aarch64", target_os = "linux")))]
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn test_incremental_cache_speed() {
+        // Arrange
+        let workspace_paths = vec![
+            PathBuf::from("home").join("user").join("repo1"),
+            PathBuf::from("home").join("user").join("repo2"),
+        ];
+        let mut paths = Vec::new();
+        for i in 0..50000 {
+            let path = workspace_paths[i % workspace_paths.len()]
+                .join(format!("dir{}", i % 500))
+                .join(format!("file{}.ext", i));
+            paths.push(path);
+        }
+        let start_time = std::time::Instant::now();
+        let (mut cache_correction, mut cache_shortened, _) = make_cache(&paths, &workspace_paths);
+        let mut fuzzy_index = TrigramIndex::new(cache_shortened.iter());
+        let rebuild_time = start_time.elapsed();
+
+        // Act
+        let start_time = std::time::Instant::now();
+        for i in 0..100 {
+            let new_path = workspace_paths[0].join(format!("dir{}", i)).join(format!("new_file{}.ext", i));
+            cache_add_path(&mut cache_correction, &mut cache_shortened, &mut fuzzy_index, &new_path, &workspace_paths);
+        }
+        let incremental_time = start_time.elapsed();
+
+        // Assert
+        assert!(incremental_time * 10 < rebuild_time, "100 incremental adds took {:?}, full rebuild {:?}", incremental_time, rebuild_time);
+        assert_eq!(cache_shortened.len(), 50100);
+    }
+
     // cicd works with virtual machine, this test is slow
     #[cfg(not(all(target_arch = "aarch64", target_os = "linux")))]
     #[cfg(not(debug_assertions))]
@@ -1,13 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::hash::Hash;
 use std::path::PathBuf;
 use std::sync::{Arc, Weak, Mutex as StdMutex};
 use std::time::Instant;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind};
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode};
+use rand::Rng;
 use ropey::Rope;
+use serde::Serialize;
 use tokio::sync::{RwLock as ARwLock, Mutex as AMutex};
 use walkdir::WalkDir;
 use which::which;
@@ -16,9 +18,12 @@ use tracing::info;
 use crate::git::operations::git_ls_files;
 use crate::global_context::GlobalContext;
 use crate::telemetry;
-use crate::file_filter::{is_this_inside_blacklisted_dir, is_valid_file, BLACKLISTED_DIRS, SOURCE_FILE_EXTENSIONS};
+use crate::file_filter::{is_blacklisted_dir_name, is_this_inside_blacklisted_dir, is_valid_file, FileFilterSettings, RefactIgnore, SOURCE_FILE_EXTENSIONS};
 use crate::ast::ast_indexer_thread::ast_indexer_enqueue_files;
 use crate::privacy::{check_file_privacy, load_privacy_if_needed, PrivacySettings, FilePrivacyLevel};
+use crate::integrations::process_io_utils::{output_with_timeout, subprocess_timeout};
+
+const CACHE_INCREMENTAL_UPDATE_MAX_FILES: usize = 100;  // more than that at once, and a full rebuild is faster
 
 
 #[derive(Debug, Eq, Hash, PartialEq, Clone)]
@@ -37,7 +42,8 @@ pub async fn get_file_text_from_memory_or_disk(global_context: Arc<ARwLock<Globa
             return Ok(doc.doc_text.as_ref().unwrap().to_string());
         }
     }
-    read_file_from_disk_without_privacy_check(&file_path)
+    let max_file_size_bytes = global_context.read().await.cmdline.max_file_size_bytes;
+    read_file_from_disk_without_privacy_check(&file_path, max_file_size_bytes)
         .await.map(|x|x.to_string())
         .map_err(|e|format!("Not found in memory, not found on disk: {}", e))
 }
@@ -49,7 +55,8 @@ impl Document {
 
     #[cfg(feature="vecdb")]
     pub async fn update_text_from_disk(&mut self, gcx: Arc<ARwLock<GlobalContext>>) -> Result<(), String> {
-        match read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &self.doc_path).await {
+        let max_file_size_bytes = gcx.read().await.cmdline.max_file_size_bytes;
+        match read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &self.doc_path, max_file_size_bytes).await {
             Ok(res) => {
                 self.doc_text = Some(res);
                 return Ok(());
@@ -64,7 +71,8 @@ impl Document {
         if self.doc_text.is_some() {
             return Ok(self.doc_text.as_ref().unwrap().to_string());
         }
-        read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &self.doc_path).await.map(|x|x.to_string())
+        let max_file_size_bytes = gcx.read().await.cmdline.max_file_size_bytes;
+        read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &self.doc_path, max_file_size_bytes).await.map(|x|x.to_string())
     }
 
     pub fn update_text(&mut self, text: &String) {
@@ -114,23 +122,25 @@ pub struct DocumentsState {
     pub cache_dirty: Arc<AMutex<f64>>,
     pub cache_correction: Arc<HashMap<String, HashSet<String>>>,  // map dir3/file.ext -> to /dir1/dir2/dir3/file.ext
     pub cache_shortened: Arc<HashSet<String>>,
+    pub cache_fuzzy_index: Arc<crate::fuzzy_search::TrigramIndex>,  // over cache_shortened
+    pub indexing_stats: Arc<StdMutex<WorkspaceIndexingStats>>,
     pub fs_watcher: Arc<ARwLock<RecommendedWatcher>>,
 }
 
 async fn mem_overwrite_or_create_document(
     global_context: Arc<ARwLock<GlobalContext>>,
     document: Document
-) -> (Arc<ARwLock<Document>>, Arc<AMutex<f64>>, bool) {
+) -> (Arc<ARwLock<Document>>, bool) {
     let mut cx = global_context.write().await;
     let doc_map = &mut cx.documents_state.memory_document_map;
     if let Some(existing_doc) = doc_map.get_mut(&document.doc_path) {
         *existing_doc.write().await = document;
-        (existing_doc.clone(), cx.documents_state.cache_dirty.clone(), false)
+        (existing_doc.clone(), false)
     } else {
         let path = document.doc_path.clone();
         let darc = Arc::new(ARwLock::new(document));
         doc_map.insert(path, darc.clone());
-        (darc, cx.documents_state.cache_dirty.clone(), true)
+        (darc, true)
     }
 }
 
@@ -149,6 +159,8 @@ impl DocumentsState {
             cache_dirty: Arc::new(AMutex::<f64>::new(0.0)),
             cache_correction: Arc::new(HashMap::<String, HashSet<String>>::new()),
             cache_shortened: Arc::new(HashSet::<String>::new()),
+            cache_fuzzy_index: Arc::new(crate::fuzzy_search::TrigramIndex::default()),
+            indexing_stats: Arc::new(StdMutex::new(WorkspaceIndexingStats::default())),
             fs_watcher: Arc::new(ARwLock::new(watcher)),
         }
     }
@@ -158,13 +170,15 @@ pub async fn watcher_init(
     gcx: Arc<ARwLock<GlobalContext>>
 ) {
     let gcx_weak = Arc::downgrade(&gcx);
-    let rt = tokio::runtime::Handle::current();
-    let event_callback = move |res| {
-        rt.block_on(async {
-            if let Ok(event) = res {
-                file_watcher_event(event, gcx_weak.clone()).await;
-            }
-        });
+    let debounce_window = std::time::Duration::from_millis(gcx.read().await.cmdline.watcher_debounce_ms);
+    let max_files_per_minute = gcx.read().await.cmdline.watcher_max_files_per_minute;
+    let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
+    // the loop stops when the watcher (and the sender inside the callback) is dropped
+    tokio::spawn(watcher_debounce_loop(events_rx, gcx_weak, debounce_window, max_files_per_minute));
+    let event_callback = move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = events_tx.send(event);
+        }
     };
     let mut watcher = RecommendedWatcher::new(event_callback, Config::default()).unwrap();
 
@@ -184,7 +198,9 @@ pub async fn watcher_init(
 
 async fn read_file_from_disk_without_privacy_check(
     path: &PathBuf,
+    max_file_size_bytes: u64,
 ) -> Result<Rope, String> {
+    crate::file_filter::check_file_size(path, max_file_size_bytes)?;
     tokio::fs::read_to_string(path).await
         .map(|x|Rope::from_str(&x))
         .map_err(|e|
@@ -195,19 +211,21 @@ async fn read_file_from_disk_without_privacy_check(
 pub async fn read_file_from_disk(
     privacy_settings: Arc<PrivacySettings>,
     path: &PathBuf,
+    max_file_size_bytes: u64,
 ) -> Result<Rope, String> {
     check_file_privacy(privacy_settings, path, &FilePrivacyLevel::AllowToSendAnywhere)?;
-    read_file_from_disk_without_privacy_check(path).await
+    read_file_from_disk_without_privacy_check(path, max_file_size_bytes).await
 }
 
-async fn _run_command(cmd: &str, args: &[&str], path: &PathBuf, filter_out_status: bool) -> Option<Vec<PathBuf>> {
+async fn _run_command(cmd: &str, args: &[&str], path: &PathBuf, filter_out_status: bool, timeout: std::time::Duration) -> Option<Vec<PathBuf>> {
     info!("{} EXEC {} {}", path.display(), cmd, args.join(" "));
-    let output = async_process::Command::new(cmd)
-        .args(args)
-        .current_dir(path)
-        .output()
-        .await
-        .ok()?;
+    let output = match output_with_timeout(tokio::process::Command::new(cmd).args(args).current_dir(path), timeout).await {
+        Ok(output) => output,
+        Err(e) => {
+            info!("{} {}", path.display(), e);
+            return None;
+        }
+    };
 
     if !output.status.success() {
         return None;
@@ -225,23 +243,47 @@ async fn _run_command(cmd: &str, args: &[&str], path: &PathBuf, filter_out_statu
         }).collect())
 }
 
-async fn ls_files_under_version_control(path: &PathBuf) -> Option<Vec<PathBuf>> {
-    if path.join(".git").exists() {
-        git_ls_files(path)
+// In a linked worktree or a submodule `.git` is a file with "gitdir: <path>" pointing to the real git dir
+fn gitdir_from_dot_git_file(dot_git: &PathBuf) -> Option<PathBuf> {
+    let content = fs::read_to_string(dot_git).ok()?;
+    let gitdir = content.lines().find_map(|l| l.strip_prefix("gitdir:"))?.trim();
+    let gitdir = PathBuf::from(gitdir);
+    let gitdir = if gitdir.is_absolute() { gitdir } else { dot_git.parent()?.join(gitdir) };
+    if gitdir.is_dir() { Some(gitdir) } else { None }
+}
+
+fn is_git_root(dir: &PathBuf) -> bool {
+    let dot_git = dir.join(".git");
+    dot_git.is_dir() || (dot_git.is_file() && gitdir_from_dot_git_file(&dot_git).is_some())
+}
+
+async fn ls_files_under_version_control(path: &PathBuf, subprocess_timeout: std::time::Duration) -> Option<Vec<PathBuf>> {
+    if is_git_root(path) {
+        match git_ls_files(path) {
+            Some(files) => Some(files),
+            None if which("git").is_ok() => {
+                // libgit2 doesn't understand some repository layouts, the git binary might
+                _run_command("git", &["ls-files", "--cached", "--others", "--exclude-standard"], path, false, subprocess_timeout).await
+            }
+            None => {
+                info!("{} looks like a git repository, but neither libgit2 nor git binary can list files in it", path.display());
+                None
+            }
+        }
     } else if path.join(".hg").exists() && which("hg").is_ok() {
         // Mercurial repository
-        _run_command("hg", &["status", "--added", "--modified", "--clean", "--unknown", "--no-status"], path, false).await
+        _run_command("hg", &["status", "--added", "--modified", "--clean", "--unknown", "--no-status"], path, false, subprocess_timeout).await
     } else if path.join(".svn").exists() && which("svn").is_ok() {
         // SVN repository
-        let files_under_vc = _run_command("svn", &["list", "-R"], path, false).await;
-        let files_changed = _run_command("svn", &["status"], path, true).await;
+        let files_under_vc = _run_command("svn", &["list", "-R"], path, false, subprocess_timeout).await;
+        let files_changed = _run_command("svn", &["status"], path, true, subprocess_timeout).await;
         Some(files_under_vc.unwrap_or_default().into_iter().chain(files_changed.unwrap_or_default().into_iter()).collect())
     } else {
         None
     }
 }
 
-pub fn ls_files(path: &PathBuf, recursive: bool) -> Result<Vec<PathBuf>, String> {
+pub fn ls_files(path: &PathBuf, recursive: bool, filter_settings: &FileFilterSettings) -> Result<Vec<PathBuf>, String> {
     if !path.is_dir() {
         return Err(format!("path '{}' is not a directory", path.display()));
     }
@@ -267,7 +309,7 @@ pub fn ls_files(path: &PathBuf, recursive: bool) -> Result<Vec<PathBuf>, String>
             let path = entry.path();
             if recursive && path.is_dir() && !(
                 path.file_name().unwrap_or_default().to_str().unwrap_or_default().starts_with(".") ||
-                BLACKLISTED_DIRS.contains(&path.file_name().unwrap_or_default().to_str().unwrap_or_default())
+                is_blacklisted_dir_name(path.file_name().unwrap_or_default().to_str().unwrap_or_default(), filter_settings)
             ) {
                 dirs_to_visit.push(path);
             } else if path.is_file() {
@@ -284,7 +326,7 @@ pub async fn detect_vcs_for_a_file_path(file_path: &PathBuf) -> Option<(PathBuf,
         dir.pop();
     }
     loop {
-        if dir.join(".git").is_dir() {
+        if is_git_root(&dir) {
             return Some((dir.clone(), "git"));
         } else if dir.join(".svn").is_dir() {
             return Some((dir.clone(), "svn"));
@@ -328,48 +370,132 @@ pub async fn detect_vcs_for_a_file_path(file_path: &PathBuf) -> Option<(PathBuf,
 //         .unwrap_or(false)
 // }
 
+/// What the last workspace scan found, the reasons files were not indexed are the interesting part.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct WorkspaceIndexingStats {
+    pub files_discovered: usize,
+    pub files_rejected: usize,
+    pub rejected_reasons: HashMap<String, usize>,
+    pub rejected_paths_sample: HashMap<String, RejectedPathsSample>,
+    #[serde(skip)]
+    pub rejected_paths_sample_size: usize,
+    pub blacklisted_dirs_cnt: usize,
+    pub refactignored_cnt: usize,
+    pub symlinks_skipped_cnt: usize,
+    pub total_bytes: u64,
+    pub vcs_roots: Vec<PathBuf>,
+    pub scan_finished_ts: f64,
+}
+
+/// A few of the rejected paths for one reason, every rejected path has the same chance to be there (reservoir sampling)
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct RejectedPathsSample {
+    #[serde(skip)]
+    seen: usize,
+    pub paths: Vec<PathBuf>,
+}
+
+impl RejectedPathsSample {
+    fn add(&mut self, path: &PathBuf, sample_size: usize) {
+        self.seen += 1;
+        if self.paths.len() < sample_size {
+            self.paths.push(path.clone());
+            return;
+        }
+        let i = rand::thread_rng().gen_range(0..self.seen);
+        if i < self.paths.len() {
+            self.paths[i] = path.clone();
+        }
+    }
+}
+
+fn _note_rejected(rejected_reasons: &mut HashMap<String, usize>, stats: &mut WorkspaceIndexingStats, reason: String, path: &PathBuf) {
+    let sample_size = stats.rejected_paths_sample_size;
+    if sample_size > 0 {
+        stats.rejected_paths_sample.entry(reason.clone()).or_default().add(path, sample_size);
+    }
+    *rejected_reasons.entry(reason).or_insert(0) += 1;
+}
+
 async fn _ls_files_under_version_control_recursive(
     all_files: &mut Vec<PathBuf>,
     vcs_folders: &mut Vec<PathBuf>,
+    stats: &mut WorkspaceIndexingStats,
     path: PathBuf,
     allow_files_in_hidden_folders: bool,
-    ignore_size_thresholds: bool
+    ignore_size_thresholds: bool,
+    follow_symlinks: bool,
+    filter_settings: &FileFilterSettings,
+    subprocess_timeout: std::time::Duration,
 ) {
     let mut candidates: Vec<PathBuf> = vec![path.clone()];
     let mut rejected_reasons: HashMap<String, usize> = HashMap::new();
     let mut blacklisted_dirs_cnt: usize = 0;
+    let mut refactignore = RefactIgnore::new(&path);
+    let mut refactignored_cnt: usize = 0;
+    // symlinks can point to an ancestor, without this the loop never ends
+    let root_canonical = path.canonicalize().unwrap_or(path.clone());
+    let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+    let mut symlinks_skipped_cnt: usize = 0;
     while !candidates.is_empty() {
         let local_path = candidates.pop().unwrap();
         if local_path.is_file() {
+            if refactignore.is_file_ignored(&local_path) {
+                refactignored_cnt += 1;
+                continue;
+            }
             let maybe_valid = is_valid_file(
-                &local_path, allow_files_in_hidden_folders, ignore_size_thresholds);
+                &local_path, allow_files_in_hidden_folders, ignore_size_thresholds, filter_settings);
             match maybe_valid {
                 Ok(_) => {
+                    stats.total_bytes += local_path.metadata().map(|m| m.len()).unwrap_or(0);
                     all_files.push(local_path.clone());
                 }
                 Err(e) => {
-                    rejected_reasons.entry(e.to_string()).and_modify(|x| *x += 1).or_insert(1);
+                    _note_rejected(&mut rejected_reasons, stats, e.to_string(), &local_path);
                     continue;
                 }
             }
         }
         if local_path.is_dir() {
-            if BLACKLISTED_DIRS.contains(&local_path.file_name().unwrap().to_str().unwrap()) {
+            if is_blacklisted_dir_name(local_path.file_name().unwrap().to_str().unwrap(), filter_settings) {
                 blacklisted_dirs_cnt += 1;
                 continue;
             }
-            let maybe_files = ls_files_under_version_control(&local_path).await;
+            if refactignore.is_dir_ignored(&local_path) {
+                refactignored_cnt += 1;
+                continue;
+            }
+            let local_canonical = match local_path.canonicalize() {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let is_symlink = local_path.symlink_metadata().map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if is_symlink && !follow_symlinks && !local_canonical.starts_with(&root_canonical) {
+                symlinks_skipped_cnt += 1;
+                continue;
+            }
+            if !visited_dirs.insert(local_canonical) {
+                symlinks_skipped_cnt += 1;
+                continue;
+            }
+            let maybe_files = ls_files_under_version_control(&local_path, subprocess_timeout).await;
             if let Some(v) = maybe_files {
                 vcs_folders.push(local_path.clone());
                 for x in v.iter() {
+                    if refactignore.is_file_ignored(x) {
+                        refactignored_cnt += 1;
+                        continue;
+                    }
                     let maybe_valid = is_valid_file(
-                        x, allow_files_in_hidden_folders, ignore_size_thresholds);
+                        x, allow_files_in_hidden_folders, ignore_size_thresholds, filter_settings);
                     match maybe_valid {
                         Ok(_) => {
+                            stats.total_bytes += x.metadata().map(|m| m.len()).unwrap_or(0);
                             all_files.push(x.clone());
                         }
                         Err(e) => {
-                            rejected_reasons.entry(e.to_string()).and_modify(|x| *x += 1).or_insert(1);
+                            _note_rejected(&mut rejected_reasons, stats, e.to_string(), x);
                         }
                     }
                 }
@@ -387,34 +513,158 @@ async fn _ls_files_under_version_control_recursive(
     info!("when inspecting {:?} rejected files reasons:", path);
     for (reason, count) in &rejected_reasons {
         info!("    {:>6} {}", count, reason);
+        for sample_path in stats.rejected_paths_sample.get(reason).map(|x| x.paths.as_slice()).unwrap_or_default() {
+            info!("           for example {}", sample_path.display());
+        }
     }
     if rejected_reasons.is_empty() {
         info!("    no bad files at all");
     }
     info!("also the loop bumped into {} blacklisted dirs", blacklisted_dirs_cnt);
+    if refactignored_cnt > 0 {
+        info!("and skipped {} files or dirs listed in .refactignore", refactignored_cnt);
+    }
+    if symlinks_skipped_cnt > 0 {
+        info!("and skipped {} symlinked dirs, already visited or outside of {:?}", symlinks_skipped_cnt, path);
+    }
+    stats.files_rejected += rejected_reasons.values().sum::<usize>();
+    for (reason, count) in rejected_reasons {
+        *stats.rejected_reasons.entry(reason).or_insert(0) += count;
+    }
+    stats.blacklisted_dirs_cnt += blacklisted_dirs_cnt;
+    stats.refactignored_cnt += refactignored_cnt;
+    stats.symlinks_skipped_cnt += symlinks_skipped_cnt;
 }
 
 pub async fn retrieve_files_in_workspace_folders(
+    gcx: Arc<ARwLock<GlobalContext>>,
     proj_folders: Vec<PathBuf>,
     allow_files_in_hidden_folders: bool,   // true when syncing to remote container
     ignore_size_thresholds: bool,
+    follow_symlinks: bool,
 ) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (all_files, vcs_folders, _stats) = retrieve_files_in_workspace_folders_with_stats(
+        gcx,
+        proj_folders,
+        allow_files_in_hidden_folders,
+        ignore_size_thresholds,
+        follow_symlinks,
+        0,
+    ).await;
+    (all_files, vcs_folders)
+}
+
+pub async fn retrieve_files_in_workspace_folders_with_stats(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    proj_folders: Vec<PathBuf>,
+    allow_files_in_hidden_folders: bool,
+    ignore_size_thresholds: bool,
+    follow_symlinks: bool,
+    rejected_paths_sample_size: usize,
+) -> (Vec<PathBuf>, Vec<PathBuf>, WorkspaceIndexingStats) {
+    let filter_settings = FileFilterSettings::from_cmdline(&gcx.read().await.cmdline);
+    let subprocess_timeout = subprocess_timeout(gcx.clone()).await;
     let mut all_files: Vec<PathBuf> = Vec::new();
     let mut vcs_folders: Vec<PathBuf> = Vec::new();
+    let mut stats = WorkspaceIndexingStats { rejected_paths_sample_size, ..Default::default() };
     for proj_folder in proj_folders {
         _ls_files_under_version_control_recursive(
             &mut all_files,
             &mut vcs_folders,
+            &mut stats,
             proj_folder.clone(),
             allow_files_in_hidden_folders,
-            ignore_size_thresholds
+            ignore_size_thresholds,
+            follow_symlinks,
+            &filter_settings,
+            subprocess_timeout,
         ).await;
     }
     info!("in all workspace folders, VCS roots found:");
     for vcs_folder in vcs_folders.iter() {
         info!("    {}", vcs_folder.display());
     }
-    (all_files, vcs_folders)
+    stats.files_discovered = all_files.len();
+    stats.vcs_roots = vcs_folders.clone();
+    stats.scan_finished_ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
+    (all_files, vcs_folders, stats)
+}
+
+pub async fn workspace_indexing_stats(gcx: Arc<ARwLock<GlobalContext>>) -> WorkspaceIndexingStats {
+    let stats_arc = gcx.read().await.documents_state.indexing_stats.clone();
+    let stats = stats_arc.lock().unwrap().clone();
+    stats
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FolderStats {
+    pub folder: PathBuf,
+    pub files_cnt: usize,
+    pub vecdb_indexed_cnt: usize,
+    pub ast_indexed_cnt: usize,
+    pub last_enqueue_ts: f64,
+}
+
+// A file belongs to the deepest workspace folder it's in, folders can be nested
+fn folders_stats_from_files(
+    folders: &Vec<PathBuf>,
+    files: &Vec<PathBuf>,
+    vecdb_indexed: &HashSet<String>,
+    ast_indexed: &HashSet<String>,
+    last_enqueue_ts: f64,
+) -> Vec<FolderStats> {
+    let mut result = folders.iter().map(|folder| FolderStats {
+        folder: folder.clone(),
+        files_cnt: 0,
+        vecdb_indexed_cnt: 0,
+        ast_indexed_cnt: 0,
+        last_enqueue_ts,
+    }).collect::<Vec<_>>();
+    for file in files.iter() {
+        let owner = result.iter_mut()
+            .filter(|s| file.starts_with(&s.folder))
+            .max_by_key(|s| s.folder.components().count());
+        if let Some(s) = owner {
+            let file_str = file.to_string_lossy().to_string();
+            s.files_cnt += 1;
+            s.vecdb_indexed_cnt += vecdb_indexed.contains(&file_str) as usize;
+            s.ast_indexed_cnt += ast_indexed.contains(&file_str) as usize;
+        }
+    }
+    result
+}
+
+pub async fn workspace_folders_stats(gcx: Arc<ARwLock<GlobalContext>>) -> Vec<FolderStats> {
+    let (folders, files, last_enqueue_ts, vec_db, ast_service) = {
+        let gcx_locked = gcx.read().await;
+        let docs = &gcx_locked.documents_state;
+        (
+            docs.workspace_folders.lock().unwrap().clone(),
+            docs.workspace_files.lock().unwrap().clone(),
+            docs.indexing_stats.lock().unwrap().scan_finished_ts,
+            gcx_locked.vec_db.clone(),
+            gcx_locked.ast_service.clone(),
+        )
+    };
+
+    #[cfg(feature="vecdb")]
+    let vecdb_indexed: HashSet<String> = match *vec_db.lock().await {
+        Some(ref db) => db.vectorized_files().await.unwrap_or_else(|e| {
+            tracing::warn!("cannot list vectorized files: {}", e);
+            vec![]
+        }).into_iter().collect(),
+        None => HashSet::new(),
+    };
+    #[cfg(not(feature="vecdb"))]
+    let vecdb_indexed: HashSet<String> = { let _ = vec_db; HashSet::new() };
+
+    let mut ast_indexed: HashSet<String> = HashSet::new();
+    if let Some(ast) = ast_service {
+        let ast_index = ast.lock().await.ast_index.clone();
+        ast_indexed.extend(crate::ast::ast_db::indexed_docs(ast_index).await);
+    }
+
+    folders_stats_from_files(&folders, &files, &vecdb_indexed, &ast_indexed, last_enqueue_ts)
 }
 
 pub fn is_path_to_enqueue_valid(path: &PathBuf) -> Result<(), String> {
@@ -457,7 +707,7 @@ async fn enqueue_some_docs(
             moar_files.push(PathBuf::from(p.clone()));
         }
     }
-    if moar_files.len() > 0 {
+    if moar_files.len() > CACHE_INCREMENTAL_UPDATE_MAX_FILES {
         info!("this made file cache dirty");
         let dirty_arc = {
             let gcx_locked = gcx.read().await;
@@ -466,6 +716,11 @@ async fn enqueue_some_docs(
         };
         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
         *dirty_arc.lock().await = now + 1.0;  // next rebuild will be one second later, to prevent rapid-fire rebuilds from file events
+    } else if moar_files.len() > 0 {
+        gcx.read().await.documents_state.workspace_files.lock().unwrap().extend(moar_files.clone());
+        for f in moar_files.iter() {
+            crate::files_correction::files_cache_add_path(gcx.clone(), f).await;
+        }
     }
 }
 
@@ -475,12 +730,17 @@ pub async fn enqueue_all_files_from_workspace_folders(
     vecdb_only: bool,
 ) -> i32 {
     let folders: Vec<PathBuf> = gcx.read().await.documents_state.workspace_folders.lock().unwrap().clone();
+    let follow_symlinks = gcx.read().await.cmdline.follow_symlinks;
+    let rejected_paths_sample_size = gcx.read().await.cmdline.rejected_paths_sample_size;
 
     info!("enqueue_all_files_from_workspace_folders started files search with {} folders", folders.len());
-    let (all_files, vcs_folders) = retrieve_files_in_workspace_folders(
-        folders,
+    let (all_files, vcs_folders, indexing_stats) = retrieve_files_in_workspace_folders_with_stats(
+        gcx.clone(),
+        folders.clone(),
+        false,
         false,
-        false
+        follow_symlinks,
+        rejected_paths_sample_size,
     ).await;
     info!("enqueue_all_files_from_workspace_folders found {} files => workspace_files", all_files.len());
     let mut workspace_vcs_roots: Arc<StdMutex<Vec<PathBuf>>> = Arc::new(StdMutex::new(vcs_folders.clone()));
@@ -496,6 +756,7 @@ pub async fn enqueue_all_files_from_workspace_folders(
         {
             std::mem::swap(&mut gcx_locked.documents_state.workspace_vcs_roots, &mut workspace_vcs_roots);
         }
+        *gcx_locked.documents_state.indexing_stats.lock().unwrap() = indexing_stats;
         gcx_locked.documents_state.cache_dirty.clone()
     };
 
@@ -515,7 +776,15 @@ pub async fn enqueue_all_files_from_workspace_folders(
 
     #[cfg(feature="vecdb")]
     if let Some(ref mut db) = *vec_db_module.lock().await {
-        db.vectorizer_enqueue_files(&paths_nodups, wake_up_indexers).await;
+        // files outside of all workspace folders are from a removed folder, remove_folder() already dropped them from vecdb
+        let all_files_set: HashSet<&PathBuf> = all_files.iter().collect();
+        let removed_old: HashSet<String> = old_workspace_files.iter()
+            .filter(|p| !all_files_set.contains(p) && !folders.iter().any(|f| p.starts_with(f)))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let vecdb_paths: Vec<String> = paths_nodups.iter().filter(|p| !removed_old.contains(*p)).cloned().collect();
+        db.vectorizer_reset_cache_counters().await;
+        db.vectorizer_enqueue_files(&vecdb_paths, wake_up_indexers).await;
     }
     #[cfg(not(feature="vecdb"))]
     let _ = vec_db_module;
@@ -552,12 +821,16 @@ pub async fn on_did_open(
     let mut doc = Document::new(cpath);
     doc.update_text(text);
     info!("on_did_open {}", crate::nicer_logs::last_n_chars(&cpath.display().to_string(), 30));
-    let (_doc_arc, dirty_arc, mark_dirty) = mem_overwrite_or_create_document(gcx.clone(), doc).await;
-    if mark_dirty {
-        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-        *dirty_arc.lock().await = now;
+    let (_doc_arc, is_new) = mem_overwrite_or_create_document(gcx.clone(), doc).await;
+    if is_new {
+        crate::files_correction::files_cache_add_path(gcx.clone(), cpath).await;
     }
     gcx.write().await.documents_state.active_file_path = Some(cpath.clone());
+    let ast_service = gcx.read().await.ast_service.clone();
+    if let Some(ast_service) = ast_service {
+        let ast_index = ast_service.lock().await.ast_index.clone();
+        ast_index.lock().await.parse_cache.document_opened(cpath.to_string_lossy().to_string());
+    }
 }
 
 pub async fn on_did_close(
@@ -571,6 +844,11 @@ pub async fn on_did_close(
             tracing::error!("on_did_close: failed to remove from memory_document_map {:?}", cpath.display());
         }
     }
+    let ast_service = gcx.read().await.ast_service.clone();
+    if let Some(ast_service) = ast_service {
+        let ast_index = ast_service.lock().await.ast_index.clone();
+        ast_index.lock().await.parse_cache.document_closed(&cpath.to_string_lossy().to_string());
+    }
 }
 
 pub async fn on_did_change(
@@ -579,23 +857,22 @@ pub async fn on_did_change(
     text: &String,
 ) {
     let t0 = Instant::now();
-    let (doc_arc, dirty_arc, mark_dirty) = {
+    let (doc_arc, is_new) = {
         let mut doc = Document::new(path);
         doc.update_text(text);
-        let (doc_arc, dirty_arc, set_mark_dirty) = mem_overwrite_or_create_document(gcx.clone(), doc).await;
-        (doc_arc, dirty_arc, set_mark_dirty)
+        mem_overwrite_or_create_document(gcx.clone(), doc).await
     };
 
-    if mark_dirty {
-        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-        *dirty_arc.lock().await = now;
+    if is_new {
+        crate::files_correction::files_cache_add_path(gcx.clone(), path).await;
     }
 
     gcx.write().await.documents_state.active_file_path = Some(path.clone());
 
     let mut go_ahead = true;
     {
-        let is_it_good = is_valid_file(path, false, false);
+        let filter_settings = FileFilterSettings::from_cmdline(&gcx.read().await.cmdline);
+        let is_it_good = is_valid_file(path, false, false, &filter_settings);
         if is_it_good.is_err() {
             info!("{:?} ignoring changes: {}", path, is_it_good.err().unwrap());
             go_ahead = false;
@@ -620,14 +897,13 @@ pub async fn on_did_delete(gcx: Arc<ARwLock<GlobalContext>>, path: &PathBuf)
 {
     info!("on_did_delete {}", crate::nicer_logs::last_n_chars(&path.to_string_lossy().to_string(), 30));
 
-    let (vec_db_module, ast_service, dirty_arc) = {
+    let (vec_db_module, ast_service) = {
         let mut cx = gcx.write().await;
         cx.documents_state.memory_document_map.remove(path);
-        (cx.vec_db.clone(), cx.ast_service.clone(), cx.documents_state.cache_dirty.clone())
+        (cx.vec_db.clone(), cx.ast_service.clone())
     };
 
-    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-    (*dirty_arc.lock().await) = now;
+    crate::files_correction::files_cache_remove_path(gcx.clone(), path).await;
 
     #[cfg(feature="vecdb")]
     match *vec_db_module.lock().await {
@@ -662,24 +938,36 @@ pub async fn remove_folder(gcx: Arc<ARwLock<GlobalContext>>, path: &PathBuf)
     };
     if was_removed {
         tracing::info!("Folder {} was successfully removed from workspace_folders.", path.display());
+        #[cfg(feature="vecdb")]
+        {
+            let vec_db_module = gcx.read().await.vec_db.clone();
+            if let Some(ref db) = *vec_db_module.lock().await {
+                db.remove_files_under(path).await;
+            }
+        }
         on_workspaces_init(gcx.clone()).await;
     } else {
         tracing::error!("Folder {} was not found in workspace_folders.", path.display());
     }
 }
 
-pub async fn file_watcher_event(event: Event, gcx_weak: Weak<ARwLock<GlobalContext>>)
-{
-    async fn on_create_modify(gcx_weak: Weak<ARwLock<GlobalContext>>, event: Event) {
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WatcherChange {
+    Modified,
+    Removed,
+}
+
+fn file_watcher_event_to_changes(event: &Event, filter_settings: &FileFilterSettings) -> Vec<(String, WatcherChange)> {
+    fn on_create_modify(event: &Event, filter_settings: &FileFilterSettings) -> Vec<(String, WatcherChange)> {
         let mut docs = vec![];
         for p in &event.paths {
-            if is_this_inside_blacklisted_dir(&p) {  // important to filter BEFORE canonical_path
+            if is_this_inside_blacklisted_dir(&p, filter_settings) {  // important to filter BEFORE canonical_path
                 continue;
             }
 
             let mut go_ahead = true;
             {
-                let is_it_good = is_valid_file(p, false, false);
+                let is_it_good = is_valid_file(p, false, false, filter_settings);
                 if is_it_good.is_err() {
                     // info!("{:?} ignoring changes: {}", p, is_it_good.err().unwrap());
                     go_ahead = false;
@@ -688,48 +976,405 @@ pub async fn file_watcher_event(event: Event, gcx_weak: Weak<ARwLock<GlobalConte
 
             if go_ahead {
                 let cpath = crate::files_correction::canonical_path(&p.to_string_lossy().to_string());
-                docs.push(cpath.to_string_lossy().to_string());
+                docs.push((cpath.to_string_lossy().to_string(), WatcherChange::Modified));
             }
         }
-        if docs.is_empty() {
-            return;
+        docs
+    }
+
+    fn on_remove(event: &Event, filter_settings: &FileFilterSettings) -> Vec<(String, WatcherChange)> {
+        let mut docs = vec![];
+        for p in &event.paths {
+            if is_this_inside_blacklisted_dir(&p, filter_settings) {
+                continue;
+            }
+            let cpath = crate::files_correction::canonical_path(&p.to_string_lossy().to_string());
+            docs.push((cpath.to_string_lossy().to_string(), WatcherChange::Removed));
         }
-        // info!("EventKind::Create/Modify {} paths", event.paths.len());
-        if let Some(gcx) = gcx_weak.clone().upgrade() {
-            enqueue_some_docs(gcx, &docs, false).await;
+        docs
+    }
+
+    // Platforms report renames differently: inotify sends From, To and then Both, macOS sends Any for each side,
+    // others send remove + create. All of them end up as one remove for the old path and one modify for the new
+    // one, because WatcherDebounce keeps only the last change per path.
+    fn on_rename(event: &Event, mode: RenameMode, filter_settings: &FileFilterSettings) -> Vec<(String, WatcherChange)> {
+        match mode {
+            RenameMode::Both if event.paths.len() == 2 => {
+                let mut docs = on_remove(&Event { paths: vec![event.paths[0].clone()], ..event.clone() }, filter_settings);
+                docs.extend(on_create_modify(&Event { paths: vec![event.paths[1].clone()], ..event.clone() }, filter_settings));
+                docs
+            }
+            RenameMode::From => on_remove(event, filter_settings),
+            RenameMode::To => on_create_modify(event, filter_settings),
+            _ => {
+                let (existing, gone): (Vec<PathBuf>, Vec<PathBuf>) = event.paths.iter().cloned().partition(|p| p.exists());
+                let mut docs = on_remove(&Event { paths: gone, ..event.clone() }, filter_settings);
+                docs.extend(on_create_modify(&Event { paths: existing, ..event.clone() }, filter_settings));
+                docs
+            }
         }
     }
 
-    async fn on_remove(gcx_weak: Weak<ARwLock<GlobalContext>>, event: Event) {
-        let mut never_mind = true;
-        for p in &event.paths {
-            never_mind &= is_this_inside_blacklisted_dir(&p);
+    match event.kind {
+        EventKind::Any => vec![],
+        EventKind::Access(_) => vec![],
+        EventKind::Create(CreateKind::File) => on_create_modify(event, filter_settings),
+        EventKind::Remove(RemoveKind::File) => on_remove(event, filter_settings),
+        EventKind::Modify(ModifyKind::Data(DataChange::Content)) => on_create_modify(event, filter_settings),
+        EventKind::Modify(ModifyKind::Name(mode)) => on_rename(event, mode, filter_settings),
+        EventKind::Other => vec![],
+        _ => vec![],
+    }
+}
+
+const WATCHER_REMOVES_FOR_FULL_RESET: usize = 100;  // a burst of removes that large is a git checkout or similar, rescan everything
+
+// Coalesces events per path: the last event wins, and the path is ready once it's been quiet for `window`
+struct WatcherDebounce {
+    window: std::time::Duration,
+    pending: IndexMap<String, (WatcherChange, Instant)>,
+}
+
+impl WatcherDebounce {
+    fn new(window: std::time::Duration) -> Self {
+        WatcherDebounce { window, pending: IndexMap::new() }
+    }
+
+    fn push(&mut self, path: String, change: WatcherChange, now: Instant) {
+        self.pending.shift_remove(&path);
+        self.pending.insert(path, (change, now));
+    }
+
+    fn take_ready(&mut self, now: Instant) -> (Vec<String>, Vec<String>) {
+        let mut modified = vec![];
+        let mut removed = vec![];
+        let window = self.window;
+        self.pending.retain(|path, (change, last_event)| {
+            if now.duration_since(*last_event) < window {
+                return true;
+            }
+            match change {
+                WatcherChange::Modified => modified.push(path.clone()),
+                WatcherChange::Removed => removed.push(path.clone()),
+            }
+            false
+        });
+        (modified, removed)
+    }
+}
+
+const WATCHER_RATE_LIMIT_PERIOD: std::time::Duration = std::time::Duration::from_secs(60);
+
+// Modified files over the limit wait in a queue, at most `max_per_period` go out in any sliding period.
+// Removes are cheap and go through immediately, they also cancel a queued modification of the same file.
+struct WatcherRateLimit {
+    max_per_period: usize,
+    period: std::time::Duration,
+    sent: VecDeque<Instant>,
+    queued: IndexSet<String>,
+}
+
+impl WatcherRateLimit {
+    fn new(max_per_period: usize, period: std::time::Duration) -> Self {
+        WatcherRateLimit { max_per_period, period, sent: VecDeque::new(), queued: IndexSet::new() }
+    }
+
+    fn push(&mut self, modified: Vec<String>, removed: &Vec<String>) {
+        for path in removed.iter() {
+            self.queued.shift_remove(path);
         }
-        let mut docs = vec![];
-        if !never_mind {
-            for p in &event.paths {
-                if is_this_inside_blacklisted_dir(&p) {
-                    continue;
+        self.queued.extend(modified);
+    }
+
+    fn take_allowed(&mut self, now: Instant) -> Vec<String> {
+        if self.max_per_period == 0 {
+            return self.queued.drain(..).collect();
+        }
+        while self.sent.front().map_or(false, |t| now.duration_since(*t) >= self.period) {
+            self.sent.pop_front();
+        }
+        let n = self.max_per_period.saturating_sub(self.sent.len()).min(self.queued.len());
+        self.sent.extend(std::iter::repeat(now).take(n));
+        self.queued.drain(..n).collect()
+    }
+}
+
+async fn watcher_apply_changes(gcx_weak: Weak<ARwLock<GlobalContext>>, modified: Vec<String>, removed: Vec<String>) {
+    let gcx = match gcx_weak.upgrade() {
+        Some(gcx) => gcx,
+        None => return,
+    };
+    if removed.len() > WATCHER_REMOVES_FOR_FULL_RESET {
+        info!("watcher: {} files removed at once, rescanning workspace folders", removed.len());
+        enqueue_all_files_from_workspace_folders(gcx.clone(), false, false).await;
+    } else if !removed.is_empty() {
+        enqueue_some_docs(gcx.clone(), &removed, false).await;
+    }
+    if !modified.is_empty() {
+        enqueue_some_docs(gcx.clone(), &modified, false).await;
+    }
+}
+
+async fn watcher_debounce_loop(
+    mut events_rx: tokio::sync::mpsc::UnboundedReceiver<Event>,
+    gcx_weak: Weak<ARwLock<GlobalContext>>,
+    window: std::time::Duration,
+    max_files_per_minute: usize,
+) {
+    let filter_settings = match gcx_weak.upgrade() {
+        Some(gcx) => FileFilterSettings::from_cmdline(&gcx.read().await.cmdline),
+        None => return,
+    };
+    let mut debounce = WatcherDebounce::new(window);
+    let mut rate_limit = WatcherRateLimit::new(max_files_per_minute, WATCHER_RATE_LIMIT_PERIOD);
+    let tick = (window / 2).max(std::time::Duration::from_millis(10));
+    loop {
+        let channel_open = tokio::select! {
+            event_mb = events_rx.recv() => {
+                match event_mb {
+                    Some(event) => {
+                        let now = Instant::now();
+                        for (path, change) in file_watcher_event_to_changes(&event, &filter_settings) {
+                            debounce.push(path, change, now);
+                        }
+                        true
+                    }
+                    None => false,
                 }
-                let cpath = crate::files_correction::canonical_path(&p.to_string_lossy().to_string());
-                docs.push(cpath.to_string_lossy().to_string());
+            },
+            _ = tokio::time::sleep(tick) => true,
+        };
+        // when the watcher is gone, flush whatever is left and quit
+        let now = if channel_open { Instant::now() } else { Instant::now() + window };
+        let (modified, removed) = debounce.take_ready(now);
+        rate_limit.push(modified, &removed);
+        let modified = rate_limit.take_allowed(Instant::now());
+        if !modified.is_empty() || !removed.is_empty() {
+            watcher_apply_changes(gcx_weak.clone(), modified, removed).await;
+        }
+        if !channel_open {
+            if !rate_limit.queued.is_empty() {
+                info!("watcher: stopped with {} files still waiting for the rate limit", rate_limit.queued.len());
             }
+            break;
         }
-        if docs.is_empty() {
-            return;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_debounce_coalesces_events() {
+        let t0 = Instant::now();
+        let window = std::time::Duration::from_millis(500);
+        let mut debounce = WatcherDebounce::new(window);
+
+        debounce.push("/frog/frog.py".to_string(), WatcherChange::Modified, t0);
+        debounce.push("/frog/frog.py".to_string(), WatcherChange::Modified, t0 + std::time::Duration::from_millis(300));
+        debounce.push("/frog/tadpole.py".to_string(), WatcherChange::Modified, t0);
+        debounce.push("/frog/tadpole.py".to_string(), WatcherChange::Removed, t0 + std::time::Duration::from_millis(100));
+
+        assert_eq!(debounce.take_ready(t0 + std::time::Duration::from_millis(400)), (vec![], vec![]));
+        assert_eq!(debounce.take_ready(t0 + std::time::Duration::from_millis(650)), (vec![], vec!["/frog/tadpole.py".to_string()]));
+        assert_eq!(debounce.take_ready(t0 + std::time::Duration::from_millis(800)), (vec!["/frog/frog.py".to_string()], vec![]));
+        assert_eq!(debounce.take_ready(t0 + std::time::Duration::from_millis(2000)), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_watcher_rate_limit_spreads_a_burst() {
+        let t0 = Instant::now();
+        let minute = std::time::Duration::from_secs(60);
+        let mut rate_limit = WatcherRateLimit::new(10, minute);
+        let burst: Vec<String> = (0..25).map(|i| format!("/frog/tadpole{}.py", i)).collect();
+        rate_limit.push(burst.clone(), &vec![]);
+        rate_limit.push(vec![], &vec!["/frog/tadpole24.py".to_string()]);
+
+        let mut processed = vec![];
+        let mut per_tick = vec![];
+        for second in (0..=180).step_by(10) {
+            let allowed = rate_limit.take_allowed(t0 + std::time::Duration::from_secs(second));
+            per_tick.push((second, allowed.len()));
+            processed.extend(allowed);
         }
-        if let Some(gcx) = gcx_weak.clone().upgrade() {
-            enqueue_some_docs(gcx, &docs, false).await;
+        assert_eq!(processed, burst[..24].to_vec());
+        // never more than 10 files within a minute
+        for (second, _) in per_tick.iter() {
+            let in_window: usize = per_tick.iter().filter(|(s, _)| *s >= *second && *s < *second + 60).map(|(_, n)| *n).sum();
+            assert!(in_window <= 10, "{} files in the minute starting at {}s", in_window, second);
         }
+        assert_eq!(per_tick.iter().filter(|(_, n)| *n > 0).map(|(s, _)| *s).collect::<Vec<_>>(), vec![0, 60, 120]);
+
+        let mut unlimited = WatcherRateLimit::new(0, minute);
+        unlimited.push(burst.clone(), &vec![]);
+        assert_eq!(unlimited.take_allowed(t0), burst);
     }
 
-    match event.kind {
-        EventKind::Any => {},
-        EventKind::Access(_) => {},
-        EventKind::Create(CreateKind::File) => on_create_modify(gcx_weak.clone(), event).await,
-        EventKind::Remove(RemoveKind::File) => on_remove(gcx_weak.clone(), event).await,
-        EventKind::Modify(ModifyKind::Data(DataChange::Content)) => on_create_modify(gcx_weak.clone(), event).await,
-        EventKind::Other => {}
-        _ => {}
+    #[test]
+    fn test_folders_stats_attributes_files_to_folders() {
+        let folders = vec![PathBuf::from("/pond"), PathBuf::from("/swamp"), PathBuf::from("/pond/lily")];
+        let files = vec![
+            PathBuf::from("/pond/frog.py"),
+            PathBuf::from("/pond/tadpole.py"),
+            PathBuf::from("/pond/lily/pad.py"),
+            PathBuf::from("/swamp/toad.py"),
+            PathBuf::from("/pondering/not_in_pond.py"),
+        ];
+        let vecdb_indexed = HashSet::from(["/pond/frog.py".to_string(), "/swamp/toad.py".to_string()]);
+        let ast_indexed = HashSet::from(["/pond/frog.py".to_string(), "/pond/tadpole.py".to_string()]);
+
+        let stats = folders_stats_from_files(&folders, &files, &vecdb_indexed, &ast_indexed, 123.0);
+
+        let counts = stats.iter().map(|s| (s.folder.clone(), s.files_cnt, s.vecdb_indexed_cnt, s.ast_indexed_cnt)).collect::<Vec<_>>();
+        assert_eq!(counts, vec![
+            (PathBuf::from("/pond"), 2, 1, 2),
+            (PathBuf::from("/swamp"), 1, 1, 0),
+            (PathBuf::from("/pond/lily"), 1, 0, 0),
+        ]);
+        assert!(stats.iter().all(|s| s.last_enqueue_ts == 123.0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ls_files_survives_symlink_loop() {
+        let workspace = tempfile::Builder::new().prefix("frog_pond").tempdir().unwrap();
+        let outside = tempfile::Builder::new().prefix("frog_outside").tempdir().unwrap();
+        let lily_pad = workspace.path().join("lily").join("pad");
+        std::fs::create_dir_all(&lily_pad).unwrap();
+        std::fs::write(lily_pad.join("frog.py"), "print(\"croak\")\n").unwrap();
+        std::fs::write(outside.path().join("toad.py"), "print(\"ribbit\")\n").unwrap();
+        std::os::unix::fs::symlink(workspace.path(), lily_pad.join("back_to_pond")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), workspace.path().join("outside")).unwrap();
+        let pond = tempfile::Builder::new().prefix("frog_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(pond.path(), vec![]).await;
+        let names = |files: &Vec<PathBuf>| {
+            let mut names = files.iter().map(|f| f.file_name().unwrap().to_string_lossy().to_string()).collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        let (files, _) = retrieve_files_in_workspace_folders(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, false).await;
+        assert_eq!(names(&files), vec!["frog.py"]);
+
+        let (files, _) = retrieve_files_in_workspace_folders(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, true).await;
+        assert_eq!(names(&files), vec!["frog.py", "toad.py"]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_indexing_stats() {
+        let workspace = tempfile::Builder::new().prefix("frog_stats").tempdir().unwrap();
+        let frog_text = "def croak():\n    print(\"ribbit\")\n";
+        std::fs::write(workspace.path().join("frog.py"), frog_text).unwrap();
+        std::fs::write(workspace.path().join("egg.py"), "").unwrap();
+        std::fs::create_dir_all(workspace.path().join("node_modules")).unwrap();
+        std::fs::write(workspace.path().join("node_modules").join("tadpole.js"), frog_text).unwrap();
+        let pond = tempfile::Builder::new().prefix("frog_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(pond.path(), vec![]).await;
+
+        let (files, _, stats) = retrieve_files_in_workspace_folders_with_stats(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, false, 10).await;
+
+        assert_eq!(files, vec![workspace.path().join("frog.py")]);
+        assert_eq!(stats.files_discovered, 1);
+        assert_eq!(stats.files_rejected, 1);
+        assert_eq!(stats.rejected_reasons.get("File size is too small"), Some(&1));
+        assert_eq!(stats.blacklisted_dirs_cnt, 1);
+        assert_eq!(stats.total_bytes, frog_text.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_paths_sample_is_capped() {
+        let workspace = tempfile::Builder::new().prefix("frog_eggs").tempdir().unwrap();
+        let eggs: HashSet<PathBuf> = (0..50).map(|i| workspace.path().join(format!("egg{}.py", i))).collect();
+        for egg in eggs.iter() {
+            std::fs::write(egg, "").unwrap();
+        }
+        let pond = tempfile::Builder::new().prefix("frog_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(pond.path(), vec![]).await;
+
+        let (files, _, stats) = retrieve_files_in_workspace_folders_with_stats(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, false, 5).await;
+
+        assert!(files.is_empty());
+        assert_eq!(stats.rejected_reasons.get("File size is too small"), Some(&50));
+        let sample = &stats.rejected_paths_sample.get("File size is too small").unwrap().paths;
+        assert_eq!(sample.len(), 5);
+        assert!(sample.iter().all(|p| eggs.contains(p)));
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 5);
+
+        let (_, _, no_sample_stats) = retrieve_files_in_workspace_folders_with_stats(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, false, 0).await;
+        assert!(no_sample_stats.rejected_paths_sample.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_blacklisted_dir_is_skipped() {
+        let workspace = tempfile::Builder::new().prefix("frog_blacklist").tempdir().unwrap();
+        let frog_text = "def croak():\n    print(\"ribbit\")\n";
+        std::fs::write(workspace.path().join("frog.py"), frog_text).unwrap();
+        std::fs::create_dir_all(workspace.path().join("frog_swamp")).unwrap();
+        std::fs::write(workspace.path().join("frog_swamp").join("tadpole.py"), frog_text).unwrap();
+        let pond = tempfile::Builder::new().prefix("frog_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(pond.path(), vec![]).await;
+        gcx.write().await.cmdline.blacklist_dir = vec!["frog_swamp".to_string()];
+        let filter_settings = FileFilterSettings::from_cmdline(&gcx.read().await.cmdline);
+
+        let (files, _) = retrieve_files_in_workspace_folders(gcx.clone(), vec![workspace.path().to_path_buf()], false, false, false).await;
+
+        assert_eq!(files, vec![workspace.path().join("frog.py")]);
+        assert!(is_this_inside_blacklisted_dir(&workspace.path().join("frog_swamp").join("tadpole.py"), &filter_settings));
+        assert!(!is_this_inside_blacklisted_dir(&PathBuf::from("/pond/frog.py"), &filter_settings));
+        assert!(!is_this_inside_blacklisted_dir(&workspace.path().join("frog_swamp").join("tadpole.py"), &FileFilterSettings::default()));
+    }
+
+    #[tokio::test]
+    async fn test_ls_files_in_git_worktree() {
+        let tmp = tempfile::Builder::new().prefix("frog_worktree").tempdir().unwrap();
+        let main_path = tmp.path().join("main");
+        let repo = git2::Repository::init(&main_path).unwrap();
+        crate::git::commit_file_for_tests(&repo, "frog.py", "print(\"croak\")\n", "frog");
+        let wt_path = tmp.path().join("linked");
+        repo.worktree("linked", &wt_path, None).unwrap();
+
+        assert!(wt_path.join(".git").is_file());
+        assert!(gitdir_from_dot_git_file(&wt_path.join(".git")).is_some());
+        let files = ls_files_under_version_control(&wt_path, std::time::Duration::from_secs(30)).await.unwrap();
+        assert!(files.contains(&wt_path.join("frog.py")), "{:?}", files);
+        assert_eq!(detect_vcs_for_a_file_path(&wt_path.join("frog.py")).await, Some((wt_path.clone(), "git")));
+
+        std::fs::write(tmp.path().join(".git"), "gitdir: /no/such/frog/dir\n").unwrap();
+        assert!(!is_git_root(&tmp.path().to_path_buf()));
+    }
+
+    #[test]
+    fn test_watcher_rename_events() {
+        let dir = tempfile::Builder::new().prefix("frog_rename").tempdir().unwrap();
+        let old_path = dir.path().join("frog.py");
+        let new_path = dir.path().join("toad.py");
+        std::fs::write(&new_path, "def jump():\n    print(\"the frog is now a toad\")\n").unwrap();
+        let cpath = |p: &PathBuf| crate::files_correction::canonical_path(&p.to_string_lossy().to_string()).to_string_lossy().to_string();
+        let rename_event = |mode: RenameMode, paths: Vec<PathBuf>| Event::new(EventKind::Modify(ModifyKind::Name(mode))).set_paths(paths);
+
+        let both = file_watcher_event_to_changes(&rename_event(RenameMode::Both, vec![old_path.clone(), new_path.clone()]), &FileFilterSettings::default());
+        assert_eq!(both, vec![(cpath(&old_path), WatcherChange::Removed), (cpath(&new_path), WatcherChange::Modified)]);
+
+        // inotify style: From, To, Both for the same rename, debounced into a single change per path
+        let t0 = Instant::now();
+        let mut debounce = WatcherDebounce::new(std::time::Duration::from_millis(500));
+        for event in [
+            rename_event(RenameMode::From, vec![old_path.clone()]),
+            rename_event(RenameMode::To, vec![new_path.clone()]),
+            rename_event(RenameMode::Both, vec![old_path.clone(), new_path.clone()]),
+        ] {
+            for (path, change) in file_watcher_event_to_changes(&event, &FileFilterSettings::default()) {
+                debounce.push(path, change, t0);
+            }
+        }
+        let (modified, removed) = debounce.take_ready(t0 + std::time::Duration::from_millis(1000));
+        assert_eq!(modified, vec![cpath(&new_path)]);
+        assert_eq!(removed, vec![cpath(&old_path)]);
+
+        // macOS style: RenameMode::Any for each side, existence decides which one it is
+        let any = file_watcher_event_to_changes(&rename_event(RenameMode::Any, vec![old_path.clone(), new_path.clone()]), &FileFilterSettings::default());
+        assert_eq!(any, vec![(cpath(&old_path), WatcherChange::Removed), (cpath(&new_path), WatcherChange::Modified)]);
     }
 }
@@ -1,4 +1,102 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+type Trigram = (char, char, char);
+
+const TOMBSTONES_COMPACT_MIN: usize = 1024;
+
+fn trigrams(s: &str) -> HashSet<Trigram> {
+    s.to_lowercase().chars().collect::<Vec<_>>().windows(3).map(|w| (w[0], w[1], w[2])).collect()
+}
+
+/// Trigram postings over the fuzzy search candidates, used to skip candidates that have nothing in common with the query.
+/// Removed entries leave a tombstone, ids stay stable so the postings don't need to be rewritten,
+/// once tombstones outnumber live entries the index is rebuilt from what's left.
+#[derive(Default, Clone, Debug)]
+pub struct TrigramIndex {
+    entries: Vec<Option<String>>,
+    ids: HashMap<String, u32>,
+    postings: HashMap<Trigram, Vec<u32>>,
+}
+
+impl TrigramIndex {
+    pub fn new<'a, I>(candidates: I) -> Self
+    where I: IntoIterator<Item = &'a String> {
+        let mut index = TrigramIndex::default();
+        for c in candidates {
+            index.add(c);
+        }
+        index
+    }
+
+    pub fn add(&mut self, candidate: &String) {
+        if self.ids.contains_key(candidate) {
+            return;
+        }
+        let id = self.entries.len() as u32;
+        self.entries.push(Some(candidate.clone()));
+        self.ids.insert(candidate.clone(), id);
+        for t in trigrams(candidate) {
+            self.postings.entry(t).or_insert_with(Vec::new).push(id);
+        }
+    }
+
+    pub fn remove(&mut self, candidate: &String) {
+        if let Some(id) = self.ids.remove(candidate) {
+            self.entries[id as usize] = None;
+            if self.entries.len() > TOMBSTONES_COMPACT_MIN && self.entries.len() > 2 * self.ids.len() {
+                self.compact();
+            }
+        }
+    }
+
+    fn compact(&mut self) {
+        let live = std::mem::take(&mut self.entries).into_iter().flatten().collect::<Vec<_>>();
+        *self = TrigramIndex::new(live.iter());
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().filter_map(|x| x.as_ref())
+    }
+
+    /// Candidates sharing at least half of the query trigrams, in insertion order.
+    /// None if the query is too short to have trigrams, the caller should look at everything then.
+    fn prefilter(&self, query: &str) -> Option<Vec<String>> {
+        let query_trigrams = trigrams(query);
+        if query_trigrams.is_empty() {
+            return None;
+        }
+        let min_shared = (query_trigrams.len() / 2).max(1) as u16;
+        let mut shared = vec![0u16; self.entries.len()];
+        for t in query_trigrams.iter() {
+            for id in self.postings.get(t).into_iter().flatten() {
+                shared[*id as usize] = shared[*id as usize].saturating_add(1);
+            }
+        }
+        Some(shared.iter().enumerate()
+            .filter(|(_, cnt)| **cnt >= min_shared)
+            .filter_map(|(id, _)| self.entries[id].clone())
+            .collect())
+    }
+}
+
+/// Like `fuzzy_search` over everything in the index, but only scores candidates passing the trigram prefilter.
+/// A candidate sharing less than half of the query trigrams is never scored, so the tail of top_n can differ from a full scan,
+/// the best match on real paths agrees.
+pub fn fuzzy_search_indexed(
+    correction_candidate: &String,
+    index: &TrigramIndex,
+    top_n: usize,
+    separator_chars: &[char],
+) -> Vec<String> {
+    match index.prefilter(correction_candidate) {
+        Some(candidates) if !candidates.is_empty() => fuzzy_search(correction_candidate, candidates, top_n, separator_chars),
+        _ => fuzzy_search(correction_candidate, index.iter().cloned(), top_n, separator_chars),
+    }
+}
 
 pub fn fuzzy_search<I>(
     correction_candidate: &String,
@@ -81,11 +179,15 @@ mod tests {
     async fn get_candidates_from_workspace_files() -> Vec<String> {
         let proj_folders = vec![PathBuf::from(".").canonicalize().unwrap()];
         let proj_folder = &proj_folders[0];
+        let pond = tempfile::Builder::new().prefix("frog_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(pond.path(), vec![]).await;
 
         let (workspace_files, _vcs_folders) = retrieve_files_in_workspace_folders(
+            gcx,
             proj_folders.clone(),
             false,
-            false
+            false,
+            false,
         ).await;
 
         workspace_files
@@ -169,6 +271,77 @@ mod tests {
         assert_eq!(sorted_result, sorted_expected, "The result should contain the expected paths in any order, found {:?} instead", result);
     }
 
+    #[tokio::test]
+    async fn test_fuzzy_search_indexed_best_match_same_as_full_scan() {
+        let candidates = get_candidates_from_workspace_files().await;
+        let index = TrigramIndex::new(candidates.iter());
+
+        for query in ["frog.p", "emergency_frog_situation/wo", "fuzzy_serch.rs", "ab"] {
+            let full = fuzzy_search(&query.to_string(), candidates.clone(), 1, &['/', '\\']);
+            let indexed = fuzzy_search_indexed(&query.to_string(), &index, 1, &['/', '\\']);
+            assert_eq!(indexed, full, "query {:?}", query);
+        }
+    }
+
+    #[test]
+    fn test_trigram_index_remove() {
+        let frog = "frog.py".to_string();
+        let toad = "toad.py".to_string();
+        let mut index = TrigramIndex::new([frog.clone(), toad.clone()].iter());
+        index.remove(&frog);
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(fuzzy_search_indexed(&"frog.py".to_string(), &index, 3, &['/']), Vec::<String>::new());
+        assert_eq!(fuzzy_search_indexed(&"toad.p".to_string(), &index, 3, &['/']), vec![toad]);
+    }
+
+    #[test]
+    fn test_trigram_index_compacts_tombstones() {
+        let tadpoles = (0..4 * TOMBSTONES_COMPACT_MIN).map(|i| format!("pond/tadpole_{}.py", i)).collect::<Vec<_>>();
+        let mut index = TrigramIndex::new(tadpoles.iter());
+        for t in tadpoles.iter().skip(10) {
+            index.remove(t);
+        }
+
+        assert_eq!(index.len(), 10);
+        assert!(index.entries.len() <= TOMBSTONES_COMPACT_MIN, "{} entries kept after removing", index.entries.len());
+        assert_eq!(index.iter().cloned().collect::<Vec<_>>(), tadpoles[..10].to_vec());
+        assert_eq!(fuzzy_search_indexed(&"tadpole_7.py".to_string(), &index, 1, &['/']), vec![tadpoles[7].clone()]);
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn test_fuzzy_search_indexed_speed() {
+        // Arrange
+        let workspace_paths = vec![
+            PathBuf::from("home").join("user").join("repo1"),
+            PathBuf::from("home").join("user").join("repo2"),
+        ];
+        let words = ["frog", "toad", "pond", "lily", "swamp", "tadpole", "heron", "reed"];
+        let mut paths_str = Vec::new();
+        for i in 0..100000 {
+            let path = workspace_paths[i % workspace_paths.len()]
+                .join(words[i % words.len()])
+                .join(format!("{}_{}", words[(i / 8) % words.len()], i / 64))
+                .join(format!("{}_{}.ext", words[(i / 3) % words.len()], i));
+            paths_str.push(path.to_string_lossy().to_string());
+        }
+        let index = TrigramIndex::new(paths_str.iter());
+        let correction_candidate = PathBuf::from("lily_77").join("heron_4999.ext").to_string_lossy().to_string();
+
+        // Act
+        let start_time = std::time::Instant::now();
+        let full = fuzzy_search(&correction_candidate, paths_str.clone(), 10, &['/', '\\']);
+        let full_time = start_time.elapsed();
+        let start_time = std::time::Instant::now();
+        let indexed = fuzzy_search_indexed(&correction_candidate, &index, 10, &['/', '\\']);
+        let indexed_time = start_time.elapsed();
+
+        // Assert
+        assert_eq!(indexed.first(), full.first());
+        assert!(indexed_time * 10 < full_time, "indexed search took {:?}, full scan {:?}", indexed_time, full_time);
+    }
+
     // #[cfg(not(debug_assertions))]
     #[ignore]
     #[test]
@@ -8,7 +8,7 @@ use serde::{Serialize, Deserialize};
 
 use crate::ast::chunk_utils::official_text_hashing_function;
 use crate::custom_error::MapErrToString;
-use crate::file_filter::BLACKLISTED_DIRS;
+use crate::file_filter::{blacklisted_dir_names, FileFilterSettings};
 use crate::files_correction::{deserialize_path, get_active_workspace_folder, get_project_dirs, serialize_path};
 use crate::global_context::GlobalContext;
 use crate::git::{FileChange, FileChangeStatus, DiffStatusType};
@@ -30,7 +30,7 @@ impl Checkpoint {
 async fn open_shadow_repo_and_nested_repos(
     gcx: Arc<ARwLock<GlobalContext>>, workspace_folder: &Path, allow_init_main_repo: bool,
 ) -> Result<(Repository, Vec<Repository>, String), String> {
-    fn open_repos(paths: &[PathBuf], allow_init: bool, nested: bool, cache_dir: &Path) -> Result<Vec<Repository>, String> {
+    fn open_repos(paths: &[PathBuf], allow_init: bool, nested: bool, cache_dir: &Path, blacklisted_dirs: &[String]) -> Result<Vec<Repository>, String> {
         let mut result = Vec::new();
         for path in paths {
             let path_hash = official_text_hashing_function(&path.to_string_lossy().to_string());
@@ -45,8 +45,8 @@ async fn open_shadow_repo_and_nested_repos(
                 Repository::open(&git_dir_path).map_err_to_string()
             }?;
             nested_repo.set_workdir(path, false).map_err_to_string()?;
-            for blacklisted_dir in  BLACKLISTED_DIRS {
-                if let Err(e) = nested_repo.add_ignore_rule(blacklisted_dir) {
+            for blacklisted_dir in blacklisted_dirs {
+                if let Err(e) = nested_repo.add_ignore_rule(&blacklisted_dir) {
                     tracing::warn!("Failed to add ignore rule for {blacklisted_dir}: {e}");
                 }
             }
@@ -55,9 +55,13 @@ async fn open_shadow_repo_and_nested_repos(
         Ok(result)
     }
     
-    let (cache_dir, vcs_roots) = {
+    let (cache_dir, vcs_roots, blacklisted_dirs) = {
         let gcx_locked = gcx.read().await;
-        (gcx_locked.cache_dir.clone(), gcx_locked.documents_state.workspace_vcs_roots.clone())
+        (
+            gcx_locked.cache_dir.clone(),
+            gcx_locked.documents_state.workspace_vcs_roots.clone(),
+            blacklisted_dir_names(&FileFilterSettings::from_cmdline(&gcx_locked.cmdline)),
+        )
     };
     let nested_vcs_roots: Vec<PathBuf> = {
         let vcs_roots_locked = vcs_roots.lock().unwrap();
@@ -66,9 +70,9 @@ async fn open_shadow_repo_and_nested_repos(
     };
     let workspace_folder_hash = official_text_hashing_function(&workspace_folder.to_string_lossy().to_string());
 
-    let repo = open_repos(&[workspace_folder.to_path_buf()], allow_init_main_repo, false, &cache_dir)?
+    let repo = open_repos(&[workspace_folder.to_path_buf()], allow_init_main_repo, false, &cache_dir, &blacklisted_dirs)?
         .into_iter().next().unwrap();
-    let nested_repos = open_repos(&nested_vcs_roots, true, true, &cache_dir)?;
+    let nested_repos = open_repos(&nested_vcs_roots, true, true, &cache_dir, &blacklisted_dirs)?;
 
     Ok((repo, nested_repos, workspace_folder_hash))
 }
@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use git2::{Repository, Sort};
+use tokio::sync::RwLock as ARwLock;
+
+use crate::ast::ast_parse_anything::parse_anything;
+use crate::ast::ast_structs::{AstDefinition, AstErrorStats};
+use crate::custom_error::MapErrToString;
+use crate::global_context::GlobalContext;
+
+
+const CHURN_MAX_COMMITS: usize = 500;
+
+// Line ranges (1-based, inclusive) of `new_text` that a commit changed, a pure deletion touches the line it happened at
+fn changed_line_ranges(old_blob: Option<&git2::Blob>, new_blob: &git2::Blob) -> Result<Vec<(usize, usize)>, String> {
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(0);
+    let patch = git2::Patch::from_blobs(old_blob, None, Some(new_blob), None, Some(&mut options))
+        .map_err_with_prefix("Failed to diff blobs:")?;
+    let mut ranges = vec![];
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(hunk_idx).map_err_to_string()?;
+        let line1 = (hunk.new_start() as usize).max(1);
+        let line2 = line1 + (hunk.new_lines() as usize).max(1) - 1;
+        ranges.push((line1, line2));
+    }
+    Ok(ranges)
+}
+
+fn churn_symbol_path(def: &AstDefinition) -> String {
+    def.official_path.iter().skip_while(|c| c.as_str() == "root").cloned().collect::<Vec<_>>().join("::")
+}
+
+// Each commit's changes are mapped onto the symbols of the file as it was in that commit, so renamed symbols count
+// separately. Commits where the file doesn't parse are skipped.
+fn symbol_churn_in_history(file_path: &Path) -> Result<Vec<(String, usize)>, String> {
+    let repository = Repository::discover(file_path.parent().unwrap_or(file_path))
+        .map_err_with_prefix("Not inside a git repository:")?;
+    let workdir = repository.workdir().ok_or("Repository has no working directory".to_string())?;
+    let workdir = workdir.canonicalize().unwrap_or(workdir.to_path_buf());
+    let file_path = file_path.canonicalize().map_err_with_prefix("Failed to canonicalize path:")?;
+    let relative_path = file_path.strip_prefix(&workdir).map_err_with_prefix("File is outside of the repository:")?;
+    let cpath = file_path.to_string_lossy().to_string();
+
+    let mut revwalk = repository.revwalk().map_err_to_string()?;
+    revwalk.push_head().map_err_with_prefix("Failed to find HEAD:")?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME).map_err_to_string()?;
+
+    let mut churn: HashMap<String, usize> = HashMap::new();
+    for oid in revwalk.take(CHURN_MAX_COMMITS) {
+        let commit = repository.find_commit(oid.map_err_to_string()?).map_err_to_string()?;
+        let new_blob = match commit.tree().ok().and_then(|t| t.get_path(relative_path).ok()).and_then(|e| repository.find_blob(e.id()).ok()) {
+            Some(blob) => blob,
+            None => continue,
+        };
+        let old_blob = commit.parent(0).ok()
+            .and_then(|p| p.tree().ok())
+            .and_then(|t| t.get_path(relative_path).ok())
+            .and_then(|e| repository.find_blob(e.id()).ok());
+        if old_blob.as_ref().map_or(false, |b| b.id() == new_blob.id()) {
+            continue;
+        }
+        let ranges = changed_line_ranges(old_blob.as_ref(), &new_blob)?;
+        let text = String::from_utf8_lossy(new_blob.content()).to_string();
+        let (definitions, _language, _imports) = match parse_anything(&cpath, &text, &mut None, &mut AstErrorStats::default()) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::info!("churn skips commit {} of {}: {}", commit.id(), cpath, e);
+                continue;
+            }
+        };
+        let mut touched: HashSet<String> = HashSet::new();
+        for def in definitions.iter() {
+            // "<toplevel>" is the whole file, it changes in every commit
+            if def.name().starts_with("<") {
+                continue;
+            }
+            if ranges.iter().any(|(line1, line2)| *line1 <= def.full_line2() && def.full_line1() <= *line2) {
+                let path = churn_symbol_path(def);
+                if !path.is_empty() {
+                    touched.insert(path);
+                }
+            }
+        }
+        for path in touched {
+            *churn.entry(path).or_insert(0) += 1;
+        }
+    }
+
+    let mut result: Vec<(String, usize)> = churn.into_iter().collect();
+    result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(result)
+}
+
+/// How many commits touched each symbol of `file_path`, most changed first. Best-effort and git only. When the AST
+/// index has the file, symbols that are gone from it (deleted or renamed since) are dropped, so the result
+/// is about the code as it is now.
+pub async fn symbol_churn(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    file_path: &Path,
+) -> Result<Vec<(String, usize)>, String> {
+    let path = file_path.to_path_buf();
+    let churn = tokio::task::spawn_blocking(move || symbol_churn_in_history(&path)).await.map_err_to_string()??;
+
+    let ast_service = match gcx.read().await.ast_service.clone() {
+        Some(ast_service) => ast_service,
+        None => return Ok(churn),
+    };
+    let ast_index = ast_service.lock().await.ast_index.clone();
+    let cpath = file_path.canonicalize().unwrap_or(file_path.to_path_buf()).to_string_lossy().to_string();
+    let current_symbols = crate::ast::ast_db::doc_defs(ast_index, &cpath).await
+        .iter()
+        .map(|d| churn_symbol_path(d))
+        .collect::<HashSet<_>>();
+    if current_symbols.is_empty() {
+        return Ok(churn);  // not indexed yet, or nothing to map onto
+    }
+    Ok(churn.into_iter().filter(|(path, _)| current_symbols.contains(path)).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::commit_file_for_tests;
+
+    #[test]
+    fn test_symbol_churn_counts_commits_per_function() {
+        let tmp = tempfile::Builder::new().prefix("frog_churn").tempdir().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let file_path = tmp.path().join("frog.py");
+        let commit_text = |text: &str, message: &str| commit_file_for_tests(&repo, "frog.py", text, message);
+
+        let croak = "def croak():\n    print(\"croak\")\n";
+        commit_text(&format!("def jump():\n    return 1\n\n\n{}", croak), "The frog can jump and croak");
+        for height in 2..5 {
+            commit_text(&format!("def jump():\n    return {}\n\n\n{}", height, croak), "Jump higher");
+        }
+
+        let churn = symbol_churn_in_history(&file_path).unwrap();
+
+        assert_eq!(churn, vec![("jump".to_string(), 4), ("croak".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_symbol_churn_skips_commits_that_dont_parse() {
+        let tmp = tempfile::Builder::new().prefix("frog_churn_generated").tempdir().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let file_path = tmp.path().join("frog.js");
+        let commit_text = |text: &str, message: &str| commit_file_for_tests(&repo, "frog.js", text, message);
+
+        commit_text("function jump() {\n    return 1;\n}\n", "The frog can jump");
+        // too many symbols, parse_anything refuses generated files
+        let tadpoles = (0..10_001).map(|i| format!("function tadpole{}() {{ return {}; }}\n", i, i)).collect::<String>();
+        commit_text(&format!("function jump() {{\n    return 1;\n}}\n{}", tadpoles), "Generate tadpoles");
+        commit_text("function jump() {\n    return 2;\n}\n", "Jump higher");
+
+        let churn = symbol_churn_in_history(&file_path).unwrap();
+
+        assert!(churn.iter().all(|(path, _)| !path.contains("tadpole")), "{:?}", churn);
+        assert_eq!(churn.iter().find(|(path, _)| path.ends_with("jump")).map(|(_, n)| *n), Some(2), "{:?}", churn);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_churn_keeps_only_current_symbols() {
+        let tmp = tempfile::Builder::new().prefix("frog_churn_ast").tempdir().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        let file_path = tmp.path().join("frog.py");
+        let commit_text = |text: &str, message: &str| commit_file_for_tests(&repo, "frog.py", text, message);
+        commit_text("def swim():\n    return 1\n\n\ndef jump():\n    return 1\n", "The frog can swim and jump");
+        commit_text("def swim():\n    return 2\n\n\ndef jump():\n    return 2\n", "Faster");
+        let current_text = "def jump():\n    return 3\n";
+        commit_text(current_text, "Frogs don't swim anymore");
+
+        let gcx = crate::global_context::create_global_context_for_tests(tmp.path(), vec![file_path.clone()]).await;
+        let history_only = symbol_churn(gcx.clone(), &file_path).await.unwrap();
+        assert!(history_only.iter().any(|(path, _)| path == "swim"), "{:?}", history_only);
+
+        let ast_service = crate::ast::ast_indexer_thread::ast_service_init("".to_string(), 10).await;
+        let ast_index = ast_service.lock().await.ast_index.clone();
+        gcx.write().await.ast_service = Some(ast_service);
+        let cpath = file_path.canonicalize().unwrap().to_string_lossy().to_string();
+        crate::ast::ast_db::doc_add(ast_index.clone(), &cpath, &current_text.to_string(), &mut AstErrorStats::default()).await.unwrap();
+        crate::ast::ast_db::flush_sled_batch(ast_index, 0).await;
+
+        let churn = symbol_churn(gcx.clone(), &file_path).await.unwrap();
+
+        assert_eq!(churn, vec![("jump".to_string(), 3)]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod checkpoints;
+pub mod churn;
 pub mod commit_info;
 pub mod operations;
 
@@ -7,6 +8,19 @@ use std::path::PathBuf;
 
 use crate::files_correction::{serialize_path, deserialize_path};
 
+// Writes text to file_name in the working directory of repo and commits it on top of HEAD
+#[cfg(test)]
+pub fn commit_file_for_tests(repo: &git2::Repository, file_name: &str, text: &str, message: &str) {
+    std::fs::write(repo.workdir().unwrap().join(file_name), text).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(std::path::Path::new(file_name)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("Frog Author", "frog@pond.com").unwrap();
+    let parents = repo.head().ok().and_then(|h| h.peel_to_commit().ok()).into_iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents.iter().collect::<Vec<_>>()).unwrap();
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CommitInfo {
     pub project_path: url::Url,
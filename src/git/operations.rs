@@ -286,6 +286,62 @@ pub fn open_or_init_repo(path: &Path) -> Result<Repository, String> {
     }
 }
 
+pub struct BlameLine {
+    pub line_n: usize,
+    pub commit_short: String,
+    pub author: String,
+    pub summary: String,
+    pub text: String,
+}
+
+/// Blames `line1..=line2` (1-based) of the committed version of `file_path`, the repository is found by walking up from the file.
+pub fn git_blame_lines(file_path: &Path, line1: usize, line2: usize) -> Result<Vec<BlameLine>, String> {
+    let repository = Repository::discover(file_path.parent().unwrap_or(file_path))
+        .map_err_with_prefix("Not inside a git repository:")?;
+    let workdir = repository.workdir().ok_or("Repository has no working directory".to_string())?;
+    let workdir = workdir.canonicalize().unwrap_or(workdir.to_path_buf());
+    let file_path = file_path.canonicalize().map_err_with_prefix("Failed to canonicalize path:")?;
+    let relative_path = file_path.strip_prefix(&workdir).map_err_with_prefix("File is outside of the repository:")?;
+
+    let file_text = std::fs::read_to_string(&file_path).map_err_with_prefix("Failed to read file:")?;
+    let lines_cnt = file_text.lines().count();
+    let line2 = line2.min(lines_cnt);
+    if line1 == 0 || line1 > line2 {
+        return Err(format!("Invalid line range {}-{}, the file has {} lines", line1, line2, lines_cnt));
+    }
+
+    let mut options = git2::BlameOptions::new();
+    options.min_line(line1).max_line(line2);
+    let blame = repository.blame_file(relative_path, Some(&mut options))
+        .map_err_with_prefix("Failed to blame file:")?;
+
+    let mut result = vec![];
+    for (idx, text) in file_text.lines().enumerate().skip(line1 - 1).take(line2 + 1 - line1) {
+        let line_n = idx + 1;
+        let hunk = match blame.get_line(line_n) {
+            Some(hunk) => hunk,
+            None => continue,
+        };
+        let commit_id = hunk.final_commit_id();
+        let (author, summary) = if commit_id.is_zero() {
+            ("Not Committed Yet".to_string(), "".to_string())
+        } else {
+            let summary = repository.find_commit(commit_id)
+                .map(|c| c.summary().unwrap_or_default().to_string())
+                .unwrap_or_default();
+            (hunk.final_signature().name().unwrap_or_default().to_string(), summary)
+        };
+        result.push(BlameLine {
+            line_n,
+            commit_short: commit_id.to_string().chars().take(8).collect(),
+            author,
+            summary,
+            text: text.to_string(),
+        });
+    }
+    Ok(result)
+}
+
 pub fn get_commit_datetime(repository: &Repository, commit_oid: &Oid) -> Result<DateTime<Utc>, String> {
     let commit = repository.find_commit(commit_oid.clone()).map_err_to_string()?;
 
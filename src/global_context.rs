@@ -61,6 +61,8 @@ pub struct CommandLine {
     // pub ast_light_mode: bool,
     #[structopt(long, default_value="50000", help="Maximum files for AST index, to avoid OOM on large projects.")]
     pub ast_max_files: usize,
+    #[structopt(long, number_of_values=1, use_delimiter=true, help="Only parse files of these languages with AST, for example --ast-languages python,rust. Other files can still go to VecDB. Empty means all languages.")]
+    pub ast_languages: Vec<String>,
     #[structopt(long, default_value="", help="Give it a path for AST database to make it permanent, if there is the database already, process starts without parsing all the files (careful). This quick start is helpful for automated solution search.")]
     pub ast_permanent: String,
 
@@ -76,17 +78,73 @@ pub struct CommandLine {
     #[cfg(feature="vecdb")]
     #[structopt(long, default_value="", help="Set VecDB storage path manually.")]
     pub vecdb_force_path: String,
+    #[cfg(feature="vecdb")]
+    #[structopt(long, help="Keep test files (tests/ folders, test_*.py, *_test.go, ...) in a separate VecDB scope, search in the workspace skips them unless asked for the tests scope.")]
+    pub vecdb_separate_test_scope: bool,
+    #[cfg(feature="vecdb")]
+    #[structopt(long, number_of_values=1, help="Also index the workspace with this embedding model, to compare search results, format is name:embedding_size. It uses the endpoint of the default embedding model, unless followed by @style:endpoint, for example gte-small:384@local:/models/gte-small. Can be repeated.")]
+    pub vecdb_extra_model: Vec<String>,
+    #[cfg(feature="vecdb")]
+    #[structopt(long, default_value="300", help="Memories added within this many milliseconds are vectorized together in one pass.")]
+    pub vecdb_memories_flush_ms: u64,
 
     #[structopt(long, short="f", default_value="", help="A path to jsonl file with {\"path\": ...} on each line, files will immediately go to VecDB and AST.")]
     pub files_jsonl_path: String,
     #[structopt(long, short="w", default_value="", help="Workspace folder to find all the files. An LSP or HTTP request can override this later.")]
     pub workspace_folder: String,
+    #[structopt(long, default_value="500", help="File system events for the same file within this many milliseconds are coalesced into one reindex.")]
+    pub watcher_debounce_ms: u64,
+    #[structopt(long, default_value="0", help="At most this many files changed on disk are reindexed per minute, the rest waits in a queue. Saves battery when something keeps writing files. Changes from the IDE are not limited. 0 means no limit.")]
+    pub watcher_max_files_per_minute: usize,
+    #[structopt(long, default_value="10", help="How many paths to keep as an example for each reason files are not indexed, see the workspace indexing stats. 0 means none.")]
+    pub rejected_paths_sample_size: usize,
+    #[structopt(long, help="Follow symlinked directories that point outside of the workspace folder when looking for files.")]
+    pub follow_symlinks: bool,
+    #[structopt(long, default_value="10485760", help="Files larger than this are never read or indexed, even if size thresholds are ignored.")]
+    pub max_file_size_bytes: u64,
+    #[structopt(long, default_value="30", help="Subprocesses like gh, glab, git ls-files are killed if they run longer than this many seconds.")]
+    pub subprocess_timeout_secs: u64,
+    #[structopt(long, number_of_values=1, help="A directory name to skip when looking for files, in addition to built-in ones like node_modules. Can be repeated.")]
+    pub blacklist_dir: Vec<String>,
 
     #[structopt(long, help="create manually bring-your-own-key.yaml, customization.yaml and privacy.yaml and exit.")]
     pub only_create_yaml_configs: bool,
     #[structopt(long, help="Print combined customization settings from both system defaults and customization.yaml.")]
     pub print_customization: bool,
 
+    #[structopt(long, default_value="0", help="When a patch section isn't found exactly, retry matching its lines with normalized Damerau-Levenshtein similarity above this ratio (0..1). Zero means exact matching only.")]
+    pub patch_fuzzy_anchor_tolerance: f64,
+
+    #[structopt(long, default_value="0", help="When a patch names a file that doesn't exist, replace it with the only existing path at least this similar (0..1, normalized Levenshtein), like 0.9 for one-letter typos. Zero means no correction.")]
+    pub patch_path_typo_similarity: f64,
+
+    #[structopt(long, default_value="reject", possible_values=&["reject", "add", "fuzzy"], help="What to do when a patch edits a file that doesn't exist: \"reject\" it, \"add\" the file with the added lines, or \"fuzzy\" correct the path to the nearest existing file.")]
+    pub patch_missing_file: crate::diffs::MissingFilePolicy,
+
+    #[structopt(long, default_value="0", help="Apply all patch chunks at once only when they are at least this confident (0..100), return the rest for review. Zero applies everything.")]
+    pub patch_auto_apply_min_confidence: u8,
+
+    #[structopt(long, default_value="4", help="How many columns a tab takes when a patch is re-indented to match the file.")]
+    pub patch_tab_width: usize,
+
+    #[structopt(long, number_of_values=1, help="Ask before the patch tool edits files matching this glob, it is matched against \"patch /absolute/path\", like \"patch */.github/*\". Can be repeated. Without any, every patch asks.")]
+    pub patch_ask_user: Vec<String>,
+
+    #[structopt(long, number_of_values=1, help="Never let the patch tool edit files matching this glob, it is matched against \"patch /absolute/path\", like \"patch */Cargo.lock\". Can be repeated.")]
+    pub patch_deny: Vec<String>,
+
+    #[structopt(long, default_value="2", help="How many times to ask again for follow-up suggestions if the model answers with something unparsable or an empty list.")]
+    pub follow_up_retries: usize,
+
+    #[structopt(long, default_value="2000", help="Token budget for memories added to the context, the least relevant memories are dropped to fit.")]
+    pub memories_max_tokens: usize,
+
+    #[structopt(long, default_value="0.02", help="How much a fresh memory (recently created or used) is preferred in search, in units of embedding distance. Zero ranks by distance only.")]
+    pub memories_recency_weight: f32,
+
+    #[structopt(long, default_value="30", help="Days after which the recency bonus of a memory halves.")]
+    pub memories_recency_half_life_days: f32,
+
     #[structopt(long, help="Enable experimental features, such as new integrations.")]
     pub experimental: bool,
 
@@ -165,6 +223,8 @@ pub struct GlobalContext {
     pub integration_sessions: HashMap<String, Arc<AMutex<Box<dyn IntegrationSession>>>>,
     pub codelens_cache: Arc<AMutex<crate::http::routers::v1::code_lens::CodeLensCache>>,
     pub docker_ssh_tunnel: Arc<AMutex<Option<SshTunnel>>>,
+    pub project_summary_cache: Arc<StdMutex<HashMap<String, (std::time::SystemTime, Option<String>)>>>,  // path -> (mtime, summary)
+    pub history_summary_cache: Arc<StdMutex<HashMap<String, (usize, String)>>>,  // chat_id -> (dropped messages count, summary)
 }
 
 pub type SharedGlobalContext = Arc<ARwLock<GlobalContext>>;  // TODO: remove this type alias, confusing
@@ -333,7 +393,14 @@ pub async fn create_global_context(
     cache_dir: PathBuf,
     config_dir: PathBuf,
 ) -> (Arc<ARwLock<GlobalContext>>, std::sync::mpsc::Receiver<String>, Arc<AtomicBool>, CommandLine) {
-    let cmdline = CommandLine::from_args();
+    create_global_context_with_cmdline(cache_dir, config_dir, CommandLine::from_args()).await
+}
+
+pub async fn create_global_context_with_cmdline(
+    cache_dir: PathBuf,
+    config_dir: PathBuf,
+    cmdline: CommandLine,
+) -> (Arc<ARwLock<GlobalContext>>, std::sync::mpsc::Receiver<String>, Arc<AtomicBool>, CommandLine) {
     let (ask_shutdown_sender, ask_shutdown_receiver) = std::sync::mpsc::channel::<String>();
     let shutdown_flag = Arc::new(AtomicBool::new(false));
     let mut http_client_builder = reqwest::Client::builder();
@@ -374,12 +441,31 @@ pub async fn create_global_context(
         integration_sessions: HashMap::new(),
         codelens_cache: Arc::new(AMutex::new(crate::http::routers::v1::code_lens::CodeLensCache::default())),
         docker_ssh_tunnel: Arc::new(AMutex::new(None)),
+        project_summary_cache: Arc::new(StdMutex::new(HashMap::new())),
+        history_summary_cache: Arc::new(StdMutex::new(HashMap::new())),
     };
     let gcx = Arc::new(ARwLock::new(cx));
     crate::files_in_workspace::watcher_init(gcx.clone()).await;
     (gcx, ask_shutdown_receiver, shutdown_flag, cmdline)
 }
 
+// Workspace at workspace_dir with the given files known, caches and config go into workspace_dir too
+#[cfg(test)]
+pub async fn create_global_context_for_tests(
+    workspace_dir: &std::path::Path,
+    workspace_files: Vec<PathBuf>,
+) -> Arc<ARwLock<GlobalContext>> {
+    let cmdline = CommandLine::from_iter(["refact-lsp", "--workspace-folder", &workspace_dir.to_string_lossy()]);
+    let (gcx, _, _, _) = create_global_context_with_cmdline(workspace_dir.join(".cache"), workspace_dir.join(".config"), cmdline).await;
+    let (workspace_files_arc, cache_dirty) = {
+        let gcx_locked = gcx.read().await;
+        (gcx_locked.documents_state.workspace_files.clone(), gcx_locked.documents_state.cache_dirty.clone())
+    };
+    *workspace_files_arc.lock().unwrap() = workspace_files.iter().map(|p| crate::files_correction::canonical_path(&p.to_string_lossy().to_string())).collect();
+    *cache_dirty.lock().await = 1.0;  // any timestamp in the past rebuilds the correction cache on first use
+    gcx
+}
+
 pub async fn is_metadata_supported(gcx: Arc<ARwLock<GlobalContext>>) -> bool {
     let gcx_locked = gcx.read().await;
     if let Some(caps_arc) = gcx_locked.caps.clone() {
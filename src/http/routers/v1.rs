@@ -14,13 +14,13 @@ use crate::custom_error::ScratchError;
 use crate::global_context::SharedGlobalContext;
 use crate::http::routers::v1::code_completion::{handle_v1_code_completion_web, handle_v1_code_completion_prompt};
 use crate::http::routers::v1::code_lens::handle_v1_code_lens;
-use crate::http::routers::v1::ast::{handle_v1_ast_file_dump, handle_v1_ast_file_symbols, handle_v1_ast_status};
+use crate::http::routers::v1::ast::{handle_v1_ast_document_symbols, handle_v1_ast_file_dump, handle_v1_ast_file_symbols, handle_v1_ast_import_cycles, handle_v1_ast_search_by_name_regex, handle_v1_ast_status, handle_v1_ast_unreferenced};
 use crate::http::routers::v1::at_commands::{handle_v1_command_completion, handle_v1_command_preview, handle_v1_at_command_execute};
 use crate::http::routers::v1::at_tools::{handle_v1_tools, handle_v1_tools_check_if_confirmation_needed, handle_v1_tools_execute};
 use crate::http::routers::v1::caps::handle_v1_caps;
 use crate::http::routers::v1::caps::handle_v1_ping;
 use crate::http::routers::v1::chat::{handle_v1_chat, handle_v1_chat_completions};
-use crate::http::routers::v1::chat_based_handlers::handle_v1_commit_message_from_diff;
+use crate::http::routers::v1::chat_based_handlers::{handle_v1_commit_message_from_chunks, handle_v1_commit_message_from_diff};
 use crate::http::routers::v1::dashboard::get_dashboard_plots;
 use crate::http::routers::v1::docker::{handle_v1_docker_container_action, handle_v1_docker_container_list};
 use crate::http::routers::v1::git::{handle_v1_git_commit, handle_v1_checkpoints_preview, handle_v1_checkpoints_restore};
@@ -30,19 +30,19 @@ use crate::http::routers::v1::telemetry_network::handle_v1_telemetry_network;
 use crate::http::routers::v1::telemetry_chat::handle_v1_telemetry_chat;
 use crate::http::routers::v1::links::handle_v1_links;
 use crate::http::routers::v1::lsp_like_handlers::{handle_v1_lsp_did_change, handle_v1_lsp_add_folder, handle_v1_lsp_initialize, handle_v1_lsp_remove_folder, handle_v1_set_active_document};
-use crate::http::routers::v1::status::handle_v1_rag_status;
+use crate::http::routers::v1::status::{handle_v1_rag_status, handle_v1_workspace_folders_stats, handle_v1_workspace_indexing_stats};
 use crate::http::routers::v1::customization::handle_v1_customization;
 use crate::http::routers::v1::customization::handle_v1_config_path;
 use crate::http::routers::v1::gui_help_handlers::handle_v1_fullpath;
-use crate::http::routers::v1::patch::{handle_v1_patch_apply_all, handle_v1_patch_single_file_from_ticket};
+use crate::http::routers::v1::patch::{handle_v1_patch_apply_all, handle_v1_patch_chunk_at_line, handle_v1_patch_preview, handle_v1_patch_single_file_from_ticket};
 use crate::http::routers::v1::subchat::{handle_v1_subchat, handle_v1_subchat_single};
 use crate::http::routers::v1::sync_files::handle_v1_sync_files_extract_tar;
 use crate::http::routers::v1::system_prompt::handle_v1_prepend_system_prompt_and_maybe_more_initial_messages;
 
 #[cfg(feature="vecdb")]
-use crate::http::routers::v1::vecdb::{handle_v1_vecdb_search, handle_v1_vecdb_status};
+use crate::http::routers::v1::vecdb::{handle_v1_vecdb_search, handle_v1_vecdb_status, handle_v1_vecdb_pause, handle_v1_vecdb_resume};
 #[cfg(feature="vecdb")]
-use crate::http::routers::v1::handlers_memdb::{handle_mem_query, handle_mem_add, handle_mem_erase, handle_mem_update_used, handle_mem_block_until_vectorized, handle_mem_list};
+use crate::http::routers::v1::handlers_memdb::{handle_mem_query, handle_mem_add, handle_mem_erase, handle_mem_update_used, handle_mem_block_until_vectorized, handle_mem_list, handle_ongoing_dump, handle_ongoing_update};
 use crate::http::routers::v1::v1_integrations::{handle_v1_integration_get, handle_v1_integration_icon, handle_v1_integration_save, handle_v1_integration_delete, handle_v1_integrations, handle_v1_integrations_filtered, handle_v1_integration_json_schema};
 use crate::http::utils::telemetry_wrapper;
 
@@ -107,9 +107,15 @@ pub fn make_v1_router() -> Router {
 
         .route("/ast-file-symbols", telemetry_post!(handle_v1_ast_file_symbols))
         .route("/ast-file-dump", telemetry_post!(handle_v1_ast_file_dump))
+        .route("/ast-document-symbols", telemetry_post!(handle_v1_ast_document_symbols))
         .route("/ast-status", telemetry_get!(handle_v1_ast_status))
+        .route("/ast-import-cycles", telemetry_get!(handle_v1_ast_import_cycles))
+        .route("/ast-unreferenced", telemetry_post!(handle_v1_ast_unreferenced))
+        .route("/ast-search-by-name-regex", telemetry_post!(handle_v1_ast_search_by_name_regex))
 
         .route("/rag-status", telemetry_get!(handle_v1_rag_status))
+        .route("/workspace-indexing-stats", telemetry_get!(handle_v1_workspace_indexing_stats))
+        .route("/workspace-folders-stats", telemetry_get!(handle_v1_workspace_folders_stats))
         .route("/config-path", telemetry_get!(handle_v1_config_path))
 
         .route("/customization", telemetry_get!(handle_v1_customization))
@@ -140,6 +146,8 @@ pub fn make_v1_router() -> Router {
 
         .route("/patch-single-file-from-ticket", telemetry_post!(handle_v1_patch_single_file_from_ticket))
         .route("/patch-apply-all", telemetry_post!(handle_v1_patch_apply_all))
+        .route("/patch-preview", telemetry_post!(handle_v1_patch_preview))
+        .route("/patch-chunk-at-line", telemetry_post!(handle_v1_patch_chunk_at_line))
 
         .route("/checkpoints-preview", telemetry_post!(handle_v1_checkpoints_preview))
         .route("/checkpoints-restore", telemetry_post!(handle_v1_checkpoints_restore))
@@ -151,6 +159,7 @@ pub fn make_v1_router() -> Router {
 
         .route("/code-completion-prompt", telemetry_post!(handle_v1_code_completion_prompt))
         .route("/commit-message-from-diff", telemetry_post!(handle_v1_commit_message_from_diff))
+        .route("/commit-message-from-chunks", telemetry_post!(handle_v1_commit_message_from_chunks))
 
         // to remove
         .route("/subchat", telemetry_post!(handle_v1_subchat))
@@ -161,12 +170,16 @@ pub fn make_v1_router() -> Router {
     let builder = builder
         .route("/vdb-search", telemetry_post!(handle_v1_vecdb_search))
         .route("/vdb-status", telemetry_get!(handle_v1_vecdb_status))
+        .route("/vdb-pause", telemetry_post!(handle_v1_vecdb_pause))
+        .route("/vdb-resume", telemetry_post!(handle_v1_vecdb_resume))
         .route("/mem-query", telemetry_post!(handle_mem_query))
         .route("/mem-add", telemetry_post!(handle_mem_add))
         .route("/mem-erase", telemetry_post!(handle_mem_erase))
         .route("/mem-update-used", telemetry_post!(handle_mem_update_used))
         .route("/mem-block-until-vectorized", telemetry_get!(handle_mem_block_until_vectorized))
         .route("/mem-list", telemetry_get!(handle_mem_list))
+        .route("/ongoing-update", telemetry_post!(handle_ongoing_update))
+        .route("/ongoing-dump", telemetry_post!(handle_ongoing_dump))
         ;
 
     builder.layer(CorsLayer::very_permissive())
@@ -13,6 +13,7 @@ use crate::global_context::SharedGlobalContext;
 use crate::postprocessing::pp_context_files::pp_color_lines;
 use crate::postprocessing::pp_utils::{context_msgs_from_paths, pp_ast_markup_files};
 use crate::call_validation::PostprocessSettings;
+use crate::ast::treesitter::structs::SymbolType;
 
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -39,6 +40,25 @@ struct FileNameOnlyPost {
     file_name: String,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+struct AstNameRegexPost {
+    pattern: String,
+    #[serde(default)]
+    symbol_types: Vec<SymbolType>,  // any type if empty
+    #[serde(default = "default_name_regex_top_n")]
+    top_n: usize,
+}
+
+fn default_name_regex_top_n() -> usize { 50 }
+
+#[derive(Serialize, Deserialize, Clone)]
+struct AstUnreferencedPost {
+    #[serde(default)]
+    kinds: Vec<SymbolType>,  // functions and classes if empty
+    #[serde(default)]
+    file_filter: Option<String>,
+}
+
 
 pub async fn handle_v1_ast_file_dump(
     Extension(global_context): Extension<SharedGlobalContext>,
@@ -140,6 +160,29 @@ pub async fn handle_v1_ast_file_symbols(
         .unwrap())
 }
 
+pub async fn handle_v1_ast_document_symbols(
+    Extension(global_context): Extension<SharedGlobalContext>,
+    body_bytes: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<AstFileUrlPost>(&body_bytes).map_err(|e| {
+        ScratchError::new(StatusCode::BAD_REQUEST, format!("JSON problem: {}", e))
+    })?;
+    let cpath = crate::files_correction::canonical_path(&post.file_url.to_file_path().unwrap_or_default().to_string_lossy().to_string());
+    let file_text = get_file_text_from_memory_or_disk(global_context.clone(), &cpath).await.map_err(|e|
+        ScratchError::new(StatusCode::NOT_FOUND, e)
+    )?;
+    let outline = crate::ast::document_symbols::lsp_document_symbols(&cpath, &file_text).map_err(|e|
+        ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, e)
+    )?;
+    let json_string = serde_json::to_string_pretty(&outline).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
+
 pub async fn handle_v1_ast_status(
     Extension(global_context): Extension<SharedGlobalContext>,
     _: hyper::body::Bytes,
@@ -163,3 +206,65 @@ pub async fn handle_v1_ast_status(
         }
     }
 }
+
+pub async fn handle_v1_ast_import_cycles(
+    Extension(global_context): Extension<SharedGlobalContext>,
+    _: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let cycles = crate::ast::import_graph::workspace_import_cycles(global_context.clone()).await.map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    let json_string = serde_json::to_string_pretty(&json!({"cycles": cycles})).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
+
+pub async fn handle_v1_ast_unreferenced(
+    Extension(global_context): Extension<SharedGlobalContext>,
+    body_bytes: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<AstUnreferencedPost>(&body_bytes).map_err(|e| {
+        ScratchError::new(StatusCode::BAD_REQUEST, format!("JSON problem: {}", e))
+    })?;
+    let ast_service = global_context.read().await.ast_service.clone().ok_or(ScratchError::new(
+        StatusCode::INTERNAL_SERVER_ERROR, "ast module is turned off".to_string(),
+    ))?;
+    let ast_index = ast_service.lock().await.ast_index.clone();
+    let unreferenced = crate::ast::ast_db::find_unreferenced_declarations(
+        ast_index, &post.kinds, post.file_filter.as_deref(),
+    ).await;
+    let json_string = serde_json::to_string_pretty(&json!({"unreferenced": unreferenced})).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
+
+pub async fn handle_v1_ast_search_by_name_regex(
+    Extension(global_context): Extension<SharedGlobalContext>,
+    body_bytes: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<AstNameRegexPost>(&body_bytes).map_err(|e| {
+        ScratchError::new(StatusCode::BAD_REQUEST, format!("JSON problem: {}", e))
+    })?;
+    let ast_service = global_context.read().await.ast_service.clone().ok_or(ScratchError::new(
+        StatusCode::INTERNAL_SERVER_ERROR, "ast module is turned off".to_string(),
+    ))?;
+    let ast_index = ast_service.lock().await.ast_index.clone();
+    let search_res = crate::ast::ast_db::search_by_name_regex(
+        ast_index, &post.pattern, &post.symbol_types, post.top_n,
+    ).await.map_err(|e| ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let json_string = serde_json::to_string_pretty(&search_res).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
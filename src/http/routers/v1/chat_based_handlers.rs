@@ -6,7 +6,8 @@ use hyper::Body;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::RwLock as ARwLock;
-use crate::agentic::generate_commit_message::generate_commit_message_by_diff;
+use crate::agentic::generate_commit_message::{generate_commit_message_by_chunks, generate_commit_message_by_diff};
+use crate::call_validation::DiffChunk;
 
 #[derive(Deserialize)]
 struct CommitMessageFromDiffPost {
@@ -36,3 +37,32 @@ pub async fn handle_v1_commit_message_from_diff(
         .body(Body::from(commit_message))
         .unwrap())
 }
+
+#[derive(Deserialize)]
+struct CommitMessageFromChunksPost {
+    chunks: Vec<DiffChunk>,
+    #[serde(default)]
+    text: Option<String>, // a prompt for the commit message
+}
+
+pub async fn handle_v1_commit_message_from_chunks(
+    Extension(global_context): Extension<Arc<ARwLock<GlobalContext>>>,
+    body_bytes: hyper::body::Bytes,
+) -> axum::response::Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<CommitMessageFromChunksPost>(&body_bytes).map_err(|e| {
+        ScratchError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("JSON problem: {}", e),
+        )
+    })?;
+
+    let commit_message = generate_commit_message_by_chunks(global_context.clone(), &post.chunks, &post.text)
+        .await
+        .map_err(|e| ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(commit_message))
+        .unwrap())
+}
@@ -6,8 +6,10 @@ use axum::Extension;
 use axum::response::Result;
 use hyper::{Body, Response, StatusCode};
 use serde::Deserialize;
+use indexmap::IndexMap;
 use crate::custom_error::ScratchError;
 use crate::global_context::GlobalContext;
+use crate::vecdb::vdb_structs::OngoingToolFailure;
 
 
 #[derive(Deserialize)]
@@ -39,6 +41,27 @@ struct MemQuery {
     top_n: usize,
 }
 
+#[derive(Deserialize)]
+struct OngoingUpdateRequest {
+    goal: String,
+    #[serde(default)]
+    progress: IndexMap<String, serde_json::Value>,
+    #[serde(default)]
+    action_sequence: IndexMap<String, serde_json::Value>,
+    #[serde(default)]
+    output: IndexMap<String, IndexMap<String, serde_json::Value>>,
+    #[serde(default)]
+    failures: Vec<OngoingToolFailure>,  // tool calls of this attempt that errored
+}
+
+#[derive(Deserialize)]
+struct OngoingDumpRequest {
+    #[serde(default = "default_include_failures")]
+    include_failures: bool,
+}
+
+fn default_include_failures() -> bool { true }
+
 pub async fn handle_mem_add(
     Extension(gcx): Extension<Arc<ARwLock<GlobalContext>>>,
     body_bytes: hyper::body::Bytes,
@@ -184,3 +207,54 @@ pub async fn handle_mem_list(
     Ok(response)
 }
 
+
+pub async fn handle_ongoing_update(
+    Extension(gcx): Extension<Arc<ARwLock<GlobalContext>>>,
+    body_bytes: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let post: OngoingUpdateRequest = serde_json::from_slice(&body_bytes).map_err(|e| {
+        tracing::info!("cannot parse input:\n{:?}", body_bytes);
+        ScratchError::new(StatusCode::BAD_REQUEST, format!("JSON problem: {}", e))
+    })?;
+
+    let vec_db = gcx.read().await.vec_db.clone();
+    let attempt_n = crate::vecdb::vdb_highlev::ongoing_update_or_create(
+        vec_db,
+        post.goal,
+        post.progress,
+        post.action_sequence,
+        post.output,
+        post.failures,
+    ).await.map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+    })?;
+
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(&json!({"attempt_n": attempt_n})).unwrap()))
+        .unwrap();
+
+    Ok(response)
+}
+
+pub async fn handle_ongoing_dump(
+    Extension(gcx): Extension<Arc<ARwLock<GlobalContext>>>,
+    body_bytes: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let post: OngoingDumpRequest = serde_json::from_slice(&body_bytes).map_err(|e| {
+        tracing::info!("cannot parse input:\n{:?}", body_bytes);
+        ScratchError::new(StatusCode::BAD_REQUEST, format!("JSON problem: {}", e))
+    })?;
+
+    let vec_db = gcx.read().await.vec_db.clone();
+    let dump = crate::vecdb::vdb_highlev::ongoing_dump(vec_db, post.include_failures).await.map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+    })?;
+
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(&json!({"dump": dump})).unwrap()))
+        .unwrap();
+
+    Ok(response)
+}
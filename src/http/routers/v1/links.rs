@@ -11,7 +11,7 @@ use crate::custom_error::ScratchError;
 use crate::global_context::GlobalContext;
 use crate::integrations::go_to_configuration_message;
 use crate::tools::tool_patch_aux::tickets_parsing::get_tickets_from_messages;
-use crate::agentic::generate_follow_up_message::generate_follow_up_message;
+use crate::agentic::generate_follow_up_message::{generate_follow_up_message, FOLLOW_UPS_DEFAULT_MAX};
 use crate::git::commit_info::{get_commit_information_from_current_changes, generate_commit_messages};
 // use crate::http::routers::v1::git::GitCommitPost;
 
@@ -381,7 +381,7 @@ pub async fn handle_v1_links(
     // Follow-up
     if false {
         if post.meta.chat_mode != ChatMode::NO_TOOLS && links.is_empty() && post.messages.len() > 2 {
-            let follow_up_messages: Vec<String> = generate_follow_up_message(post.messages.clone(), gcx.clone(), &post.model_name, &post.meta.chat_id).await
+            let follow_up_messages: Vec<String> = generate_follow_up_message(post.messages.clone(), gcx.clone(), &post.model_name, &post.meta.chat_id, FOLLOW_UPS_DEFAULT_MAX).await
                 .map_err(|e| ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Error generating follow-up message: {}", e)))?;
             for follow_up_message in follow_up_messages {
                 tracing::info!("follow-up {:?}", follow_up_message);
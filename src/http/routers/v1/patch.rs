@@ -10,7 +10,8 @@ use tokio::sync::{RwLock as ARwLock, Mutex as AMutex};
 use crate::at_commands::at_commands::AtCommandsContext;
 use crate::call_validation::{ChatUsage, DiffChunk};
 use crate::custom_error::ScratchError;
-use crate::diffs::{ApplyDiffResult, correct_and_validate_chunks, read_files_n_apply_diff_chunks, unwrap_diff_apply_outputs, ApplyDiffOutput, ApplyDiffUnwrapped};
+use crate::diffs::{ApplyDiffResult, chunk_at_line, chunk_id, chunk_side_by_side, correct_and_validate_chunks, partition_chunks_by_confidence, enclosing_symbol_for_chunk, preview_diff_chunks, read_files_n_apply_diff_chunks, unwrap_diff_apply_outputs, ApplyDiffOutput, ApplyDiffUnwrapped, DiffContextMode, FilePreview};
+use crate::files_correction::canonical_path;
 use crate::global_context::GlobalContext;
 use crate::http::routers::v1::chat::deserialize_messages_from_post;
 use crate::tools::tool_patch_aux::tickets_parsing::{correct_and_validate_active_ticket, get_and_correct_active_tickets, get_tickets_from_messages, TicketToApply};
@@ -31,6 +32,40 @@ pub struct PatchApplyAllPost {
     pub messages: Vec<serde_json::Value>,
 }
 
+#[derive(Deserialize)]
+pub struct PatchPreviewPost {
+    pub chunks: Vec<DiffChunk>,
+    #[serde(default)]
+    pub context_lines: Option<usize>,  // context of the unified diffs, by default just enough to make each hunk unique
+}
+
+#[derive(Serialize)]
+pub struct ChunkPreview {
+    stable_id: String,  // same as in the apply state, so the client can match a preview to what got applied
+    enclosing_symbol: Option<String>,  // innermost function or class the chunk edits, when the AST knows it
+    side_by_side: Vec<(Option<String>, Option<String>)>,  // (removed, added) rows for a two-column view
+}
+
+#[derive(Serialize)]
+pub struct PatchPreviewResponse {
+    previews: Vec<FilePreview>,
+    chunks: Vec<DiffChunk>,  // after path correction, in the order the client sent them
+    chunk_previews: Vec<ChunkPreview>,  // one for each of the chunks above
+}
+
+#[derive(Deserialize)]
+pub struct PatchChunkAtLinePost {
+    pub chunks: Vec<DiffChunk>,
+    pub file_name: String,
+    pub line: usize,
+}
+
+#[derive(Serialize)]
+pub struct PatchChunkAtLineResponse {
+    chunk: Option<DiffChunk>,
+    stable_id: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct PatchResponse {
     state: Vec<ApplyDiffUnwrapped>,
@@ -41,6 +76,7 @@ pub struct PatchResponse {
 #[derive(Serialize)]
 pub struct PatchApplyAllResponse {
     chunks: Vec<DiffChunk>,
+    chunks_to_review: Vec<DiffChunk>,  // not confident enough to apply without asking
 }
 
 pub fn resolve_diff_apply_outputs(
@@ -189,8 +225,10 @@ pub async fn handle_v1_patch_apply_all(
         filename_by_ticket.insert(ticket.filename_before.clone(), ticket);
     }
 
+    let min_confidence = global_context.read().await.cmdline.patch_auto_apply_min_confidence;
     let mut usage = ChatUsage { ..Default::default() };
     let mut all_diff_chunks = vec![];
+    let mut chunks_to_review = vec![];
     for ticket in filename_by_ticket.into_values() {
         let mut tickets = vec![ticket];
         let indices = tickets.iter().map(|ticket| ticket.id.clone()).collect::<Vec<_>>();
@@ -209,12 +247,16 @@ pub async fn handle_v1_patch_apply_all(
                 StatusCode::UNPROCESSABLE_ENTITY, format!("Couldn't process some of the tickets: {bad_ticket_ids}"
                 )))
         }
-        let mut diff_chunks = diff_chunks_maybe.map_err(|(e, _)|
+        let diff_chunks = diff_chunks_maybe.map_err(|(e, _)|
             ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, e)
         )?;
-        diff_apply(global_context.clone(), &mut diff_chunks).await.map_err(|err| ScratchError::new(
-            StatusCode::UNPROCESSABLE_ENTITY, format!("Couldn't apply the diff: {err}"))
-        )?;
+        let (mut diff_chunks, ask) = partition_chunks_by_confidence(diff_chunks, min_confidence);
+        chunks_to_review.extend(ask);
+        if !diff_chunks.is_empty() {
+            diff_apply(global_context.clone(), &mut diff_chunks).await.map_err(|err| ScratchError::new(
+                StatusCode::UNPROCESSABLE_ENTITY, format!("Couldn't apply the diff: {err}"))
+            )?;
+        }
         all_diff_chunks.extend(diff_chunks);
     }
 
@@ -222,7 +264,66 @@ pub async fn handle_v1_patch_apply_all(
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
         .body(Body::from(serde_json::to_string_pretty(&PatchApplyAllResponse {
-            chunks: all_diff_chunks
+            chunks: all_diff_chunks,
+            chunks_to_review,
+        }).unwrap()))
+        .unwrap())
+}
+
+pub async fn handle_v1_patch_preview(
+    Extension(global_context): Extension<Arc<ARwLock<GlobalContext>>>,
+    body_bytes: hyper::body::Bytes,
+) -> axum::response::Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<PatchPreviewPost>(&body_bytes)
+        .map_err(|e| ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, format!("JSON problem: {}", e)))?;
+    let mut diff_chunks = post.chunks;
+    correct_and_validate_chunks(global_context.clone(), &mut diff_chunks).await
+        .map_err(|e| ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let context_mode = match post.context_lines {
+        Some(n) => DiffContextMode::Fixed(n),
+        None => DiffContextMode::Minimal,
+    };
+    let previews = preview_diff_chunks(global_context.clone(), &diff_chunks, context_mode).await;
+    let mut chunk_previews = vec![];
+    for chunk in diff_chunks.iter() {
+        let enclosing_symbol = if chunk.file_action == "edit" {
+            enclosing_symbol_for_chunk(global_context.clone(), chunk).await
+        } else {
+            None
+        };
+        chunk_previews.push(ChunkPreview {
+            stable_id: chunk_id(chunk),
+            enclosing_symbol,
+            side_by_side: chunk_side_by_side(chunk),
+        });
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&PatchPreviewResponse {
+            previews,
+            chunks: diff_chunks,
+            chunk_previews,
+        }).unwrap()))
+        .unwrap())
+}
+
+pub async fn handle_v1_patch_chunk_at_line(
+    Extension(_global_context): Extension<Arc<ARwLock<GlobalContext>>>,
+    body_bytes: hyper::body::Bytes,
+) -> axum::response::Result<Response<Body>, ScratchError> {
+    let post = serde_json::from_slice::<PatchChunkAtLinePost>(&body_bytes)
+        .map_err(|e| ScratchError::new(StatusCode::UNPROCESSABLE_ENTITY, format!("JSON problem: {}", e)))?;
+    let file_name = canonical_path(&post.file_name).to_string_lossy().to_string();
+    let chunk = chunk_at_line(&post.chunks, &file_name, post.line);
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&PatchChunkAtLineResponse {
+            stable_id: chunk.map(chunk_id),
+            chunk: chunk.cloned(),
         }).unwrap()))
         .unwrap())
 }
@@ -63,3 +63,31 @@ pub async fn handle_v1_rag_status(
         .body(Body::from(json_string))
         .unwrap())
 }
+
+pub async fn handle_v1_workspace_indexing_stats(
+    Extension(gcx): Extension<SharedGlobalContext>,
+    _: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let stats = crate::files_in_workspace::workspace_indexing_stats(gcx.clone()).await;
+    let json_string = serde_json::to_string_pretty(&stats).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
+
+pub async fn handle_v1_workspace_folders_stats(
+    Extension(gcx): Extension<SharedGlobalContext>,
+    _: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    let stats = crate::files_in_workspace::workspace_folders_stats(gcx.clone()).await;
+    let json_string = serde_json::to_string_pretty(&stats).map_err(|e| {
+        ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("JSON serialization problem: {}", e))
+    })?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json_string))
+        .unwrap())
+}
@@ -106,6 +106,7 @@ pub async fn handle_v1_subchat_single(
         None,
         None,
         None,
+        None,
     ).await.map_err(|e| ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Error: {}", e)))?;
 
     let new_messages = new_messages.into_iter()
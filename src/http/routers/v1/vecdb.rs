@@ -1,18 +1,35 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use async_stream::stream;
 use axum::response::Result;
 use axum::Extension;
 use hyper::{Body, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 
+use crate::at_commands::at_search::active_vecdb_namespace;
 use crate::caps::get_custom_embedding_api_key;
 use crate::custom_error::ScratchError;
 use crate::global_context::SharedGlobalContext;
-use crate::vecdb::vdb_structs::VecdbSearch;
+use crate::vecdb::vdb_lance::scope_filter_in_namespace;
+use crate::vecdb::vdb_structs::{VecdbNamespace, VecdbSearch};
 
 
 #[derive(Serialize, Deserialize, Clone)]
 struct VecDBPost {
     query: String,
     top_n: usize,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    diversity: Option<f32>,
+    #[serde(default)]
+    path_globs: Vec<String>,
+    #[serde(default)]
+    snap_to_symbol: bool,  // expand hits to the enclosing AST symbol, if the file has one
+    #[serde(default)]
+    all_projects: bool,  // by default only the active project is searched
+    #[serde(default)]
+    stream: bool,  // "data: {record}" events nearest first, then "data: [DONE]"
 }
 
 const NO_VECDB: &str = "Vector db is not running, check if you have --vecdb parameter and a vectorization model is running on server side.";
@@ -27,10 +44,52 @@ pub async fn handle_v1_vecdb_search(
     })?;
 
     let api_key = get_custom_embedding_api_key(gcx.clone()).await?;
+    let namespace = if post.all_projects { VecdbNamespace::AllProjects } else { active_vecdb_namespace(gcx.clone()).await };
+    let scope_filter_mb = scope_filter_in_namespace(None, &namespace);
     let cx_locked = gcx.read().await;
 
+    if post.stream {
+        if post.diversity.is_some() {
+            return Err(ScratchError::new(StatusCode::BAD_REQUEST, "diversity needs all the results at once, it cannot be streamed".to_string()));
+        }
+        let rx_res = match *cx_locked.vec_db.lock().await {
+            Some(ref db) => db.vecdb_search_stream(post.query.to_string(), post.top_n, scope_filter_mb, post.path_globs.clone(), post.model.clone(), &api_key).await,
+            None => {
+                return Err(ScratchError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR, NO_VECDB.to_string(),
+                ));
+            }
+        };
+        let ast_service_mb = if post.snap_to_symbol { cx_locked.ast_service.clone() } else { None };
+        drop(cx_locked);
+        let mut rx = rx_res.map_err(|e| ScratchError::new(StatusCode::BAD_REQUEST, e))?;
+        let ast_index_mb = match ast_service_mb {
+            Some(ast_service) => Some(ast_service.lock().await.ast_index.clone()),
+            None => None,
+        };
+        let evstream = stream! {
+            let mut already_sent: HashSet<(PathBuf, u64, u64)> = HashSet::new();  // several hits can snap to the same symbol
+            while let Some(rec) = rx.recv().await {
+                let rec = match &ast_index_mb {
+                    Some(ast_index) => crate::vecdb::vdb_highlev::snap_results_to_symbols(ast_index.clone(), vec![rec]).await.remove(0),
+                    None => rec,
+                };
+                if !already_sent.insert((rec.file_path.clone(), rec.start_line, rec.end_line)) {
+                    continue;
+                }
+                yield Result::<_, String>::Ok(format!("data: {}\n\n", serde_json::to_string(&rec).unwrap()));
+            }
+            yield Result::<_, String>::Ok("data: [DONE]\n\n".to_string());
+        };
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "text/event-stream")
+            .body(Body::wrap_stream(evstream))
+            .unwrap());
+    }
+
     let search_res = match *cx_locked.vec_db.lock().await {
-        Some(ref db) => db.vecdb_search(post.query.to_string(), post.top_n, None, &api_key).await,
+        Some(ref db) => db.vecdb_search(post.query.to_string(), post.top_n, scope_filter_mb, post.path_globs.clone(), post.model.clone(), post.diversity, &api_key).await,
         None => {
             return Err(ScratchError::new(
                 StatusCode::INTERNAL_SERVER_ERROR, NO_VECDB.to_string(),
@@ -38,6 +97,17 @@ pub async fn handle_v1_vecdb_search(
         }
     };
 
+    let ast_service_mb = cx_locked.ast_service.clone();
+    drop(cx_locked);
+    let search_res = match (search_res, ast_service_mb) {
+        (Ok(mut search_res), Some(ast_service)) if post.snap_to_symbol => {
+            let ast_index = ast_service.lock().await.ast_index.clone();
+            search_res.results = crate::vecdb::vdb_highlev::snap_results_to_symbols(ast_index, search_res.results).await;
+            Ok(search_res)
+        }
+        (search_res, _) => search_res,
+    };
+
     match search_res {
         Ok(search_res) => {
             let json_string = serde_json::to_string_pretty(&search_res).map_err(|e| {
@@ -73,3 +143,31 @@ pub async fn handle_v1_vecdb_status(
         .unwrap())
 }
 
+
+async fn _vecdb_set_paused(gcx: SharedGlobalContext, paused: bool) -> Result<Response<Body>, ScratchError> {
+    let vec_db = gcx.read().await.vec_db.clone();
+    let res = if paused {
+        crate::vecdb::vdb_highlev::vectorizer_pause(vec_db).await
+    } else {
+        crate::vecdb::vdb_highlev::vectorizer_resume(vec_db).await
+    };
+    res.map_err(|e| ScratchError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{}: {}", NO_VECDB, e)))?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(serde_json::json!({"success": true, "paused": paused}).to_string()))
+        .unwrap())
+}
+
+pub async fn handle_v1_vecdb_pause(
+    Extension(gcx): Extension<SharedGlobalContext>,
+    _: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    _vecdb_set_paused(gcx, true).await
+}
+
+pub async fn handle_v1_vecdb_resume(
+    Extension(gcx): Extension<SharedGlobalContext>,
+    _: hyper::body::Bytes,
+) -> Result<Response<Body>, ScratchError> {
+    _vecdb_set_paused(gcx, false).await
+}
@@ -8,6 +8,32 @@ use crate::global_context::GlobalContext;
 use crate::call_validation::{ChatContent, ChatMessage, ContextFile, ChatMeta};
 use crate::scratchpads::scratchpad_utils::HasRagResults;
 use crate::integrations::yaml_schema::ISchema;
+use crate::integrations::setting_up_integrations::YamlError;
+
+
+// cd_instruction messages are for the model, the IDE keeps them in the chat history but doesn't display them as content
+fn message_to_stream_back(msg: &ChatMessage) -> serde_json::Value {
+    let mut msg_json = serde_json::json!(msg);
+    if msg.role == "cd_instruction" {
+        msg_json["hidden"] = serde_json::Value::Bool(true);
+    }
+    msg_json
+}
+
+fn config_errors_instruction(error_log: &Vec<YamlError>) -> Option<ChatMessage> {
+    if error_log.is_empty() {
+        return None;
+    }
+    let mut msg = "💿 These config files have errors, mention them to the user and offer to fix them:\n".to_string();
+    for e in error_log.iter() {
+        msg.push_str(format!("{}:{} {}\n", e.integr_config_path, e.error_line, e.error_msg).as_str());
+    }
+    Some(ChatMessage {
+        role: "cd_instruction".to_string(),
+        content: ChatContent::SimpleText(msg),
+        ..Default::default()
+    })
+}
 
 
 pub async fn mix_config_messages(
@@ -132,7 +158,6 @@ pub async fn mix_config_messages(
 
     let mut error_log = Vec::new();
     let custom = crate::yaml_configs::customization_loader::load_customization(gcx.clone(), true, &mut error_log).await;
-    // XXX: let model know there are errors
     for e in error_log.iter() {
         tracing::error!(
             "{}:{} {:?}",
@@ -157,15 +182,18 @@ pub async fn mix_config_messages(
         ..Default::default()
     };
 
+    let mut new_messages = vec![system_message, context_file_message, schema_message];
+    new_messages.extend(config_errors_instruction(&error_log));
+
     if messages.len() == 1 {
-        stream_back_to_user.push_in_json(serde_json::json!(system_message));
-        stream_back_to_user.push_in_json(serde_json::json!(context_file_message));
-        stream_back_to_user.push_in_json(serde_json::json!(schema_message));
+        for msg in new_messages.iter() {
+            stream_back_to_user.push_in_json(message_to_stream_back(msg));
+        }
     } else {
         tracing::error!("more than 1 message when mixing configurtion chat context, bad things might happen!");
     }
 
-    messages.splice(0..0, vec![system_message, context_file_message, schema_message]);
+    messages.splice(0..0, new_messages);
 
     for msg in messages.iter_mut() {
         if let ChatContent::SimpleText(ref mut content) = msg.content {
@@ -173,3 +201,27 @@ pub async fn mix_config_messages(
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cd_instruction_streamed_back_hidden() {
+        let system_message = ChatMessage::new("system".to_string(), "You configure frogs.".to_string());
+        let errors_message = config_errors_instruction(&vec![YamlError {
+            integr_config_path: "/pond/.refact/integrations.d/frog.yaml".to_string(),
+            error_line: 3,
+            error_msg: "unexpected ribbit".to_string(),
+        }]).unwrap();
+
+        let system_json = message_to_stream_back(&system_message);
+        let errors_json = message_to_stream_back(&errors_message);
+        assert!(system_json.get("hidden").is_none());
+        assert_eq!(errors_json["hidden"], true);
+        assert_eq!(errors_json["role"], "cd_instruction");
+        assert!(errors_json["content"].as_str().unwrap().contains("frog.yaml:3 unexpected ribbit"));
+        assert!(config_errors_instruction(&vec![]).is_none());
+    }
+}
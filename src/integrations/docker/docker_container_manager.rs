@@ -355,9 +355,11 @@ async fn docker_container_sync_workspace(
     tar_builder.mode(async_tar::HeaderMode::Complete);
 
     let (all_files, _vcs_folders) = crate::files_in_workspace::retrieve_files_in_workspace_folders(
+        gcx.clone(),
         vec![workspace_folder.clone()],
         false,
         false,
+        false,
     ).await;
 
     for file in &all_files {
@@ -12,7 +12,8 @@ use crate::global_context::GlobalContext;
 use crate::integrations::integr_abstract::{IntegrationTrait, IntegrationCommon, IntegrationConfirmation};
 use crate::tools::tools_description::Tool;
 use crate::integrations::docker::docker_ssh_tunnel_utils::{SshConfig, forward_remote_docker_if_needed};
-use crate::integrations::utils::{serialize_num_to_str, deserialize_str_to_num};
+use crate::integrations::docker::tool_docker_logs::ToolDockerLogs;
+use crate::integrations::utils::{serialize_num_to_str, deserialize_str_to_num, CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES};
 
 #[derive(Clone, Serialize, Deserialize, Default, Debug)]
 pub struct SettingsDocker {
@@ -25,8 +26,23 @@ pub struct SettingsDocker {
     #[serde(serialize_with = "serialize_num_to_str", deserialize_with = "deserialize_str_to_num")]
     pub ssh_port: u16,
     pub ssh_identity_file: String,
+    #[serde(default = "_default_logs_max_bytes", serialize_with = "serialize_num_to_str", deserialize_with = "deserialize_str_to_num")]
+    pub logs_max_bytes: usize,
+    #[serde(default = "_default_logs_redact_patterns")]
+    pub logs_redact_patterns: String,
 }
 
+fn _default_logs_max_bytes() -> usize {
+    CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES
+}
+
+fn _default_logs_redact_patterns() -> String {
+    DOCKER_LOGS_DEFAULT_REDACT_PATTERNS.to_string()
+}
+
+// one regular expression per line, private key blocks are always redacted
+const DOCKER_LOGS_DEFAULT_REDACT_PATTERNS: &str = r"(?i)(password|passwd|secret|token|api[_-]?key)\s*[=:]";
+
 impl SettingsDocker {
     pub fn get_ssh_config(&self) -> Option<SshConfig> {
         if self.remote_docker {
@@ -84,11 +100,17 @@ impl IntegrationTrait for ToolDocker {
     }
 
     fn integr_tools(&self, _integr_name: &str) -> Vec<Box<dyn crate::tools::tools_description::Tool + Send>> {
-        vec![Box::new(ToolDocker {
-            common: self.common.clone(),
-            settings_docker: self.settings_docker.clone(),
-            config_path: self.config_path.clone(),
-        })]
+        // docker_and_isolation_load() expects ToolDocker first
+        vec![
+            Box::new(ToolDocker {
+                common: self.common.clone(),
+                settings_docker: self.settings_docker.clone(),
+                config_path: self.config_path.clone(),
+            }) as Box<dyn Tool + Send>,
+            Box::new(ToolDockerLogs {
+                docker: self.clone(),
+            }) as Box<dyn Tool + Send>,
+        ]
     }
 
     fn integr_schema(&self) -> &str
@@ -324,6 +346,18 @@ fields:
     f_desc: "Path to the SSH identity file to connect to remote Docker."
     f_label: "SSH Identity File"
     f_extra: true
+  logs_max_bytes:
+    f_type: string_short
+    f_desc: "The docker_logs tool cuts logs larger than this. 0 means no limit."
+    f_default: "50000"
+    f_label: "Logs Limit, Bytes"
+    f_extra: true
+  logs_redact_patterns:
+    f_type: string_long
+    f_desc: "Regular expressions, one per line, log lines matching any of them are hidden from the model. Private key blocks are always hidden."
+    f_default: "(?i)(password|passwd|secret|token|api[_-]?key)\\s*[=:]"
+    f_label: "Logs Redact Patterns"
+    f_extra: true
 available:
   on_your_laptop_possible: true
   when_isolated_possible: false
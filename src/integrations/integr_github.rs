@@ -11,6 +11,8 @@ use crate::call_validation::{ContextEnum, ChatMessage, ChatContent, ChatUsage};
 
 use crate::files_correction::to_pathbuf_normalize;
 use crate::integrations::go_to_configuration_message;
+use crate::integrations::process_io_utils::{output_with_timeout, subprocess_timeout};
+use crate::integrations::utils::{stdout_with_json_array_hint, truncate_cli_output, serialize_num_to_str, deserialize_str_to_num, CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES};
 use crate::tools::tools_description::Tool;
 use serde_json::Value;
 use crate::integrations::integr_abstract::{IntegrationCommon, IntegrationConfirmation, IntegrationTrait};
@@ -21,6 +23,14 @@ use crate::integrations::integr_abstract::{IntegrationCommon, IntegrationConfirm
 pub struct SettingsGitHub {
     pub gh_binary_path: String,
     pub gh_token: String,
+    #[serde(default)]
+    pub gh_host: String,
+    #[serde(default = "_default_output_max_bytes", serialize_with = "serialize_num_to_str", deserialize_with = "deserialize_str_to_num")]
+    pub output_max_bytes: usize,
+}
+
+fn _default_output_max_bytes() -> usize {
+    CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES
 }
 
 #[derive(Default)]
@@ -79,7 +89,7 @@ impl Tool for ToolGithub {
 
     async fn tool_execute(
         &mut self,
-        _ccx: Arc<AMutex<AtCommandsContext>>,
+        ccx: Arc<AMutex<AtCommandsContext>>,
         tool_call_id: &String,
         args: &HashMap<String, Value>,
     ) -> Result<(bool, Vec<ContextEnum>), String> {
@@ -94,42 +104,25 @@ impl Tool for ToolGithub {
         if gh_binary_path.is_empty() {
             gh_binary_path = "gh".to_string();
         }
-        let output = Command::new(&gh_binary_path)
-            .args(&command_args)
+        let mut command = Command::new(&gh_binary_path);
+        command.args(&command_args)
             .current_dir(&to_pathbuf_normalize(&project_dir))
             .env("GH_TOKEN", &self.settings_github.gh_token)
             .env("GITHUB_TOKEN", &self.settings_github.gh_token)
-            .stdin(std::process::Stdio::null())
-            .output()
+            .stdin(std::process::Stdio::null());
+        if !self.settings_github.gh_host.is_empty() {
+            command.env("GH_HOST", &self.settings_github.gh_host);
+        }
+        let gcx = ccx.lock().await.global_context.clone();
+        let output = output_with_timeout(&mut command, subprocess_timeout(gcx).await)
             .await
-            .map_err(|e| format!("!{}, {} failed:\n{}",
-                go_to_configuration_message("github"), gh_binary_path, e.to_string()))?;
+            .map_err(|e| format!("!{}, {}", go_to_configuration_message("github"), e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        let stdout_content = if stdout.starts_with("[") {
-            match serde_json::from_str::<Value>(&stdout) {
-                Ok(Value::Array(arr)) => {
-                    let row_count = arr.len();
-                    format!("{}\n\n💿 The UI has the capability to view tool result json efficiently. The result contains {} rows. Unless user specified otherwise, write no more than 3 rows as text and possibly \"and N more\" wording, keep it short.",
-                        stdout, row_count
-                    )
-                },
-                Ok(_) => stdout,
-                Err(_) => stdout,
-            }
-        } else {
-            stdout
-        };
+        let stderr = truncate_cli_output(String::from_utf8_lossy(&output.stderr).to_string(), self.settings_github.output_max_bytes);
 
-        let mut content = String::new();
-        if !stdout_content.is_empty() {
-            content.push_str(format!("stdout:\n{}\n", stdout_content).as_str());
-        }
-        if !stderr.is_empty() {
-            content.push_str(format!("stderr:\n{}\n", stderr).as_str());
-        }
+        let stdout_content = stdout_with_json_array_hint(stdout, self.settings_github.output_max_bytes);
+        let content = gh_output_to_tool_content(output.status.success(), output.status.code(), &stdout_content, &stderr)?;
 
         let mut results = vec![];
         results.push(ContextEnum::ChatMessage(ChatMessage {
@@ -149,6 +142,11 @@ impl Tool for ToolGithub {
     ) -> Result<String, String> {
         let mut command_args = parse_command_args(args)?;
         command_args.insert(0, "gh".to_string());
+        // at the end, so "gh * delete *" still matches, and "gh * --hostname github.example.com" can be a separate rule
+        if !self.settings_github.gh_host.is_empty() {
+            command_args.push("--hostname".to_string());
+            command_args.push(self.settings_github.gh_host.clone());
+        }
         Ok(command_args.join(" "))
     }
 
@@ -171,6 +169,25 @@ impl Tool for ToolGithub {
     }
 }
 
+// gh writes warnings (api version, pager, update notices) to stderr and still succeeds, only the exit code means failure
+fn gh_output_to_tool_content(success: bool, exit_code: Option<i32>, stdout: &str, stderr: &str) -> Result<String, String> {
+    let mut content = String::new();
+    if !stdout.is_empty() {
+        content.push_str(format!("stdout:\n{}\n", stdout).as_str());
+    }
+    if success {
+        if !stderr.is_empty() {
+            content.push_str(format!("warnings:\n{}\n", stderr).as_str());
+        }
+        return Ok(content);
+    }
+    let exit_code_str = exit_code.map(|c| c.to_string()).unwrap_or("unknown".to_string());
+    if !stderr.is_empty() {
+        content.push_str(format!("stderr:\n{}\n", stderr).as_str());
+    }
+    Err(format!("gh failed with exit code {}\n{}", exit_code_str, content))
+}
+
 fn parse_command_args(args: &HashMap<String, Value>) -> Result<Vec<String>, String> {
     let command = match args.get("command") {
         Some(Value::String(s)) => s,
@@ -208,6 +225,18 @@ fields:
     f_placeholder: "/usr/local/bin/gh"
     f_label: "GH Binary Path"
     f_extra: true
+  output_max_bytes:
+    f_type: string_short
+    f_desc: "Output of a gh command larger than this is truncated, json arrays keep only their first rows. 0 means no limit."
+    f_default: "50000"
+    f_label: "Output Limit, Bytes"
+    f_extra: true
+  gh_host:
+    f_type: string_short
+    f_desc: "GitHub Enterprise Server hostname, passed to the CLI as GH_HOST. Leave empty for github.com. Commands are matched against the confirmation rules with `--hostname <host>` appended, so a rule like `gh * --hostname github.example.com` only applies to that server."
+    f_placeholder: "github.example.com"
+    f_label: "GH Host"
+    f_extra: true
 description: |
   The GitHub integration allows interaction with GitHub repositories using the GitHub CLI.
   It provides functionality for various GitHub operations such as creating issues, pull requests, and more.
@@ -226,3 +255,21 @@ smartlinks:
           If it doesn't work or the tool isn't available, go through the usual plan in the system prompt.
     sl_enable_only_with_tool: true
 "#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gh_stderr_warning_is_not_fatal() {
+        let stdout = "[{\"number\":1,\"title\":\"Frog can't jump\"}]";
+        let warning = "warning: the pond API is deprecated";
+
+        let content = gh_output_to_tool_content(true, Some(0), stdout, warning).unwrap();
+        assert_eq!(content, format!("stdout:\n{}\nwarnings:\n{}\n", stdout, warning));
+
+        let err = gh_output_to_tool_content(false, Some(1), "", "could not find the pond").unwrap_err();
+        assert_eq!(err, "gh failed with exit code 1\nstderr:\ncould not find the pond\n");
+    }
+}
@@ -11,6 +11,8 @@ use crate::at_commands::at_commands::AtCommandsContext;
 use crate::call_validation::{ContextEnum, ChatMessage, ChatContent, ChatUsage};
 use crate::files_correction::to_pathbuf_normalize;
 use crate::integrations::go_to_configuration_message;
+use crate::integrations::process_io_utils::{output_with_timeout, subprocess_timeout};
+use crate::integrations::utils::{stdout_with_json_array_hint, truncate_cli_output, serialize_num_to_str, deserialize_str_to_num, CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES};
 use crate::tools::tools_description::Tool;
 use crate::integrations::integr_abstract::{IntegrationCommon, IntegrationConfirmation, IntegrationTrait};
 
@@ -19,16 +21,22 @@ use crate::integrations::integr_abstract::{IntegrationCommon, IntegrationConfirm
 pub struct SettingsGitLab {
     pub glab_binary_path: String,
     pub glab_token: String,
+    #[serde(default = "_default_output_max_bytes", serialize_with = "serialize_num_to_str", deserialize_with = "deserialize_str_to_num")]
+    pub output_max_bytes: usize,
+}
+
+fn _default_output_max_bytes() -> usize {
+    CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES
 }
 
 #[derive(Default)]
-pub struct ToolGitlab {
+pub struct ToolGitLab {
     pub common:  IntegrationCommon,
     pub settings_gitlab: SettingsGitLab,
     pub config_path: String,
 }
 
-impl IntegrationTrait for ToolGitlab {
+impl IntegrationTrait for ToolGitLab {
     fn as_any(&self) -> &dyn std::any::Any { self }
 
     fn integr_settings_apply(&mut self, value: &Value, config_path: String) -> Result<(), String> {
@@ -62,7 +70,7 @@ impl IntegrationTrait for ToolGitlab {
     }
 
     fn integr_tools(&self, _integr_name: &str) -> Vec<Box<dyn crate::tools::tools_description::Tool + Send>> {
-        vec![Box::new(ToolGitlab {
+        vec![Box::new(ToolGitLab {
             common: self.common.clone(),
             settings_gitlab: self.settings_gitlab.clone(),
             config_path: self.config_path.clone(),
@@ -73,12 +81,12 @@ impl IntegrationTrait for ToolGitlab {
 }
 
 #[async_trait]
-impl Tool for ToolGitlab {
+impl Tool for ToolGitLab {
     fn as_any(&self) -> &dyn std::any::Any { self }
 
     async fn tool_execute(
         &mut self,
-        _ccx: Arc<AMutex<AtCommandsContext>>,
+        ccx: Arc<AMutex<AtCommandsContext>>,
         tool_call_id: &String,
         args: &HashMap<String, Value>,
     ) -> Result<(bool, Vec<ContextEnum>), String> {
@@ -93,33 +101,20 @@ impl Tool for ToolGitlab {
         if glab_binary_path.is_empty() {
             glab_binary_path = "glab".to_string();
         }
-        let output = Command::new(&glab_binary_path)
-            .args(&command_args)
+        let mut command = Command::new(&glab_binary_path);
+        command.args(&command_args)
             .current_dir(&to_pathbuf_normalize(&project_dir))
             .env("GITLAB_TOKEN", &self.settings_gitlab.glab_token)
-            .stdin(std::process::Stdio::null())
-            .output()
+            .stdin(std::process::Stdio::null());
+        let gcx = ccx.lock().await.global_context.clone();
+        let output = output_with_timeout(&mut command, subprocess_timeout(gcx).await)
             .await
-            .map_err(|e| format!("!{}, {} failed:\n{}",
-                go_to_configuration_message("gitlab"), glab_binary_path, e.to_string()))?;
+            .map_err(|e| format!("!{}, {}", go_to_configuration_message("gitlab"), e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        let stdout_content = if stdout.starts_with("[") {
-            match serde_json::from_str::<Value>(&stdout) {
-                Ok(Value::Array(arr)) => {
-                    let row_count = arr.len();
-                    format!("{}\n\n💿 The UI has the capability to view tool result json efficiently. The result contains {} rows. Unless user specified otherwise, write no more than 3 rows as text and possibly \"and N more\" wording, keep it short.",
-                        stdout, row_count
-                    )
-                },
-                Ok(_) => stdout,
-                Err(_) => stdout,
-            }
-        } else {
-            stdout
-        };
+        let stderr = truncate_cli_output(String::from_utf8_lossy(&output.stderr).to_string(), self.settings_gitlab.output_max_bytes);
+
+        let stdout_content = stdout_with_json_array_hint(stdout, self.settings_gitlab.output_max_bytes);
 
         let mut content = String::new();
         if !stdout_content.is_empty() {
@@ -205,6 +200,12 @@ fields:
     f_placeholder: "/usr/local/bin/glab"
     f_label: "glab binary path"
     f_extra: true
+  output_max_bytes:
+    f_type: string_short
+    f_desc: "Output of a glab command larger than this is truncated, json arrays keep only their first rows. 0 means no limit."
+    f_default: "50000"
+    f_label: "Output Limit, Bytes"
+    f_extra: true
 description: |
   The GitLab integration allows interaction with GitLab repositories using the GitLab CLI.
   It provides functionality for various GitLab operations such as creating issues, merge requests, and more.
@@ -212,7 +213,7 @@ available:
   on_your_laptop_possible: true
   when_isolated_possible: true
 confirmation:
-  ask_user_default: ["glab * delete *"]
+  ask_user_default: ["glab * delete *", "glab * close *"]
   deny_default: ["glab auth token *"]
 smartlinks:
   - sl_label: "Test"
@@ -8,6 +8,7 @@ use tokio::sync::{Mutex as AMutex, RwLock as ARwLock};
 use async_trait::async_trait;
 use std::process::Stdio;
 use tokio::process::Command;
+use glob::Pattern;
 
 use crate::at_commands::at_commands::AtCommandsContext;
 use crate::files_correction::get_active_project_path;
@@ -26,6 +27,30 @@ pub struct SettingsShell {
     pub timeout: String,
     #[serde(default)]
     pub output_filter: CmdlineOutputFilter,
+    #[serde(default)]
+    pub allowlist: String,
+}
+
+// with an allowlist "make *" must not let "make; rm -rf ~" through
+const SHELL_CONTROL_SEQUENCES: [&str; 8] = [";", "&", "|", "`", "$(", ">", "<", "\n"];
+
+fn check_command_allowed(command: &str, allowlist: &str) -> Result<(), String> {
+    let patterns: Vec<&str> = allowlist.split(",").map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
+    if patterns.is_empty() {
+        return Ok(());
+    }
+    if let Some(seq) = SHELL_CONTROL_SEQUENCES.iter().find(|seq| command.contains(*seq)) {
+        return Err(format!("the command contains {:?}, only single commands from the allowlist can run: {}", seq, patterns.join(", ")));
+    }
+    let command = command.trim();
+    for pattern in patterns.iter() {
+        match Pattern::new(pattern) {
+            Ok(p) if p.matches(command) => return Ok(()),
+            Ok(_) => {},
+            Err(e) => tracing::warn!("shell allowlist pattern {:?} is invalid: {}", pattern, e),
+        }
+    }
+    Err(format!("the command is not in the allowlist, allowed are: {}", patterns.join(", ")))
 }
 
 #[derive(Default)]
@@ -90,6 +115,7 @@ impl Tool for ToolShell {
         args: &HashMap<String, Value>,
     ) -> Result<(bool, Vec<ContextEnum>), String> {
         let (command, workdir_maybe) = parse_args(args)?;
+        check_command_allowed(&command, &self.cfg.allowlist)?;
         let timeout = self.cfg.timeout.parse::<u64>().unwrap_or(10);
 
         let gcx = ccx.lock().await.global_context.clone();
@@ -156,6 +182,13 @@ impl Tool for ToolShell {
         if command_to_match.is_empty() {
             return Err("Empty command to match".to_string());
         }
+        if let Err(e) = check_command_allowed(&command_to_match, &self.cfg.allowlist) {
+            return Ok(MatchConfirmDeny {
+                result: MatchConfirmDenyResult::DENY,
+                command: command_to_match.clone(),
+                rule: e,
+            });
+        }
         if let Some(rules) = &self.confirm_deny_rules() {
             let (is_denied, deny_rule) = command_should_be_denied(&command_to_match, &rules.deny);
             if is_denied {
@@ -280,6 +313,11 @@ fields:
     f_type: "output_filter"
     f_desc: "The output from the command can be long or even quasi-infinite. This section allows to set limits, prioritize top or bottom, or use regexp to show the model the relevant part."
     f_extra: true
+  allowlist:
+    f_type: string_long
+    f_desc: "Comma-separated glob patterns of the commands that can run, for example `make *, npm test, npm run *`. Other commands, and commands with `;` `&&` `|` redirects or substitutions, are rejected before running. Leave empty to allow any command."
+    f_placeholder: "make *, npm test, cargo test*"
+    f_extra: true
 description: |
   Allows to execute any command line tool with confirmation from the chat itself.
 available:
@@ -289,3 +327,22 @@ confirmation:
   ask_user_default: ["*"]
   deny_default: ["sudo*"]
 "#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_allowlist() {
+        let allowlist = "make *, npm test, npm run *";
+        assert!(check_command_allowed("make frog", allowlist).is_ok());
+        assert!(check_command_allowed("npm test", allowlist).is_ok());
+        assert!(check_command_allowed("npm run lint --fix", allowlist).is_ok());
+        assert!(check_command_allowed("npm install toad", allowlist).is_err());
+        assert!(check_command_allowed("make frog; rm -rf ~/pond", allowlist).is_err());
+        assert!(check_command_allowed("make frog && curl evil.pond", allowlist).is_err());
+        assert!(check_command_allowed("npm run $(cat tadpole)", allowlist).is_err());
+        assert!(check_command_allowed("rm -rf ~/pond", "").is_ok());
+    }
+}
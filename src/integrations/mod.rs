@@ -37,7 +37,7 @@ pub fn integration_from_name(n: &str) -> Result<Box<dyn IntegrationTrait + Send
 {
     match n {
         "github" => Ok(Box::new(integr_github::ToolGithub { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
-        "gitlab" => Ok(Box::new(integr_gitlab::ToolGitlab { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
+        "gitlab" => Ok(Box::new(integr_gitlab::ToolGitLab { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
         "pdb" => Ok(Box::new(integr_pdb::ToolPdb { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
         "chrome" => Ok(Box::new(integr_chrome::ToolChrome { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
         "postgres" => Ok(Box::new(integr_postgres::ToolPostgres { ..Default::default() }) as Box<dyn IntegrationTrait + Send + Sync>),
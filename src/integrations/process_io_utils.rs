@@ -1,10 +1,31 @@
+use std::sync::Arc;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::process::ChildStdin;
+use tokio::sync::RwLock as ARwLock;
 use tokio::time::Duration;
+use std::process::Output;
 use std::time::Instant;
 use tracing::error;
 
+use crate::global_context::GlobalContext;
+
+
+// A hung gh or git must not stall the whole LSP
+pub async fn subprocess_timeout(gcx: Arc<ARwLock<GlobalContext>>) -> Duration {
+    Duration::from_secs(gcx.read().await.cmdline.subprocess_timeout_secs)
+}
+
+/// `cmd.output()` that kills the child and fails if it runs longer than `timeout`
+pub async fn output_with_timeout(cmd: &mut tokio::process::Command, timeout: Duration) -> Result<Output, String> {
+    cmd.kill_on_drop(true);
+    let program = cmd.as_std().get_program().to_string_lossy().to_string();
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(output) => output.map_err(|e| format!("{} failed: {}", program, e)),
+        Err(_) => Err(format!("command timed out: {} was running for more than {} seconds and was killed", program, timeout.as_secs_f64())),
+    }
+}
+
 
 pub async fn write_to_stdin_and_flush(stdin: &mut ChildStdin, text_to_write: &str) -> Result<(), String>
 {
@@ -110,3 +131,21 @@ pub fn last_n_lines(msg: &str, n: usize) -> String {
 
     output
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_with_timeout_kills_hung_command() {
+        let t0 = Instant::now();
+        let err = output_with_timeout(tokio::process::Command::new("sleep").arg("10"), Duration::from_millis(200)).await.unwrap_err();
+        assert!(err.starts_with("command timed out: sleep"), "{}", err);
+        assert!(t0.elapsed() < Duration::from_secs(5));
+
+        let output = output_with_timeout(tokio::process::Command::new("echo").arg("ribbit"), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ribbit\n");
+    }
+}
@@ -557,6 +557,7 @@ pub async fn integration_config_save(
     file.write_all(sanitized_yaml_string.as_bytes()).await.map_err(|e| {
         format!("Failed to write to {}: {}", config_path.display(), e)
     })?;
+    crate::tools::tools_description::openai_style_cache_invalidate();
 
     Ok(())
 }
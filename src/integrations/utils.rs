@@ -3,6 +3,66 @@ use std::fmt::Display;
 use serde::{Deserialize, Serializer, Deserializer};
 
 use crate::integrations::docker::docker_container_manager::Port;
+use crate::postprocessing::pp_utils::finish_truncated_text;
+
+pub const CLI_TOOL_OUTPUT_DEFAULT_MAX_BYTES: usize = 50_000;
+
+fn truncate_text_at_line(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    match text[..cut].rfind('\n') {
+        Some(pos) if pos > 0 => &text[..pos + 1],
+        _ => &text[..cut],
+    }
+}
+
+/// Output of a cli tool that fits `max_bytes` (0 is no limit), with a notice when something was cut
+pub fn truncate_cli_output(text: String, max_bytes: usize) -> String {
+    if max_bytes == 0 || text.len() <= max_bytes {
+        return text;
+    }
+    let kept = truncate_text_at_line(&text, max_bytes);
+    finish_truncated_text(kept, text.len() - kept.len())
+}
+
+/// Tools that print json arrays (`gh ... --json`, `glab ... --output json`) get a hint appended,
+/// the UI renders such results as a table, so the model doesn't need to repeat every row.
+/// An array larger than `max_bytes` keeps only its first rows, other output is cut at a line boundary.
+pub fn stdout_with_json_array_hint(stdout: String, max_bytes: usize) -> String {
+    if !stdout.starts_with("[") {
+        return truncate_cli_output(stdout, max_bytes);
+    }
+    match serde_json::from_str::<serde_json::Value>(&stdout) {
+        Ok(serde_json::Value::Array(arr)) => {
+            let row_count = arr.len();
+            let mut rows_text = stdout;
+            let mut truncation_notice = String::new();
+            if max_bytes > 0 && rows_text.len() > max_bytes {
+                let mut kept = vec![];
+                let mut kept_bytes = 2;
+                for row in arr.into_iter() {
+                    let row_bytes = serde_json::to_string(&row).map(|x| x.len()).unwrap_or(0) + 1;
+                    if kept_bytes + row_bytes > max_bytes {
+                        break;
+                    }
+                    kept_bytes += row_bytes;
+                    kept.push(row);
+                }
+                truncation_notice = format!(" Only the first {} rows are shown here because the output is too large, use filters or a limit in the command to get the rest.", kept.len());
+                rows_text = serde_json::to_string(&serde_json::Value::Array(kept)).unwrap_or_default();
+            }
+            format!("{}\n\n💿 The UI has the capability to view tool result json efficiently. The result contains {} rows.{} Unless user specified otherwise, write no more than 3 rows as text and possibly \"and N more\" wording, keep it short.",
+                rows_text, row_count, truncation_notice
+            )
+        },
+        _ => truncate_cli_output(stdout, max_bytes),
+    }
+}
 
 pub fn serialize_opt_num_to_str<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
     serializer.serialize_str(&value.as_ref().map_or_else(String::new, |v| v.to_string()))
@@ -37,4 +97,32 @@ pub fn deserialize_ports<'de, D: Deserializer<'de>>(deserializer: D) -> Result<V
             .ok_or_else(|| serde::de::Error::custom("expected format 'published:target'"))?;
         Ok(Port { published: published.to_string(), target: target.to_string() })
     }).collect()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stdout_with_json_array_hint_truncates() {
+        let rows: Vec<String> = (0..100).map(|i| format!("{{\"number\":{},\"title\":\"frog number {}\"}}", i, i)).collect();
+        let stdout = format!("[{}]", rows.join(","));
+
+        let untouched = stdout_with_json_array_hint(stdout.clone(), 0);
+        assert!(untouched.starts_with(&stdout));
+        assert!(untouched.contains("contains 100 rows."));
+
+        let truncated = stdout_with_json_array_hint(stdout.clone(), 200);
+        let (json_part, hint) = truncated.split_once("\n\n💿").unwrap();
+        let kept = serde_json::from_str::<serde_json::Value>(json_part).unwrap();
+        assert!(json_part.len() <= 200);
+        assert_eq!(kept.as_array().unwrap()[0]["title"], "frog number 0");
+        assert!(hint.contains("contains 100 rows."));
+        assert!(hint.contains(&format!("Only the first {} rows", kept.as_array().unwrap().len())));
+
+        let text = "frog 1\nfrog 2\nfrog 3\n".repeat(10);
+        let text_truncated = truncate_cli_output(text.clone(), 20);
+        assert_eq!(text_truncated, "frog 1\nfrog 2\n[truncated, 196 bytes omitted]\n");
+        assert_eq!(truncate_cli_output(text.clone(), 1000), text);
+    }
+}
@@ -48,11 +48,17 @@ fn map_row_to_memo_record(row: &rusqlite::Row) -> rusqlite::Result<MemoRecord> {
         mstat_correct: row.get(6)?,
         mstat_relevant: row.get(7)?,
         mstat_times_used: row.get(8)?,
+        created_ts: row.get(9)?,
+        last_used_ts: row.get(10)?,
     })
 }
 
 fn fields_ordered() -> String {
-    "memid,m_type,m_goal,m_project,m_payload,m_origin,mstat_correct,mstat_relevant,mstat_times_used".to_string()
+    "memid,m_type,m_goal,m_project,m_payload,m_origin,mstat_correct,mstat_relevant,mstat_times_used,created_ts,last_used_ts".to_string()
+}
+
+fn now_ts() -> f64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64()
 }
 
 impl MemoriesDatabase {
@@ -106,6 +112,7 @@ impl MemoriesDatabase {
         };
         db._permdb_create_table(reset_memory)?;
         db._migrate_add_m_origin()?;
+        db._migrate_add_timestamps()?;
         Ok(db)
     }
 
@@ -127,6 +134,23 @@ impl MemoriesDatabase {
         Ok(())
     }
 
+    fn _migrate_add_timestamps(&self) -> Result<(), String> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("PRAGMA table_info(memories)").map_err(|e| e.to_string())?;
+        let columns = stmt.query_map([], |row| row.get::<_, String>(1))
+            .map_err(|e| e.to_string())?
+            .filter_map(|result| result.ok())
+            .collect::<Vec<_>>();
+        // old memories get zero, they are as old as it gets
+        for column in ["created_ts", "last_used_ts"] {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(&format!("ALTER TABLE memories ADD COLUMN {} REAL NOT NULL DEFAULT 0", column), [])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
     fn _permdb_create_table(&self, reset_memory: bool) -> Result<(), String> {
         let conn = self.conn.lock();
         if reset_memory {
@@ -142,7 +166,9 @@ impl MemoriesDatabase {
                 m_origin TEXT NOT NULL,
                 mstat_correct REAL NOT NULL DEFAULT 0,
                 mstat_relevant REAL NOT NULL DEFAULT 0,
-                mstat_times_used INTEGER NOT NULL DEFAULT 0
+                mstat_times_used INTEGER NOT NULL DEFAULT 0,
+                created_ts REAL NOT NULL DEFAULT 0,
+                last_used_ts REAL NOT NULL DEFAULT 0
             )",
             [],
         ).map_err(|e| e.to_string())?;
@@ -161,8 +187,8 @@ impl MemoriesDatabase {
         let conn = self.conn.lock();
         let memid = generate_memid();
         conn.execute(
-            "INSERT INTO memories (memid, m_type, m_goal, m_project, m_payload, m_origin) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![memid, mem_type, goal, project, payload, m_origin],
+            "INSERT INTO memories (memid, m_type, m_goal, m_project, m_payload, m_origin, created_ts) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![memid, mem_type, goal, project, payload, m_origin, now_ts()],
         ).map_err(|e| e.to_string())?;
         Ok(memid)
     }
@@ -189,8 +215,8 @@ impl MemoriesDatabase {
     pub fn permdb_update_used(&self, memid: &str, mstat_correct: i32, mstat_relevant: i32) -> Result<usize, String> {
         let conn = self.conn.lock();
         let affected_rows = conn.execute(
-            "UPDATE memories SET mstat_times_used = mstat_times_used + 1, mstat_correct = mstat_correct + ?1, mstat_relevant = mstat_relevant + ?2 WHERE memid = ?3",
-            params![mstat_correct, mstat_relevant, memid],
+            "UPDATE memories SET mstat_times_used = mstat_times_used + 1, mstat_correct = mstat_correct + ?1, mstat_relevant = mstat_relevant + ?2, last_used_ts = ?4 WHERE memid = ?3",
+            params![mstat_correct, mstat_relevant, memid, now_ts()],
         ).map_err(|e| e.to_string())?;
         Ok(affected_rows)
     }
@@ -266,6 +292,8 @@ impl MemoriesDatabase {
                     record.mstat_correct = db_record.mstat_correct;
                     record.mstat_relevant = db_record.mstat_relevant;
                     record.mstat_times_used = db_record.mstat_times_used;
+                    record.created_ts = db_record.created_ts;
+                    record.last_used_ts = db_record.last_used_ts;
                     Some(record)
                 } else {
                     tracing::warn!("permdb_memids2records() not found memid={}", record.memid);
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use tracing::info;
+
+
+// endpoint_embeddings_style "local": endpoint_embeddings_template is a directory with
+// config.json, tokenizer.json and model.safetensors of a BERT-like sentence embedding model,
+// for example a copy of https://huggingface.co/thenlper/gte-small
+
+struct LocalEmbeddingModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+lazy_static::lazy_static! {
+    static ref LOADED_MODELS: StdMutex<HashMap<String, Arc<LocalEmbeddingModel>>> = StdMutex::new(HashMap::new());
+}
+
+fn load_model(model_dir: &String) -> Result<LocalEmbeddingModel, String> {
+    let dir = PathBuf::from(model_dir);
+    let config_text = std::fs::read_to_string(dir.join("config.json"))
+        .map_err(|e| format!("cannot read {}/config.json: {}", model_dir, e))?;
+    let config: BertConfig = serde_json::from_str(&config_text)
+        .map_err(|e| format!("cannot parse {}/config.json: {}", model_dir, e))?;
+
+    let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+        .map_err(|e| format!("cannot load {}/tokenizer.json: {}", model_dir, e))?;
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        ..Default::default()
+    }));
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: config.max_position_embeddings,
+        ..Default::default()
+    })).map_err(|e| format!("cannot set truncation: {}", e))?;
+
+    let device = Device::Cpu;
+    let vb = unsafe {
+        VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DType::F32, &device)
+            .map_err(|e| format!("cannot load {}/model.safetensors: {}", model_dir, e))?
+    };
+    let model = BertModel::load(vb, &config).map_err(|e| format!("cannot init the model from {}: {}", model_dir, e))?;
+    info!("local embeddings model loaded from {}", model_dir);
+    Ok(LocalEmbeddingModel { model, tokenizer, device })
+}
+
+fn get_or_load_model(model_dir: &String) -> Result<Arc<LocalEmbeddingModel>, String> {
+    let mut loaded = LOADED_MODELS.lock().unwrap();
+    if let Some(m) = loaded.get(model_dir) {
+        return Ok(m.clone());
+    }
+    let m = Arc::new(load_model(model_dir)?);
+    loaded.insert(model_dir.clone(), m.clone());
+    Ok(m)
+}
+
+fn embed_batch(m: &LocalEmbeddingModel, text: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+    let encodings = m.tokenizer.encode_batch(text, true).map_err(|e| format!("tokenizer error: {}", e))?;
+    let to_tensor = |rows: Vec<&[u32]>| -> Result<Tensor, candle_core::Error> {
+        let rows = rows.into_iter().map(|r| Tensor::new(r, &m.device)).collect::<Result<Vec<_>, _>>()?;
+        Tensor::stack(&rows, 0)
+    };
+    let run = || -> Result<Vec<Vec<f32>>, candle_core::Error> {
+        let input_ids = to_tensor(encodings.iter().map(|e| e.get_ids()).collect())?;
+        let attention_mask = to_tensor(encodings.iter().map(|e| e.get_attention_mask()).collect())?;
+        let token_type_ids = input_ids.zeros_like()?;
+        let hidden = m.model.forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+        // mean pooling over the real tokens, then L2 normalization, the same as sentence-transformers do
+        let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+        let normalized = pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?;
+        normalized.to_vec2::<f32>()
+    };
+    run().map_err(|e| format!("local embeddings model error: {}", e))
+}
+
+pub async fn get_embedding_local_style(
+    text: Vec<String>,
+    endpoint_template: &String,
+) -> Result<Vec<Vec<f32>>, String> {
+    if text.is_empty() {
+        return Ok(vec![]);
+    }
+    let model_dir = endpoint_template.clone();
+    tokio::task::spawn_blocking(move || {
+        let m = get_or_load_model(&model_dir)?;
+        embed_batch(&m, text)
+    }).await.map_err(|e| format!("local embeddings task failed: {}", e))?
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+    use tokenizers::models::wordlevel::WordLevel;
+    use tokenizers::pre_tokenizers::whitespace::Whitespace;
+
+    const TINY_HIDDEN_SIZE: usize = 8;
+
+    // A randomly initialized BERT small enough to be created right in the test, the output only has to have the right shape
+    fn write_tiny_bert(dir: &std::path::Path) {
+        let config_text = serde_json::json!({
+            "vocab_size": 8,
+            "hidden_size": TINY_HIDDEN_SIZE,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": 16,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.0,
+            "max_position_embeddings": 32,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 0,
+            "position_embedding_type": "absolute",
+            "use_cache": false,
+            "classifier_dropout": null,
+            "model_type": "bert",
+        }).to_string();
+        std::fs::write(dir.join("config.json"), &config_text).unwrap();
+
+        let config: BertConfig = serde_json::from_str(&config_text).unwrap();
+        let varmap = VarMap::new();
+        BertModel::load(VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu), &config).unwrap();
+        varmap.save(dir.join("model.safetensors")).unwrap();
+
+        let vocab = ["[PAD]", "[UNK]", "frog", "jumps", "croaks", "in", "the", "pond"].iter()
+            .enumerate()
+            .map(|(i, w)| (w.to_string(), i as u32))
+            .collect::<HashMap<_, _>>();
+        let mut tokenizer = Tokenizer::new(WordLevel::builder().vocab(vocab).unk_token("[UNK]".to_string()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(Whitespace::default()));
+        tokenizer.save(dir.join("tokenizer.json"), false).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_model_loads_and_embeds_with_its_hidden_size() {
+        let dir = tempfile::Builder::new().prefix("frog_local_embeddings").tempdir().unwrap();
+        write_tiny_bert(dir.path());
+        let model_dir = dir.path().to_string_lossy().to_string();
+
+        // texts of different lengths go through one padded batch
+        let embeddings = get_embedding_local_style(vec!["frog jumps".to_string(), "frog croaks in the pond".to_string()], &model_dir).await.unwrap();
+        assert_eq!(embeddings.len(), 2);
+        for embedding in embeddings.iter() {
+            assert_eq!(embedding.len(), TINY_HIDDEN_SIZE);
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-4, "not normalized: {:?}", embedding);
+        }
+        let alone = get_embedding_local_style(vec!["frog jumps".to_string()], &model_dir).await.unwrap();
+        assert!(alone[0].iter().zip(embeddings[0].iter()).all(|(a, b)| (a - b).abs() < 1e-4), "padding changed the embedding");
+
+        assert!(get_embedding_local_style(vec![], &model_dir).await.unwrap().is_empty());
+        assert!(get_embedding_local_style(vec!["frog".to_string()], &"/no/such/pond".to_string()).await.is_err());
+    }
+}
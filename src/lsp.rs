@@ -259,6 +259,7 @@ impl LanguageServer for LspBackend {
                     TextDocumentSyncKind::FULL,
                 )),
                 completion_provider: Some(completion_options),
+                document_symbol_provider: Some(OneOf::Left(true)),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -337,6 +338,21 @@ impl LanguageServer for LspBackend {
         Ok(Some(CompletionResponse::Array(vec![])))
     }
 
+    async fn document_symbol(&self, params: DocumentSymbolParams) -> Result<Option<DocumentSymbolResponse>> {
+        let cpath = crate::files_correction::canonical_path(&params.text_document.uri.to_file_path().unwrap_or_default().display().to_string());
+        let text = match files_in_workspace::get_file_text_from_memory_or_disk(self.gcx.clone(), &cpath).await {
+            Ok(text) => text,
+            Err(e) => {
+                info!("document_symbol {}: {}", crate::nicer_logs::last_n_chars(&cpath.display().to_string(), 30), e);
+                return Ok(None);
+            }
+        };
+        match crate::ast::document_symbols::lsp_document_symbols(&cpath, &text) {
+            Ok(outline) => Ok(Some(DocumentSymbolResponse::Nested(outline))),
+            Err(_) => Ok(None),  // no parser for this language
+        }
+    }
+
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
         for folder in params.event.added {
             info!("did_change_workspace_folders/add {}", folder.name);
@@ -55,6 +55,8 @@ mod scratchpads;
 mod fetch_embedding;
 mod forward_to_hf_endpoint;
 mod forward_to_openai_endpoint;
+#[cfg(feature="local-embeddings")]
+mod local_embeddings;
 mod restream;
 
 mod call_validation;
@@ -131,6 +133,20 @@ async fn main() {
         }
     }
 
+    info!("files larger than {} bytes will not be read or indexed", cmdline.max_file_size_bytes);
+    if !cmdline.ast_languages.is_empty() {
+        match crate::ast::treesitter::parsers::ast_languages_from_cmdline(&cmdline.ast_languages) {
+            Ok(_) => info!("AST will only parse {:?}", cmdline.ast_languages),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if !cmdline.blacklist_dir.is_empty() {
+        info!("also skipping dirs {:?}", cmdline.blacklist_dir);
+    }
+
     let byok_config_path = yaml_configs_try_create_all(gcx.clone()).await;
     if cmdline.only_create_yaml_configs {
         println!("{}", byok_config_path);
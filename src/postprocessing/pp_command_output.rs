@@ -2,6 +2,8 @@ use serde::Serialize;
 use serde::Deserialize;
 use regex::Regex;
 
+use crate::postprocessing::pp_utils::{finish_truncated_text, truncation_marker};
+
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct CmdlineOutputFilter {
@@ -111,21 +113,22 @@ pub fn output_mini_postprocessing(filter: &CmdlineOutputFilter, output: &str) ->
     }
 
     let mut result = String::new();
-    let mut skipped_lines = 0;
+    let mut skipped_bytes = 0;
     for (i, &line) in lines.iter().enumerate() {
         if approve[i] {
-            if skipped_lines > 0 {
-                result.push_str(&format!("...{} lines skipped...\n", skipped_lines));
-                skipped_lines = 0;
+            if skipped_bytes > 0 {
+                result.push_str(&truncation_marker(skipped_bytes));
+                result.push('\n');
+                skipped_bytes = 0;
             }
             result.push_str(line);
             result.push('\n');
         } else {
-            skipped_lines += 1;
+            skipped_bytes += line.len() + 1;
         }
     }
-    if skipped_lines > 0 {
-        result.push_str(&format!("...{} lines skipped...\n", skipped_lines));
+    if skipped_bytes > 0 {
+        result = finish_truncated_text(&result, skipped_bytes);
     }
     result
 }
@@ -153,7 +156,7 @@ line6
             grep_context_lines: 1,
             remove_from_output: "".to_string(),
         }, output_to_filter);
-        assert_eq!(result, "line1\nline2\nline3\n...3 lines skipped...\n");
+        assert_eq!(result, "line1\nline2\nline3\n[truncated, 18 bytes omitted]\n");
 
         let result = output_mini_postprocessing(&CmdlineOutputFilter {
             limit_lines: 2,
@@ -163,7 +166,7 @@ line6
             grep_context_lines: 1,
             remove_from_output: "".to_string(),
         }, output_to_filter);
-        assert_eq!(result, "...3 lines skipped...\nline4\nline5\nline6\n");
+        assert_eq!(result, "[truncated, 18 bytes omitted]\nline4\nline5\nline6\n");
 
         let result = output_mini_postprocessing(&CmdlineOutputFilter {
             limit_lines: 2,
@@ -173,7 +176,7 @@ line6
             grep_context_lines: 1,
             remove_from_output: "".to_string(),
         }, output_to_filter);
-        assert_eq!(result, "...2 lines skipped...\nline3\nline4\nline5\n...1 lines skipped...\n");
+        assert_eq!(result, "[truncated, 12 bytes omitted]\nline3\nline4\nline5\n[truncated, 6 bytes omitted]\n");
 
         let result = output_mini_postprocessing(&CmdlineOutputFilter {
             limit_lines: 100,
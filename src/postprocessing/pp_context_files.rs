@@ -11,7 +11,7 @@ use crate::call_validation::{ContextFile, PostprocessSettings};
 use crate::ast::ast_structs::AstDefinition;
 use crate::global_context::GlobalContext;
 use crate::nicer_logs::{first_n_chars, last_n_chars};
-use crate::postprocessing::pp_utils::{color_with_gradient_type, colorize_comments_up, colorize_if_more_useful, colorize_minus_one, colorize_parentof, downgrade_lines_if_subsymbol, pp_ast_markup_files};
+use crate::postprocessing::pp_utils::{color_with_gradient_type, colorize_comments_up, colorize_if_more_useful, colorize_minus_one, colorize_parentof, downgrade_lines_if_subsymbol, finish_truncated_text, pp_ast_markup_files};
 use crate::scratchpads::scratchpad_utils::count_tokens;
 
 
@@ -324,7 +324,8 @@ async fn pp_limit_and_merge(
             prev_line = i;
         }
         if last_line > prev_line + 1 {
-            out.push_str("...\n");
+            let omitted_bytes: usize = lines[prev_line + 1..].iter().map(|l| l.line_content.len() + 1).sum();
+            out = finish_truncated_text(&out, omitted_bytes);
         }
         if DEBUG >= 2 {
             info!("file {:?}:\n{}", cpath, out);
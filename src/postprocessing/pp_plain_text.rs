@@ -3,6 +3,7 @@ use std::sync::RwLock;
 use tokenizers::Tokenizer;
 
 use crate::call_validation::{ChatContent, ChatMessage};
+use crate::postprocessing::pp_utils::finish_truncated_text;
 use crate::scratchpads::multimodality::MultimodalElement;
 use crate::scratchpads::scratchpad_utils::count_tokens;
 
@@ -14,16 +15,17 @@ fn limit_text_content(
     tok_per_m: usize,
 ) -> String {
     let mut new_text_lines = vec![];
+    let mut kept_bytes = 0;
     for line in text.lines() {
         let line_tokens = count_tokens(tokenizer_guard, &line);
         if tok_used.clone() + line_tokens > tok_per_m {
             if new_text_lines.is_empty() {
                 new_text_lines.push("No content: tokens limit reached");
             }
-            new_text_lines.push("Truncated: too many tokens\n");
-            break;
+            return finish_truncated_text(&new_text_lines.join("\n"), text.len().saturating_sub(kept_bytes));
         }
         *tok_used += line_tokens;
+        kept_bytes += line.len() + 1;
         new_text_lines.push(line);
     }
     new_text_lines.join("\n")
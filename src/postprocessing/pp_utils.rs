@@ -31,7 +31,6 @@ pub fn color_with_gradient_type(msg: &ContextFile, lines: &mut Vec<FileLine>) {
     let (m11, c11) = find_line_parameters(msg.line1 as f32, msg.usefulness, msg.line1 as f32 - t_fade_away_lines as f32, 0. );
     let (m12, c12) = find_line_parameters(msg.line1 as f32, msg.usefulness, msg.line1 as f32 + t_fade_away_lines as f32, 0. );
     let (m21, c21) = find_line_parameters(msg.line2 as f32, msg.usefulness, msg.line2 as f32 - t_fade_away_lines as f32, 0. );
-    let (m22, c22) = find_line_parameters(msg.line2 as f32, msg.usefulness, msg.line2 as f32 + t_fade_away_lines as f32, 0. );
 
     for (line_n, line) in lines.iter_mut().enumerate() {
         let line_n = line_n + 1;
@@ -40,15 +39,7 @@ pub fn color_with_gradient_type(msg: &ContextFile, lines: &mut Vec<FileLine>) {
             1 => if line_n < msg.line1 {(line_n as f32 * m11 + c11).max(0.)} else {(line_n as f32 * m12 + c12).max(0.)},
             2 => if line_n <= msg.line2 {(line_n as f32 * m21 + c21).max(0.) } else {-1.},
             3 => if line_n < msg.line1 {-1.} else {(line_n as f32 * m12 + c12).max(0.)},
-            4 => {
-                if line_n < msg.line1 {
-                    line_n as f32 * m11 + c11
-                } else if line_n >= msg.line1 && line_n <= msg.line2 {
-                    100.
-                } else {
-                    line_n as f32 * m22 + c22
-                }
-            }.max(0.),
+            4 => if line_n >= msg.line1 && line_n <= msg.line2 {100.} else {-1.},  // an explicit range, nothing outside of it
             _ => 0.0,
         };
         set_useful_for_line(line, usefulness, format!("gradient_type: {:?}", msg.gradient_type));
@@ -247,3 +238,60 @@ pub fn downgrade_lines_if_subsymbol(lines: &mut Vec<FileLine>, line1_base0: usiz
         info!("        {}..{} ({} affected) <= subsymbol {:?} downgrade {}", line1_base0, line2_base0, changes_cnt, subsymbol, downgrade_coef);
     }
 }
+
+pub fn truncation_marker(omitted_bytes: usize) -> String {
+    format!("[truncated, {} bytes omitted]", omitted_bytes)
+}
+
+// The backticks of a fence opened and not closed in `text`, "```" for a cut in the middle of a code block
+fn unclosed_fence(text: &str) -> Option<String> {
+    let mut open: Option<String> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let ticks = trimmed.chars().take_while(|c| *c == '`').count();
+        if ticks < 3 {
+            continue;
+        }
+        match &open {
+            Some(fence) if ticks >= fence.len() && trimmed[ticks..].trim().is_empty() => open = None,
+            Some(_) => {}
+            None => open = Some("`".repeat(ticks)),
+        }
+    }
+    open
+}
+
+/// Finishes the part of a text that survived truncation: closes a code fence left open by the cut,
+/// then puts the marker on its own line, so the result is still valid markdown.
+pub fn finish_truncated_text(kept: &str, omitted_bytes: usize) -> String {
+    let mut result = kept.to_string();
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    if let Some(fence) = unclosed_fence(&result) {
+        result.push_str(&fence);
+        result.push('\n');
+    }
+    result.push_str(&truncation_marker(omitted_bytes));
+    result.push('\n');
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_truncated_text_closes_fence() {
+        let text = "The frog says:\n```python\ndef croak():\n    print(\"ribbit\")\n\ndef jump():\n    pass\n```\nThat's all.\n";
+        let cut = 55;
+        let result = finish_truncated_text(&text[..cut], text.len() - cut);
+        assert!(result.starts_with("The frog says:\n```python\ndef croak():\n"));
+        assert_eq!(result.lines().filter(|l| l.trim_start().starts_with("```")).count() % 2, 0, "{}", result);
+        assert!(result.ends_with(&format!("```\n[truncated, {} bytes omitted]\n", text.len() - cut)));
+
+        let result = finish_truncated_text("```\nribbit\n```\nlily pad", 10);
+        assert_eq!(result, "```\nribbit\n```\nlily pad\n[truncated, 10 bytes omitted]\n");
+    }
+}
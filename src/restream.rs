@@ -102,7 +102,7 @@ pub async fn scratchpad_interaction_not_stream_json(
 
     let mut save_url: String = String::new();
     let _ = slowdown_arc.acquire().await;
-    let mut model_says = if only_deterministic_messages {
+    let model_says = if only_deterministic_messages {
         save_url = "only-det-messages".to_string();
         Ok(serde_json::Value::Object(serde_json::Map::new()))
     } else if endpoint_style == "hf" {
@@ -146,6 +146,15 @@ pub async fn scratchpad_interaction_not_stream_json(
     info!("forward to endpoint {:.2}ms, url was {}", t2.elapsed().unwrap().as_millis() as f64, save_url);
     crate::global_context::look_for_piggyback_fields(gcx.clone(), &model_says).await;
 
+    scratchpad_result_from_model_says(scratchpad, model_says, only_deterministic_messages)
+}
+
+// Turns what the model said into the response, by the scratchpad
+fn scratchpad_result_from_model_says(
+    scratchpad: &mut Box<dyn ScratchpadAbstract>,
+    mut model_says: Value,
+    only_deterministic_messages: bool,
+) -> Result<Value, ScratchError> {
     let scratchpad_result: Result<serde_json::Value, String>;
     if only_deterministic_messages {
         if let Ok(det_msgs) = scratchpad.response_spontaneous() {
@@ -179,20 +188,19 @@ pub async fn scratchpad_interaction_not_stream_json(
             if let Ok(det_msgs) = scratchpad.response_spontaneous() {
                 model_says["deterministic_messages"] = json!(det_msgs);
             }
-            let choices = oai_choices.clone().as_array().unwrap().iter().map(|x| {
-                match (x.get("message"), x.get("message").and_then(|msg| msg.get("content")), x.get("message").and_then(|msg| msg.get("content")).and_then(|content| content.as_str())) {
-                    (Some(_), Some(_), Some(content)) => content.to_string(),
-                    (msg, content, as_str) => {
-                        tracing::info!(
-                            "no text content: msg={:?}, content={:?}, as_str={:?}",
-                            msg, content, as_str
-                        );
-                        "".to_string()
+            let messages = oai_choices.as_array().unwrap().iter()
+                .map(|x| x.get("message").cloned().unwrap_or(json!({})))
+                .collect::<Vec<_>>();
+            scratchpad_result = match scratchpad.response_message_n_choices(messages, finish_reasons) {
+                Ok(mut res) => {
+                    // usage, metering and deterministic messages are not the scratchpad's business
+                    if let (Some(res_map), Some(says_map)) = (res.as_object_mut(), model_says.as_object()) {
+                        for (k, v) in says_map.iter() {
+                            res_map.entry(k.clone()).or_insert(v.clone());
+                        }
                     }
-                }
-            }).collect::<Vec<_>>();
-            scratchpad_result = match scratchpad.response_message_n_choices(choices, finish_reasons) {
-                Ok(res) => Ok(res),
+                    Ok(res)
+                },
                 Err(err) => {
                     if err == "not implemented" {
                         info!("scratchpad doesn't implement response_message_n_choices, passing the original message through");
@@ -655,3 +663,64 @@ pub async fn cached_stream(
        .unwrap();
     return Ok(response);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::call_validation::ChatPost;
+    use crate::scratchpads::chat_passthrough::ChatPassthrough;
+
+    const DUMMY_TOKENIZER: &str = include_str!("ast/dummy_tokenizer.json");
+
+    #[test]
+    fn test_passthrough_not_streaming_tool_calls() {
+        let tokenizer = Arc::new(StdRwLock::new(tokenizers::Tokenizer::from_str(DUMMY_TOKENIZER).unwrap()));
+        let mut scratchpad: Box<dyn ScratchpadAbstract> = Box::new(ChatPassthrough::new(tokenizer, &ChatPost::default(), &vec![], false, false, true, false));
+        let model_says = json!({
+            "id": "chatcmpl-frog",
+            "object": "chat.completion",
+            "choices": [
+                {
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_frog1",
+                            "type": "function",
+                            "function": {"name": "definition", "arguments": "{\"symbol\": \"Frog\"}"},
+                        }],
+                    },
+                    "finish_reason": "tool_calls",
+                },
+                {
+                    "index": 1,
+                    "message": {"role": "assistant", "content": "The frog jumps over the pond"},
+                    "finish_reason": "length",
+                },
+            ],
+            "usage": {"prompt_tokens": 42, "completion_tokens": 7},
+        });
+
+        let res = scratchpad_result_from_model_says(&mut scratchpad, model_says, false).unwrap();
+        let res_choices = res["choices"].as_array().unwrap();
+        assert_eq!(res_choices.len(), 2);
+
+        assert_eq!(res_choices[0]["index"], 0);
+        assert_eq!(res_choices[0]["message"]["role"], "assistant");
+        assert_eq!(res_choices[0]["message"]["content"], Value::Null);
+        assert_eq!(res_choices[0]["message"]["tool_calls"][0]["id"], "call_frog1");
+        assert_eq!(res_choices[0]["message"]["tool_calls"][0]["function"]["name"], "definition");
+        assert_eq!(res_choices[0]["finish_reason"], "tool_calls");
+
+        assert_eq!(res_choices[1]["index"], 1);
+        assert_eq!(res_choices[1]["message"]["content"], "The frog jumps over the pond");
+        assert!(res_choices[1]["message"].get("tool_calls").is_none());
+        assert_eq!(res_choices[1]["finish_reason"], "length");
+
+        assert_eq!(res["usage"]["prompt_tokens"], 42);
+        assert!(res.get("deterministic_messages").is_some());
+    }
+}
@@ -96,9 +96,10 @@ pub trait ScratchpadAbstract: Send {
         finish_reason: FinishReason
     ) -> Result<(Value, FinishReason), String>;
 
+    // Not streaming, the model answered with messages (choices[i].message)
     fn response_message_n_choices(
         &mut self,
-        _choices: Vec<String>,    // XXX replace with Value
+        _messages: Vec<Value>,
         _finish_reasons: Vec<FinishReason>,
     ) -> Result<Value, String> {
         Err("not implemented".to_string())
@@ -4,18 +4,21 @@ use std::sync::RwLock as StdRwLock;
 use serde_json::{json, Value};
 use tokenizers::Tokenizer;
 use tokio::sync::Mutex as AMutex;
+use tokio::sync::RwLock as ARwLock;
 use async_trait::async_trait;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::at_commands::execute_at::{run_at_commands_locally, run_at_commands_remotely};
 use crate::at_commands::at_commands::AtCommandsContext;
-use crate::call_validation::{ChatMessage, ChatPost, SamplingParameters};
+use crate::call_validation::{ChatContent, ChatMessage, ChatPost, SamplingParameters};
+use crate::global_context::GlobalContext;
 use crate::scratchpad_abstract::{FinishReason, HasTokenizerAndEot, ScratchpadAbstract};
-use crate::scratchpads::chat_utils_limit_history::limit_messages_history;
+use crate::agentic::generate_history_summary::{generate_history_summary, HISTORY_SUMMARY_MAX_TOKENS};
+use crate::scratchpads::chat_utils_limit_history::{limit_messages_history, limit_messages_history_with_dropped};
 use crate::scratchpads::scratchpad_utils::HasRagResults;
 use crate::scratchpads::chat_utils_prompts::prepend_the_right_system_prompt_and_maybe_more_initial_messages;
 use crate::scratchpads::passthrough_convert_messages::convert_messages_to_openai_format;
-use crate::tools::tools_description::{tool_description_list_from_yaml, tools_merged_and_filtered};
+use crate::tools::tools_description::{tool_description_list_from_yaml, tool_descs_to_openai_style, tools_merged_and_filtered};
 use crate::tools::tools_execute::{run_tools_locally, run_tools_remotely};
 
 
@@ -87,6 +90,73 @@ impl ChatPassthrough {
     }
 }
 
+impl ChatPassthrough {
+    async fn limit_messages_history_summarizing_dropped(
+        &self,
+        gcx: Arc<ARwLock<GlobalContext>>,
+        messages: &Vec<ChatMessage>,
+        undroppable_msg_n: usize,
+        max_new_tokens: usize,
+        n_ctx: usize,
+    ) -> Result<Vec<ChatMessage>, String> {
+        let chat_id = self.post.meta.chat_id.clone();
+        let model = self.post.model.clone();
+        limit_messages_history_summarizing(&self.t, messages, undroppable_msg_n, max_new_tokens, n_ctx, |dropped_msgs| async move {
+            // every next turn drops the same messages until the history grows more, don't summarize them again
+            let cache = gcx.read().await.history_summary_cache.clone();
+            let cached = if chat_id.is_empty() { None } else { cache.lock().unwrap().get(&chat_id).cloned() };
+            if let Some((dropped_cnt, summary)) = cached {
+                if dropped_cnt == dropped_msgs.len() {
+                    return Ok(summary);
+                }
+            }
+            let summary = generate_history_summary(&dropped_msgs, gcx.clone(), &model, &chat_id).await?;
+            if !chat_id.is_empty() {
+                cache.lock().unwrap().insert(chat_id.clone(), (dropped_msgs.len(), summary.clone()));
+            }
+            Ok(summary)
+        }).await
+    }
+}
+
+// Messages that don't fit are replaced with a summary at the end of the system prompt, the rest of the history
+// keeps its roles in order. Falls back to dropping them if summarization fails.
+async fn limit_messages_history_summarizing<F, Fut>(
+    t: &HasTokenizerAndEot,
+    messages: &Vec<ChatMessage>,
+    undroppable_msg_n: usize,
+    max_new_tokens: usize,
+    n_ctx: usize,
+    summarize: F,
+) -> Result<Vec<ChatMessage>, String>
+where
+    F: FnOnce(Vec<ChatMessage>) -> Fut,
+    Fut: std::future::Future<Output = Result<String, String>>,
+{
+    let (mut limited_msgs, dropped_msgs) = limit_messages_history_with_dropped(
+        t, messages, undroppable_msg_n, max_new_tokens + HISTORY_SUMMARY_MAX_TOKENS, n_ctx)?;
+    if dropped_msgs.is_empty() {
+        return Ok(limited_msgs);
+    }
+    let dropped_cnt = dropped_msgs.len();
+    match summarize(dropped_msgs).await {
+        Ok(summary) => {
+            let summary_text = format!("Summary of the earlier conversation that didn't fit into the context:\n{}", summary);
+            match limited_msgs.first_mut() {
+                Some(first) if first.role == "system" => {
+                    first.content = ChatContent::SimpleText(format!("{}\n\n{}", first.content.content_text_only(), summary_text));
+                }
+                _ => limited_msgs.insert(0, ChatMessage::new("system".to_string(), summary_text)),
+            }
+            Ok(limited_msgs)
+        }
+        Err(e) => {
+            warn!("cannot summarize {} dropped messages, dropping them: {}", dropped_cnt, e);
+            limit_messages_history(t, messages, undroppable_msg_n, max_new_tokens, n_ctx)
+        }
+    }
+}
+
 #[async_trait]
 impl ScratchpadAbstract for ChatPassthrough {
     async fn apply_model_adaptation_patch(
@@ -132,7 +202,11 @@ impl ScratchpadAbstract for ChatPassthrough {
         };
 
         _remove_unanswered_tool_call_messages(&mut messages);
-        let limited_msgs = limit_messages_history(&self.t, &messages, undroppable_msg_n, sampling_parameters_to_patch.max_new_tokens, n_ctx).unwrap_or_else(|e| {
+        let limited_msgs = if self.post.summarize_dropped_history {
+            self.limit_messages_history_summarizing_dropped(gcx.clone(), &messages, undroppable_msg_n, sampling_parameters_to_patch.max_new_tokens, n_ctx).await
+        } else {
+            limit_messages_history(&self.t, &messages, undroppable_msg_n, sampling_parameters_to_patch.max_new_tokens, n_ctx)
+        }.unwrap_or_else(|e| {
             error!("error limiting messages: {}", e);
             vec![]
         });
@@ -167,7 +241,7 @@ impl ScratchpadAbstract for ChatPassthrough {
                 let allow_experimental = gcx.read().await.cmdline.experimental;
                 // and take descriptions of tools from the official source
                 let tool_descriptions = tool_description_list_from_yaml(at_tools, &turned_on, allow_experimental).await?;
-                Some(tool_descriptions.into_iter().map(|x|x.into_openai_style()).collect::<Vec<_>>())
+                Some(tool_descs_to_openai_style(tool_descriptions).as_ref().clone())
             } else {
                 None
             };
@@ -197,10 +271,41 @@ impl ScratchpadAbstract for ChatPassthrough {
 
     fn response_n_choices(
         &mut self,
-        _choices: Vec<String>,
-        _finish_reasons: Vec<FinishReason>,
+        choices: Vec<String>,
+        finish_reasons: Vec<FinishReason>,
     ) -> Result<Value, String> {
-        Err("not implemented".to_string())
+        let messages = choices.into_iter().map(|content| json!({"role": "assistant", "content": content})).collect();
+        self.response_message_n_choices(messages, finish_reasons)
+    }
+
+    fn response_message_n_choices(
+        &mut self,
+        messages: Vec<Value>,
+        finish_reasons: Vec<FinishReason>,
+    ) -> Result<Value, String> {
+        let json_choices = messages.iter().enumerate().map(|(i, msg)| {
+            let tool_calls = msg.get("tool_calls").filter(|tc| tc.as_array().map_or(false, |a| !a.is_empty()));
+            let mut message = json!({
+                "role": "assistant",
+                "content": msg.get("content").cloned().unwrap_or(Value::Null),
+            });
+            let finish_reason = match tool_calls {
+                Some(tool_calls) => {
+                    message["tool_calls"] = tool_calls.clone();
+                    json!("tool_calls")
+                },
+                None => finish_reasons.get(i).copied().unwrap_or(FinishReason::None).to_json_val(),
+            };
+            json!({
+                "index": i,
+                "message": message,
+                "finish_reason": finish_reason,
+            })
+        }).collect::<Vec<_>>();
+        Ok(json!({
+            "choices": json_choices,
+            "object": "chat.completion",
+        }))
     }
 
     fn response_streaming(
@@ -268,3 +373,45 @@ fn _remove_unanswered_tool_call_messages(messages: &mut Vec<ChatMessage>) {
         }
     });
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const DUMMY_TOKENIZER: &str = include_str!("../ast/dummy_tokenizer.json");
+
+    #[tokio::test]
+    async fn test_history_summary_goes_to_system_prompt_or_falls_back() {
+        let tokenizer = Arc::new(StdRwLock::new(tokenizers::Tokenizer::from_str(DUMMY_TOKENIZER).unwrap()));
+        let t = HasTokenizerAndEot::new(tokenizer);
+        let messages = vec![
+            ChatMessage::new("system".to_string(), "You are a frog assistant".to_string()),
+            ChatMessage::new("user".to_string(), "Let's rename jump() to leap() in frog.py".to_string()),
+            ChatMessage::new("assistant".to_string(), "Renamed jump() to leap() in frog.py, the callers are in pond.py".to_string()),
+            ChatMessage::new("user".to_string(), "Also fix the callers".to_string()),
+            ChatMessage::new("assistant".to_string(), "Done".to_string()),
+            ChatMessage::new("user".to_string(), "Now croak".to_string()),
+        ];
+        let n_ctx = 110 + HISTORY_SUMMARY_MAX_TOKENS;
+
+        let summarized = limit_messages_history_summarizing(&t, &messages, 5, 10, n_ctx, |dropped| async move {
+            assert_eq!(dropped.len(), 2);
+            Ok("- jump() was renamed to leap() in frog.py, callers are in pond.py".to_string())
+        }).await.unwrap();
+        assert_eq!(summarized.iter().map(|m| m.role.as_str()).collect::<Vec<_>>(), vec!["system", "user", "assistant", "user"]);
+        assert!(summarized[0].content.content_text_only().starts_with("You are a frog assistant\n\nSummary of the earlier conversation"));
+        assert!(summarized[0].content.content_text_only().contains("callers are in pond.py"));
+
+        let fallback = limit_messages_history_summarizing(&t, &messages, 5, 10, n_ctx, |_| async move {
+            Err("subchat timed out".to_string())
+        }).await.unwrap();
+        let dropped = limit_messages_history(&t, &messages, 5, 10, n_ctx).unwrap();
+        assert_eq!(
+            fallback.iter().map(|m| m.content.content_text_only()).collect::<Vec<_>>(),
+            dropped.iter().map(|m| m.content.content_text_only()).collect::<Vec<_>>(),
+        );
+        assert_eq!(fallback[0].content.content_text_only(), "You are a frog assistant");
+    }
+}
@@ -10,6 +10,18 @@ pub fn limit_messages_history(
     max_new_tokens: usize,
     context_size: usize,
 ) -> Result<Vec<ChatMessage>, String>
+{
+    limit_messages_history_with_dropped(t, messages, last_user_msg_starts, max_new_tokens, context_size).map(|(messages_out, _)| messages_out)
+}
+
+// Also returns the dropped messages
+pub fn limit_messages_history_with_dropped(
+    t: &HasTokenizerAndEot,
+    messages: &Vec<ChatMessage>,
+    last_user_msg_starts: usize,
+    max_new_tokens: usize,
+    context_size: usize,
+) -> Result<(Vec<ChatMessage>, Vec<ChatMessage>), String>
 {
     let tokens_limit: i32 = context_size as i32 - max_new_tokens as i32;
     tracing::info!("limit_messages_history tokens_limit={} because context_size={} and max_new_tokens={}", tokens_limit, context_size, max_new_tokens);
@@ -79,5 +91,40 @@ pub fn limit_messages_history(
     }
 
     let messages_out: Vec<ChatMessage> = messages.iter().enumerate().filter(|(i, _)| message_take[*i]).map(|(_, x)| x.clone()).collect();
-    Ok(messages_out)
+    let messages_dropped: Vec<ChatMessage> = messages.iter().enumerate().filter(|(i, _)| !message_take[*i]).map(|(_, x)| x.clone()).collect();
+    Ok((messages_out, messages_dropped))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::{Arc, RwLock as StdRwLock};
+
+    const DUMMY_TOKENIZER: &str = include_str!("../ast/dummy_tokenizer.json");
+
+    #[test]
+    fn test_dropped_messages() {
+        let tokenizer = Arc::new(StdRwLock::new(tokenizers::Tokenizer::from_str(DUMMY_TOKENIZER).unwrap()));
+        let t = HasTokenizerAndEot::new(tokenizer);
+        let messages = vec![
+            ChatMessage::new("system".to_string(), "You are a frog assistant".to_string()),
+            ChatMessage::new("user".to_string(), "Let's rename jump() to leap() in frog.py".to_string()),
+            ChatMessage::new("assistant".to_string(), "Renamed jump() to leap() in frog.py, the callers are in pond.py".to_string()),
+            ChatMessage::new("user".to_string(), "Also fix the callers".to_string()),
+            ChatMessage::new("assistant".to_string(), "Done".to_string()),
+            ChatMessage::new("user".to_string(), "Now croak".to_string()),
+        ];
+
+        let (kept, dropped) = limit_messages_history_with_dropped(&t, &messages, 5, 10, 110).unwrap();
+
+        assert_eq!(kept.iter().map(|m| m.content.content_text_only()).collect::<Vec<_>>(),
+            vec!["You are a frog assistant", "Let's rename jump() to leap() in frog.py", "Done", "Now croak"]);
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped[0].content.content_text_only().contains("pond.py"));
+
+        let (kept, dropped) = limit_messages_history_with_dropped(&t, &messages, 5, 10, 10000).unwrap();
+        assert_eq!((kept.len(), dropped.len()), (6, 0));
+    }
 }
@@ -1,4 +1,3 @@
-use std::fs;
 use std::sync::Arc;
 use std::path::PathBuf;
 use tokio::sync::RwLock as ARwLock;
@@ -95,7 +94,7 @@ pub async fn dig_for_project_summarization_file(gcx: Arc<ARwLock<GlobalContext>>
 async fn _read_project_summary(
     summary_path: String,
 ) -> Option<String> {
-    match fs::read_to_string(summary_path) {
+    match tokio::fs::read_to_string(summary_path).await {
         Ok(content) => {
             if let Ok(yaml) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
                 if let Some(project_summary) = yaml.get("project_summary") {
@@ -122,6 +121,26 @@ async fn _read_project_summary(
     }
 }
 
+// The system prompt is built on every chat turn, the summary is parsed again only if the file's mtime changes
+async fn _read_project_summary_cached(
+    gcx: Arc<ARwLock<GlobalContext>>,
+    summary_path: String,
+) -> Option<String> {
+    let mtime = match tokio::fs::metadata(&summary_path).await.and_then(|m| m.modified()) {
+        Ok(mtime) => mtime,
+        Err(_) => return _read_project_summary(summary_path).await,
+    };
+    let cache = gcx.read().await.project_summary_cache.clone();
+    if let Some((cached_mtime, summary)) = cache.lock().unwrap().get(&summary_path) {
+        if *cached_mtime == mtime {
+            return summary.clone();
+        }
+    }
+    let summary = _read_project_summary(summary_path.clone()).await;
+    cache.lock().unwrap().insert(summary_path, (mtime, summary.clone()));
+    summary
+}
+
 pub async fn system_prompt_add_workspace_info(
     gcx: Arc<ARwLock<GlobalContext>>,
     system_prompt: &String,
@@ -146,7 +165,7 @@ pub async fn system_prompt_add_workspace_info(
         let (exists, summary_path_option) = dig_for_project_summarization_file(gcx.clone()).await;
         if exists {
             if let Some(summary_path) = summary_path_option {
-                if let Some(project_info) = _read_project_summary(summary_path).await {
+                if let Some(project_info) = _read_project_summary_cached(gcx.clone(), summary_path).await {
                     system_prompt = system_prompt.replace("%PROJECT_SUMMARY%", &project_info);
                 } else {
                     system_prompt = system_prompt.replace("%PROJECT_SUMMARY%", "");
@@ -157,7 +176,13 @@ pub async fn system_prompt_add_workspace_info(
         }
     }
 
+    system_prompt_add_date_and_os(system_prompt, chrono::Local::now().date_naive())
+}
+
+fn system_prompt_add_date_and_os(system_prompt: String, today: chrono::NaiveDate) -> String {
     system_prompt
+        .replace("%CURRENT_DATE%", &today.format("%Y-%m-%d").to_string())
+        .replace("%OS_INFO%", &format!("{} {}", std::env::consts::OS, std::env::consts::ARCH))
 }
 
 pub async fn prepend_the_right_system_prompt_and_maybe_more_initial_messages(
@@ -243,3 +268,17 @@ pub async fn prepend_system_prompt_and_maybe_more_initial_messages_from_remote(
 
     Ok(response.messages)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_and_os_variables() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let prompt = system_prompt_add_date_and_os("Today is %CURRENT_DATE%, the frog runs on %OS_INFO%.".to_string(), today);
+        assert_eq!(prompt, format!("Today is 2024-02-29, the frog runs on {} {}.", std::env::consts::OS, std::env::consts::ARCH));
+        assert_eq!(system_prompt_add_date_and_os("No variables here".to_string(), today), "No variables here");
+    }
+}
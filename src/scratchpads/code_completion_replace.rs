@@ -1049,9 +1049,12 @@ impl ScratchpadAbstract for CodeCompletionReplacePassthroughScratchpad {
 
     fn response_message_n_choices(
         &mut self,
-        choices: Vec<String>,
+        messages: Vec<Value>,
         finish_reasons: Vec<FinishReason>,
     ) -> Result<Value, String> {
+        let choices = messages.iter()
+            .map(|m| m.get("content").and_then(|c| c.as_str()).unwrap_or("").to_string())
+            .collect::<Vec<_>>();
         let json_choices = process_n_choices(
             &mut self.cursor_subblock,
             &choices,
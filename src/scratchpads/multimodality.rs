@@ -5,18 +5,24 @@ use tokenizers::Tokenizer;
 use crate::call_validation::{ChatContent, ChatMessage, ChatToolCall};
 use crate::scratchpads::scratchpad_utils::{calculate_image_tokens_openai, count_tokens as count_tokens_simple_text, image_reader_from_b64string, parse_image_b64_from_image_url_openai};
 
+// images we can't look at (remote urls, broken base64) cost as much as a 1024x1024 image with detail=high
+const IMAGE_TOKENS_ESTIMATE: i32 = 765;
+
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct MultimodalElement {
-    pub m_type: String, // "text", "image/png" etc
+    pub m_type: String, // "text", "image/png" etc, or "image_url" for a remote image passed upstream as is
     pub m_content: String,
 }
 
 impl MultimodalElement {
     pub fn new(m_type: String, m_content: String) -> Result<Self, String> {
-        if !(m_type == "text") && !m_type.starts_with("image/") {
+        if !(m_type == "text") && !m_type.starts_with("image/") && m_type != "image_url" {
             return Err(format!("MultimodalElement::new() received invalid type: {}", m_type));
         }
+        if m_type == "image_url" && !is_remote_image_url(&m_content) {
+            return Err(format!("MultimodalElement::new() expected an http(s) url, got: {}", m_content));
+        }
         if m_type.starts_with("image/") {
             let _ = image_reader_from_b64string(&m_content)
                 .map_err(|e| format!("MultimodalElement::new() failed to parse m_content: {}", e));
@@ -29,10 +35,17 @@ impl MultimodalElement {
     }
 
     pub fn is_image(&self) -> bool {
-        self.m_type.starts_with("image/")
+        self.m_type.starts_with("image/") || self.is_image_url()
+    }
+
+    pub fn is_image_url(&self) -> bool {
+        self.m_type == "image_url"
     }
 
     pub fn from_openai_image(openai_image: MultimodalElementImageOpenAI) -> Result<Self, String> {
+        if is_remote_image_url(&openai_image.image_url.url) {
+            return MultimodalElement::new("image_url".to_string(), openai_image.image_url.url);
+        }
         let (image_type, _, image_content) = parse_image_b64_from_image_url_openai(&openai_image.image_url.url)
             .ok_or(format!("Failed to parse image URL: {}", openai_image.image_url.url))?;
         MultimodalElement::new(image_type, image_content)
@@ -59,7 +72,11 @@ impl MultimodalElement {
     }
 
     fn to_openai_image(&self) -> ChatMultimodalElement {
-        let image_url = format!("data:{};base64,{}", self.m_type, self.m_content);
+        let image_url = if self.is_image_url() {
+            self.m_content.clone()
+        } else {
+            format!("data:{};base64,{}", self.m_type, self.m_content)
+        };
         ChatMultimodalElement::MultimodalElementImageOpenAI(MultimodalElementImageOpenAI {
             content_type: "image_url".to_string(),
             image_url: MultimodalElementImageOpenAIImageURL {
@@ -83,11 +100,13 @@ impl MultimodalElement {
             } else {
                 return Err("count_tokens() received no tokenizer".to_string());
             }
+        } else if self.is_image_url() {
+            Ok(IMAGE_TOKENS_ESTIMATE)
         } else if self.is_image() {
             let style = style.clone().unwrap_or("openai".to_string());
             match style.as_str() {
                 "openai" => {
-                    calculate_image_tokens_openai(&self.m_content, "high")
+                    Ok(calculate_image_tokens_openai(&self.m_content, "high").unwrap_or(IMAGE_TOKENS_ESTIMATE))
                 },
                 _ => unreachable!(),
             }
@@ -122,6 +141,10 @@ fn default_detail() -> String {
     "high".to_string()
 }
 
+fn is_remote_image_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)] // tries to deserialize each enum variant in order
 pub enum ChatMultimodalElement {
@@ -217,8 +240,8 @@ pub fn chat_content_raw_from_value(value: Value) -> Result<ChatContentRaw, Strin
                 if el.content_type != "image_url" {
                     return Err("Invalid multimodal element: type must be `image_url`".to_string());
                 }
-                if parse_image_b64_from_image_url_openai(&el.image_url.url).is_none() {
-                    return Err("Invalid image URL in MultimodalElementImageOpenAI: must pass regexp `data:image/(png|jpeg|jpg|webp|gif);base64,([A-Za-z0-9+/=]+)`".to_string());
+                if parse_image_b64_from_image_url_openai(&el.image_url.url).is_none() && !is_remote_image_url(&el.image_url.url) {
+                    return Err("Invalid image URL in MultimodalElementImageOpenAI: must be an http(s) url or pass regexp `data:image/(png|jpeg|jpg|webp|gif);base64,([A-Za-z0-9+/=]+)`".to_string());
                 }
             }
             ChatMultimodalElement::MultimodalElement(_el) => {}
@@ -187,4 +187,31 @@ mod tests {
 
         assert_eq!(roles_out, roles_out_expected);
     }
+
+    #[test]
+    fn test_remote_image_passes_through_untouched() {
+        let frog_url = "https://example.com/frogs/tree_frog.jpg";
+        let user_msg: ChatMessage = serde_json::from_value(json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What frog is this?"},
+                {"type": "image_url", "image_url": {"url": frog_url}},
+            ],
+        })).unwrap();
+        if let ChatContent::Multimodal(elements) = &user_msg.content {
+            assert!(elements[1].is_image_url());
+            assert_eq!(elements[1].count_tokens(None, &None), Ok(765));
+        } else {
+            panic!("expected multimodal content");
+        }
+        let messages = vec![user_msg, ChatMessage::new("user".to_string(), "just text".to_string())];
+
+        let output = convert_messages_to_openai_format(messages, &Some("openai".to_string()));
+
+        assert_eq!(output[0]["content"], json!([
+            {"type": "text", "text": "What frog is this?"},
+            {"type": "image_url", "image_url": {"url": frog_url, "detail": "high"}},
+        ]));
+        assert_eq!(output[1]["content"], json!("just text"));
+    }
 }
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
 use tokio::sync::RwLock as ARwLock;
 use tokio::sync::Mutex as AMutex;
 use serde_json::{json, Value};
 use tracing::{error, info, warn};
 
-use crate::tools::tools_description::{tools_merged_and_filtered, tool_description_list_from_yaml};
+use crate::tools::tools_description::{tools_merged_and_filtered, tool_description_list_from_yaml, tool_descs_to_openai_style};
 use crate::at_commands::at_commands::AtCommandsContext;
 use crate::call_validation::{SamplingParameters, PostprocessSettings, ChatPost, ChatMessage, ChatUsage, ChatToolCall, ReasoningEffort};
 use crate::global_context::{GlobalContext, try_load_caps_quickly_if_not_present, is_metadata_supported};
@@ -262,6 +264,20 @@ fn update_usage_from_messages(usage: &mut ChatUsage, messages: &Vec<Vec<ChatMess
     }
 }
 
+/// Errors of timed out subchats start with this, callers can check for it and degrade gracefully
+pub const SUBCHAT_TIMEOUT_ERROR: &str = "subchat timed out";
+
+async fn with_subchat_timeout<T>(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    match timeout {
+        Some(t) => tokio::time::timeout(t, fut).await
+            .map_err(|_| format!("{} after {:.1}s", SUBCHAT_TIMEOUT_ERROR, t.as_secs_f32()))?,
+        None => fut.await,
+    }
+}
+
 pub async fn subchat_single(
     ccx: Arc<AMutex<AtCommandsContext>>,
     model_name: &str,
@@ -277,6 +293,7 @@ pub async fn subchat_single(
     usage_collector_mb: Option<&mut ChatUsage>,
     tx_toolid_mb: Option<String>,
     tx_chatid_mb: Option<String>,
+    timeout: Option<Duration>,
 ) -> Result<Vec<Vec<ChatMessage>>, String> {
     let (gcx, should_execute_remotely) = {
         let ccx_locked = ccx.lock().await;
@@ -292,29 +309,30 @@ pub async fn subchat_single(
         error!("Error loading compiled_in_tools: {:?}", e);
         vec![]
     });
-    let tools = tools_desclist.into_iter().map(|x|x.into_openai_style()).collect::<Vec<_>>();
+    let tools = tool_descs_to_openai_style(tools_desclist).as_ref().clone();
     info!("tools_subset {:?}", tools_subset);
     info!("tools_turned_on_by_cmdline_set {:?}", tools_turned_on_by_cmdline_set);
     info!("tools_on_intersection {:?}", tools_on_intersection);
 
     let max_new_tokens = max_new_tokens.unwrap_or(MAX_NEW_TOKENS);
-    let (mut chat_post, spad) = create_chat_post_and_scratchpad(
-        gcx.clone(),
-        ccx.clone(),
-        model_name,
-        messages.iter().collect::<Vec<_>>(),
-        temperature,
-        max_new_tokens,
-        n,
-        reasoning_effort,
-        prepend_system_prompt,
-        Some(tools),
-        tool_choice.clone(),
-        only_deterministic_messages,
-        should_execute_remotely,
-    ).await?;
-
-    let chat_response_msgs = chat_interaction(ccx.clone(), spad, &mut chat_post).await?;
+    let chat_response_msgs = with_subchat_timeout(timeout, async {
+        let (mut chat_post, spad) = create_chat_post_and_scratchpad(
+            gcx.clone(),
+            ccx.clone(),
+            model_name,
+            messages.iter().collect::<Vec<_>>(),
+            temperature,
+            max_new_tokens,
+            n,
+            reasoning_effort,
+            prepend_system_prompt,
+            Some(tools),
+            tool_choice.clone(),
+            only_deterministic_messages,
+            should_execute_remotely,
+        ).await?;
+        chat_interaction(ccx.clone(), spad, &mut chat_post).await
+    }).await?;
 
     let old_messages = messages.clone();
     // no need to remove user from old_messages here, because allow_at is false
@@ -401,6 +419,7 @@ pub async fn subchat(
                 Some(&mut usage_collector),
                 tx_toolid_mb.clone(),
                 tx_chatid_mb.clone(),
+                None,
             ).await?[0].clone();
             step_n += 1;
         }
@@ -424,6 +443,7 @@ pub async fn subchat(
                 Some(&mut usage_collector),
                 tx_toolid_mb.clone(),
                 tx_chatid_mb.clone(),
+                None,
             ).await?[0].clone();
         }
     }
@@ -443,9 +463,31 @@ pub async fn subchat(
         Some(&mut usage_collector),
         tx_toolid_mb.clone(),
         tx_chatid_mb.clone(),
+        None,
     ).await?;
     // if let Some(last_message) = messages.last_mut() {
     //     last_message.usage = Some(usage_collector);
     // }
     Ok(choices)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subchat_timeout_fires() {
+        let never_responds = std::future::pending::<Result<Vec<Vec<ChatMessage>>, String>>();
+        let t0 = std::time::Instant::now();
+
+        let err = with_subchat_timeout(Some(Duration::from_millis(100)), never_responds).await.unwrap_err();
+
+        assert!(err.starts_with(SUBCHAT_TIMEOUT_ERROR), "{}", err);
+        assert!(t0.elapsed() < Duration::from_secs(5));
+        let fast = with_subchat_timeout(Some(Duration::from_secs(5)), async { Ok::<_, String>(42) }).await;
+        assert_eq!(fast, Ok(42));
+        let no_timeout = with_subchat_timeout(None, async { Ok::<_, String>(7) }).await;
+        assert_eq!(no_timeout, Ok(7));
+    }
+}
@@ -11,6 +11,7 @@ use crate::at_commands::at_file::{file_repair_candidates, return_one_candidate_o
 use crate::tools::tools_description::Tool;
 use crate::call_validation::{ChatMessage, ChatContent, ContextEnum, ContextFile};
 use crate::files_correction::{correct_to_nearest_dir_path, get_project_dirs};
+use crate::file_filter::FileFilterSettings;
 use crate::files_in_workspace::{get_file_text_from_memory_or_disk, ls_files};
 use crate::scratchpads::multimodality::MultimodalElement;
 
@@ -211,7 +212,8 @@ pub async fn paths_and_symbols_to_cat(
                 Ok(f) => f,
                 Err(e) => { not_found_messages.push(e); continue;}
             };
-            let files_in_dir = ls_files(&PathBuf::from(candidate), false).unwrap_or(vec![]);
+            let filter_settings = FileFilterSettings::from_cmdline(&gcx.read().await.cmdline);
+            let files_in_dir = ls_files(&PathBuf::from(candidate), false, &filter_settings).unwrap_or(vec![]);
             corrected_paths.extend(files_in_dir.into_iter().map(|x|x.to_string_lossy().to_string()));
         }
     }
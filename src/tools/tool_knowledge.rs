@@ -8,11 +8,36 @@ use async_trait::async_trait;
 use crate::at_commands::at_commands::AtCommandsContext;
 use crate::tools::tools_description::Tool;
 use crate::call_validation::{ChatMessage, ChatContent, ContextEnum};
+use crate::cached_tokenizers;
+use crate::global_context::try_load_caps_quickly_if_not_present;
+use crate::scratchpads::scratchpad_utils::count_tokens;
 use crate::vecdb::vdb_highlev::memories_search;
+use crate::vecdb::vdb_structs::MemoRecord;
 
 
 pub struct ToolGetKnowledge;
 
+fn format_memory(m: &MemoRecord) -> String {
+    format!("🗃️{}\n{}\n\n", m.memid, m.m_payload)
+}
+
+// Lower distance is more relevant. Memories that don't fit are skipped, so a shorter less relevant one can still get in.
+fn fit_memories_into_budget(mut memories: Vec<MemoRecord>, tokens_budget: usize, count: impl Fn(&str) -> usize) -> Vec<MemoRecord> {
+    memories.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+    let mut tokens_used = 0;
+    let mut fitted = vec![];
+    for m in memories {
+        let n = count(&format_memory(&m));
+        if tokens_used + n > tokens_budget {
+            info!("memory {} doesn't fit, {} tokens, {} of {} used", m.memid, n, tokens_used, tokens_budget);
+            continue;
+        }
+        tokens_used += n;
+        fitted.push(m);
+    }
+    fitted
+}
+
 
 #[async_trait]
 impl Tool for ToolGetKnowledge {
@@ -26,9 +51,9 @@ impl Tool for ToolGetKnowledge {
     ) -> Result<(bool, Vec<ContextEnum>), String> {
         info!("run @get-knowledge {:?}", args);
 
-        let (gcx, _top_n) = {
+        let (gcx, _top_n, current_model) = {
             let ccx_locked = ccx.lock().await;
-            (ccx_locked.global_context.clone(), ccx_locked.top_n)
+            (ccx_locked.global_context.clone(), ccx_locked.top_n, ccx_locked.current_model.clone())
         };
 
         let im_going_to_use_tools = match args.get("im_going_to_use_tools") {
@@ -63,14 +88,20 @@ impl Tool for ToolGetKnowledge {
             .filter(|m| seen_memids.insert(m.memid.clone()))
             .collect();
 
-        let memories_str = unique_memories.iter().map(|m| {
-            let payload: String = m.m_payload.clone();
-            let mut combined = String::new();
-            combined.push_str(&format!("🗃️{}\n", m.memid));
-            combined.push_str(&payload);
-            combined.push_str("\n\n");
-            combined
-        }).collect::<String>();
+        let memories_max_tokens = gcx.read().await.cmdline.memories_max_tokens;
+        let caps = try_load_caps_quickly_if_not_present(gcx.clone(), 0).await.map_err(|x| x.message)?;
+        let fitted_memories = match cached_tokenizers::cached_tokenizer(caps, gcx.clone(), current_model).await {
+            Ok(tokenizer) => {
+                let tokenizer_locked = tokenizer.read().unwrap();
+                fit_memories_into_budget(unique_memories, memories_max_tokens, |text| count_tokens(&tokenizer_locked, text))
+            }
+            Err(e) => {
+                info!("cannot load tokenizer, estimating memories size by characters: {}", e);
+                fit_memories_into_budget(unique_memories, memories_max_tokens, |text| text.chars().count() / 4)
+            }
+        };
+
+        let memories_str = fitted_memories.iter().map(format_memory).collect::<String>();
 
         let mut results = vec![];
         results.push(ContextEnum::ChatMessage(ChatMessage {
@@ -88,3 +119,35 @@ impl Tool for ToolGetKnowledge {
         vec!["vecdb".to_string()]
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(memid: &str, distance: f32, words: usize) -> MemoRecord {
+        MemoRecord {
+            memid: memid.to_string(),
+            distance,
+            m_payload: vec!["frog"; words].join(" "),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_memories_fit_into_budget() {
+        let count_words = |text: &str| text.split_whitespace().count();
+        let memories = vec![
+            memory("far", 0.9, 300),
+            memory("closest", 0.1, 400),
+            memory("close", 0.3, 400),
+            memory("medium", 0.5, 400),
+        ];
+
+        let fitted = fit_memories_into_budget(memories, 1000, count_words);
+
+        let total: usize = fitted.iter().map(|m| count_words(&format_memory(m))).sum();
+        assert!(total <= 1000, "total {} is over the budget", total);
+        assert_eq!(fitted.iter().map(|m| m.memid.as_str()).collect::<Vec<_>>(), vec!["closest", "close"]);
+    }
+}
@@ -6,14 +6,17 @@ use tokio::sync::Mutex as AMutex;
 
 use crate::at_commands::at_commands::AtCommandsContext;
 use crate::call_validation::{ChatMessage, ChatContent, ChatUsage, ContextEnum, DiffChunk, SubchatParameters};
+use crate::diffs::ApplyDiffUnwrapped;
 use crate::files_correction::to_pathbuf_normalize;
+use crate::privacy::load_privacy_if_needed;
 use crate::tools::tool_patch_aux::diff_apply::diff_apply;
 use crate::tools::tool_patch_aux::model_based_edit::partial_edit::partial_edit_tickets_to_chunks;
 use crate::tools::tool_patch_aux::no_model_edit::{full_rewrite_diff, rewrite_symbol_diff};
 use crate::tools::tool_patch_aux::postprocessing_utils::postprocess_diff_chunks;
+use crate::tools::tool_patch_aux::search_replace_parser::{has_search_replace_blocks, search_replace_to_diff_chunks};
 use crate::tools::tool_patch_aux::tickets_parsing::{get_and_correct_active_tickets, get_tickets_from_messages, good_error_text, PatchAction, TicketToApply};
 use crate::tools::tools_description::{MatchConfirmDeny, MatchConfirmDenyResult, Tool};
-use crate::tools::tools_execute::unwrap_subchat_params;
+use crate::tools::tools_execute::{command_should_be_confirmed_by_user, command_should_be_denied, unwrap_subchat_params};
 use crate::integrations::integr_abstract::IntegrationConfirmation;
 
 
@@ -55,6 +58,24 @@ pub async fn process_tickets(
                 }
             }
         }
+        PatchAction::PartialEdit if active_tickets.iter().all(|t| has_search_replace_blocks(&t.code)) => {
+            // the edit is already written as SEARCH/REPLACE blocks, no need to ask a model to rewrite the sections
+            let content = active_tickets.iter()
+                .map(|t| format!("{}\n{}", t.filename_before, t.code))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let privacy_settings = load_privacy_if_needed(gcx.clone()).await;
+            match search_replace_to_diff_chunks(&content, gcx.clone(), privacy_settings).await {
+                Ok(mut chunks) => {
+                    postprocess_diff_chunks(gcx.clone(), &mut chunks)
+                        .await
+                        .map_err(|err| (err, None))
+                }
+                Err(err) => {
+                    Err((err, None))
+                }
+            }
+        }
         PatchAction::PartialEdit => {
             partial_edit_tickets_to_chunks(
                 ccx_subchat.clone(), active_tickets.clone(), params, tool_call_id, usage,
@@ -158,10 +179,24 @@ async fn create_ccx(ccx: Arc<AMutex<AtCommandsContext>>, params: &SubchatParamet
     ).await)))
 }
 
-async fn can_execute_patch(
+fn summarize_apply_outputs(chunks: &Vec<DiffChunk>, outputs: &Vec<ApplyDiffUnwrapped>) -> (bool, String) {
+    let failed = outputs.iter().filter(|o| !o.success).collect::<Vec<_>>();
+    let mut summary = format!("applied {} of {} chunks", outputs.len() - failed.len(), outputs.len());
+    for o in failed.iter() {
+        let chunk = &chunks[o.chunk_id];
+        summary.push_str(&format!(
+            "\nchunk #{} failed: {}:{}-{} {}",
+            o.chunk_id, chunk.file_name, chunk.line1, chunk.line2, o.detail.clone().unwrap_or_default()
+        ));
+    }
+    (failed.is_empty(), summary)
+}
+
+/// Absolute paths of the files the patch is going to change, an error if the patch cannot be executed
+async fn patch_target_paths(
     ccx: Arc<AMutex<AtCommandsContext>>,
     args: &HashMap<String, Value>,
-) -> Result<(), String> {
+) -> Result<Vec<String>, String> {
     let (tickets, path, explanation_mb) = parse_args(args)?;
     let params = unwrap_subchat_params(ccx.clone(), "patch").await?;
     let ccx_subchat = create_ccx(ccx.clone(), &params).await?;
@@ -187,7 +222,53 @@ async fn can_execute_patch(
         return Err("the filename of the ticket(s) you provided doesn't match the filename of the file you provided".to_string());
     }
 
-    Ok(())
+    let mut paths: Vec<String> = vec![];
+    for ticket in active_tickets.iter() {
+        let p = to_pathbuf_normalize(&ticket.filename_before).to_string_lossy().to_string();
+        if !paths.contains(&p) {
+            paths.push(p);
+        }
+    }
+    Ok(paths)
+}
+
+fn default_patch_confirmation() -> IntegrationConfirmation {
+    IntegrationConfirmation {
+        ask_user: vec!["patch*".to_string()],
+        deny: vec![],
+    }
+}
+
+// Deny rules alone don't turn off the default confirmation, only explicit ask_user rules replace it
+fn patch_confirmation_from_cmdline(ask_user: &Vec<String>, deny: &Vec<String>) -> IntegrationConfirmation {
+    IntegrationConfirmation {
+        ask_user: if ask_user.is_empty() { default_patch_confirmation().ask_user } else { ask_user.clone() },
+        deny: deny.clone(),
+    }
+}
+
+// Each path is matched as "patch /absolute/path", one denied path denies the whole patch
+fn match_patch_paths(paths: &Vec<String>, rules: &IntegrationConfirmation) -> MatchConfirmDeny {
+    let mut confirmation: Option<(String, String)> = None;
+    for path in paths.iter() {
+        let command = format!("patch {}", path);
+        let (is_denied, deny_rule) = command_should_be_denied(&command, &rules.deny);
+        if is_denied {
+            return MatchConfirmDeny { result: MatchConfirmDenyResult::DENY, command, rule: deny_rule };
+        }
+        let (needs_confirmation, confirmation_rule) = command_should_be_confirmed_by_user(&command, &rules.ask_user);
+        if needs_confirmation && confirmation.is_none() {
+            confirmation = Some((command, confirmation_rule));
+        }
+    }
+    match confirmation {
+        Some((command, rule)) => MatchConfirmDeny { result: MatchConfirmDenyResult::CONFIRMATION, command, rule },
+        None => MatchConfirmDeny {
+            result: MatchConfirmDenyResult::PASS,
+            command: format!("patch {}", paths.join(" ")),
+            rule: "".to_string(),
+        },
+    }
 }
 
 #[async_trait]
@@ -252,10 +333,12 @@ impl Tool for ToolPatch {
                 return return_cd_instruction_or_error(&err, &cd_instruction, &tool_call_id, &usage);
             }
         };
-        diff_apply(gcx.clone(), &mut diff_chunks).await.map_err(
+        let apply_outputs = diff_apply(gcx.clone(), &mut diff_chunks).await.map_err(
             |err| format!("Couldn't apply the diff: {}", err)
         )?;
-        let results = vec![
+        let (all_applied, summary) = summarize_apply_outputs(&diff_chunks, &apply_outputs);
+        tracing::info!("patch: {}", summary);
+        let mut results = vec![
             ChatMessage {
                 role: "diff".to_string(),
                 content: ChatContent::SimpleText(json!(diff_chunks).to_string()),
@@ -264,49 +347,125 @@ impl Tool for ToolPatch {
                 usage: Some(usage),
                 ..Default::default()
             }
-        ]
-            .into_iter()
-            .map(|x| ContextEnum::ChatMessage(x))
-            .collect::<Vec<_>>();
-        Ok((false, results))
+        ];
+        if !all_applied {
+            results.push(ChatMessage::new("cd_instruction".to_string(), format!(
+                "💿 {}\nRead the failed chunks' files again and call patch for them with corrected tickets.", summary
+            )));
+        }
+        Ok((false, results.into_iter().map(|x| ContextEnum::ChatMessage(x)).collect::<Vec<_>>()))
     }
 
     async fn match_against_confirm_deny(&self, ccx: Arc<AMutex<AtCommandsContext>>, args: &HashMap<String, Value>) -> Result<MatchConfirmDeny, String> {
-        let msgs_len = ccx.lock().await.messages.len();
-
-        // workaround: if messages weren't passed by ToolsPermissionCheckPost, legacy
-        if msgs_len != 0 {
-            // if we cannot execute patch, there's no need for confirmation
-            if let Err(_) = can_execute_patch(ccx.clone(), args).await {
-                return Ok(MatchConfirmDeny {
-                    result: MatchConfirmDenyResult::PASS,
-                    command: "patch".to_string(),
-                    rule: "".to_string(),
-                });
+        let (gcx, msgs_len) = {
+            let ccx_lock = ccx.lock().await;
+            (ccx_lock.global_context.clone(), ccx_lock.messages.len())
+        };
+
+        // workaround: if messages weren't passed by ToolsPermissionCheckPost, legacy, the path argument is all we know
+        let paths = if msgs_len != 0 {
+            match patch_target_paths(ccx.clone(), args).await {
+                Ok(paths) => paths,
+                // if we cannot execute patch, there's no need for confirmation
+                Err(_) => {
+                    return Ok(MatchConfirmDeny {
+                        result: MatchConfirmDenyResult::PASS,
+                        command: "patch".to_string(),
+                        rule: "".to_string(),
+                    });
+                }
             }
-        }
-        Ok(MatchConfirmDeny {
-            result: MatchConfirmDenyResult::CONFIRMATION,
-            command: "patch".to_string(),
-            rule: "default".to_string(),
-        })
+        } else {
+            match parse_args(args) {
+                Ok((_, path, _)) => vec![to_pathbuf_normalize(&path).to_string_lossy().to_string()],
+                Err(_) => {
+                    return Ok(MatchConfirmDeny {
+                        result: MatchConfirmDenyResult::CONFIRMATION,
+                        command: "patch".to_string(),
+                        rule: "default".to_string(),
+                    });
+                }
+            }
+        };
+
+        let rules = {
+            let gcx_locked = gcx.read().await;
+            patch_confirmation_from_cmdline(&gcx_locked.cmdline.patch_ask_user, &gcx_locked.cmdline.patch_deny)
+        };
+        tracing::info!("confirmation: match patch of {:?} against {:?}", paths, rules);
+        Ok(match_patch_paths(&paths, &rules))
     }
 
     fn command_to_match_against_confirm_deny(
         &self,
-        _args: &HashMap<String, Value>,
+        args: &HashMap<String, Value>,
     ) -> Result<String, String> {
-        Ok("patch".to_string())
+        match parse_args(args) {
+            Ok((_, path, _)) => Ok(format!("patch {}", to_pathbuf_normalize(&path).to_string_lossy())),
+            Err(_) => Ok("patch".to_string()),
+        }
     }
 
     fn confirm_deny_rules(&self) -> Option<IntegrationConfirmation> {
-        return Some(IntegrationConfirmation {
-            ask_user: vec!["patch*".to_string()],
-            deny: vec![],
-        });
+        Some(default_patch_confirmation())
     }
 
     fn usage(&mut self) -> &mut Option<ChatUsage> {
         &mut self.usage
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diffs::chunk_id;
+
+    #[test]
+    fn test_patch_paths_confirm_deny() {
+        let rules = IntegrationConfirmation {
+            ask_user: vec!["patch */.github/*".to_string(), "patch */Cargo.toml".to_string()],
+            deny: vec!["patch */Cargo.lock".to_string()],
+        };
+        let paths = |p: &[&str]| p.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        let m = match_patch_paths(&paths(&["/home/frog/pond/src/jump.rs"]), &rules);
+        assert!(matches!(m.result, MatchConfirmDenyResult::PASS));
+
+        let m = match_patch_paths(&paths(&["/home/frog/pond/src/jump.rs", "/home/frog/pond/.github/workflows/ci.yml"]), &rules);
+        assert!(matches!(m.result, MatchConfirmDenyResult::CONFIRMATION));
+        assert_eq!(m.command, "patch /home/frog/pond/.github/workflows/ci.yml");
+        assert_eq!(m.rule, "patch */.github/*");
+
+        let m = match_patch_paths(&paths(&["/home/frog/pond/Cargo.toml", "/home/frog/pond/Cargo.lock"]), &rules);
+        assert!(matches!(m.result, MatchConfirmDenyResult::DENY));
+        assert_eq!(m.rule, "patch */Cargo.lock");
+
+        let m = match_patch_paths(&paths(&["/home/frog/pond/src/jump.rs"]), &default_patch_confirmation());
+        assert!(matches!(m.result, MatchConfirmDenyResult::CONFIRMATION));
+
+        let deny_only = patch_confirmation_from_cmdline(&vec![], &vec!["patch */Cargo.lock".to_string()]);
+        let m = match_patch_paths(&paths(&["/home/frog/pond/src/jump.rs"]), &deny_only);
+        assert!(matches!(m.result, MatchConfirmDenyResult::CONFIRMATION));
+        assert_eq!(m.rule, "patch*");
+        let m = match_patch_paths(&paths(&["/home/frog/pond/Cargo.lock"]), &deny_only);
+        assert!(matches!(m.result, MatchConfirmDenyResult::DENY));
+    }
+
+    #[test]
+    fn test_summarize_apply_outputs_reports_failed_chunk() {
+        let chunks = vec![
+            DiffChunk { file_name: "frog.py".to_string(), file_action: "edit".to_string(), line1: 3, line2: 5, ..Default::default() },
+            DiffChunk { file_name: "frog.py".to_string(), file_action: "edit".to_string(), line1: 12, line2: 14, ..Default::default() },
+        ];
+        let outputs = vec![
+            ApplyDiffUnwrapped { chunk_id: 0, stable_id: chunk_id(&chunks[0]), applied: true, can_unapply: true, success: true, detail: None },
+            ApplyDiffUnwrapped { chunk_id: 1, stable_id: chunk_id(&chunks[1]), applied: false, can_unapply: false, success: false, detail: Some("lines to remove not found".to_string()) },
+        ];
+
+        let (all_applied, summary) = summarize_apply_outputs(&chunks, &outputs);
+
+        assert!(!all_applied);
+        assert_eq!(summary, "applied 1 of 2 chunks\nchunk #1 failed: frog.py:12-14 lines to remove not found");
+    }
+}
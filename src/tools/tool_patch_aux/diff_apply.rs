@@ -90,7 +90,8 @@ async fn write_results_on_disk(
             apply_rename_action(rename_from, rename_into)?;
             if PathBuf::from(rename_into).is_file() {
                 let mut doc = Document::new(&PathBuf::from(rename_into));
-                let text = read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &doc.doc_path).await?.to_string();
+                let max_file_size_bytes = gcx.read().await.cmdline.max_file_size_bytes;
+                let text = read_file_from_disk(load_privacy_if_needed(gcx.clone()).await, &doc.doc_path, max_file_size_bytes).await?.to_string();
                 doc.update_text(&text);
                 docs2index.push(doc);
             }
@@ -112,7 +113,7 @@ async fn write_results_on_disk(
 async fn set_chunks_detail_and_sync_documents_ast_vecdb(
     gcx: Arc<ARwLock<GlobalContext>>,
     new_documents: Vec<Document>,
-    apply_outputs: Vec<ApplyDiffUnwrapped>,
+    apply_outputs: &Vec<ApplyDiffUnwrapped>,
     chunks: &mut Vec<DiffChunk>,
 ) -> Result<(), String> {
     let ast_service_mb = gcx.read().await.ast_service.clone();
@@ -148,7 +149,7 @@ async fn set_chunks_detail_and_sync_documents_ast_vecdb(
 pub async fn diff_apply(
     gcx: Arc<ARwLock<GlobalContext>>,
     chunks: &mut Vec<DiffChunk>,
-) -> Result<(), String> {
+) -> Result<Vec<ApplyDiffUnwrapped>, String> {
     correct_and_validate_chunks(gcx.clone(), chunks).await?;
     let (results, outputs) = read_files_n_apply_diff_chunks(
         gcx.clone(),
@@ -161,5 +162,6 @@ pub async fn diff_apply(
         gcx.clone(), results.clone(),
     ).await?;
     let outputs_unwrapped = unwrap_diff_apply_outputs(outputs, chunks.clone());
-    set_chunks_detail_and_sync_documents_ast_vecdb(gcx.clone(), new_documents, outputs_unwrapped, chunks).await
+    set_chunks_detail_and_sync_documents_ast_vecdb(gcx.clone(), new_documents, &outputs_unwrapped, chunks).await?;
+    Ok(outputs_unwrapped)
 }
@@ -1,5 +1,6 @@
 use crate::call_validation::DiffChunk;
 use itertools::Itertools;
+use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -122,6 +123,57 @@ pub fn diff_blocks_to_diff_chunks(diff_blocks: &Vec<DiffBlock>) -> Vec<DiffChunk
 }
 
 
+fn chunk_display_name(chunk: &DiffChunk) -> String {
+    let first_line = chunk.lines_remove.lines().next().unwrap_or("").trim();
+    format!("hunk at lines {}-{} (`{}`)", chunk.line1, chunk.line2.saturating_sub(1).max(chunk.line1), first_line)
+}
+
+/// Sorts `edit` chunks of every file by `line1` and rejects chunks whose ranges overlap,
+/// identical duplicates are merged into one.
+pub fn sort_and_merge_overlapping_chunks(chunks: Vec<DiffChunk>) -> Result<Vec<DiffChunk>, String> {
+    let mut file_order: Vec<String> = vec![];
+    let mut per_file: HashMap<String, Vec<DiffChunk>> = HashMap::new();
+    let mut other_chunks = vec![];
+    for chunk in chunks {
+        if chunk.file_action != "edit" {
+            other_chunks.push(chunk);
+            continue;
+        }
+        if !per_file.contains_key(&chunk.file_name) {
+            file_order.push(chunk.file_name.clone());
+        }
+        per_file.entry(chunk.file_name.clone()).or_insert(vec![]).push(chunk);
+    }
+
+    let mut result = vec![];
+    let mut errors = vec![];
+    for file_name in file_order {
+        let mut file_chunks = per_file.remove(&file_name).unwrap_or_default();
+        file_chunks.sort_by_key(|c| (c.line1, c.line2));
+        file_chunks.dedup();
+        let mut merged: Vec<DiffChunk> = vec![];
+        for chunk in file_chunks {
+            if let Some(prev) = merged.last() {
+                let prev_removes_lines = prev.line2 > prev.line1;
+                if prev_removes_lines && chunk.line1 < prev.line2 {
+                    errors.push(format!(
+                        "{}: {} overlaps with {}",
+                        file_name, chunk_display_name(prev), chunk_display_name(&chunk)
+                    ));
+                    continue;
+                }
+            }
+            merged.push(chunk);
+        }
+        result.extend(merged);
+    }
+    if !errors.is_empty() {
+        return Err(format!("Conflicting hunks were generated, they edit the same lines:\n{}", errors.join("\n")));
+    }
+    result.extend(other_chunks);
+    Ok(result)
+}
+
 pub fn chunks_from_diffs(file_path: PathBuf, diffs: Vec<diff::Result<&str>>) -> Result<Vec<DiffChunk>, String> {
     let mut line_num: usize = 0;
     let mut blocks = vec![];
@@ -174,4 +226,47 @@ pub fn chunks_from_diffs(file_path: PathBuf, diffs: Vec<diff::Result<&str>>) ->
     }
 
     Ok(diff_blocks_to_diff_chunks(&blocks))
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_chunk(line1: usize, line2: usize, lines_remove: &str, lines_add: &str) -> DiffChunk {
+        DiffChunk {
+            file_name: "frog.py".to_string(),
+            file_action: "edit".to_string(),
+            line1,
+            line2,
+            lines_remove: lines_remove.to_string(),
+            lines_add: lines_add.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_overlapping_hunks_are_rejected() {
+        let chunks = vec![
+            edit_chunk(3, 6, "    x = 1\n    y = 2\n    z = 3\n", "    x = 10\n"),
+            edit_chunk(2, 4, "def jump():\n    x = 1\n", "def jump_high():\n    x = 1\n"),
+        ];
+
+        let err = sort_and_merge_overlapping_chunks(chunks).unwrap_err();
+
+        assert!(err.contains("hunk at lines 2-3 (`def jump():`) overlaps with hunk at lines 3-5 (`x = 1`)"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_non_overlapping_hunks_are_sorted() {
+        let chunks = vec![
+            edit_chunk(5, 6, "    return x\n", "    return x * 2\n"),
+            edit_chunk(2, 3, "def jump():\n", "def jump_high():\n"),
+            edit_chunk(2, 3, "def jump():\n", "def jump_high():\n"),
+        ];
+
+        let result = sort_and_merge_overlapping_chunks(chunks).unwrap();
+
+        assert_eq!(result.iter().map(|c| c.line1).collect::<Vec<_>>(), vec![2, 5]);
+    }
+}
@@ -6,3 +6,4 @@ pub mod postprocessing_utils;
 pub mod tickets_parsing;
 pub mod fs_utils;
 pub mod diff_apply;
+pub mod search_replace_parser;
@@ -2,15 +2,16 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::call_validation::DiffChunk;
-use crate::tools::tool_patch_aux::diff_structs::{diff_blocks_to_diff_chunks, DiffBlock, DiffLine, LineType};
+use crate::tools::tool_patch_aux::diff_structs::{diff_blocks_to_diff_chunks, sort_and_merge_overlapping_chunks, DiffBlock, DiffLine, LineType};
 use itertools::Itertools;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock as ARwLock;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 use crate::global_context::GlobalContext;
 use crate::tools::tool_patch_aux::fs_utils::read_file;
-use crate::tools::tool_patch_aux::postprocessing_utils::{minimal_common_indent, place_indent};
+use crate::tools::tool_patch_aux::postprocessing_utils::{file_indent_unit, minimal_common_indent, minimal_common_indent_width, place_indent, place_indent_width, reindent_to_unit};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum SectionType {
@@ -18,16 +19,50 @@ pub enum SectionType {
     Modified,
 }
 
+const NO_NEWLINE_AT_EOF: &str = "\\ No newline at end of file";
+// a fence like ```numbered says the block was copied from a view with line numbers
+const NUMBERED_LINES_HINTS: [&str; 2] = ["numbered", "line-numbers"];
+
 #[derive(Clone, Debug)]
 pub struct EditSection {
     hunk: Vec<String>,
     type_: SectionType,
+    no_newline_at_eof: bool,
+}
+
+fn fence_says_numbered(fence_line: &str) -> bool {
+    let info = fence_line.trim().trim_start_matches('`').to_lowercase();
+    info.split(|c: char| c.is_whitespace() || c == ',').any(|word| NUMBERED_LINES_HINTS.contains(&word))
+}
+
+// Strips `NNN: ` or `NNN|` prefixes, only if every non-empty line has one with the same separator, otherwise
+// the numbers are probably a part of the code and the hunk is returned as is.
+fn strip_line_number_prefixes(hunk: Vec<&str>) -> Vec<&str> {
+    let re = Regex::new(r"^\s*\d+ ?([:|]) ?").unwrap();
+    let mut separator: Option<String> = None;
+    let mut stripped = vec![];
+    for &line in hunk.iter() {
+        if line.trim().is_empty() {
+            stripped.push(line);
+            continue;
+        }
+        let Some(caps) = re.captures(line) else {
+            return hunk;
+        };
+        let sep = caps[1].to_string();
+        if *separator.get_or_insert(sep.clone()) != sep {
+            return hunk;
+        }
+        stripped.push(&line[caps[0].len()..]);
+    }
+    stripped
 }
 
 fn process_fenced_block(
     lines: &[&str],
     start_line_num: usize,
     is_original: bool,
+    strip_line_numbers: bool,
 ) -> (usize, EditSection) {
     let mut line_num = start_line_num;
     while line_num < lines.len() {
@@ -36,11 +71,16 @@ fn process_fenced_block(
         }
         line_num += 1;
     }
+    // models copy this marker from unified diffs, it's not a part of the code
+    let (markers, hunk): (Vec<&str>, Vec<&str>) = lines[start_line_num..line_num.min(lines.len())].iter()
+        .partition(|x| x.trim() == NO_NEWLINE_AT_EOF);
+    let hunk = if strip_line_numbers { strip_line_number_prefixes(hunk) } else { hunk };
     (
         line_num + 1,
         EditSection {
-            hunk: lines[start_line_num..line_num].iter().map(|x| x.to_string()).collect(),
+            hunk: hunk.iter().map(|x| x.to_string()).collect(),
             type_: if is_original { SectionType::Original } else { SectionType::Modified },
+            no_newline_at_eof: !markers.is_empty(),
         }
     )
 }
@@ -52,14 +92,15 @@ fn get_edit_sections(content: &str) -> Vec<EditSection> {
     while line_num < lines.len() - 1 {
         while line_num < lines.len() - 1 {
             let line = lines[line_num];
+            let strip_line_numbers = lines.get(line_num + 1).map(|fence| fence_says_numbered(fence)).unwrap_or(false);
             if line.contains("Original Section") {
-                let (new_line_num, section) = process_fenced_block(&lines, line_num + 2, true);
+                let (new_line_num, section) = process_fenced_block(&lines, line_num + 2, true, strip_line_numbers);
                 line_num = new_line_num;
                 sections.push(section);
                 break;
             }
             if line.contains("Modified Section") {
-                let (new_line_num, section) = process_fenced_block(&lines, line_num + 2, false);
+                let (new_line_num, section) = process_fenced_block(&lines, line_num + 2, false, strip_line_numbers);
                 line_num = new_line_num;
                 sections.push(section);
                 break;
@@ -114,26 +155,120 @@ fn search_block_line_by_line(file_text: &Vec<String>, block_to_find: &Vec<String
     }
 }
 
+pub(crate) fn find_section_start(
+    file_lines: &Vec<String>,
+    hunk: &Vec<String>,
+    fuzzy_tolerance: Option<f64>,
+) -> Option<usize> {
+    let hunk_span = hunk.iter()
+        .map(|x| x.trim_start().to_string())
+        .collect::<Vec<_>>();
+    for file_line_idx in 0..=file_lines.len().saturating_sub(hunk.len()) {
+        let file_lines_span = file_lines[file_line_idx..(file_line_idx + hunk.len()).min(file_lines.len())]
+            .iter()
+            .map(|x| x.trim_start().to_string())
+            .collect::<Vec<_>>();
+        if file_lines_span == hunk_span {
+            return Some(file_line_idx);
+        }
+    }
+
+    let tolerance = fuzzy_tolerance.filter(|t| *t > 0.0)?;
+    if hunk.is_empty() || file_lines.len() < hunk.len() {
+        return None;
+    }
+    let mut best: Option<(usize, f64)> = None;
+    for file_line_idx in 0..=file_lines.len() - hunk.len() {
+        let similarities = hunk_span.iter().zip(file_lines[file_line_idx..].iter())
+            .map(|(h, f)| strsim::normalized_damerau_levenshtein(h.trim(), f.trim()))
+            .collect::<Vec<_>>();
+        if similarities.iter().any(|s| *s < tolerance) {
+            continue;
+        }
+        let score = similarities.iter().sum::<f64>() / similarities.len() as f64;
+        if best.map(|(_, best_score)| score > best_score).unwrap_or(true) {
+            best = Some((file_line_idx, score));
+        }
+    }
+    let (start, _) = best?;
+    for (idx, (h, f)) in hunk_span.iter().zip(file_lines[start..].iter()).enumerate() {
+        if h.trim() != f.trim() {
+            info!("section line matched approximately at file line {}:\n  section: {:?}\n  file:    {:?}", start + idx + 1, h.trim(), f.trim());
+        }
+    }
+    Some(start)
+}
+
+fn section_to_diff_block(
+    filename: &PathBuf,
+    file_lines: &Vec<String>,
+    file_ends_with_newline: bool,
+    start_offset: usize,
+    orig_section: &EditSection,
+    modified_section: &EditSection,
+    modified_section_hunk: &Vec<String>,
+    hunk_idx: usize,
+) -> DiffBlock {
+    let orig_end = start_offset + orig_section.hunk.len();
+    let mut minus_lines = file_lines[start_offset..orig_end].to_vec();
+    let mut plus_lines = modified_section_hunk.clone();
+    // the file is split by '\n' when applying, so the trailing newline is an empty last line we can remove or add
+    if orig_end == file_lines.len() {
+        if modified_section.no_newline_at_eof && file_ends_with_newline {
+            minus_lines.push("".to_string());
+        } else if orig_section.no_newline_at_eof && !modified_section.no_newline_at_eof && !file_ends_with_newline {
+            plus_lines.push("".to_string());
+        }
+    }
+    DiffBlock {
+        file_name_before: filename.clone(),
+        file_name_after: filename.clone(),
+        action: "edit".to_string(),
+        diff_lines: minus_lines
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| DiffLine {
+                line: x.clone(),
+                line_type: LineType::Minus,
+                file_line_num_idx: Some(start_offset + idx),
+                correct_spaces_offset: None,
+            })
+            .chain(plus_lines
+                .iter()
+                .map(|x| DiffLine {
+                    line: x.clone(),
+                    line_type: LineType::Plus,
+                    file_line_num_idx: Some(start_offset),
+                    correct_spaces_offset: None,
+                }))
+            .collect::<Vec<_>>(),
+        hunk_idx,
+        file_lines: Arc::new(vec![]),
+    }
+}
+
 async fn sections_to_diff_blocks(
     gcx: Arc<ARwLock<GlobalContext>>,
     sections: &Vec<EditSection>,
     filename: &PathBuf,
+    fuzzy_tolerance: Option<f64>,
 ) -> Result<Vec<DiffBlock>, String> {
     let mut diff_blocks = vec![];
-    let file_lines = read_file(gcx.clone(), filename.to_string_lossy().to_string())
-        .await
-        .map(|x| x.file_content.lines().into_iter()
-            .map(|x| {
-                if let Some(stripped_row) = x.to_string()
-                    .replace("\r\n", "\n")
-                    .strip_suffix("\n") {
-                    stripped_row.to_string()
-                } else {
-                    x.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-        )?;
+    let file_content = read_file(gcx.clone(), filename.to_string_lossy().to_string()).await?.file_content;
+    let file_ends_with_newline = file_content.ends_with('\n');
+    let file_lines = file_content.lines().into_iter()
+        .map(|x| {
+            if let Some(stripped_row) = x.to_string()
+                .replace("\r\n", "\n")
+                .strip_suffix("\n") {
+                stripped_row.to_string()
+            } else {
+                x.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    let indent_unit = file_indent_unit(filename, &file_lines.join("\n"));
+    let tab_width = gcx.read().await.cmdline.patch_tab_width.max(1);
     let mut errors: Vec<String> = vec![];
     for (idx, sections) in sections.iter().chunks(2).into_iter()
         .map(|x| x.collect::<Vec<_>>()).enumerate() {
@@ -142,50 +277,26 @@ async fn sections_to_diff_blocks(
         if orig_section.type_ != SectionType::Original || modified_section.type_ != SectionType::Modified {
             return Err("section types are messed up, try to regenerate the diff".to_string());
         }
-        let orig_section_span = orig_section.hunk.iter()
-            .map(|x| x.trim_start().to_string())
-            .collect::<Vec<_>>();
-        let mut start_offset = None;
-        for file_line_idx in 0..=file_lines.len().saturating_sub(orig_section.hunk.len()) {
-            let file_lines_span = file_lines[file_line_idx..(file_line_idx + orig_section.hunk.len()).min(file_lines.len())]
-                .iter()
-                .map(|x| x.trim_start().to_string())
-                .collect::<Vec<_>>();
-            if file_lines_span == orig_section_span {
-                start_offset = Some(file_line_idx);
-                break;
-            }
-        }
+        let start_offset = find_section_start(&file_lines, &orig_section.hunk, fuzzy_tolerance);
         if let Some(start_offset) = start_offset {
             let file_section = file_lines[start_offset..start_offset + orig_section.hunk.len()].to_vec();
-            let (indent_spaces, indent_tabs) = minimal_common_indent(&file_section.iter().map(|x| x.as_str()).collect::<Vec<_>>());
-            let modified_section_hunk = place_indent(&modified_section.hunk.iter().map(|x| x.as_str()).collect::<Vec<_>>(), indent_spaces, indent_tabs);
-            diff_blocks.push(DiffBlock {
-                file_name_before: filename.clone(),
-                file_name_after: filename.clone(),
-                action: "edit".to_string(),
-                diff_lines: file_lines
-                    [start_offset..start_offset + orig_section.hunk.len()]
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, x)| DiffLine {
-                        line: x.clone(),
-                        line_type: LineType::Minus,
-                        file_line_num_idx: Some(start_offset + idx),
-                        correct_spaces_offset: None,
-                    })
-                    .chain(modified_section_hunk
-                        .iter()
-                        .map(|x| DiffLine {
-                            line: x.clone(),
-                            line_type: LineType::Plus,
-                            file_line_num_idx: Some(start_offset),
-                            correct_spaces_offset: None,
-                        }))
-                    .collect::<Vec<_>>(),
-                hunk_idx: idx,
-                file_lines: Arc::new(vec![]),
-            })
+            let file_section = file_section.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+            let modified_hunk = modified_section.hunk.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+            let modified_section_hunk = match &indent_unit {
+                Some(unit) => {
+                    let modified_hunk = reindent_to_unit(&modified_hunk, unit);
+                    let target_width = minimal_common_indent_width(&file_section, tab_width);
+                    place_indent_width(&modified_hunk.iter().map(|x| x.as_str()).collect::<Vec<_>>(), target_width, unit, tab_width)
+                }
+                None => {
+                    let (indent_spaces, indent_tabs) = minimal_common_indent(&file_section);
+                    place_indent(&modified_hunk, indent_spaces, indent_tabs)
+                }
+            };
+            diff_blocks.push(section_to_diff_block(
+                filename, &file_lines, file_ends_with_newline, start_offset,
+                orig_section, modified_section, &modified_section_hunk, idx,
+            ))
         } else {
             match search_block_line_by_line(&file_lines, &orig_section.hunk) {
                 Ok(res) => {
@@ -300,17 +411,101 @@ If there are multiple functions in one section, create individual sections for e
         gcx: Arc<ARwLock<GlobalContext>>,
         content: &str,
         filename: &PathBuf,
+        fuzzy_tolerance: Option<f64>,
     ) -> Result<Vec<DiffChunk>, String> {
         let sections = get_edit_sections(content);
         if sections.is_empty() {
             warn!("no sections found, probably an empty diff");
             return Ok(vec![]);
         }
-        let diff_blocks = sections_to_diff_blocks(gcx, &sections, &filename).await?;
+        let diff_blocks = sections_to_diff_blocks(gcx, &sections, &filename, fuzzy_tolerance).await?;
         let chunks = diff_blocks_to_diff_chunks(&diff_blocks)
             .into_iter()
             .unique()
             .collect::<Vec<_>>();
-        Ok(chunks)
+        sort_and_merge_overlapping_chunks(chunks)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_lines(text: &str) -> Vec<String> {
+        text.lines().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_section_start_exact_by_default() {
+        let file_lines = to_lines("def jump():\n    x = 1\n    return x\n");
+        let hunk = to_lines("    x = 1;\n    return x");
+
+        assert_eq!(find_section_start(&file_lines, &to_lines("x = 1\nreturn x"), None), Some(1));
+        assert_eq!(find_section_start(&file_lines, &hunk, None), None);
+        assert_eq!(find_section_start(&file_lines, &hunk, Some(0.8)), Some(1));
+        assert_eq!(find_section_start(&file_lines, &to_lines("    y = 7\n    yield y"), Some(0.8)), None);
+    }
+
+    #[test]
+    fn test_numbered_lines_stripped_when_hinted() {
+        let clean = "### Original Section (to be replaced)\n```\ndef croak():\n\n    print(\"croak\")\n```\n### Modified Section (to replace with)\n```\ndef croak():\n\n    print(\"ribbit\")\n```\n";
+        let numbered = "### Original Section (to be replaced)\n```numbered\n 7: def croak():\n 8:\n 9:     print(\"croak\")\n```\n### Modified Section (to replace with)\n```python numbered\n7| def croak():\n8|\n9|     print(\"ribbit\")\n```\n";
+        let not_hinted = numbered.replace("```numbered", "```").replace("```python numbered", "```python");
+
+        let hunks = |content: &str| get_edit_sections(content).into_iter().map(|s| s.hunk).collect::<Vec<_>>();
+        assert_eq!(hunks(numbered), hunks(clean));
+        assert_ne!(hunks(&not_hinted), hunks(clean));
+        // a dict literal is not a line-numbered view
+        assert_eq!(strip_line_number_prefixes(vec!["1: \"one\",", "two = 2"]), vec!["1: \"one\",", "two = 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_modified_section_keeps_alignment_in_tab_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frog.py");
+        let file_text = "class Frog:\n\tdef jump(self, pond):\n\t\tself.x = clip(self.x,\n\t\t              pond.width)\n\t\treturn self.x\n";
+        std::fs::write(&path, file_text).unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(dir.path(), vec![path.clone()]).await;
+        let content = concat!(
+            "### Original Section (to be replaced)\n```\nself.x = clip(self.x,\n              pond.width)\nreturn self.x\n```\n",
+            "### Modified Section (to replace with)\n```\nself.x = clip(self.x + self.vx,\n              pond.width)\nif self.x > 0:\n    return self.x\n```\n",
+        );
+
+        let blocks = sections_to_diff_blocks(gcx, &get_edit_sections(content), &path, None).await.unwrap();
+
+        let plus_lines = blocks[0].diff_lines.iter()
+            .filter(|l| l.line_type == LineType::Plus)
+            .map(|l| l.line.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(plus_lines, vec![
+            "\t\tself.x = clip(self.x + self.vx,",
+            "\t\t              pond.width)",
+            "\t\tif self.x > 0:",
+            "\t\t\treturn self.x",
+        ]);
+    }
+
+    #[test]
+    fn test_no_newline_at_eof_marker() {
+        let file_text = "def croak():\n    print(\"croak\")\n".to_string();
+        let file_lines = to_lines(&file_text);
+        let content = "### Original Section (to be replaced)\n```\n    print(\"croak\")\n```\n### Modified Section (to replace with)\n```\n    print(\"ribbit\")\n\\ No newline at end of file\n```\n";
+
+        let sections = get_edit_sections(content);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].hunk, vec!["    print(\"ribbit\")".to_string()]);
+        assert!(sections[1].no_newline_at_eof);
+
+        let block = section_to_diff_block(
+            &PathBuf::from("frog.py"), &file_lines, true, 1,
+            &sections[0], &sections[1], &sections[1].hunk, 0,
+        );
+        let chunks = diff_blocks_to_diff_chunks(&vec![block]);
+        let (results, outputs) = crate::diffs::apply_diff_chunks_to_text(
+            &file_text, chunks.iter().enumerate().collect(), vec![], 1,
+        );
+        assert_eq!(outputs.get(&0), Some(&crate::diffs::ApplyDiffOutput::Ok()));
+        assert_eq!(results[0].file_text.clone().unwrap(), "def croak():\n    print(\"ribbit\")");
     }
 }
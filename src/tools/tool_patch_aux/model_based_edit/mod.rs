@@ -1,4 +1,4 @@
 mod model_execution;
-mod blocks_of_code_parser;
+pub(crate) mod blocks_of_code_parser;
 mod whole_file_parser;
 pub mod partial_edit;
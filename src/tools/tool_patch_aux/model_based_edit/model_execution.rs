@@ -141,11 +141,12 @@ pub async fn get_valid_chunks_from_messages(
         let filename = filename.clone();
         let content = m.content.clone();
         let gcx = ccx.lock().await.global_context.clone();
+        let fuzzy_tolerance = Some(gcx.read().await.cmdline.patch_fuzzy_anchor_tolerance).filter(|t| *t > 0.0);
         tasks.push(tokio::spawn(async move {
             if use_whole_file_parser {
                 WholeFileParser::parse_message(gcx.clone(), content.content_text_only().as_str(), &filename).await
             } else {
-                BlocksOfCodeParser::parse_message(gcx.clone(), content.content_text_only().as_str(), &filename).await
+                BlocksOfCodeParser::parse_message(gcx.clone(), content.content_text_only().as_str(), &filename, fuzzy_tolerance).await
             }
         }));
     }
@@ -203,6 +204,7 @@ pub async fn execute_blocks_of_code_patch(
         Some(usage),
         Some(tool_call_id.clone()),
         Some(format!("{log_prefix}-patch")),
+        None,
     ).await.map_err(|e| (e, None))?;
 
     let last_messages = response.iter()
@@ -257,6 +259,7 @@ pub async fn execute_blocks_of_code_patch(
         Some(usage),
         Some(tool_call_id.clone()),
         Some(format!("{log_prefix}-patch")),
+        None,
     ).await.map_err(|e| (e, None))?;
     let last_messages = response.iter()
         .filter_map(|x| x.iter().last())
@@ -324,6 +327,7 @@ pub async fn execute_whole_file_patch(
         Some(usage),
         Some(tool_call_id.clone()),
         Some(format!("{log_prefix}-patch")),
+        None,
     ).await.map_err(|e| (e, None))?;
     let last_messages = response.iter()
         .filter_map(|x| x.iter().last())
@@ -6,7 +6,7 @@ use crate::call_validation::DiffChunk;
 use crate::global_context::GlobalContext;
 use crate::tools::tool_patch_aux::diff_structs::chunks_from_diffs;
 use crate::tools::tool_patch_aux::fs_utils::read_file;
-use crate::tools::tool_patch_aux::postprocessing_utils::{minimal_common_indent, place_indent};
+use crate::tools::tool_patch_aux::postprocessing_utils::{file_indent_unit, minimal_common_indent, place_indent, reindent_to_unit};
 use crate::tools::tool_patch_aux::tickets_parsing::TicketToApply;
 
 pub async fn full_rewrite_diff(
@@ -54,7 +54,11 @@ pub async fn rewrite_symbol_diff(
     let ticket_code = ticket.code.clone();
     let ticket_line_ending = if ticket_code.contains("\r\n") { "\r\n" } else { "\n" };
     let ticket_code_lines = ticket_code.split(ticket_line_ending).collect::<Vec<&str>>();
-    let ticket_code_lines = place_indent(&ticket_code_lines, indent_spaces, indent_tabs);
+    let ticket_code_lines = match file_indent_unit(&context_file_path, &file_text) {
+        Some(unit) => reindent_to_unit(&ticket_code_lines, &unit),
+        None => ticket_code_lines.iter().map(|x| x.to_string()).collect(),
+    };
+    let ticket_code_lines = place_indent(&ticket_code_lines.iter().map(|x| x.as_str()).collect::<Vec<_>>(), indent_spaces, indent_tabs);
 
     let new_code_lines = file_lines[..symbol.full_line1() - 1].iter()
         .map(|s| s.to_string())
@@ -5,7 +5,8 @@ use crate::diffs::{apply_diff_chunks_to_text, correct_and_validate_chunks, unwra
 use crate::global_context::GlobalContext;
 use crate::tools::tool_patch_aux::ast_lint::{lint_and_get_error_messages, parse_and_get_error_symbols};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use glob::Pattern;
 use std::sync::Arc;
 use tokio::sync::RwLock as ARwLock;
 use tracing::warn;
@@ -42,6 +43,202 @@ pub fn place_indent(code_lines: &[&str], indent_spaces: usize, indent_tabs: usiz
     }).collect()
 }
 
+pub fn indent_width(line: &str, tab_width: usize) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_width - width % tab_width,
+            _ => break,
+        }
+    }
+    width
+}
+
+pub fn minimal_common_indent_width(lines: &[&str], tab_width: usize) -> usize {
+    lines.iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_width(l, tab_width))
+        .min()
+        .unwrap_or(0)
+}
+
+// Removes `columns` columns of leading whitespace, a tab that crosses the boundary leaves its remainder as spaces
+fn strip_indent_columns(line: &str, columns: usize, tab_width: usize) -> String {
+    let mut width = 0;
+    for (idx, c) in line.char_indices() {
+        if width >= columns || (c != ' ' && c != '\t') {
+            return " ".repeat(width - columns.min(width)) + &line[idx..];
+        }
+        width += if c == '\t' { tab_width - width % tab_width } else { 1 };
+    }
+    "".to_string()
+}
+
+// Like place_indent, but measures indentation in columns so tabs and mixed indentation shift correctly.
+// Only the common indent is replaced with the file's unit, whatever is deeper (nested levels, alignment spaces)
+// stays as it is in the hunk, reindent_to_unit() takes care of the nested levels.
+pub fn place_indent_width(code_lines: &[&str], target_width: usize, unit: &IndentUnit, tab_width: usize) -> Vec<String> {
+    let min_width = minimal_common_indent_width(code_lines, tab_width);
+    let target_indent = match unit {
+        IndentUnit::Tabs => "\t".repeat(target_width / tab_width) + &" ".repeat(target_width % tab_width),
+        IndentUnit::Spaces(_) => " ".repeat(target_width),
+    };
+    code_lines.iter().map(|line| {
+        if line.trim().is_empty() {
+            return "".to_string();
+        }
+        format!("{}{}", target_indent, strip_indent_columns(line, min_width, tab_width))
+    }).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndentUnit {
+    Tabs,
+    Spaces(usize),
+}
+
+impl IndentUnit {
+    pub fn as_str(&self) -> String {
+        match self {
+            IndentUnit::Tabs => "\t".to_string(),
+            IndentUnit::Spaces(n) => " ".repeat(*n),
+        }
+    }
+}
+
+fn leading_spaces_unit(lines: &[&str]) -> Option<usize> {
+    let mut steps: HashMap<usize, usize> = HashMap::new();
+    let mut prev = 0;
+    for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+        let spaces = line.chars().take_while(|c| *c == ' ').count();
+        // wider steps are alignment, nobody indents by more than 8
+        if spaces > prev && spaces - prev <= 8 {
+            *steps.entry(spaces - prev).or_insert(0) += 1;
+        }
+        prev = spaces;
+    }
+    // the most common step between a line and the one above it, aligned continuation lines are rare steps
+    steps.into_iter()
+        .max_by(|(step_a, cnt_a), (step_b, cnt_b)| cnt_a.cmp(cnt_b).then(step_b.cmp(step_a)))
+        .map(|(step, _)| step)
+}
+
+pub fn detect_indent_unit(file_text: &str) -> Option<IndentUnit> {
+    let lines = file_text.lines().filter(|l| !l.trim().is_empty()).collect::<Vec<_>>();
+    let tab_lines = lines.iter().filter(|l| l.starts_with('\t')).count();
+    let space_lines = lines.iter().filter(|l| l.starts_with(' ')).count();
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines >= space_lines {
+        Some(IndentUnit::Tabs)
+    } else {
+        leading_spaces_unit(&lines).map(IndentUnit::Spaces)
+    }
+}
+
+fn editorconfig_section_matches(section: &str, file_name: &str) -> bool {
+    // expand a single `{a,b}` group, that's what most .editorconfig files use
+    let patterns = match (section.find('{'), section.find('}')) {
+        (Some(open), Some(close)) if open < close => {
+            section[open + 1..close].split(',')
+                .map(|alt| format!("{}{}{}", &section[..open], alt, &section[close + 1..]))
+                .collect::<Vec<_>>()
+        }
+        _ => vec![section.to_string()],
+    };
+    patterns.iter().any(|p| {
+        let p = p.trim_start_matches("**/").trim_start_matches('/');
+        Pattern::new(p).map(|pat| pat.matches(file_name)).unwrap_or(false)
+    })
+}
+
+pub fn editorconfig_indent_unit(file_path: &Path) -> Option<IndentUnit> {
+    let file_name = file_path.file_name()?.to_string_lossy().to_string();
+    let mut configs = vec![];
+    for dir in file_path.ancestors().skip(1) {
+        if let Ok(text) = std::fs::read_to_string(dir.join(".editorconfig")) {
+            let is_root = text.lines()
+                .map(|l| l.trim().replace(' ', "").to_lowercase())
+                .any(|l| l == "root=true");
+            configs.push(text);
+            if is_root {
+                break;
+            }
+        }
+    }
+
+    let mut indent_style: Option<String> = None;
+    let mut indent_size: Option<usize> = None;
+    // the nearest .editorconfig wins, so apply them from the farthest one
+    for text in configs.iter().rev() {
+        let mut section_matches = false;
+        for line in text.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section_matches = editorconfig_section_matches(&line[1..line.len() - 1], &file_name);
+                continue;
+            }
+            if !section_matches {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim().to_lowercase();
+                match key.trim().to_lowercase().as_str() {
+                    "indent_style" => indent_style = Some(value),
+                    "indent_size" => indent_size = value.parse::<usize>().ok().or(indent_size),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    match indent_style.as_deref() {
+        Some("tab") => Some(IndentUnit::Tabs),
+        Some("space") => Some(IndentUnit::Spaces(indent_size.unwrap_or(4))),
+        _ => None,
+    }
+}
+
+pub fn file_indent_unit(file_path: &Path, file_text: &str) -> Option<IndentUnit> {
+    editorconfig_indent_unit(file_path).or_else(|| detect_indent_unit(file_text))
+}
+
+// Converts the hunk's indentation to the file's unit if the styles differ (tabs vs spaces).
+// Continuation lines keep their alignment: only the indent of the line above them is converted.
+pub fn reindent_to_unit(code_lines: &[&str], unit: &IndentUnit) -> Vec<String> {
+    let uses_tabs = code_lines.iter().any(|l| l.starts_with('\t'));
+    let uses_spaces = code_lines.iter().any(|l| l.starts_with(' ') && !l.trim().is_empty());
+    let styles_differ = match unit {
+        IndentUnit::Tabs => uses_spaces,
+        IndentUnit::Spaces(_) => uses_tabs,
+    };
+    if !styles_differ {
+        return code_lines.iter().map(|x| x.to_string()).collect();
+    }
+    let source_width = leading_spaces_unit(code_lines).unwrap_or(4);
+    // (width, levels) of the last line indented with whole levels
+    let mut last_structural = (0, 0);
+    code_lines.iter().map(|line| {
+        let indent = line.chars().take_while(|c| *c == ' ' || *c == '\t').collect::<String>();
+        let (levels, alignment) = if line.starts_with('\t') {
+            let tabs = indent.chars().take_while(|c| *c == '\t').count();
+            (tabs, indent.len() - tabs)
+        } else if indent.len() % source_width == 0 {
+            last_structural = (indent.len(), indent.len() / source_width);
+            (indent.len() / source_width, 0)
+        } else if indent.len() > last_structural.0 {
+            (last_structural.1, indent.len() - last_structural.0)
+        } else {
+            (indent.len() / source_width, indent.len() % source_width)
+        };
+        format!("{}{}{}", unit.as_str().repeat(levels), " ".repeat(alignment), &line[indent.len()..])
+    }).collect()
+}
+
 pub async fn does_doc_have_symbol(
     gcx: Arc<ARwLock<GlobalContext>>,
     symbol: &String,
@@ -200,3 +397,77 @@ pub async fn fill_out_already_applied_status(
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_hunk_into_tab_file_uses_tabs() {
+        let file_text = "class Frog:\n\tdef jump(self):\n\t\treturn 1\n";
+        let file_section = vec!["\tdef jump(self):", "\t\treturn 1"];
+        let hunk = vec!["    def jump(self):", "        x = 2", "        return x"];
+
+        let unit = file_indent_unit(&PathBuf::from("/nonexistent/frog.py"), file_text).unwrap();
+        assert_eq!(unit, IndentUnit::Tabs);
+        let hunk = reindent_to_unit(&hunk, &unit);
+        let (indent_spaces, indent_tabs) = minimal_common_indent(&file_section);
+        let result = place_indent(&hunk.iter().map(|x| x.as_str()).collect::<Vec<_>>(), indent_spaces, indent_tabs);
+
+        assert_eq!(result, vec!["\tdef jump(self):", "\t\tx = 2", "\t\treturn x"]);
+    }
+
+    #[test]
+    fn test_indent_unit_ignores_aligned_continuation_lines() {
+        let file_text = "def jump(frog):\n    h = (frog.legs +\n         frog.tail)\n    if h > 2:\n        return h\n    return 0\n";
+        let unit = detect_indent_unit(file_text).unwrap();
+        assert_eq!(unit, IndentUnit::Spaces(4));
+
+        let tab_hunk = vec!["\th = (frog.legs +", "\t     frog.tail)", "\treturn h"];
+        assert_eq!(reindent_to_unit(&tab_hunk, &unit), vec!["    h = (frog.legs +", "         frog.tail)", "    return h"]);
+
+        let space_hunk = vec!["    h = (frog.legs +", "         frog.tail)", "        return h"];
+        assert_eq!(reindent_to_unit(&space_hunk, &IndentUnit::Tabs), vec!["\th = (frog.legs +", "\t     frog.tail)", "\t\treturn h"]);
+        // same style, nothing to convert
+        assert_eq!(reindent_to_unit(&space_hunk, &unit), space_hunk);
+    }
+
+    #[test]
+    fn test_hunk_into_tab_file_keeps_mixed_indentation() {
+        let file_text = "class Frog:\n\tdef jump(self, pond):\n\t\tself.x = clip(self.x,\n\t\t              pond.width)\n\t\treturn self.x\n";
+        let file_lines = file_text.lines().collect::<Vec<_>>();
+        let file_section = file_lines[2..5].to_vec();
+        let hunk = vec![
+            "self.x = clip(self.x + self.vx,",
+            "              pond.width)",
+            "return self.x",
+        ];
+
+        let unit = file_indent_unit(&PathBuf::from("/nonexistent/frog.py"), file_text).unwrap();
+        assert_eq!(unit, IndentUnit::Tabs);
+        assert_eq!(minimal_common_indent(&file_section), (0, 2));
+        let target_width = minimal_common_indent_width(&file_section, 4);
+        assert_eq!(target_width, 8);
+        let result = place_indent_width(&hunk, target_width, &unit, 4);
+
+        assert_eq!(result, vec![
+            "\t\tself.x = clip(self.x + self.vx,",
+            "\t\t              pond.width)",
+            "\t\treturn self.x",
+        ]);
+        assert_eq!(indent_width("\t  x", 8), 10);
+        assert_eq!(indent_width("  \tx", 4), 4);
+    }
+
+    #[test]
+    fn test_editorconfig_indent_unit() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\n\n[*.{go,mk}]\nindent_style = tab\n",
+        ).unwrap();
+
+        assert_eq!(editorconfig_indent_unit(&dir.path().join("main.go")), Some(IndentUnit::Tabs));
+        assert_eq!(editorconfig_indent_unit(&dir.path().join("frog.py")), Some(IndentUnit::Spaces(2)));
+    }
+}
@@ -0,0 +1,354 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock as ARwLock;
+
+use crate::at_commands::at_file::{file_repair_candidates, return_one_candidate_or_a_good_error};
+use crate::call_validation::DiffChunk;
+use crate::files_correction::get_project_dirs;
+use crate::files_in_workspace::get_file_text_from_memory_or_disk;
+use crate::global_context::GlobalContext;
+use crate::privacy::{check_file_privacy, FilePrivacyLevel, PrivacySettings};
+use crate::tools::tool_patch_aux::diff_structs::{diff_blocks_to_diff_chunks, DiffBlock, DiffLine, LineType};
+use crate::tools::tool_patch_aux::model_based_edit::blocks_of_code_parser::find_section_start;
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum MarkerKind {
+    Search,
+    Divider,
+    Replace,
+    New,
+    Remove,
+    End,
+}
+
+fn parse_marker(line: &str) -> Option<MarkerKind> {
+    let trimmed = line.trim();
+    let prefix_char = trimmed.chars().next()?;
+    if !['<', '=', '>'].contains(&prefix_char) {
+        return None;
+    }
+    let prefix_len = trimmed.chars().take_while(|c| *c == prefix_char).count();
+    if prefix_len < 7 {
+        return None;
+    }
+    let word = trimmed[prefix_len..].trim();
+    match (prefix_char, word) {
+        ('<', "SEARCH") => Some(MarkerKind::Search),
+        ('<', "NEW") => Some(MarkerKind::New),
+        ('<', "REMOVE") => Some(MarkerKind::Remove),
+        ('=', "") => Some(MarkerKind::Divider),
+        ('>', "REPLACE") => Some(MarkerKind::Replace),
+        ('>', "END") => Some(MarkerKind::End),
+        _ => None,
+    }
+}
+
+fn collect_until(lines: &[&str], start: usize, stop: MarkerKind) -> Result<(Vec<String>, usize), String> {
+    let mut collected = vec![];
+    for (idx, line) in lines.iter().enumerate().skip(start) {
+        if parse_marker(line) == Some(stop.clone()) {
+            return Ok((collected, idx + 1));
+        }
+        collected.push(line.to_string());
+    }
+    Err(format!("unterminated block starting at line {}: expected {:?} marker", start, stop))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SearchReplaceBlock {
+    Edit { search: Vec<String>, replace: Vec<String> },
+    New(Vec<String>),
+    Remove,
+}
+
+// The file a block belongs to is written on the last line before its marker, like "src/frog.py" or "`src/frog.py`:"
+fn path_hint(lines: &[&str], marker_idx: usize) -> Option<String> {
+    for line in lines[..marker_idx].iter().rev() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("```") {
+            continue;
+        }
+        if parse_marker(trimmed).is_some() {
+            return None;
+        }
+        let path = trimmed.trim_end_matches(':').trim_matches(|c| c == '`' || c == '*').trim();
+        return if path.is_empty() || path.contains(' ') { None } else { Some(path.to_string()) };
+    }
+    None
+}
+
+// Models put explanations in fenced blocks between the edits, a python snippet with "=======" in it is not a broken edit.
+// Returns the index after the closing fence if the fenced block at `fence_idx` has no SEARCH, NEW or REMOVE in it.
+fn skip_fenced_block_without_edits(lines: &[&str], fence_idx: usize) -> Option<usize> {
+    let close_idx = lines.iter().enumerate().skip(fence_idx + 1).find(|(_, l)| l.trim() == "```").map(|(i, _)| i)?;
+    let has_edits = lines[fence_idx + 1..close_idx].iter()
+        .any(|l| matches!(parse_marker(l), Some(MarkerKind::Search) | Some(MarkerKind::New) | Some(MarkerKind::Remove)));
+    if has_edits { None } else { Some(close_idx + 1) }
+}
+
+fn parse_blocks(content: &str) -> Result<Vec<(Option<String>, SearchReplaceBlock)>, String> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut blocks = vec![];
+    let mut line_idx = 0;
+    let mut inside_fence = false;
+    while line_idx < lines.len() {
+        if lines[line_idx].trim_start().starts_with("```") {
+            if inside_fence {
+                inside_fence = false;
+            } else if let Some(next_idx) = skip_fenced_block_without_edits(&lines, line_idx) {
+                line_idx = next_idx;
+                continue;
+            } else {
+                inside_fence = true;
+            }
+            line_idx += 1;
+            continue;
+        }
+        let block = match parse_marker(lines[line_idx]) {
+            Some(MarkerKind::Search) => {
+                let (search, next_idx) = collect_until(&lines, line_idx + 1, MarkerKind::Divider)?;
+                let (replace, next_idx) = collect_until(&lines, next_idx, MarkerKind::Replace)?;
+                if search.is_empty() {
+                    return Err(format!("SEARCH block at line {} is empty", line_idx + 1));
+                }
+                (SearchReplaceBlock::Edit { search, replace }, next_idx)
+            }
+            Some(MarkerKind::New) => {
+                let (new_lines, next_idx) = collect_until(&lines, line_idx + 1, MarkerKind::End)?;
+                (SearchReplaceBlock::New(new_lines), next_idx)
+            }
+            Some(MarkerKind::Remove) => {
+                let (_, next_idx) = collect_until(&lines, line_idx + 1, MarkerKind::End)?;
+                (SearchReplaceBlock::Remove, next_idx)
+            }
+            Some(marker) => {
+                return Err(format!("unexpected {:?} marker at line {}", marker, line_idx + 1));
+            }
+            None => {
+                line_idx += 1;
+                continue;
+            }
+        };
+        blocks.push((path_hint(&lines, line_idx), block.0));
+        line_idx = block.1;
+    }
+    Ok(blocks)
+}
+
+fn plus_lines(lines: &[String], file_line_num_idx: usize) -> Vec<DiffLine> {
+    lines.iter().map(|l| DiffLine {
+        line: l.clone(),
+        line_type: LineType::Plus,
+        file_line_num_idx: Some(file_line_num_idx),
+        correct_spaces_offset: Some(0),
+    }).collect()
+}
+
+// SEARCH blocks are located with the same code that locates "Original Section" blocks of the model-based edit
+fn block_to_diff_block(path: &PathBuf, file_text: Option<&String>, block: &SearchReplaceBlock) -> Result<DiffBlock, String> {
+    let file_name = path.to_string_lossy().to_string();
+    let (action, diff_lines) = match block {
+        SearchReplaceBlock::Edit { search, replace } => {
+            let file_text = file_text.ok_or(format!("cannot read file '{}'", file_name))?;
+            let file_lines = file_text.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+            let start = find_section_start(&file_lines, search, None).ok_or(format!(
+                "SEARCH block not found in '{}':\n```\n{}\n```",
+                file_name, search.join("\n")
+            ))?;
+            let mut diff_lines = file_lines[start..start + search.len()].iter().enumerate().map(|(idx, l)| DiffLine {
+                line: l.clone(),
+                line_type: LineType::Minus,
+                file_line_num_idx: Some(start + idx),
+                correct_spaces_offset: Some(0),
+            }).collect::<Vec<_>>();
+            diff_lines.extend(plus_lines(replace, start));
+            ("edit", diff_lines)
+        }
+        SearchReplaceBlock::New(new_lines) => ("add", plus_lines(new_lines, 0)),
+        SearchReplaceBlock::Remove => ("remove", vec![]),
+    };
+    Ok(DiffBlock {
+        file_name_before: path.clone(),
+        file_name_after: path.clone(),
+        action: action.to_string(),
+        diff_lines,
+        hunk_idx: 0,
+        file_lines: Arc::new(vec![]),
+    })
+}
+
+/// Parses `<<<<<<<< SEARCH / ======== / >>>>>>>> REPLACE` blocks into DiffChunks for `path`,
+/// together with the `NEW`/`END` (add file) and `REMOVE`/`END` (delete file) variants.
+/// SEARCH blocks are located in the file on disk to compute line ranges.
+pub fn parse_search_replace(content: &str, path: &PathBuf) -> Result<Vec<DiffChunk>, String> {
+    let blocks = parse_blocks(content)?;
+    let needs_text = blocks.iter().any(|(_, b)| matches!(b, SearchReplaceBlock::Edit { .. }));
+    let file_text = if needs_text {
+        Some(std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read file '{}': {}", path.display(), e))?)
+    } else {
+        None
+    };
+    let diff_blocks = blocks.iter()
+        .map(|(_, block)| block_to_diff_block(path, file_text.as_ref(), block))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(diff_blocks_to_diff_chunks(&diff_blocks))
+}
+
+pub fn has_search_replace_blocks(content: &str) -> bool {
+    content.lines().any(|l| matches!(parse_marker(l), Some(MarkerKind::Search) | Some(MarkerKind::New) | Some(MarkerKind::Remove)))
+}
+
+/// The same as `parse_search_replace`, but for blocks of several files, each one written after its file path.
+/// A block without a path of its own belongs to the file of the block before it.
+/// Paths are corrected to workspace files, files blocked by privacy settings are refused.
+pub async fn search_replace_to_diff_chunks(
+    content: &str,
+    gcx: Arc<ARwLock<GlobalContext>>,
+    privacy_settings: Arc<PrivacySettings>,
+) -> Result<Vec<DiffChunk>, String> {
+    let mut diff_blocks = vec![];
+    let mut last_path: Option<String> = None;
+    for (path_mb, block) in parse_blocks(content)? {
+        last_path = path_mb.or(last_path);
+        let path_as_written = last_path.clone().ok_or("cannot find the file path for a SEARCH/REPLACE block, write it on the line before the block".to_string())?;
+        let (path, file_text) = if let SearchReplaceBlock::New(_) = block {
+            (PathBuf::from(&path_as_written), None)
+        } else {
+            let candidates = file_repair_candidates(gcx.clone(), &path_as_written, 10, false).await;
+            let path = return_one_candidate_or_a_good_error(gcx.clone(), &path_as_written, &candidates, &get_project_dirs(gcx.clone()).await, false).await?;
+            let text = get_file_text_from_memory_or_disk(gcx.clone(), &PathBuf::from(&path)).await?;
+            (PathBuf::from(path), Some(text))
+        };
+        check_file_privacy(privacy_settings.clone(), &path, &FilePrivacyLevel::OnlySendToServersIControl)
+            .map_err(|e| format!("cannot edit '{}': {}", path.display(), e))?;
+        diff_blocks.push(block_to_diff_block(&path, file_text.as_ref(), &block)?);
+    }
+    Ok(diff_blocks_to_diff_chunks(&diff_blocks))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::diffs::{apply_diff_chunks_to_text, ApplyDiffOutput};
+
+    const FROG_PY: &str = "def jump():\n    x = 1\n    return x\n\ndef croak():\n    print(\"croak\")\n";
+
+    fn frog_file() -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(".py").tempfile().unwrap();
+        file.write_all(FROG_PY.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_search_replace_edit_applies() {
+        let file = frog_file();
+        let path = file.path().to_path_buf();
+        let content = "Here is the fix:\n<<<<<<<< SEARCH\n    x = 1\n    return x\n========\n    x = 2\n    return x * 2\n>>>>>>>> REPLACE\n";
+
+        let chunks = parse_search_replace(content, &path).unwrap();
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!((chunks[0].line1, chunks[0].line2), (2, 4));
+        let (results, outputs) = apply_diff_chunks_to_text(
+            &FROG_PY.to_string(), chunks.iter().enumerate().collect(), vec![], 1,
+        );
+        assert_eq!(outputs.get(&0), Some(&ApplyDiffOutput::Ok()));
+        assert_eq!(
+            results[0].file_text.clone().unwrap(),
+            "def jump():\n    x = 2\n    return x * 2\n\ndef croak():\n    print(\"croak\")\n"
+        );
+    }
+
+    #[test]
+    fn test_search_replace_add_and_remove() {
+        let path = PathBuf::from("/tmp/frog_new.py");
+        let chunks = parse_search_replace("<<<<<<<< NEW\nprint(1)\n>>>>>>>> END\n", &path).unwrap();
+        assert_eq!(chunks[0].file_action, "add");
+        assert_eq!(chunks[0].lines_add, "print(1)\n");
+
+        let chunks = parse_search_replace("<<<<<<<< REMOVE\n>>>>>>>> END\n", &path).unwrap();
+        assert_eq!(chunks[0].file_action, "remove");
+    }
+
+    #[test]
+    fn test_blocks_after_file_path_give_identical_chunks() {
+        let file = frog_file();
+        let path = file.path().to_path_buf();
+        let edit = "<<<<<<<< SEARCH\n    x = 1\n    return x\n========\n    x = 2\n    return x * 2\n>>>>>>>> REPLACE\n";
+        let add = "<<<<<<<< NEW\nprint(1)\n>>>>>>>> END\n";
+        let expected = parse_search_replace(&format!("{}{}", edit, add), &path).unwrap();
+        assert_eq!(expected[0], DiffChunk {
+            file_name: path.to_string_lossy().to_string(),
+            file_action: "edit".to_string(),
+            line1: 2,
+            line2: 4,
+            lines_remove: "    x = 1\n    return x\n".to_string(),
+            lines_add: "    x = 2\n    return x * 2\n".to_string(),
+            ..Default::default()
+        });
+
+        let content = format!("Here is the fix:\n`{}`:\n```\n{}```\n\n**{}**\n{}", path.display(), edit, path.display(), add);
+        let blocks = parse_blocks(&content).unwrap();
+        assert_eq!(blocks.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>(), vec![Some(path.to_string_lossy().to_string()); 2]);
+        let diff_blocks = blocks.iter()
+            .map(|(_, block)| block_to_diff_block(&path, Some(&FROG_PY.to_string()), block).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(diff_blocks_to_diff_chunks(&diff_blocks), expected);
+    }
+
+    #[test]
+    fn test_plain_fenced_block_between_edits_is_skipped() {
+        let file = frog_file();
+        let path = file.path().to_path_buf();
+        let content = concat!(
+            "```python\n<<<<<<<< SEARCH\n    x = 1\n========\n    x = 2\n>>>>>>>> REPLACE\n```\n",
+            "The frog docs keep their header:\n",
+            "```rst\nFrog\n========\n```\n",
+            "```python\n<<<<<<<< SEARCH\n    print(\"croak\")\n========\n    print(\"ribbit\")\n>>>>>>>> REPLACE\n```\n",
+        );
+
+        let chunks = parse_search_replace(content, &path).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].lines_remove.as_str(), chunks[0].lines_add.as_str()), ("    x = 1\n", "    x = 2\n"));
+        assert_eq!((chunks[1].lines_remove.as_str(), chunks[1].lines_add.as_str()), ("    print(\"croak\")\n", "    print(\"ribbit\")\n"));
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_to_diff_chunks_corrects_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let frog_path = dir.path().join("frog.py");
+        std::fs::write(&frog_path, FROG_PY).unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(dir.path(), vec![frog_path.clone()]).await;
+        let frog_path = crate::files_correction::canonical_path(&frog_path.to_string_lossy().to_string());
+        let content = concat!(
+            "frog.py\n<<<<<<<< SEARCH\n    x = 1\n========\n    x = 2\n>>>>>>>> REPLACE\n",
+            "<<<<<<<< SEARCH\n    print(\"croak\")\n========\n    print(\"ribbit\")\n>>>>>>>> REPLACE\n",
+        );
+
+        let chunks = search_replace_to_diff_chunks(content, gcx.clone(), Arc::new(PrivacySettings::default())).await.unwrap();
+
+        let expected = parse_search_replace(content, &frog_path).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks, expected);
+
+        let err = search_replace_to_diff_chunks("<<<<<<<< SEARCH\n    x = 1\n========\n    x = 2\n>>>>>>>> REPLACE\n", gcx.clone(), Arc::new(PrivacySettings::default())).await.unwrap_err();
+        assert!(err.contains("cannot find the file path"), "unexpected error: {}", err);
+        assert!(search_replace_to_diff_chunks(&content.replace("frog.py", "toad.py"), gcx.clone(), Arc::new(PrivacySettings::default())).await.is_err());
+    }
+
+    #[test]
+    fn test_search_replace_not_found() {
+        let file = frog_file();
+        let path = file.path().to_path_buf();
+        let content = "<<<<<<<< SEARCH\n    y = 1\n========\n    y = 2\n>>>>>>>> REPLACE\n";
+
+        let err = parse_search_replace(content, &path).unwrap_err();
+
+        assert!(err.contains("SEARCH block not found"), "unexpected error: {}", err);
+    }
+}
@@ -12,6 +12,7 @@ use crate::at_commands::at_file::{file_repair_candidates, return_one_candidate_o
 use crate::at_commands::at_search::execute_at_search;
 use crate::files_correction::{correct_to_nearest_dir_path, get_project_dirs};
 use crate::tools::tools_description::Tool;
+use crate::vecdb::vdb_structs::VecdbTestScope;
 use crate::call_validation::{ChatMessage, ChatContent, ContextEnum, ContextFile};
 
 
@@ -24,7 +25,13 @@ async fn execute_att_search(
 ) -> Result<Vec<ContextFile>, String> {
     let gcx = ccx.lock().await.global_context.clone();
     if scope == "workspace" {
-        return execute_at_search(ccx.clone(), &query, None).await
+        return execute_at_search(ccx.clone(), &query, None, VecdbTestScope::Exclude, false).await
+    }
+    if scope == "all_projects" {
+        return execute_at_search(ccx.clone(), &query, None, VecdbTestScope::Exclude, true).await
+    }
+    if scope == "tests" {
+        return execute_at_search(ccx.clone(), &query, None, VecdbTestScope::Only, false).await
     }
     let scope_is_dir = scope.ends_with('/') || scope.ends_with('\\');
 
@@ -58,7 +65,7 @@ async fn execute_att_search(
     };
 
     info!("att-search: filter: {:?}", filter);
-    execute_at_search(ccx.clone(), &query, Some(filter)).await
+    execute_at_search(ccx.clone(), &query, Some(filter), VecdbTestScope::Include, true).await
 }
 
 #[async_trait]
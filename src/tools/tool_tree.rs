@@ -11,6 +11,7 @@ use crate::at_commands::at_tree::{construct_tree_out_of_flat_list_of_paths, prin
 use crate::tools::tools_description::Tool;
 use crate::call_validation::{ChatMessage, ChatContent, ContextEnum};
 use crate::files_correction::{correct_to_nearest_dir_path, correct_to_nearest_filename, get_project_dirs, paths_from_anywhere};
+use crate::file_filter::FileFilterSettings;
 use crate::files_in_workspace::ls_files;
 
 
@@ -64,13 +65,14 @@ impl Tool for ToolTree {
                     return Err(format!("Cannot execute tree(), '{path}' is not within the project directories."));
                 }
 
-                let paths_in_dir = ls_files(&true_path, true).unwrap_or(vec![]);
+                let filter_settings = FileFilterSettings::from_cmdline(&gcx.read().await.cmdline);
+                let paths_in_dir = ls_files(&true_path, true, &filter_settings).unwrap_or(vec![]);
                 construct_tree_out_of_flat_list_of_paths(&paths_in_dir)
             },
             None => construct_tree_out_of_flat_list_of_paths(&paths_from_anywhere)
         };
 
-        let content = print_files_tree_with_budget(ccx.clone(), tree, use_ast).await.map_err(|err| {
+        let content = print_files_tree_with_budget(ccx.clone(), tree, use_ast, None).await.map_err(|err| {
             warn!("print_files_tree_with_budget err: {}", err);
             err
         })?;
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use indexmap::IndexMap;
+use lazy_static::lazy_static;
 use serde_json::{Value, json};
 use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
@@ -176,7 +179,7 @@ tools:
         description: "Single line, paragraph or code sample to search for similar content."
       - name: "scope"
         type: "string"
-        description: "'workspace' to search all files in workspace, 'dir/subdir/' to search in files within a directory, 'dir/file.ext' to search in a single file."
+        description: "'workspace' to search all files in the active project, 'all_projects' to search every open project, 'tests' to search only in test files, 'dir/subdir/' to search in files within a directory, 'dir/file.ext' to search in a single file."
     parameters_required:
       - "query"
       - "scope"
@@ -384,7 +387,7 @@ const NOT_READY_TOOLS: &str = r####"
 "####;
 
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Hash)]
 pub struct ToolDesc {
     pub name: String,
     #[serde(default)]
@@ -396,7 +399,7 @@ pub struct ToolDesc {
     pub parameters_required: Vec<String>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Hash)]
 pub struct ToolParam {
     pub name: String,
     #[serde(rename = "type", default = "default_param_type")]
@@ -458,16 +461,49 @@ pub struct ToolDictDeserialize {
     pub tools: Vec<ToolDesc>,
 }
 
+lazy_static! {
+    static ref BUILT_IN_TOOL_DESCS: Result<Vec<ToolDesc>, String> = serde_yaml::from_str::<ToolDictDeserialize>(BUILT_IN_TOOLS)
+        .map(|x| x.tools)
+        .map_err(|e| format!("Failed to parse BUILT_IN_TOOLS: {}", e));
+    // the last converted list keyed by tools fingerprint, tools only change when integrations or the subset of turned on tools change
+    static ref OPENAI_STYLE_CACHE: StdMutex<Option<(u64, Arc<Vec<Value>>)>> = StdMutex::new(None);
+}
+
+static OPENAI_STYLE_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Call when custom commands or other integration tools are saved, their descriptions might have changed.
+pub fn openai_style_cache_invalidate() {
+    OPENAI_STYLE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    *OPENAI_STYLE_CACHE.lock().unwrap() = None;
+}
+
+fn tool_descs_fingerprint(tool_descs: &Vec<ToolDesc>) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    OPENAI_STYLE_GENERATION.load(Ordering::SeqCst).hash(&mut hasher);
+    tool_descs.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn tool_descs_to_openai_style(tool_descs: Vec<ToolDesc>) -> Arc<Vec<Value>> {
+    let fingerprint = tool_descs_fingerprint(&tool_descs);
+    let mut cache = OPENAI_STYLE_CACHE.lock().unwrap();
+    if let Some((cached_fingerprint, cached_values)) = cache.as_ref() {
+        if *cached_fingerprint == fingerprint {
+            return cached_values.clone();
+        }
+    }
+    let values = Arc::new(tool_descs.into_iter().map(|x| x.into_openai_style()).collect::<Vec<_>>());
+    *cache = Some((fingerprint, values.clone()));
+    values
+}
+
 pub async fn tool_description_list_from_yaml(
     tools: IndexMap<String, Box<dyn Tool + Send>>,
     turned_on: &Vec<String>,
     allow_experimental: bool,
 ) -> Result<Vec<ToolDesc>, String> {
-    let tool_desc_deser: ToolDictDeserialize = serde_yaml::from_str(BUILT_IN_TOOLS)
-        .map_err(|e|format!("Failed to parse BUILT_IN_TOOLS: {}", e))?;
-
     let mut tool_desc_vec = vec![];
-    tool_desc_vec.extend(tool_desc_deser.tools.iter().cloned());
+    tool_desc_vec.extend(BUILT_IN_TOOL_DESCS.as_ref().map_err(|e| e.clone())?.iter().cloned());
 
     for (tool_name, tool) in tools {
         if !tool_desc_vec.iter().any(|desc| desc.name == tool_name) {
@@ -480,3 +516,39 @@ pub async fn tool_description_list_from_yaml(
         .cloned()
         .collect::<Vec<_>>())
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_style_cached_until_tools_change() {
+        let built_in = BUILT_IN_TOOL_DESCS.as_ref().unwrap().clone();
+        assert!(std::ptr::eq(BUILT_IN_TOOL_DESCS.as_ref().unwrap(), BUILT_IN_TOOL_DESCS.as_ref().unwrap()));
+
+        let first = tool_descs_to_openai_style(built_in.clone());
+        let second = tool_descs_to_openai_style(built_in.clone());
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*first, built_in.iter().cloned().map(|x| x.into_openai_style()).collect::<Vec<_>>());
+
+        let mut with_custom = built_in.clone();
+        with_custom.push(ToolDesc {
+            name: "frog_jump".to_string(),
+            agentic: false,
+            experimental: false,
+            description: "Make the frog jump".to_string(),
+            parameters: vec![],
+            parameters_required: vec![],
+        });
+        let third = tool_descs_to_openai_style(with_custom);
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(third.len(), first.len() + 1);
+
+        let fourth = tool_descs_to_openai_style(built_in.clone());
+        openai_style_cache_invalidate();
+        let fifth = tool_descs_to_openai_style(built_in.clone());
+        assert!(!Arc::ptr_eq(&fourth, &fifth));
+        assert_eq!(*fourth, *fifth);
+    }
+}
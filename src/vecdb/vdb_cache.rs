@@ -7,6 +7,7 @@ use tokio_rusqlite::Connection;
 use tracing::info;
 use rusqlite::{OpenFlags, params, Result};
 
+use crate::vecdb::vdb_lance::like_pattern_for_dir;
 use crate::vecdb::vdb_structs::{SplitResult, SimpleTextHashVector};
 
 
@@ -21,6 +22,19 @@ pub struct VecDBCache {
 }
 
 const EMB_TABLE_NAME: &str = "embeddings";
+const FILES_TABLE_NAME: &str = "files_vectorized";
+
+/// A file that went all the way into vecdb, with the chunks it produced. If the file is unchanged on
+/// restart, the chunks are restored from the embeddings table without splitting or vectorizing.
+/// Chunks depend on how the file was split, `splitter_fingerprint` must match for the marker to be used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileVectorizedMarker {
+    pub file_path: String,
+    pub content_hash: String,
+    pub mtime_ms: i64,
+    pub splitter_fingerprint: String,
+    pub chunks: Vec<(u64, u64, String)>,  // start_line, end_line, window_text_hash
+}
 
 #[derive(Debug, PartialEq)]
 struct DataColumn {
@@ -71,6 +85,29 @@ async fn check_and_recreate_embeddings_table(db: &Connection) -> tokio_rusqlite:
     }).await
 }
 
+async fn create_files_table_if_needed(db: &Connection) -> tokio_rusqlite::Result<()> {
+    db.call(move |conn| {
+        // markers from before splitter_fingerprint can't be trusted, it's only a cache
+        let columns = conn.prepare(&format!("PRAGMA table_info({FILES_TABLE_NAME});"))?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|c| c.ok())
+            .collect::<Vec<_>>();
+        if !columns.is_empty() && !columns.iter().any(|c| c == "splitter_fingerprint") {
+            info!("vectorized files table is from an older version, recreating");
+            conn.execute(&format!("DROP TABLE IF EXISTS {FILES_TABLE_NAME}"), [])?;
+        }
+        conn.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {FILES_TABLE_NAME} (
+            file_path TEXT PRIMARY KEY,
+            content_hash TEXT NOT NULL,
+            mtime_ms INTEGER NOT NULL,
+            splitter_fingerprint TEXT NOT NULL,
+            chunks TEXT NOT NULL
+        )"), [])?;
+        Ok(())
+    }).await
+}
+
 impl VecDBCache {
     pub async fn init(cache_dir: &PathBuf, model_name: &String, embedding_size: i32) -> Result<VecDBCache, String> {
         let cache_dir_str = match cache_dir.join("refact_vecdb_cache")
@@ -104,6 +141,10 @@ impl VecDBCache {
             Ok(_) => {}
             Err(err) => return Err(format!("{:?}", err))
         }
+        match create_files_table_if_needed(&cache_database).await {
+            Ok(_) => {}
+            Err(err) => return Err(format!("{:?}", err))
+        }
 
         info!("building window_text_hashes complete");
 
@@ -221,6 +262,78 @@ impl VecDBCache {
         }
     }
 
+    pub async fn files_vectorized_get(&mut self, file_paths: &Vec<String>) -> Result<HashMap<String, FileVectorizedMarker>, String> {
+        let mut markers = HashMap::new();
+        for paths_batch in file_paths.chunks(500) {
+            let placeholders: String = paths_batch.iter().map(|_| "?").collect::<Vec<&str>>().join(",");
+            let query = format!("SELECT file_path, content_hash, mtime_ms, splitter_fingerprint, chunks FROM {FILES_TABLE_NAME} WHERE file_path IN ({placeholders})");
+            let paths_batch = paths_batch.to_vec();
+            let found = self.cache_database.call(move |connection| {
+                let mut statement = connection.prepare(&query)?;
+                let result = statement.query_map(rusqlite::params_from_iter(paths_batch.iter()), |row| {
+                    let chunks_json: String = row.get(4)?;
+                    Ok(FileVectorizedMarker {
+                        file_path: row.get(0)?,
+                        content_hash: row.get(1)?,
+                        mtime_ms: row.get(2)?,
+                        splitter_fingerprint: row.get(3)?,
+                        chunks: serde_json::from_str(&chunks_json).unwrap_or_default(),
+                    })
+                })?;
+                Ok(result.filter_map(|r| r.ok()).collect::<Vec<_>>())
+            }).await.map_err(|err| format!("Error querying database: {:?}", err))?;
+            markers.extend(found.into_iter().map(|m| (m.file_path.clone(), m)));
+        }
+        Ok(markers)
+    }
+
+    pub async fn files_vectorized_set(&mut self, markers: Vec<FileVectorizedMarker>) -> Result<(), String> {
+        self.cache_database.call(move |connection| {
+            let transaction = connection.transaction()?;
+            for m in markers {
+                transaction.execute(&format!(
+                    "INSERT OR REPLACE INTO {FILES_TABLE_NAME} (file_path, content_hash, mtime_ms, splitter_fingerprint, chunks) VALUES (?1, ?2, ?3, ?4, ?5)"),
+                    rusqlite::params![
+                        m.file_path,
+                        m.content_hash,
+                        m.mtime_ms,
+                        m.splitter_fingerprint,
+                        serde_json::to_string(&m.chunks).unwrap_or("[]".to_string()),
+                    ],
+                )?;
+            }
+            transaction.commit()?;
+            Ok(())
+        }).await.map_err(|err| format!("{:?}", err))
+    }
+
+    pub async fn files_vectorized_all_paths(&self) -> Result<Vec<String>, String> {
+        self.cache_database.call(move |connection| {
+            let mut statement = connection.prepare(&format!("SELECT file_path FROM {FILES_TABLE_NAME}"))?;
+            let result = statement.query_map([], |row| row.get::<_, String>(0))?;
+            Ok(result.filter_map(|r| r.ok()).collect::<Vec<_>>())
+        }).await.map_err(|err| format!("{:?}", err))
+    }
+
+    pub async fn files_vectorized_remove(&mut self, file_paths: Vec<String>) -> Result<(), String> {
+        self.cache_database.call(move |connection| {
+            let transaction = connection.transaction()?;
+            for file_path in file_paths {
+                transaction.execute(&format!("DELETE FROM {FILES_TABLE_NAME} WHERE file_path = ?1"), rusqlite::params![file_path])?;
+            }
+            transaction.commit()?;
+            Ok(())
+        }).await.map_err(|err| format!("{:?}", err))
+    }
+
+    pub async fn files_vectorized_remove_under(&mut self, dir: &PathBuf) -> Result<(), String> {
+        let pattern = like_pattern_for_dir(dir);
+        self.cache_database.call(move |connection| {
+            connection.execute(&format!("DELETE FROM {FILES_TABLE_NAME} WHERE file_path LIKE ?1 ESCAPE '\\'"), rusqlite::params![pattern])?;
+            Ok(())
+        }).await.map_err(|err| format!("{:?}", err))
+    }
+
     pub async fn size(&self) -> Result<usize, String> {
         self.cache_database.call(move |connection| {
             let mut stmt = connection.prepare(
@@ -234,3 +347,57 @@ impl VecDBCache {
             })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_files_vectorized_survive_reopen() {
+        let cache_dir = tempfile::Builder::new().prefix("frog_vecdb_cache").tempdir().unwrap();
+        let cache_dir = cache_dir.path().to_path_buf();
+        let model = "frog-embeddings".to_string();
+        let marker = FileVectorizedMarker {
+            file_path: "/pond/frog.py".to_string(),
+            content_hash: "abc".to_string(),
+            mtime_ms: 1700000000123,
+            splitter_fingerprint: "v1 frog-embeddings window=256".to_string(),
+            chunks: vec![(0, 10, "h1".to_string()), (11, 20, "h2".to_string())],
+        };
+        {
+            let mut cache = VecDBCache::init(&cache_dir, &model, 4).await.unwrap();
+            cache.files_vectorized_set(vec![marker.clone()]).await.unwrap();
+        }
+
+        let mut cache = VecDBCache::init(&cache_dir, &model, 4).await.unwrap();
+        let paths = vec!["/pond/frog.py".to_string(), "/pond/toad.py".to_string()];
+        let found = cache.files_vectorized_get(&paths).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found.get("/pond/frog.py"), Some(&marker));
+
+        cache.files_vectorized_remove(vec!["/pond/frog.py".to_string()]).await.unwrap();
+        assert!(cache.files_vectorized_get(&paths).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_files_vectorized_remove_under() {
+        let cache_dir = tempfile::Builder::new().prefix("frog_vecdb_cache").tempdir().unwrap();
+        let mut cache = VecDBCache::init(&cache_dir.path().to_path_buf(), &"frog-embeddings".to_string(), 4).await.unwrap();
+        let marker = |file_path: &str| FileVectorizedMarker {
+            file_path: file_path.to_string(),
+            content_hash: "abc".to_string(),
+            mtime_ms: 0,
+            splitter_fingerprint: "".to_string(),
+            chunks: vec![],
+        };
+        let paths = vec!["/pond/lily_pad/frog.py", "/pond/lily_pad/deep/toad.py", "/pond/lilyXpad/newt.py", "/pond/lily_pad2/tadpole.py"];
+        cache.files_vectorized_set(paths.iter().map(|p| marker(p)).collect()).await.unwrap();
+
+        cache.files_vectorized_remove_under(&PathBuf::from("/pond/lily_pad")).await.unwrap();
+
+        let mut left = cache.files_vectorized_all_paths().await.unwrap();
+        left.sort();
+        assert_eq!(left, vec!["/pond/lilyXpad/newt.py".to_string(), "/pond/lily_pad2/tadpole.py".to_string()]);
+    }
+}
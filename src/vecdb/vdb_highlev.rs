@@ -1,10 +1,14 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::{Mutex as AMutex, RwLock as ARwLock};
+use std::sync::{Arc, Mutex as StdMutex, RwLock as StdRwLock};
+use tokio::sync::{mpsc, Mutex as AMutex, RwLock as ARwLock};
 use tokio::task::JoinHandle;
 use async_trait::async_trait;
+use indexmap::IndexMap;
 use tracing::{error, info};
 
+use crate::ast::ast_db::doc_defs;
+use crate::ast::ast_structs::{AstDB, AstDefinition};
 use crate::background_tasks::BackgroundTasksHolder;
 use crate::caps::get_custom_embedding_api_key;
 use crate::fetch_embedding;
@@ -12,11 +16,12 @@ use crate::global_context::{CommandLine, GlobalContext};
 use crate::knowledge::{lance_search, MemoriesDatabase};
 use crate::trajectories::try_to_download_trajectories;
 use crate::vecdb::vdb_cache::VecDBCache;
-use crate::vecdb::vdb_lance::VecDBHandler;
-use crate::vecdb::vdb_structs::{MemoRecord, MemoSearchResult, SearchResult, VecDbStatus, VecdbConstants, VecdbSearch};
-use crate::vecdb::vdb_thread::{vecdb_start_background_tasks, vectorizer_enqueue_dirty_memory, vectorizer_enqueue_files, FileVectorizerService};
+use crate::vecdb::vdb_lance::{cosine_distance, PathGlobFilter, VecDBHandler};
+use crate::vecdb::vdb_structs::{DistanceMetric, MemoRecord, MemoSearchResult, OngoingToolFailure, OngoingWork, SearchResult, VecDbStatus, VecdbConstants, VecdbRecord, VecdbSearch};
+use crate::vecdb::vdb_thread::{vecdb_start_background_tasks, vectorizer_enqueue_dirty_memory, vectorizer_enqueue_files, vectorizer_reset_cache_counters, vectorizer_set_paused, FileVectorizerService};
 
 
+// Calibrated for cosine distance, with dot or l2 caps should set embedding_distance_reject
 fn model_to_rejection_threshold(embedding_model: &str) -> f32 {
     match embedding_model {
         "text-embedding-3-small" => 0.63,
@@ -25,6 +30,21 @@ fn model_to_rejection_threshold(embedding_model: &str) -> f32 {
     }
 }
 
+fn distance_reject_for_model(embedding_model: &str, from_caps: Option<f32>) -> Result<f32, String> {
+    match from_caps {
+        Some(x) if !(0.0..=1.0).contains(&x) => Err(format!("embedding_distance_reject must be within [0, 1], got {}", x)),
+        Some(x) => Ok(x),
+        None => Ok(model_to_rejection_threshold(embedding_model)),
+    }
+}
+
+
+// An additional embedding model indexing the same files into its own tables, memories stay with the default model
+struct VecDbModel {
+    constants: VecdbConstants,
+    vecdb_handler: Arc<AMutex<VecDBHandler>>,
+    vectorizer_service: Arc<AMutex<FileVectorizerService>>,
+}
 
 pub struct VecDb {
     pub memdb: Arc<AMutex<MemoriesDatabase>>,
@@ -33,13 +53,85 @@ pub struct VecDb {
     pub vectorizer_service: Arc<AMutex<FileVectorizerService>>,
     // cmdline: CommandLine,  // TODO: take from command line what's needed, don't store a copy
     constants: VecdbConstants,
+    extra_models: HashMap<String, VecDbModel>,  // embedding model name -> model, from --vecdb-extra-model
+    mem_ongoing: Arc<StdMutex<HashMap<String, OngoingWork>>>,  // goal -> ongoing work, lives as long as the process
+}
+
+const MMR_CANDIDATES_MULTIPLIER: usize = 3;
+
+/// Maximal Marginal Relevance: picks `top_n` of the candidates (sorted by distance, with vectors) one by one,
+/// each time the one most similar to the query but least similar to what's already picked. Diversity 0.0 keeps
+/// the nearest-first order, 1.0 only cares about being different.
+pub fn mmr_rerank(candidates: Vec<VecdbRecord>, top_n: usize, diversity: f32) -> Vec<VecdbRecord> {
+    let mut remaining = candidates;
+    let mut selected: Vec<VecdbRecord> = vec![];
+    while selected.len() < top_n && !remaining.is_empty() {
+        let mut best_idx = 0;
+        let mut best_score = f32::MIN;
+        for (idx, rec) in remaining.iter().enumerate() {
+            let relevance = 1.0 - rec.distance.abs();
+            let max_similarity = match &rec.vector {
+                Some(v) => selected.iter()
+                    .filter_map(|s| s.vector.as_ref())
+                    .map(|sv| 1.0 - cosine_distance(v, sv))
+                    .fold(0.0, f32::max),
+                None => 0.0,
+            };
+            let score = (1.0 - diversity) * relevance - diversity * max_similarity;
+            if score > best_score {
+                best_score = score;
+                best_idx = idx;
+            }
+        }
+        selected.push(remaining.remove(best_idx));
+    }
+    selected
+}
+
+/// Lower is better: the distance minus a bonus for being created or used recently, the bonus halves every
+/// `half_life_days`. Keep the weight small so the distance dominates and recency mostly breaks ties.
+fn memory_score(m: &MemoRecord, now: f64, recency_weight: f32, half_life_days: f32) -> f32 {
+    let freshest_ts = m.created_ts.max(m.last_used_ts);
+    if freshest_ts <= 0.0 || half_life_days <= 0.0 {
+        return m.distance;
+    }
+    let age_days = ((now - freshest_ts).max(0.0) / 86400.0) as f32;
+    m.distance - recency_weight * 0.5f32.powf(age_days / half_life_days)
+}
+
+fn sort_memories_by_score(memories: &mut Vec<MemoRecord>, now: f64, recency_weight: f32, half_life_days: f32) {
+    memories.sort_by(|a, b| {
+        let score_a = memory_score(a, now, recency_weight, half_life_days);
+        let score_b = memory_score(b, now, recency_weight, half_life_days);
+        score_a.partial_cmp(&score_b).unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Parses `name:embedding_size` from --vecdb-extra-model, optionally followed by `@style:endpoint` for a model
+/// that is not served by the endpoint of the default one, for example a local model `gte-small:384@local:/models/gte-small`.
+fn parse_extra_model_spec(spec: &str) -> Result<(String, i32, Option<(String, String)>), String> {
+    let (model, endpoint) = match spec.split_once('@') {
+        Some((model, endpoint)) => {
+            let (style, template) = endpoint.split_once(':')
+                .filter(|(style, template)| !style.trim().is_empty() && !template.trim().is_empty())
+                .ok_or(format!("--vecdb-extra-model {:?} should look like name:embedding_size@style:endpoint", spec))?;
+            (model, Some((style.trim().to_string(), template.trim().to_string())))
+        }
+        None => (spec, None),
+    };
+    let (name, size) = model.rsplit_once(':').ok_or(format!("--vecdb-extra-model {:?} should look like name:embedding_size", spec))?;
+    let size = size.trim().parse::<i32>().map_err(|e| format!("--vecdb-extra-model {:?} has a bad embedding size: {}", spec, e))?;
+    if name.trim().is_empty() || size <= 0 {
+        return Err(format!("--vecdb-extra-model {:?} should look like name:embedding_size", spec));
+    }
+    Ok((name.trim().to_string(), size, endpoint))
 }
 
 async fn vecdb_test_request(
     vecdb: &VecDb,
     api_key: &String,
 ) -> Result<(), String> {
-    let search_result = vecdb.vecdb_search("test query".to_string(), 3, None, api_key).await;
+    let search_result = vecdb.vecdb_search("test query".to_string(), 3, None, vec![], None, None, api_key).await;
     match search_result {
         Ok(_) => {
             Ok(())
@@ -55,6 +147,7 @@ async fn _create_vecdb(
     gcx: Arc<ARwLock<GlobalContext>>,
     background_tasks: &mut BackgroundTasksHolder,
     constants: VecdbConstants,
+    extra_constants: Vec<VecdbConstants>,
 ) -> Result<(), String> {
     info!("vecdb: attempting to launch");
     let api_key = get_custom_embedding_api_key(gcx.clone()).await;
@@ -77,6 +170,7 @@ async fn _create_vecdb(
         &base_dir_config,
         cmdline.clone(),
         constants,
+        extra_constants,
         &api_key
     ).await {
         Ok(res) => Some(res),
@@ -119,7 +213,7 @@ async fn _create_vecdb(
 
 async fn do_i_need_to_reload_vecdb(
     gcx: Arc<ARwLock<GlobalContext>>,
-) -> (bool, Option<VecdbConstants>) {
+) -> (bool, Option<(VecdbConstants, Vec<VecdbConstants>)>) {
     let caps = match crate::global_context::try_load_caps_quickly_if_not_present(gcx.clone(), 0).await {
         Ok(caps) => caps,
         Err(e) => {
@@ -129,7 +223,17 @@ async fn do_i_need_to_reload_vecdb(
         }
     };
 
-    let vecdb_max_files = gcx.read().await.cmdline.vecdb_max_files;
+    let (vecdb_max_files, extra_model_specs, memories_flush_interval_ms) = {
+        let gcx_locked = gcx.read().await;
+        (gcx_locked.cmdline.vecdb_max_files, gcx_locked.cmdline.vecdb_extra_model.clone(), gcx_locked.cmdline.vecdb_memories_flush_ms)
+    };
+    let mut extra_models: Vec<(String, i32, Option<(String, String)>)> = vec![];
+    for spec in extra_model_specs.iter() {
+        match parse_extra_model_spec(spec) {
+            Ok(x) => extra_models.push(x),
+            Err(e) => error!("vecdb: {}", e),
+        }
+    }
     let mut consts = {
         let caps_locked = caps.read().unwrap();
         let mut b = caps_locked.embedding_batch;
@@ -150,8 +254,20 @@ async fn do_i_need_to_reload_vecdb(
             endpoint_embeddings_style: caps_locked.endpoint_embeddings_style.clone(),
             splitter_window_size: caps_locked.embedding_n_ctx / 2,
             vecdb_max_files: vecdb_max_files,
+            distance_reject: distance_reject_for_model(&caps_locked.embedding_model, caps_locked.embedding_distance_reject).unwrap_or_else(|e| {
+                error!("vecdb: {}, using the default for {}", e, caps_locked.embedding_model);
+                model_to_rejection_threshold(&caps_locked.embedding_model)
+            }),
+            distance_metric: DistanceMetric::from_caps(&caps_locked.embedding_distance_metric).unwrap_or_else(|e| {
+                error!("vecdb: {}, using cosine", e);
+                DistanceMetric::Cosine
+            }),
+            memories_flush_interval_ms,
         }
     };
+    if consts.distance_metric != DistanceMetric::Cosine && caps.read().unwrap().embedding_distance_reject.is_none() {
+        tracing::warn!("vecdb: the default distance_reject {} is meant for cosine distance, not {:?}, set embedding_distance_reject in caps", consts.distance_reject, consts.distance_metric);
+    }
 
     let vec_db = gcx.write().await.vec_db.clone();
     match *vec_db.lock().await {
@@ -163,7 +279,14 @@ async fn do_i_need_to_reload_vecdb(
                 db.constants.endpoint_embeddings_style == consts.endpoint_embeddings_style &&
                 db.constants.splitter_window_size == consts.splitter_window_size &&
                 db.constants.embedding_batch == consts.embedding_batch &&
-                db.constants.embedding_size == consts.embedding_size
+                db.constants.embedding_size == consts.embedding_size &&
+                db.constants.distance_reject == consts.distance_reject &&
+                db.constants.distance_metric == consts.distance_metric &&
+                db.extra_models.len() == extra_models.len() &&
+                extra_models.iter().all(|(name, size, endpoint)| db.extra_models.get(name).map(|m| {
+                    m.constants.embedding_size == *size &&
+                    endpoint.as_ref().map_or(true, |(style, template)| m.constants.endpoint_embeddings_style == *style && m.constants.endpoint_embeddings_template == *template)
+                }).unwrap_or(false))
             {
                 return (false, None);
             }
@@ -183,7 +306,38 @@ async fn do_i_need_to_reload_vecdb(
     }
     consts.tokenizer = Some(tokenizer_maybe.clone().unwrap());
 
-    return (true, Some(consts));
+    let mut extra_consts = vec![];
+    for (name, size, endpoint) in extra_models {
+        if name == consts.embedding_model {
+            continue;
+        }
+        let (endpoint_embeddings_style, endpoint_embeddings_template) = endpoint.unwrap_or((
+            consts.endpoint_embeddings_style.clone(),
+            consts.endpoint_embeddings_template.clone(),
+        ));
+        // a local model keeps its tokenizer next to the weights, there's nothing to download
+        let tokenizer_mb = if endpoint_embeddings_style.to_lowercase() == "local" {
+            tokenizers::Tokenizer::from_file(PathBuf::from(&endpoint_embeddings_template).join("tokenizer.json"))
+                .map(|t| Arc::new(StdRwLock::new(t)))
+                .map_err(|e| format!("cannot load {}/tokenizer.json: {}", endpoint_embeddings_template, e))
+        } else {
+            crate::cached_tokenizers::cached_tokenizer(caps.clone(), gcx.clone(), name.clone()).await
+        };
+        match tokenizer_mb {
+            Ok(tokenizer) => extra_consts.push(VecdbConstants {
+                distance_reject: model_to_rejection_threshold(&name),
+                embedding_model: name,
+                embedding_size: size,
+                tokenizer: Some(tokenizer),
+                endpoint_embeddings_style,
+                endpoint_embeddings_template,
+                ..consts.clone()
+            }),
+            Err(e) => error!("vecdb: extra embedding model {} skipped, its tokenizer didn't load: {}", name, e),
+        }
+    }
+
+    return (true, Some((consts, extra_consts)));
 }
 
 pub async fn vecdb_background_reload(
@@ -201,12 +355,13 @@ pub async fn vecdb_background_reload(
         if need_reload {
             background_tasks.abort().await;
         }
-        if need_reload && consts.is_some() {
+        if let (true, Some((consts, extra_consts))) = (need_reload, consts) {
             background_tasks = BackgroundTasksHolder::new(vec![]);
             match _create_vecdb(
                 gcx.clone(),
                 &mut background_tasks,
-                consts.unwrap(),
+                consts,
+                extra_consts,
             ).await {
                 Ok(_) => {
                     gcx.write().await.vec_db_error = "".to_string();
@@ -237,9 +392,10 @@ impl VecDb {
         config_dir: &PathBuf,
         cmdline: CommandLine,
         constants: VecdbConstants,
+        extra_constants: Vec<VecdbConstants>,
         api_key: &String
     ) -> Result<VecDb, String> {
-        let handler = VecDBHandler::init(constants.embedding_size).await?;
+        let handler = VecDBHandler::init(constants.embedding_size, constants.distance_metric).await?;
         let cache = VecDBCache::init(cache_dir, &constants.embedding_model, constants.embedding_size).await?;
         let vecdb_handler = Arc::new(AMutex::new(handler));
         let vecdb_cache = Arc::new(AMutex::new(cache));
@@ -252,32 +408,108 @@ impl VecDb {
             api_key.clone(),
             memdb.clone(),
         ).await));
+
+        let mut extra_models = HashMap::new();
+        for extra in extra_constants {
+            let handler = Arc::new(AMutex::new(VecDBHandler::init(extra.embedding_size, extra.distance_metric).await?));
+            let cache = Arc::new(AMutex::new(VecDBCache::init(cache_dir, &extra.embedding_model, extra.embedding_size).await?));
+            let service = Arc::new(AMutex::new(FileVectorizerService::new(
+                handler.clone(),
+                cache,
+                extra.clone(),
+                api_key.clone(),
+                memdb.clone(),
+            ).await));
+            info!("vecdb: also indexing with {}", extra.embedding_model);
+            extra_models.insert(extra.embedding_model.clone(), VecDbModel {
+                constants: extra,
+                vecdb_handler: handler,
+                vectorizer_service: service,
+            });
+        }
+
         Ok(VecDb {
             memdb: memdb.clone(),
             vecdb_emb_client: Arc::new(AMutex::new(reqwest::Client::new())),
             vecdb_handler,
             vectorizer_service,
             constants: constants.clone(),
+            extra_models,
+            mem_ongoing: Arc::new(StdMutex::new(HashMap::new())),
         })
     }
 
+    fn model_parts(&self, model_mb: &Option<String>) -> Result<(&VecdbConstants, Arc<AMutex<VecDBHandler>>, Arc<AMutex<FileVectorizerService>>), String> {
+        match model_mb {
+            Some(model) if *model != self.constants.embedding_model => {
+                let m = self.extra_models.get(model).ok_or(format!(
+                    "embedding model {:?} is not indexed, available: {:?}",
+                    model, std::iter::once(&self.constants.embedding_model).chain(self.extra_models.keys()).collect::<Vec<_>>()
+                ))?;
+                Ok((&m.constants, m.vecdb_handler.clone(), m.vectorizer_service.clone()))
+            }
+            _ => Ok((&self.constants, self.vecdb_handler.clone(), self.vectorizer_service.clone())),
+        }
+    }
+
     pub async fn vecdb_start_background_tasks(
         &self,
         gcx: Arc<ARwLock<GlobalContext>>,
     ) -> Vec<JoinHandle<()>> {
         info!("vecdb: start_background_tasks");
         vectorizer_enqueue_dirty_memory(self.vectorizer_service.clone()).await;
-        return vecdb_start_background_tasks(self.vecdb_emb_client.clone(), self.vectorizer_service.clone(), gcx.clone()).await;
+        let mut tasks = vecdb_start_background_tasks(self.vecdb_emb_client.clone(), self.vectorizer_service.clone(), gcx.clone()).await;
+        for m in self.extra_models.values() {
+            tasks.extend(vecdb_start_background_tasks(self.vecdb_emb_client.clone(), m.vectorizer_service.clone(), gcx.clone()).await);
+        }
+        tasks
+    }
+
+    pub async fn vectorizer_reset_cache_counters(&self) {
+        vectorizer_reset_cache_counters(self.vectorizer_service.clone()).await;
+        for m in self.extra_models.values() {
+            vectorizer_reset_cache_counters(m.vectorizer_service.clone()).await;
+        }
     }
 
     pub async fn vectorizer_enqueue_files(&self, documents: &Vec<String>, process_immediately: bool) {
         vectorizer_enqueue_files(self.vectorizer_service.clone(), documents, process_immediately).await;
+        for m in self.extra_models.values() {
+            vectorizer_enqueue_files(m.vectorizer_service.clone(), documents, process_immediately).await;
+        }
     }
 
     pub async fn remove_file(&self, file_path: &PathBuf) {
-        let mut handler_locked = self.vecdb_handler.lock().await;
         let file_path_str = file_path.to_string_lossy().to_string();
-        handler_locked.vecdb_records_remove(vec![file_path_str]).await;
+        for (handler, service) in std::iter::once((&self.vecdb_handler, &self.vectorizer_service))
+            .chain(self.extra_models.values().map(|m| (&m.vecdb_handler, &m.vectorizer_service)))
+        {
+            handler.lock().await.vecdb_records_remove(vec![file_path_str.clone()]).await;
+            // a file that comes back with the same mtime must not be restored from the old chunks
+            let vecdb_cache = service.lock().await.vecdb_cache.clone();
+            if let Err(err) = vecdb_cache.lock().await.files_vectorized_remove(vec![file_path_str.clone()]).await {
+                error!("cannot forget vectorized file {:?}: {}", file_path, err);
+            }
+        }
+    }
+
+    /// One delete per table for a whole folder, instead of a remove for each file in it
+    pub async fn remove_files_under(&self, dir: &PathBuf) {
+        for (handler, service) in std::iter::once((&self.vecdb_handler, &self.vectorizer_service))
+            .chain(self.extra_models.values().map(|m| (&m.vecdb_handler, &m.vectorizer_service)))
+        {
+            handler.lock().await.vecdb_records_remove_under(dir).await;
+            let vecdb_cache = service.lock().await.vecdb_cache.clone();
+            if let Err(err) = vecdb_cache.lock().await.files_vectorized_remove_under(dir).await {
+                error!("cannot forget vectorized files under {:?}: {}", dir, err);
+            }
+        }
+    }
+
+    pub async fn vectorized_files(&self) -> Result<Vec<String>, String> {
+        let vecdb_cache = self.vectorizer_service.lock().await.vecdb_cache.clone();
+        let paths = vecdb_cache.lock().await.files_vectorized_all_paths().await;
+        paths
     }
 }
 
@@ -354,11 +586,23 @@ pub async fn memories_block_until_vectorized(
 }
 
 pub async fn get_status(vec_db: Arc<AMutex<Option<VecDb>>>) -> Result<Option<VecDbStatus>, String> {
-    let vectorizer_service = {
+    let (vectorizer_service, extra_services) = {
         let vec_db_guard = vec_db.lock().await;
         let vec_db = vec_db_guard.as_ref().ok_or("VecDb is not initialized")?;
-        vec_db.vectorizer_service.clone()
+        let mut extra_services = vec_db.extra_models.iter()
+            .map(|(name, m)| (name.clone(), m.vectorizer_service.clone()))
+            .collect::<Vec<_>>();
+        extra_services.sort_by(|a, b| a.0.cmp(&b.0));
+        (vec_db.vectorizer_service.clone(), extra_services)
     };
+    let mut status = vectorizer_status(vectorizer_service).await?;
+    for (name, service) in extra_services {
+        status.other_models.insert(name, vectorizer_status(service).await?);
+    }
+    Ok(Some(status))
+}
+
+async fn vectorizer_status(vectorizer_service: Arc<AMutex<FileVectorizerService>>) -> Result<VecDbStatus, String> {
     let (vstatus, vecdb_handler, vecdb_cache) = {
         let vectorizer_locked = vectorizer_service.lock().await;
         (
@@ -379,7 +623,30 @@ pub async fn get_status(vec_db: Arc<AMutex<Option<VecDb>>>) -> Result<Option<Vec
     if vstatus_copy.state == "done" && vstatus_copy.queue_additions {
         vstatus_copy.state = "cooldown".to_string();
     }
-    return Ok(Some(vstatus_copy));
+    Ok(vstatus_copy)
+}
+
+// Extra models index the same files, pausing only the main one would keep them hitting the embeddings endpoint
+async fn vectorizer_set_paused_all_models(vec_db: Arc<AMutex<Option<VecDb>>>, paused: bool) -> Result<(), String> {
+    let vectorizer_services = {
+        let vec_db_guard = vec_db.lock().await;
+        let vec_db = vec_db_guard.as_ref().ok_or("VecDb is not initialized")?;
+        std::iter::once(vec_db.vectorizer_service.clone())
+            .chain(vec_db.extra_models.values().map(|m| m.vectorizer_service.clone()))
+            .collect::<Vec<_>>()
+    };
+    for vectorizer_service in vectorizer_services {
+        vectorizer_set_paused(vectorizer_service, paused).await;
+    }
+    Ok(())
+}
+
+pub async fn vectorizer_pause(vec_db: Arc<AMutex<Option<VecDb>>>) -> Result<(), String> {
+    vectorizer_set_paused_all_models(vec_db, true).await
+}
+
+pub async fn vectorizer_resume(vec_db: Arc<AMutex<Option<VecDb>>>) -> Result<(), String> {
+    vectorizer_set_paused_all_models(vec_db, false).await
 }
 
 pub async fn memories_select_all(
@@ -433,11 +700,10 @@ pub async fn memories_search(
     query: &String,
     top_n: usize,
 ) -> Result<MemoSearchResult, String> {
-    let vec_db = gcx.read().await.vec_db.clone();
-    fn calculate_score(distance: f32, _times_used: i32) -> f32 {
-        distance
-        // distance - (times_used as f32) * 0.01
-    }
+    let (vec_db, recency_weight, half_life_days) = {
+        let gcx_locked = gcx.read().await;
+        (gcx_locked.vec_db.clone(), gcx_locked.cmdline.memories_recency_weight, gcx_locked.cmdline.memories_recency_half_life_days)
+    };
 
     let t0 = std::time::Instant::now();
     let (memdb, vecdb_emb_client, constants) = {
@@ -474,11 +740,8 @@ pub async fn memories_search(
         Err(err) => { return Err(err.to_string()) }
     };
     let mut results: Vec<MemoRecord> = memdb.lock().await.permdb_fillout_records(lance_results).await?;
-    results.sort_by(|a, b| {
-        let score_a = calculate_score(a.distance, a.mstat_times_used);
-        let score_b = calculate_score(b.distance, b.mstat_times_used);
-        score_a.partial_cmp(&score_b).unwrap_or(std::cmp::Ordering::Equal)
-    });
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
+    sort_memories_by_score(&mut results, now, recency_weight, half_life_days);
     Ok(MemoSearchResult { query_text: query.clone(), results })
 }
 
@@ -499,46 +762,46 @@ pub async fn memories_search(
 //     }
 // }
 
-// pub async fn ongoing_dump(
-//     vec_db: Arc<AMutex<Option<VecDb>>>,
-// ) -> Result<String, String> {
-//     let ongoing_map_arc = {
-//         let vec_db_guard = vec_db.lock().await;
-//         let vec_db = vec_db_guard.as_ref().ok_or("VecDb is not initialized")?;
-//         vec_db.mem_ongoing.clone()
-//     };
-//     let ongoing_map = ongoing_map_arc.lock().unwrap();
+pub async fn ongoing_update_or_create(
+    vec_db: Arc<AMutex<Option<VecDb>>>,
+    goal: String,
+    progress: IndexMap<String, serde_json::Value>,
+    action_sequence: IndexMap<String, serde_json::Value>,
+    output: IndexMap<String, IndexMap<String, serde_json::Value>>,
+    failures: Vec<OngoingToolFailure>,
+) -> Result<usize, String> {
+    let ongoing_map_arc = {
+        let vec_db_guard = vec_db.lock().await;
+        let vec_db = vec_db_guard.as_ref().ok_or("VecDb is not initialized")?;
+        vec_db.mem_ongoing.clone()
+    };
+    let mut ongoing_map = ongoing_map_arc.lock().unwrap();
+    let ongoing = ongoing_map.entry(goal.clone()).or_insert_with(|| OngoingWork::new(&goal));
+    ongoing.update(progress, action_sequence, output, &failures);
+    Ok(ongoing.ongoing_attempt_n)
+}
 
-//     let mut output = String::new();
-//     for (_, ongoing) in ongoing_map.iter() {
-//         let mut ordered_map = IndexMap::new();
-//         ordered_map.insert("PROGRESS".to_string(), serde_json::Value::Object(ongoing.ongoing_progress.clone().into_iter().collect()));
-//         let action_sequences: Vec<serde_json::Value> = ongoing.ongoing_action_sequences
-//             .iter()
-//             .map(|map| serde_json::Value::Object(map.clone().into_iter().collect()))
-//             .collect();
-//         ordered_map.insert("TRIED_ACTION_SEQUENCES".to_string(), serde_json::Value::Array(action_sequences));
-//         let output_value: serde_json::Value = serde_json::Value::Object(
-//             ongoing.ongoing_output
-//                 .clone()
-//                 .into_iter()
-//                 .map(|(k, v)| (k, serde_json::Value::Object(v.into_iter().collect())))
-//                 .collect()
-//         );
-//         ordered_map.insert("OUTPUT".to_string(), output_value);
-//         output.push_str(&format!(
-//             "💿 Ongoing session with goal: {}\nAttempt number: {}\nSummary of progress:\n\n{}\n\n",
-//             ongoing.ongoing_goal,
-//             ongoing.ongoing_attempt_n,
-//             serde_json::to_string_pretty(&ordered_map).unwrap()
-//         ));
-//     }
-//     if output.is_empty() {
-//         output = "No ongoing work found.\n".to_string();
-//     }
+pub async fn ongoing_dump(
+    vec_db: Arc<AMutex<Option<VecDb>>>,
+    include_failures: bool,
+) -> Result<String, String> {
+    let ongoing_map_arc = {
+        let vec_db_guard = vec_db.lock().await;
+        let vec_db = vec_db_guard.as_ref().ok_or("VecDb is not initialized")?;
+        vec_db.mem_ongoing.clone()
+    };
+    let ongoing_map = ongoing_map_arc.lock().unwrap();
 
-//     Ok(output)
-// }
+    let mut output = String::new();
+    for (_, ongoing) in ongoing_map.iter() {
+        output.push_str(&ongoing.dump(include_failures));
+    }
+    if output.is_empty() {
+        output = "No ongoing work found.\n".to_string();
+    }
+
+    Ok(output)
+}
 
 #[async_trait]
 impl VecdbSearch for VecDb {
@@ -547,15 +810,26 @@ impl VecdbSearch for VecDb {
         query: String,
         top_n: usize,
         vecdb_scope_filter_mb: Option<String>,
+        path_globs: Vec<String>,
+        model_mb: Option<String>,
+        diversity_mb: Option<f32>,
         api_key: &String,
     ) -> Result<SearchResult, String> {
         // TODO: move out of struct, replace self with Arc
+        let (constants, vecdb_handler, vectorizer_service) = self.model_parts(&model_mb)?;
+        let path_filter_mb = if path_globs.is_empty() { None } else { Some(PathGlobFilter::new(&path_globs)?) };
+        if let Some(diversity) = diversity_mb {
+            if !(0.0..=1.0).contains(&diversity) {
+                return Err(format!("diversity must be within [0, 1], got {}", diversity));
+            }
+        }
+        let top_n_fetch = if diversity_mb.is_some() { top_n * MMR_CANDIDATES_MULTIPLIER } else { top_n };
         let t0 = std::time::Instant::now();
         let embedding_mb = fetch_embedding::get_embedding_with_retry(
             self.vecdb_emb_client.clone(),
-            &self.constants.endpoint_embeddings_style,
-            &self.constants.embedding_model,
-            &self.constants.endpoint_embeddings_template,
+            &constants.endpoint_embeddings_style,
+            &constants.embedding_model,
+            &constants.endpoint_embeddings_template,
             vec![query.clone()],
             api_key,
             5,
@@ -565,34 +839,21 @@ impl VecdbSearch for VecDb {
         }
         info!("search query {:?}, it took {:.3}s to vectorize the query", query, t0.elapsed().as_secs_f64());
 
-        memories_block_until_vectorized_from_vectorizer(self.vectorizer_service.clone(),
+        memories_block_until_vectorized_from_vectorizer(vectorizer_service,
                                                         5_000).await?;
 
-        let mut handler_locked = self.vecdb_handler.lock().await;
+        let mut handler_locked = vecdb_handler.lock().await;
         let t1 = std::time::Instant::now();
-        let mut results = match handler_locked.vecdb_search(&embedding_mb.unwrap()[0], top_n, vecdb_scope_filter_mb).await {
+        let mut results = match handler_locked.vecdb_search(&embedding_mb.unwrap()[0], top_n_fetch, vecdb_scope_filter_mb, path_filter_mb.as_ref(), diversity_mb.is_some()).await {
             Ok(res) => res,
             Err(err) => { return Err(err.to_string()) }
         };
         info!("search itself {:.3}s", t1.elapsed().as_secs_f64());
-        let mut dist0 = 0.0;
-        let mut filtered_results = Vec::new();
-        let rejection_threshold = model_to_rejection_threshold(self.constants.embedding_model.as_str());
-        info!("rejection_threshold {:.3}", rejection_threshold);
-        for rec in results.iter_mut() {
-            if dist0 == 0.0 {
-                dist0 = rec.distance.abs();
-            }
-            let last_35_chars = crate::nicer_logs::last_n_chars(&rec.file_path.display().to_string(), 35);
-            rec.usefulness = 100.0 - 75.0 * ((rec.distance.abs() - dist0) / (dist0 + 0.01)).max(0.0).min(1.0);
-            if rec.distance.abs() >= rejection_threshold {
-                info!("distance {:.3} -> dropped {}:{}-{}", rec.distance, last_35_chars, rec.start_line, rec.end_line);
-            } else {
-                info!("distance {:.3} -> useful {:.1}, found {}:{}-{}", rec.distance, rec.usefulness, last_35_chars, rec.start_line, rec.end_line);
-                filtered_results.push(rec.clone());
-            }
+        results = usefulness_and_rejection(results, constants.distance_reject, constants.distance_metric);
+        if let Some(diversity) = diversity_mb {
+            results = mmr_rerank(results, top_n, diversity);
+            results.iter_mut().for_each(|rec| rec.vector = None);
         }
-        results = filtered_results;
         Ok(
             SearchResult {
                 query_text: query,
@@ -600,4 +861,348 @@ impl VecdbSearch for VecDb {
             }
         )
     }
+
+    async fn vecdb_search_stream(
+        &self,
+        query: String,
+        top_n: usize,
+        vecdb_scope_filter_mb: Option<String>,
+        path_globs: Vec<String>,
+        model_mb: Option<String>,
+        api_key: &String,
+    ) -> Result<mpsc::Receiver<VecdbRecord>, String> {
+        let (constants, vecdb_handler, vectorizer_service) = self.model_parts(&model_mb)?;
+        let path_filter_mb = if path_globs.is_empty() { None } else { Some(PathGlobFilter::new(&path_globs)?) };
+        let t0 = std::time::Instant::now();
+        let embedding = fetch_embedding::get_embedding_with_retry(
+            self.vecdb_emb_client.clone(),
+            &constants.endpoint_embeddings_style,
+            &constants.embedding_model,
+            &constants.endpoint_embeddings_template,
+            vec![query.clone()],
+            api_key,
+            5,
+        ).await?;
+        let embedding = embedding.into_iter().next().ok_or("vecdb_search_stream: empty embedding".to_string())?;
+        info!("search stream query {:?}, it took {:.3}s to vectorize the query", query, t0.elapsed().as_secs_f64());
+
+        memories_block_until_vectorized_from_vectorizer(vectorizer_service,
+                                                        5_000).await?;
+
+        let mut lance_rx = vecdb_handler.lock().await.vecdb_search_stream(&embedding, top_n, vecdb_scope_filter_mb, path_filter_mb).await
+            .map_err(|err| err.to_string())?;
+        let (tx, rx) = mpsc::channel(top_n.max(1));
+        let (rejection_threshold, distance_metric) = (constants.distance_reject, constants.distance_metric);
+        tokio::spawn(async move {
+            let mut dist0 = 0.0;
+            while let Some(rec) = lance_rx.recv().await {
+                if let Some(rec) = usefulness_or_rejection(rec, &mut dist0, rejection_threshold, distance_metric) {
+                    if tx.send(rec).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn vecdb_search_batch(
+        &self,
+        queries: Vec<String>,
+        top_n: usize,
+        vecdb_scope_filter_mb: Option<String>,
+        api_key: &String,
+    ) -> Result<Vec<SearchResult>, String> {
+        if queries.is_empty() {
+            return Ok(vec![]);
+        }
+        let (constants, vecdb_handler, vectorizer_service) = self.model_parts(&None)?;
+        let t0 = std::time::Instant::now();
+        let embeddings = fetch_embedding::get_embedding_with_retry(
+            self.vecdb_emb_client.clone(),
+            &constants.endpoint_embeddings_style,
+            &constants.embedding_model,
+            &constants.endpoint_embeddings_template,
+            queries.clone(),
+            api_key,
+            5,
+        ).await?;
+        if embeddings.len() != queries.len() {
+            return Err(format!("asked to vectorize {} queries, got {} embeddings", queries.len(), embeddings.len()));
+        }
+        info!("search batch of {} queries, it took {:.3}s to vectorize them", queries.len(), t0.elapsed().as_secs_f64());
+
+        memories_block_until_vectorized_from_vectorizer(vectorizer_service,
+                                                        5_000).await?;
+
+        let mut handler_locked = vecdb_handler.lock().await;
+        let t1 = std::time::Instant::now();
+        let mut search_results = vec![];
+        for (query, embedding) in queries.into_iter().zip(embeddings.iter()) {
+            let results = handler_locked.vecdb_search(embedding, top_n, vecdb_scope_filter_mb.clone(), None, false).await
+                .map_err(|err| err.to_string())?;
+            info!("search query {:?}", query);
+            search_results.push(SearchResult {
+                query_text: query,
+                results: usefulness_and_rejection(results, constants.distance_reject, constants.distance_metric),
+            });
+        }
+        info!("search batch itself {:.3}s", t1.elapsed().as_secs_f64());
+        Ok(search_results)
+    }
+}
+
+fn usefulness_and_rejection(results: Vec<VecdbRecord>, rejection_threshold: f32, distance_metric: DistanceMetric) -> Vec<VecdbRecord> {
+    let mut dist0 = 0.0;
+    info!("rejection_threshold {:.3}", rejection_threshold);
+    results.into_iter()
+        .filter_map(|rec| usefulness_or_rejection(rec, &mut dist0, rejection_threshold, distance_metric))
+        .collect()
+}
+
+// dist0 is the distance of the nearest record, records must come nearest first
+fn usefulness_or_rejection(mut rec: VecdbRecord, dist0: &mut f32, rejection_threshold: f32, distance_metric: DistanceMetric) -> Option<VecdbRecord> {
+    let distance = distance_metric.distance_for_rejection(rec.distance);
+    if *dist0 == 0.0 {
+        *dist0 = distance;
+    }
+    let last_35_chars = crate::nicer_logs::last_n_chars(&rec.file_path.display().to_string(), 35);
+    rec.usefulness = 100.0 - 75.0 * ((distance - *dist0) / (*dist0 + 0.01)).max(0.0).min(1.0);
+    if distance >= rejection_threshold {
+        info!("distance {:.3} -> dropped {}:{}-{}", rec.distance, last_35_chars, rec.start_line, rec.end_line);
+        None
+    } else {
+        info!("distance {:.3} -> useful {:.1}, found {}:{}-{}", rec.distance, rec.usefulness, last_35_chars, rec.start_line, rec.end_line);
+        Some(rec)
+    }
+}
+
+
+/// The innermost AST symbol that contains the whole hit window, lines in the window are 0-based
+fn enclosing_symbol<'a>(rec: &VecdbRecord, defs: &'a Vec<Arc<AstDefinition>>) -> Option<&'a Arc<AstDefinition>> {
+    let (line1, line2) = (rec.start_line as usize + 1, rec.end_line as usize + 1);
+    defs.iter()
+        .filter(|d| d.full_line1() <= line1 && line2 <= d.full_line2())
+        .min_by_key(|d| d.full_line2() - d.full_line1())
+}
+
+/// Expands each hit to its enclosing symbol when there is one, keeps the raw window otherwise.
+/// Several hits snapped to the same symbol become one, the best of them survives.
+pub async fn snap_results_to_symbols(ast_index: Arc<AMutex<AstDB>>, results: Vec<VecdbRecord>) -> Vec<VecdbRecord> {
+    let mut defs_by_file: HashMap<PathBuf, Vec<Arc<AstDefinition>>> = HashMap::new();
+    for rec in results.iter() {
+        if !defs_by_file.contains_key(&rec.file_path) {
+            let defs = doc_defs(ast_index.clone(), &rec.file_path.to_string_lossy().to_string()).await;
+            defs_by_file.insert(rec.file_path.clone(), defs);
+        }
+    }
+    snap_to_symbols(results, &defs_by_file)
+}
+
+fn snap_to_symbols(results: Vec<VecdbRecord>, defs_by_file: &HashMap<PathBuf, Vec<Arc<AstDefinition>>>) -> Vec<VecdbRecord> {
+    let mut snapped: Vec<VecdbRecord> = vec![];
+    for mut rec in results {
+        let symbol_mb = defs_by_file.get(&rec.file_path).and_then(|defs| enclosing_symbol(&rec, defs));
+        if let Some(symbol) = symbol_mb {
+            rec.start_line = (symbol.full_line1() - 1) as u64;
+            rec.end_line = (symbol.full_line2() - 1) as u64;
+        }
+        let already_there = snapped.iter().any(|r| r.file_path == rec.file_path && r.start_line == rec.start_line && r.end_line == rec.end_line);
+        if !already_there {
+            snapped.push(rec);
+        }
+    }
+    snapped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ast_structs::SymbolType;
+
+    #[test]
+    fn test_parse_extra_model_spec() {
+        assert_eq!(parse_extra_model_spec("text-embedding-3-large:3072"), Ok(("text-embedding-3-large".to_string(), 3072, None)));
+        assert_eq!(parse_extra_model_spec("frog/embed:v2:768"), Ok(("frog/embed:v2".to_string(), 768, None)));
+        assert_eq!(
+            parse_extra_model_spec("gte-small:384@local:/models/gte-small"),
+            Ok(("gte-small".to_string(), 384, Some(("local".to_string(), "/models/gte-small".to_string())))),
+        );
+        assert_eq!(
+            parse_extra_model_spec("frog-embed:768@openai:http://pond:8008/v1/embeddings"),
+            Ok(("frog-embed".to_string(), 768, Some(("openai".to_string(), "http://pond:8008/v1/embeddings".to_string())))),
+        );
+        assert!(parse_extra_model_spec("gte-small:384@local").is_err());
+        assert!(parse_extra_model_spec("gte-small@local:/models/gte-small").is_err());
+        assert!(parse_extra_model_spec("text-embedding-3-large").is_err());
+        assert!(parse_extra_model_spec("frog:big").is_err());
+        assert!(parse_extra_model_spec(":768").is_err());
+    }
+
+    fn frog_chunk(file: &str, distance: f32, vector: Vec<f32>) -> VecdbRecord {
+        VecdbRecord {
+            vector: Some(vector),
+            file_path: PathBuf::from(file),
+            start_line: 0,
+            end_line: 10,
+            distance,
+            usefulness: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_mmr_rerank_spans_more_files() {
+        let candidates = vec![
+            frog_chunk("frog.py", 0.10, vec![1.0, 0.0, 0.0]),
+            frog_chunk("frog.py", 0.11, vec![0.99, 0.01, 0.0]),
+            frog_chunk("frog.py", 0.12, vec![0.98, 0.02, 0.0]),
+            frog_chunk("pond.py", 0.20, vec![0.0, 1.0, 0.0]),
+        ];
+
+        let plain = mmr_rerank(candidates.clone(), 2, 0.0);
+        let diverse = mmr_rerank(candidates, 2, 0.5);
+
+        assert_eq!(plain.iter().map(|r| r.file_path.to_string_lossy().to_string()).collect::<Vec<_>>(), vec!["frog.py", "frog.py"]);
+        assert_eq!(diverse.iter().map(|r| r.file_path.to_string_lossy().to_string()).collect::<Vec<_>>(), vec!["frog.py", "pond.py"]);
+    }
+
+    #[test]
+    fn test_snap_to_symbols_expands_mid_function_hit() {
+        let frog_def = |name: &str, symbol_type: SymbolType, line1: usize, line2: usize| Arc::new(AstDefinition {
+            official_path: vec!["frog".to_string(), name.to_string()],
+            symbol_type,
+            usages: vec![],
+            resolved_type: "".to_string(),
+            this_is_a_class: "".to_string(),
+            this_class_derived_from: vec![],
+            cpath: "frog.py".to_string(),
+            decl_line1: line1,
+            decl_line2: line1,
+            body_line1: line1 + 1,
+            body_line2: line2,
+        });
+        let defs_by_file = HashMap::from([(PathBuf::from("frog.py"), vec![
+            frog_def("Frog", SymbolType::StructDeclaration, 1, 40),
+            frog_def("jump", SymbolType::FunctionDeclaration, 10, 25),
+            frog_def("croak", SymbolType::FunctionDeclaration, 27, 35),
+        ])]);
+        let hit = |file: &str, start_line: u64, end_line: u64, distance: f32| VecdbRecord {
+            start_line,
+            end_line,
+            ..frog_chunk(file, distance, vec![])
+        };
+
+        let snapped = snap_to_symbols(vec![
+            hit("frog.py", 14, 18, 0.1),  // middle of jump()
+            hit("frog.py", 12, 16, 0.2),  // jump() again
+            hit("frog.py", 22, 29, 0.3),  // spans jump() and croak(), only the class has it all
+            hit("pond.py", 3, 7, 0.4),    // no symbols known
+        ], &defs_by_file);
+
+        let ranges = snapped.iter().map(|r| (r.file_path.to_string_lossy().to_string(), r.start_line, r.end_line, r.distance)).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![
+            ("frog.py".to_string(), 9, 24, 0.1),
+            ("frog.py".to_string(), 0, 39, 0.3),
+            ("pond.py".to_string(), 3, 7, 0.4),
+        ]);
+    }
+
+    #[test]
+    fn test_usefulness_and_rejection() {
+        let results = usefulness_and_rejection(vec![
+            frog_chunk("frog.py", 0.10, vec![]),
+            frog_chunk("pond.py", 0.15, vec![]),
+            frog_chunk("mud.py", 0.30, vec![]),
+        ], 0.25, DistanceMetric::Cosine);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].usefulness, 100.0);
+        assert!(results[1].usefulness < results[0].usefulness);
+    }
+
+    #[test]
+    fn test_memories_recency_breaks_ties() {
+        let now = 1_700_000_000.0;
+        let day = 86400.0;
+        let memo = |memid: &str, distance: f32, created_ts: f64, last_used_ts: f64| MemoRecord {
+            memid: memid.to_string(),
+            distance,
+            created_ts,
+            last_used_ts,
+            ..Default::default()
+        };
+        let mut memories = vec![
+            memo("old", 0.30, now - 365.0 * day, 0.0),
+            memo("used_yesterday", 0.30, now - 365.0 * day, now - day),
+            memo("created_last_week", 0.30, now - 7.0 * day, 0.0),
+            memo("close_but_old", 0.20, now - 365.0 * day, 0.0),
+            memo("far_but_new", 0.50, now, 0.0),
+        ];
+        sort_memories_by_score(&mut memories, now, 0.02, 30.0);
+        let order = memories.iter().map(|m| m.memid.as_str()).collect::<Vec<_>>();
+        assert_eq!(order, vec!["close_but_old", "used_yesterday", "created_last_week", "old", "far_but_new"]);
+
+        sort_memories_by_score(&mut memories, now, 0.0, 30.0);
+        assert_eq!(memories[0].memid, "close_but_old");
+        assert_eq!(memories.last().unwrap().memid, "far_but_new");
+    }
+
+    #[test]
+    fn test_distance_reject_validation() {
+        assert_eq!(distance_reject_for_model("thenlper_gte", None), Ok(0.25));
+        assert_eq!(distance_reject_for_model("thenlper_gte", Some(0.4)), Ok(0.4));
+        assert!(distance_reject_for_model("thenlper_gte", Some(1.5)).is_err());
+        assert!(distance_reject_for_model("thenlper_gte", Some(-0.1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_stream_sends_nearest_first() {
+        use structopt::StructOpt;
+        let dir = tempfile::Builder::new().prefix("frog_search_stream").tempdir().unwrap();
+        let _embeddings = mockito::mock("POST", "/v1/embeddings")
+            .match_body(mockito::Matcher::Regex("frog stream query".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"data": [{"embedding": [1.0, 0.0, 0.0], "index": 0}]}).to_string())
+            .create();
+        let constants = VecdbConstants {
+            embedding_model: "frog-embeddings".to_string(),
+            embedding_size: 3,
+            embedding_batch: 64,
+            tokenizer: None,
+            vectorizer_n_ctx: 512,
+            endpoint_embeddings_template: format!("{}/v1/embeddings", mockito::server_url()),
+            endpoint_embeddings_style: "openai".to_string(),
+            splitter_window_size: 256,
+            vecdb_max_files: 10,
+            distance_reject: 0.5,
+            distance_metric: DistanceMetric::Cosine,
+            memories_flush_interval_ms: 300,
+        };
+        let cmdline = CommandLine::from_iter(["refact-lsp"]);
+        let api_key = "frog-key".to_string();
+        let mut vecdb = VecDb::init(&dir.path().to_path_buf(), &dir.path().to_path_buf(), cmdline, constants, vec![], &api_key).await.unwrap();
+        vecdb.vecdb_emb_client = Arc::new(AMutex::new(reqwest::Client::builder().no_proxy().build().unwrap()));
+        vecdb.vectorizer_service.lock().await.vstatus.lock().await.state = "done".to_string();
+        vecdb.vecdb_handler.lock().await.vecdb_records_add(&vec![
+            frog_chunk("/pond/lily.rs", -1.0, vec![0.7, 0.3, 0.0]),
+            frog_chunk("/pond/heron.rs", -1.0, vec![0.0, 0.0, 1.0]),
+            frog_chunk("/pond/frog.rs", -1.0, vec![1.0, 0.0, 0.0]),
+            frog_chunk("/pond/toad.rs", -1.0, vec![0.9, 0.1, 0.0]),
+        ]).await;
+
+        let mut rx = vecdb.vecdb_search_stream("frog stream query".to_string(), 3, None, vec![], None, &api_key).await.unwrap();
+        let nearest = rx.recv().await.unwrap();
+        assert_eq!((nearest.file_path.clone(), nearest.usefulness), (PathBuf::from("/pond/frog.rs"), 100.0));
+        let mut streamed = vec![nearest];
+        while let Some(rec) = rx.recv().await {
+            streamed.push(rec);
+        }
+        let paths = streamed.iter().map(|r| r.file_path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths, vec![PathBuf::from("/pond/frog.rs"), PathBuf::from("/pond/toad.rs"), PathBuf::from("/pond/lily.rs")]);
+        assert!(streamed.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+        let batch = vecdb.vecdb_search("frog stream query".to_string(), 3, None, vec![], None, None, &api_key).await.unwrap();
+        assert_eq!(streamed, batch.results);
+    }
 }
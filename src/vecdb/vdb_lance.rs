@@ -1,7 +1,7 @@
 use std::any::Any;
 use itertools::Itertools;
 use std::fmt::{Debug, Formatter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use arrow::array::ArrayData;
 use arrow::buffer::Buffer;
@@ -11,13 +11,16 @@ use arrow_array::cast::{as_fixed_size_list_array, as_primitive_array, as_string_
 use arrow_array::types::{Float32Type, UInt64Type};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use futures_util::TryStreamExt;
+use tokio::sync::mpsc;
 use lance::dataset::{WriteMode, WriteParams};
+use lance::index::vector::MetricType;
 use tempfile::{tempdir, TempDir};
 use vectordb::database::Database;
 use vectordb::table::Table;
 
-use crate::vecdb::vdb_structs::VecdbRecord;
+use crate::vecdb::vdb_structs::{DistanceMetric, VecdbNamespace, VecdbRecord};
 
+const SEARCH_STREAM_CHANNEL_SIZE: usize = 16;
 
 impl Debug for VecDBHandler {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -31,6 +34,7 @@ pub struct VecDBHandler {
     schema: SchemaRef,
     // data_table_hashes: HashSet<String>,
     embedding_size: i32,
+    distance_metric: DistanceMetric,
 }
 
 fn cosine_similarity(vec1: &Vec<f32>, vec2: &Vec<f32>) -> f32 {
@@ -44,9 +48,108 @@ pub fn cosine_distance(vec1: &Vec<f32>, vec2: &Vec<f32>) -> f32 {
     1.0 - cosine_similarity(vec1, vec2)
 }
 
+// Lance picks the nearest records with its own metric, L2 unless told otherwise
+fn lance_metric_type(distance_metric: DistanceMetric) -> MetricType {
+    match distance_metric {
+        DistanceMetric::Cosine => MetricType::Cosine,
+        DistanceMetric::Dot => MetricType::Dot,
+        DistanceMetric::L2 => MetricType::L2,
+    }
+}
+
+/// Restricts search results to paths matching glob patterns, patterns starting with "!" exclude.
+/// Relative patterns match anywhere in the path, so "src/**/*.rs" works with absolute file paths.
+#[derive(Clone)]
+pub struct PathGlobFilter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl PathGlobFilter {
+    pub fn new(path_globs: &Vec<String>) -> Result<PathGlobFilter, String> {
+        let mut filter = PathGlobFilter { include: vec![], exclude: vec![] };
+        for g in path_globs.iter() {
+            let (is_exclude, pattern) = match g.strip_prefix('!') {
+                Some(p) => (true, p),
+                None => (false, g.as_str()),
+            };
+            let pattern = if pattern.starts_with('/') || pattern.starts_with("**") { pattern.to_string() } else { format!("**/{}", pattern) };
+            let compiled = glob::Pattern::new(&pattern).map_err(|e| format!("bad path glob {:?}: {}", g, e))?;
+            if is_exclude { filter.exclude.push(compiled) } else { filter.include.push(compiled) }
+        }
+        Ok(filter)
+    }
+
+    /// The globs as a lance filter on `scope`, to pick the nearest records among the matching files only.
+    /// LIKE has no "one path component" wildcard, so `*` and `?` become `%` and `_` that also match "/",
+    /// the filter lets through a bit more than the globs and `matches()` has the final word.
+    pub fn like_filter(&self) -> Option<String> {
+        let like = |p: &glob::Pattern| format!("scope LIKE '{}' ESCAPE '\\'", glob_to_like(p.as_str()).0.replace("'", "''"));
+        let mut conditions = vec![];
+        if !self.include.is_empty() {
+            conditions.push(format!("({})", self.include.iter().map(like).join(" OR ")));
+        }
+        // an exclude that lets through more than its glob would hide files the glob doesn't exclude
+        for p in self.exclude.iter().filter(|p| glob_to_like(p.as_str()).1) {
+            conditions.push(format!("NOT ({})", like(p)));
+        }
+        if conditions.is_empty() { None } else { Some(conditions.join(" AND ")) }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches_path_with(path, options))) &&
+            !self.exclude.iter().any(|p| p.matches_path_with(path, options))
+    }
+}
+
+
+// LIKE pattern (escape character is backslash) for a glob, and whether it matches exactly what the glob matches
+fn glob_to_like(glob: &str) -> (String, bool) {
+    let mut like = String::new();
+    let mut exact = true;
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    // "**/" also matches no directories at all, paths are absolute so at the start "/" is always there
+                    if chars.peek() == Some(&'/') && !like.is_empty() {
+                        chars.next();
+                        exact = false;
+                    }
+                } else {
+                    exact = false;
+                }
+                like.push('%');
+            }
+            '?' => {
+                exact = false;
+                like.push('_');
+            }
+            '[' => {
+                // a character class is one character
+                exact = false;
+                while let Some(c) = chars.next() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+                like.push('_');
+            }
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            _ => like.push(c),
+        }
+    }
+    (like, exact)
+}
 
 impl VecDBHandler {
-    pub async fn init(embedding_size: i32) -> Result<VecDBHandler, String> {
+    pub async fn init(embedding_size: i32, distance_metric: DistanceMetric) -> Result<VecDBHandler, String> {
         let data_database_temp_dir = match tempdir() {
             Ok(dir) => dir,
             Err(_) => return Err(format!("{:?}", "Error creating temp dir")),
@@ -80,6 +183,7 @@ impl VecDBHandler {
             data_table,
             // data_table_hashes: HashSet::new(),
             embedding_size,
+            distance_metric,
         })
     }
 
@@ -157,6 +261,13 @@ impl VecDBHandler {
         }
     }
 
+    pub async fn vecdb_records_remove_under(&mut self, dir: &PathBuf) {
+        let delete_query = format!("scope LIKE '{}' ESCAPE '\\'", like_pattern_for_dir(dir).replace("'", "''"));
+        if let Err(err) = self.data_table.delete(delete_query.as_str()).await {
+            tracing::error!("Error deleting {:?} from vecdb: {:?}", dir, err);
+        }
+    }
+
     pub async fn vecdb_records_remove(
         &mut self,
         scopes_to_remove: Vec<String>
@@ -210,6 +321,7 @@ impl VecDBHandler {
         record_batch: RecordBatch,
         include_embedding: bool,
         embedding_to_compare: Option<&Vec<f32>>,
+        distance_metric: DistanceMetric,
     ) -> vectordb::error::Result<Vec<VecdbRecord>> {
         (0..record_batch.num_rows()).map(|idx| {
             let gathered_vec = as_primitive_array::<Float32Type>(
@@ -222,7 +334,7 @@ impl VecDBHandler {
                 .map(|x| x.unwrap()).collect();
             let distance = match embedding_to_compare {
                 None => { -1.0 }
-                Some(embedding) => { cosine_distance(&embedding, &gathered_vec) }
+                Some(embedding) => { distance_metric.distance(&embedding, &gathered_vec) }
             };
             let embedding = match include_embedding {
                 true => Some(gathered_vec),
@@ -252,14 +364,21 @@ impl VecDBHandler {
         embedding: &Vec<f32>,
         top_n: usize,
         vecdb_scope_filter_mb: Option<String>,
+        path_filter_mb: Option<&PathGlobFilter>,
+        include_vectors: bool,
     ) -> vectordb::error::Result<Vec<VecdbRecord>> {
-        let use_prefilter = vecdb_scope_filter_mb.is_some();
+        let filter_mb = match (vecdb_scope_filter_mb, path_filter_mb.and_then(|f| f.like_filter())) {
+            (Some(scope_filter), Some(globs_filter)) => Some(format!("({}) AND {}", scope_filter, globs_filter)),
+            (scope_filter_mb, globs_filter_mb) => scope_filter_mb.or(globs_filter_mb),
+        };
+        let use_prefilter = filter_mb.is_some();
         let query = self
             .data_table
             .clone()
             .search(Some(Float32Array::from(embedding.clone())))
             .prefilter(use_prefilter)
-            .filter(vecdb_scope_filter_mb)
+            .filter(filter_mb)
+            .metric_type(Some(lance_metric_type(self.distance_metric)))
             .limit(top_n)
             .use_index(true)
             .execute()
@@ -267,20 +386,235 @@ impl VecDBHandler {
             .try_collect::<Vec<_>>()
             .await?;
         let record_batch = concat_batches(&self.schema, &query)?;
-        match VecDBHandler::parse_table_iter(record_batch, false, Some(&embedding)) {
-            Ok(records) => {
-                let filtered: Vec<VecdbRecord> = records
+        let records = VecDBHandler::parse_table_iter(record_batch, include_vectors, Some(&embedding), self.distance_metric)?;
+        Ok(records
+            .into_iter()
+            .sorted_unstable_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .dedup()
+            .filter(|rec| path_filter_mb.map(|f| f.matches(&rec.file_path)).unwrap_or(true))
+            .take(top_n)
+            .collect())
+    }
+
+    /// Same records as vecdb_search() without vectors, but the record batches lance produces are parsed and filtered
+    /// one by one in a background task, each record is sent as soon as it passes the filters. Lance returns
+    /// the nearest first, so the receiver can start working on the best ones while the rest are still parsed.
+    pub async fn vecdb_search_stream(
+        &mut self,
+        embedding: &Vec<f32>,
+        top_n: usize,
+        vecdb_scope_filter_mb: Option<String>,
+        path_filter_mb: Option<PathGlobFilter>,
+    ) -> vectordb::error::Result<mpsc::Receiver<VecdbRecord>> {
+        let filter_mb = match (vecdb_scope_filter_mb, path_filter_mb.as_ref().and_then(|f| f.like_filter())) {
+            (Some(scope_filter), Some(globs_filter)) => Some(format!("({}) AND {}", scope_filter, globs_filter)),
+            (scope_filter_mb, globs_filter_mb) => scope_filter_mb.or(globs_filter_mb),
+        };
+        let use_prefilter = filter_mb.is_some();
+        let mut batches = self
+            .data_table
+            .clone()
+            .search(Some(Float32Array::from(embedding.clone())))
+            .prefilter(use_prefilter)
+            .filter(filter_mb)
+            .metric_type(Some(lance_metric_type(self.distance_metric)))
+            .limit(top_n)
+            .use_index(true)
+            .execute()
+            .await?;
+        let (tx, rx) = mpsc::channel(SEARCH_STREAM_CHANNEL_SIZE);
+        let embedding = embedding.clone();
+        let distance_metric = self.distance_metric;
+        tokio::spawn(async move {
+            let mut sent_cnt = 0;
+            let mut last_sent: Option<VecdbRecord> = None;
+            loop {
+                let record_batch = match batches.try_next().await {
+                    Ok(Some(record_batch)) => record_batch,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::error!("vecdb search stream stopped: {}", e);
+                        break;
+                    }
+                };
+                let records = match VecDBHandler::parse_table_iter(record_batch, false, Some(&embedding), distance_metric) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        tracing::error!("vecdb search stream stopped: {}", e);
+                        break;
+                    }
+                };
+                let nearest_first = records
                     .into_iter()
-                    .dedup()
-                    .sorted_unstable_by(|a, b| {
-                        a.distance
-                            .partial_cmp(&b.distance)
-                            .unwrap_or(std::cmp::Ordering::Equal)
-                    })
-                    .collect();
-                Ok(filtered)
+                    .sorted_unstable_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal))
+                    .filter(|rec| path_filter_mb.as_ref().map(|f| f.matches(&rec.file_path)).unwrap_or(true));
+                for rec in nearest_first {
+                    if sent_cnt >= top_n {
+                        return;
+                    }
+                    if last_sent.as_ref() == Some(&rec) {
+                        continue;
+                    }
+                    if tx.send(rec.clone()).await.is_err() {
+                        return;  // nobody listens anymore
+                    }
+                    sent_cnt += 1;
+                    last_sent = Some(rec);
+                }
             }
-            Err(err) => Err(err),
+        });
+        Ok(rx)
+    }
+}
+
+
+/// LIKE pattern (escape character is backslash) that matches everything under the directory, and nothing
+/// in /pond/frog_2 for /pond/frog
+pub fn like_pattern_for_dir(dir: &PathBuf) -> String {
+    let mut dir_str = dir.to_string_lossy().to_string();
+    if !dir_str.ends_with(std::path::MAIN_SEPARATOR) {
+        dir_str.push(std::path::MAIN_SEPARATOR);
+    }
+    let escaped = dir_str.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
+/// Narrows a scope filter down to the namespace, None stays None for all projects
+pub fn scope_filter_in_namespace(filter_mb: Option<String>, namespace: &VecdbNamespace) -> Option<String> {
+    let namespace_filter = match namespace {
+        VecdbNamespace::Project(dir) => format!("(scope LIKE '{}' ESCAPE '\\')", like_pattern_for_dir(dir).replace("'", "''")),
+        VecdbNamespace::AllProjects => return filter_mb,
+    };
+    match filter_mb {
+        Some(filter) => Some(format!("({}) AND {}", filter, namespace_filter)),
+        None => Some(namespace_filter),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vecdb::vdb_structs::vecdb_record_for_tests;
+
+    #[test]
+    fn test_path_glob_filter() {
+        let filter = PathGlobFilter::new(&vec!["src/**/*.rs".to_string(), "!tests/**".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("/pond/src/frog/jump.rs")));
+        assert!(filter.matches(Path::new("/pond/src/main.rs")));
+        assert!(!filter.matches(Path::new("/pond/src/frog/jump.py")));
+        assert!(!filter.matches(Path::new("/pond/tests/src/frog.rs")));
+
+        let only_exclude = PathGlobFilter::new(&vec!["!**/*.md".to_string()]).unwrap();
+        assert!(only_exclude.matches(Path::new("/pond/frog.py")));
+        assert!(!only_exclude.matches(Path::new("/pond/README.md")));
+
+        assert!(PathGlobFilter::new(&vec!["src/[".to_string()]).is_err());
+
+        assert_eq!(glob_to_like("**/src/**/*.rs"), ("%/src/%%.rs".to_string(), false));
+        assert_eq!(glob_to_like("**/frog_[ab]/**"), ("%/frog\\__/%".to_string(), false));
+        assert_eq!(glob_to_like("**/tests/**"), ("%/tests/%".to_string(), true));
+        assert_eq!(
+            filter.like_filter().unwrap(),
+            "(scope LIKE '%/src/%%.rs' ESCAPE '\\') AND NOT (scope LIKE '%/tests/%' ESCAPE '\\')",
+        );
+        assert_eq!(only_exclude.like_filter(), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_stays_in_project_namespace() {
+        let mut handler = VecDBHandler::init(3, DistanceMetric::Cosine).await.unwrap();
+        handler.vecdb_records_add(&vec![
+            vecdb_record_for_tests("/home/user/frog_pond/frog.py", Some(vec![1.0, 0.0, 0.0])),
+            vecdb_record_for_tests("/home/user/frog_pond/lily.py", Some(vec![0.9, 0.1, 0.0])),
+            vecdb_record_for_tests("/home/user/frog_pond_2/toad.py", Some(vec![1.0, 0.05, 0.0])),
+        ]).await;
+
+        let active = VecdbNamespace::Project(PathBuf::from("/home/user/frog_pond"));
+        let found = handler.vecdb_search(&vec![1.0, 0.0, 0.0], 10, scope_filter_in_namespace(None, &active), None, false).await.unwrap();
+        let paths = found.iter().map(|r| r.file_path.clone()).sorted().collect::<Vec<_>>();
+        assert_eq!(paths, vec![PathBuf::from("/home/user/frog_pond/frog.py"), PathBuf::from("/home/user/frog_pond/lily.py")]);
+
+        let found = handler.vecdb_search(&vec![1.0, 0.0, 0.0], 10, scope_filter_in_namespace(None, &VecdbNamespace::AllProjects), None, false).await.unwrap();
+        assert_eq!(found.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_path_globs_keep_nearest_first() {
+        let mut handler = VecDBHandler::init(3, DistanceMetric::Cosine).await.unwrap();
+        handler.vecdb_records_add(&vec![
+            vecdb_record_for_tests("/pond/src/frog.rs", Some(vec![1.0, 0.0, 0.0])),
+            vecdb_record_for_tests("/pond/src/lily.rs", Some(vec![0.7, 0.3, 0.0])),
+            vecdb_record_for_tests("/pond/tests/toad.rs", Some(vec![0.9, 0.1, 0.0])),
+            vecdb_record_for_tests("/pond/src/newt.py", Some(vec![0.5, 0.5, 0.0])),
+            vecdb_record_for_tests("/pond/src/heron.rs", Some(vec![0.0, 0.0, 1.0])),
+        ]).await;
+        let filter = PathGlobFilter::new(&vec!["src/**/*.rs".to_string()]).unwrap();
+
+        let found = handler.vecdb_search(&vec![1.0, 0.0, 0.0], 3, None, Some(&filter), false).await.unwrap();
+        let paths = found.iter().map(|r| r.file_path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths, vec![PathBuf::from("/pond/src/frog.rs"), PathBuf::from("/pond/src/lily.rs"), PathBuf::from("/pond/src/heron.rs")]);
+    }
+
+    #[tokio::test]
+    async fn test_selective_path_glob_finds_far_records() {
+        let mut handler = VecDBHandler::init(3, DistanceMetric::Cosine).await.unwrap();
+        let mut records = (0..30).map(|i| vecdb_record_for_tests(&format!("/pond/tadpoles/tadpole_{}.py", i), Some(vec![1.0, 0.01 * i as f32, 0.0]))).collect::<Vec<_>>();
+        records.push(vecdb_record_for_tests("/pond/frogs/frog.rs", Some(vec![0.0, 1.0, 0.0])));
+        records.push(vecdb_record_for_tests("/pond/frogs/toad.rs", Some(vec![0.0, 0.0, 1.0])));
+        handler.vecdb_records_add(&records).await;
+        let filter = PathGlobFilter::new(&vec!["frogs/*.rs".to_string()]).unwrap();
+
+        let found = handler.vecdb_search(&vec![1.0, 0.0, 0.0], 2, None, Some(&filter), false).await.unwrap();
+
+        let paths = found.iter().map(|r| r.file_path.to_string_lossy().to_string()).sorted().collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/pond/frogs/frog.rs", "/pond/frogs/toad.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_order_depends_on_distance_metric() {
+        // a long vector pointing the same way as the query, and a short one that is close but at an angle
+        let records = vec![
+            vecdb_record_for_tests("/pond/big_frog.py", Some(vec![10.0, 1.0, 0.0])),
+            vecdb_record_for_tests("/pond/small_frog.py", Some(vec![0.6, 0.6, 0.0])),
+        ];
+        let query = vec![1.0, 0.0, 0.0];
+        let mut order_by_metric = vec![];
+        for metric in [DistanceMetric::Cosine, DistanceMetric::L2, DistanceMetric::Dot] {
+            let mut handler = VecDBHandler::init(3, metric).await.unwrap();
+            handler.vecdb_records_add(&records).await;
+            let found = handler.vecdb_search(&query, 2, None, None, false).await.unwrap();
+            assert!(found.windows(2).all(|w| w[0].distance <= w[1].distance));
+            order_by_metric.push(found.iter().map(|r| r.file_path.to_string_lossy().to_string()).collect::<Vec<_>>());
+        }
+        assert_eq!(order_by_metric[0], vec!["/pond/big_frog.py", "/pond/small_frog.py"]);
+        assert_eq!(order_by_metric[1], vec!["/pond/small_frog.py", "/pond/big_frog.py"]);
+        assert_eq!(order_by_metric[2], vec!["/pond/big_frog.py", "/pond/small_frog.py"]);
+
+        // more records than top_n, so lance has to pick the nearest ones with the same metric
+        let more_records = vec![
+            vecdb_record_for_tests("/pond/big_frog.py", Some(vec![10.0, 1.0, 0.0])),
+            vecdb_record_for_tests("/pond/small_frog.py", Some(vec![0.6, 0.6, 0.0])),
+            vecdb_record_for_tests("/pond/heron.py", Some(vec![0.0, 0.0, 5.0])),
+            vecdb_record_for_tests("/pond/snake.py", Some(vec![-3.0, 2.0, 0.0])),
+        ];
+        for (metric, nearest) in [
+            (DistanceMetric::Cosine, "/pond/big_frog.py"),
+            (DistanceMetric::L2, "/pond/small_frog.py"),
+            (DistanceMetric::Dot, "/pond/big_frog.py"),
+        ] {
+            let mut handler = VecDBHandler::init(3, metric).await.unwrap();
+            handler.vecdb_records_add(&more_records).await;
+            let found = handler.vecdb_search(&query, 1, None, None, false).await.unwrap();
+            assert_eq!(found.iter().map(|r| r.file_path.to_string_lossy().to_string()).collect::<Vec<_>>(), vec![nearest], "{:?}", metric);
         }
+
+        assert_eq!(DistanceMetric::from_caps(""), Ok(DistanceMetric::Cosine));
+        assert_eq!(DistanceMetric::from_caps("L2"), Ok(DistanceMetric::L2));
+        assert!(DistanceMetric::from_caps("manhattan").is_err());
     }
 }
@@ -17,8 +17,14 @@ impl VecdbSearch for VecDbRemote {
         query: String,
         top_n: usize,
         _vecdb_scope_filter_mb: Option<String>,
+        path_globs: Vec<String>,
+        _model_mb: Option<String>,
+        _diversity_mb: Option<f32>,
         _api_key: &String,
     ) -> Result<SearchResult, String> {
+        if !path_globs.is_empty() {
+            return Err("path_globs are not supported by the remote vecdb".to_string());
+        }
         let url = "http://127.0.0.1:8008/v1/vdb-search".to_string();
         let mut headers = HeaderMap::new();
         // headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", self.token)).unwrap());
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use indexmap::IndexMap;
 use tokenizers::Tokenizer;
 use async_trait::async_trait;
+use tokio::sync::mpsc;
 
 
 #[async_trait]
@@ -15,8 +16,86 @@ pub trait VecdbSearch: Send {
         query: String,
         top_n: usize,
         filter_mb: Option<String>,
+        path_globs: Vec<String>,  // like "src/**/*.rs", "!tests/**" excludes, empty means all files
+        model_mb: Option<String>,  // None is the default embedding model
+        diversity_mb: Option<f32>,  // None is plain nearest chunks, see mmr_rerank
         api_key: &String,
     ) -> Result<SearchResult, String>;
+
+    // Several queries at once, implementations can vectorize them in one request
+    async fn vecdb_search_batch(
+        &self,
+        queries: Vec<String>,
+        top_n: usize,
+        filter_mb: Option<String>,
+        api_key: &String,
+    ) -> Result<Vec<SearchResult>, String> {
+        let mut results = vec![];
+        for query in queries {
+            results.push(self.vecdb_search(query, top_n, filter_mb.clone(), vec![], None, None, api_key).await?);
+        }
+        Ok(results)
+    }
+
+    // Results one by one nearest first, to start working on the best ones before the rest are found.
+    // Diversity needs all the candidates at once, so there's no diversity here.
+    async fn vecdb_search_stream(
+        &self,
+        query: String,
+        top_n: usize,
+        filter_mb: Option<String>,
+        path_globs: Vec<String>,
+        model_mb: Option<String>,
+        api_key: &String,
+    ) -> Result<mpsc::Receiver<VecdbRecord>, String> {
+        let search_result = self.vecdb_search(query, top_n, filter_mb, path_globs, model_mb, None, api_key).await?;
+        let (tx, rx) = mpsc::channel(search_result.results.len().max(1));
+        for rec in search_result.results {
+            let _ = tx.try_send(rec);
+        }
+        Ok(rx)
+    }
+}
+
+/// What the embedding model was trained for, all of them are turned into a distance where lower is nearer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceMetric {
+    #[default]
+    Cosine,
+    Dot,
+    L2,
+}
+
+impl DistanceMetric {
+    pub fn from_caps(metric: &str) -> Result<DistanceMetric, String> {
+        match metric.to_lowercase().as_str() {
+            "" | "cosine" => Ok(DistanceMetric::Cosine),
+            "dot" => Ok(DistanceMetric::Dot),
+            "l2" => Ok(DistanceMetric::L2),
+            _ => Err(format!("embedding_distance_metric must be one of cosine, dot, l2, got {:?}", metric)),
+        }
+    }
+
+    pub fn distance(&self, vec1: &Vec<f32>, vec2: &Vec<f32>) -> f32 {
+        let dot_product: f32 = vec1.iter().zip(vec2).map(|(x, y)| x * y).sum();
+        match self {
+            DistanceMetric::Cosine => {
+                let magnitude_vec1: f32 = vec1.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+                let magnitude_vec2: f32 = vec2.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+                1.0 - dot_product / (magnitude_vec1 * magnitude_vec2)
+            }
+            DistanceMetric::Dot => 1.0 - dot_product,
+            DistanceMetric::L2 => vec1.iter().zip(vec2).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt(),
+        }
+    }
+
+    // usefulness and rejection want a non-negative distance
+    pub fn distance_for_rejection(&self, distance: f32) -> f32 {
+        match self {
+            DistanceMetric::Cosine => distance.abs(),
+            DistanceMetric::Dot | DistanceMetric::L2 => distance.max(0.0),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +110,9 @@ pub struct VecdbConstants {
     pub endpoint_embeddings_style: String,
     pub splitter_window_size: usize,
     pub vecdb_max_files: usize,
+    pub distance_reject: f32,  // search results with a larger distance are dropped
+    pub distance_metric: DistanceMetric,
+    pub memories_flush_interval_ms: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,10 +123,20 @@ pub struct VecDbStatus {
     pub vectors_made_since_start: usize,
     pub db_size: usize,
     pub db_cache_size: usize,
-    pub state: String,   // "starting", "parsing", "done", "cooldown"
+    pub state: String,   // "starting", "parsing", "done", "cooldown", "paused"
     pub queue_additions: bool,
     pub vecdb_max_files_hit: bool,
     pub vecdb_errors: IndexMap<String, usize>,
+    #[serde(default)]
+    pub files_skipped_by_cache: usize,  // unchanged since last vectorized, restored without splitting or embedding
+    #[serde(default)]
+    pub cache_hits: usize,  // chunks that got their vector from the embeddings cache, since the last full reindex
+    #[serde(default)]
+    pub cache_misses: usize,  // chunks not found in the cache, sent to the embedding model
+    #[serde(default)]
+    pub embeddings_computed: usize,  // vectors actually returned by the embedding model, since the last full reindex
+    #[serde(default)]
+    pub other_models: IndexMap<String, VecDbStatus>,  // embedding models from --vecdb-extra-model
 }
 
 
@@ -58,6 +150,19 @@ pub struct VecdbRecord {
     pub usefulness: f32,
 }
 
+// A record for lines 0..10 of path, distance is not known yet as if it was just added
+#[cfg(test)]
+pub fn vecdb_record_for_tests(path: &str, vector: Option<Vec<f32>>) -> VecdbRecord {
+    VecdbRecord {
+        vector,
+        file_path: PathBuf::from(path),
+        start_line: 0,
+        end_line: 10,
+        distance: -1.0,
+        usefulness: 0.0,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SplitResult {
     pub file_path: PathBuf,
@@ -94,6 +199,37 @@ pub struct MemoRecord {
     pub mstat_correct: f64,
     pub mstat_relevant: f64,
     pub mstat_times_used: i32,
+    #[serde(default)]
+    pub created_ts: f64,
+    #[serde(default)]
+    pub last_used_ts: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VecdbTestScope {
+    Include,   // tests are searched together with the rest of the code
+    Exclude,
+    Only,
+}
+
+// Records carry no project of their own: the scope column is the file path, so a workspace folder
+// owns everything under it. With several projects open, a search stays inside the active one unless asked
+#[derive(Debug, Clone, PartialEq)]
+pub enum VecdbNamespace {
+    Project(PathBuf),
+    AllProjects,
+}
+
+pub fn is_in_test_scope(record: &VecdbRecord, project_dirs: &[PathBuf]) -> bool {
+    crate::file_filter::is_test_file(&record.file_path, project_dirs)
+}
+
+pub fn filter_by_test_scope(records: Vec<VecdbRecord>, test_scope: VecdbTestScope, project_dirs: &[PathBuf]) -> Vec<VecdbRecord> {
+    match test_scope {
+        VecdbTestScope::Include => records,
+        VecdbTestScope::Exclude => records.into_iter().filter(|r| !is_in_test_scope(r, project_dirs)).collect(),
+        VecdbTestScope::Only => records.into_iter().filter(|r| is_in_test_scope(r, project_dirs)).collect(),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -110,4 +246,152 @@ pub struct OngoingWork {
     pub ongoing_progress: IndexMap<String, serde_json::Value>,                 // any dict that model sends to its future self, no additional operations on top
     pub ongoing_action_sequences: Vec<IndexMap<String, serde_json::Value>>,    // a new sequence appended to the list
     pub ongoing_output: IndexMap<String, IndexMap<String, serde_json::Value>>, // this dict updated from new data each attempt
+    #[serde(default)]
+    pub ongoing_failures: Vec<IndexMap<String, serde_json::Value>>,            // tool calls that errored, so the next attempt doesn't repeat them
+}
+
+const ONGOING_FAILURES_MAX: usize = 20;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OngoingToolFailure {
+    pub tool: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+    pub error: String,
+}
+
+impl OngoingWork {
+    pub fn new(goal: &str) -> Self {
+        OngoingWork {
+            ongoing_goal: goal.to_string(),
+            ongoing_attempt_n: 0,
+            ongoing_progress: IndexMap::new(),
+            ongoing_action_sequences: vec![],
+            ongoing_output: IndexMap::new(),
+            ongoing_failures: vec![],
+        }
+    }
+
+    /// One more attempt: progress is replaced, the action sequence is appended, output keys are updated
+    pub fn update(
+        &mut self,
+        progress: IndexMap<String, serde_json::Value>,
+        action_sequence: IndexMap<String, serde_json::Value>,
+        output: IndexMap<String, IndexMap<String, serde_json::Value>>,
+        failures: &Vec<OngoingToolFailure>,
+    ) {
+        self.ongoing_attempt_n += 1;
+        self.ongoing_progress = progress;
+        if !action_sequence.is_empty() {
+            self.ongoing_action_sequences.push(action_sequence);
+        }
+        for (k, v) in output {
+            self.ongoing_output.entry(k).or_default().extend(v);
+        }
+        for f in failures.iter() {
+            self.record_failure(&f.tool, &f.args, &f.error);
+        }
+    }
+
+    // The same call failing again replaces the older record, only the most recent failures are kept
+    fn record_failure(&mut self, tool_name: &str, tool_args: &serde_json::Value, error: &str) {
+        self.ongoing_failures.retain(|f| f.get("tool") != Some(&serde_json::json!(tool_name)) || f.get("args") != Some(tool_args));
+        let mut failure = IndexMap::new();
+        failure.insert("attempt".to_string(), serde_json::json!(self.ongoing_attempt_n));
+        failure.insert("tool".to_string(), serde_json::json!(tool_name));
+        failure.insert("args".to_string(), tool_args.clone());
+        failure.insert("error".to_string(), serde_json::json!(error));
+        self.ongoing_failures.push(failure);
+        if self.ongoing_failures.len() > ONGOING_FAILURES_MAX {
+            self.ongoing_failures.remove(0);
+        }
+    }
+
+    pub fn dump(&self, include_failures: bool) -> String {
+        let mut ordered_map = IndexMap::new();
+        ordered_map.insert("PROGRESS".to_string(), serde_json::Value::Object(self.ongoing_progress.clone().into_iter().collect()));
+        let action_sequences: Vec<serde_json::Value> = self.ongoing_action_sequences
+            .iter()
+            .map(|map| serde_json::Value::Object(map.clone().into_iter().collect()))
+            .collect();
+        ordered_map.insert("TRIED_ACTION_SEQUENCES".to_string(), serde_json::Value::Array(action_sequences));
+        let output_value: serde_json::Value = serde_json::Value::Object(
+            self.ongoing_output
+                .clone()
+                .into_iter()
+                .map(|(k, v)| (k, serde_json::Value::Object(v.into_iter().collect())))
+                .collect()
+        );
+        ordered_map.insert("OUTPUT".to_string(), output_value);
+        if include_failures && !self.ongoing_failures.is_empty() {
+            let failures: Vec<serde_json::Value> = self.ongoing_failures
+                .iter()
+                .map(|map| serde_json::Value::Object(map.clone().into_iter().collect()))
+                .collect();
+            ordered_map.insert("KNOWN_FAILURES".to_string(), serde_json::Value::Array(failures));
+        }
+        format!(
+            "💿 Ongoing session with goal: {}\nAttempt number: {}\nSummary of progress:\n\n{}\n\n",
+            self.ongoing_goal,
+            self.ongoing_attempt_n,
+            serde_json::to_string_pretty(&ordered_map).unwrap()
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_tool_call_in_ongoing_dump() {
+        let mut ongoing = OngoingWork::new("teach the frog to jump");
+        let failure = OngoingToolFailure {
+            tool: "cmdline".to_string(),
+            args: serde_json::json!({"command": "cargo test frog_jump"}),
+            error: "error[E0425]: cannot find function `jump`".to_string(),
+        };
+        let mut action_sequence = IndexMap::new();
+        action_sequence.insert("cmdline".to_string(), serde_json::json!("cargo test frog_jump"));
+        let mut output = IndexMap::new();
+        output.insert("frog.rs".to_string(), IndexMap::from([("jump".to_string(), serde_json::json!("missing"))]));
+        ongoing.update(IndexMap::new(), action_sequence.clone(), output, &vec![failure.clone()]);
+        ongoing.update(IndexMap::new(), action_sequence, IndexMap::new(), &vec![failure]);
+
+        let dump = ongoing.dump(true);
+        let failures_at = dump.find("\"KNOWN_FAILURES\"").expect(&dump);
+        let failures = &dump[failures_at..];
+        assert!(failures.contains("cargo test frog_jump"));
+        assert!(failures.contains("cannot find function `jump`"));
+        assert_eq!(ongoing.ongoing_attempt_n, 2);
+        assert_eq!(ongoing.ongoing_action_sequences.len(), 2);
+        assert_eq!(ongoing.ongoing_output["frog.rs"]["jump"], serde_json::json!("missing"));
+        assert_eq!(ongoing.ongoing_failures.len(), 1);
+        assert_eq!(ongoing.ongoing_failures[0]["attempt"], serde_json::json!(2));
+        assert!(!ongoing.dump(false).contains("KNOWN_FAILURES"));
+    }
+
+    #[test]
+    fn test_test_file_edit_goes_to_test_scope() {
+        let test_edit = vecdb_record_for_tests("/home/user/frog_project/tests/test_frog.py", None);
+        let results = vec![vecdb_record_for_tests("/home/user/frog_project/frog.py", None), test_edit.clone()];
+
+        let project_dirs = vec![PathBuf::from("/home/user/frog_project")];
+        assert!(is_in_test_scope(&test_edit, &project_dirs));
+        assert_eq!(filter_by_test_scope(results.clone(), VecdbTestScope::Exclude, &project_dirs), vec![vecdb_record_for_tests("/home/user/frog_project/frog.py", None)]);
+        assert_eq!(filter_by_test_scope(results.clone(), VecdbTestScope::Only, &project_dirs), vec![test_edit]);
+        assert_eq!(filter_by_test_scope(results.clone(), VecdbTestScope::Include, &project_dirs).len(), 2);
+    }
+
+    #[test]
+    fn test_project_inside_a_tests_dir_is_not_all_tests() {
+        let project_dirs = vec![PathBuf::from("/home/user/tests/frog_project")];
+        let test_edit = vecdb_record_for_tests("/home/user/tests/frog_project/tests/test_frog.py", None);
+        let results = vec![vecdb_record_for_tests("/home/user/tests/frog_project/frog.py", None), test_edit.clone()];
+
+        assert!(!is_in_test_scope(&vecdb_record_for_tests("/home/user/tests/frog_project/frog.py", None), &project_dirs));
+        assert_eq!(filter_by_test_scope(results.clone(), VecdbTestScope::Exclude, &project_dirs), vec![vecdb_record_for_tests("/home/user/tests/frog_project/frog.py", None)]);
+        assert_eq!(filter_by_test_scope(results, VecdbTestScope::Only, &project_dirs), vec![test_edit]);
+    }
 }
@@ -5,39 +5,90 @@ use std::io::Write;
 use std::ops::Div;
 use std::option::Option;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::{Mutex as AMutex, Notify as ANotify, RwLock as ARwLock};
 use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
 use crate::ast::file_splitter::AstBasedFileSplitter;
+use crate::ast::notebook::is_notebook;
 use crate::fetch_embedding::get_embedding_with_retry;
 use crate::files_in_workspace::{is_path_to_enqueue_valid, Document};
 use crate::global_context::GlobalContext;
 use crate::knowledge::{vectorize_dirty_memories, MemoriesDatabase};
-use crate::vecdb::vdb_cache::VecDBCache;
+use crate::vecdb::vdb_cache::{FileVectorizedMarker, VecDBCache};
 use crate::vecdb::vdb_lance::VecDBHandler;
 use crate::vecdb::vdb_structs::{SimpleTextHashVector, SplitResult, VecDbStatus, VecdbConstants, VecdbRecord};
 
 const DEBUG_WRITE_VECDB_FILES: bool = false;
 const COOLDOWN_SECONDS: u64 = 10;
+const SPLITTER_VERSION: u32 = 1;  // bump when the splitter starts cutting files differently
 
 
 enum MessageToVecdbThread {
     RegularDocument(String),
     ImmediatelyRegularDocument(String),
+    UnchangedDocument(FileVectorizedMarker),
     MemoriesSomethingDirty(),
 }
 
+// Memories arrive one by one, a single vectorization pass for all of them added within the interval
+struct MemoriesFlushDebounce {
+    interval: Duration,
+    dirty_since: Option<Instant>,
+}
+
+impl MemoriesFlushDebounce {
+    fn new(interval: Duration) -> Self {
+        MemoriesFlushDebounce { interval, dirty_since: None }
+    }
+
+    fn mark_dirty(&mut self, now: Instant) {
+        self.dirty_since.get_or_insert(now);
+    }
+
+    fn is_pending(&self) -> bool {
+        self.dirty_since.is_some()
+    }
+
+    fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.dirty_since.map(|since| self.interval.saturating_sub(now.duration_since(since)))
+    }
+
+    // true once per burst, when the interval since the first dirty mark is over
+    fn take_ready(&mut self, now: Instant) -> bool {
+        if self.time_left(now) == Some(Duration::ZERO) {
+            self.dirty_since = None;
+            return true;
+        }
+        false
+    }
+}
+
 pub struct FileVectorizerService {
     pub vecdb_handler: Arc<AMutex<VecDBHandler>>,
     pub vecdb_cache: Arc<AMutex<VecDBCache>>,
     pub vstatus: Arc<AMutex<VecDbStatus>>,
     pub vstatus_notify: Arc<ANotify>,   // fun stuff https://docs.rs/tokio/latest/tokio/sync/struct.Notify.html
+    pub vecdb_paused: Arc<AtomicBool>,
     constants: VecdbConstants,
     api_key: String,
     memdb: Arc<AMutex<MemoriesDatabase>>,
     vecdb_todo: Arc<AMutex<VecDeque<MessageToVecdbThread>>>,
+    splitter_fingerprint: String,
+}
+
+// Everything that decides where the chunks of a file start and end, markers with another fingerprint are stale
+fn splitter_fingerprint(constants: &VecdbConstants) -> String {
+    let tokenizer_hash = constants.tokenizer.as_ref()
+        .and_then(|t| t.read().ok()?.to_string(false).ok())
+        .map(|json| crate::ast::chunk_utils::official_text_hashing_function(&json))
+        .unwrap_or_default();
+    format!(
+        "v{} {} window={} n_ctx={} tokenizer={}",
+        SPLITTER_VERSION, constants.embedding_model, constants.splitter_window_size, constants.vectorizer_n_ctx, tokenizer_hash,
+    )
 }
 
 async fn vectorize_batch_from_q(
@@ -79,6 +130,7 @@ async fn vectorize_batch_from_q(
         let mut vstatus_locked = vstatus.lock().await;
         vstatus_locked.requests_made_since_start += 1;
         vstatus_locked.vectors_made_since_start += batch_result.len();
+        vstatus_locked.embeddings_computed += batch_result.len();
     }
 
     let mut send_to_cache = vec![];
@@ -125,6 +177,7 @@ async fn from_splits_to_vecdb_records_applying_cache(
     ready_to_vecdb: &mut Vec<VecdbRecord>,
     run_actual_model_on_these: &mut Vec<SplitResult>,
     vecdb_cache_arc: Arc<AMutex<VecDBCache>>,
+    vstatus: Arc<AMutex<VecDbStatus>>,
     group_size: usize,
 ) {
     while !splits.is_empty() {
@@ -135,6 +188,12 @@ async fn from_splits_to_vecdb_records_applying_cache(
         let vectors_maybe = vecdb_cache_arc.lock().await.fetch_vectors_from_cache(&batch).await;
         if let Ok(vectors) = vectors_maybe {
             // info!("query cache {} -> {} records {:.3}s", batch.len(), vectors.len(), t0.elapsed().as_secs_f32());
+            let misses = vectors.iter().filter(|v| v.is_none()).count();
+            {
+                let mut vstatus_locked = vstatus.lock().await;
+                vstatus_locked.cache_hits += vectors.len() - misses;
+                vstatus_locked.cache_misses += misses;
+            }
             for (split, maybe_vector) in batch.iter().zip(vectors.iter()) {
                 if maybe_vector.is_none() {
                     run_actual_model_on_these.push(split.clone());
@@ -155,6 +214,37 @@ async fn from_splits_to_vecdb_records_applying_cache(
     }
 }
 
+fn file_mtime_ms(path: &str) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+// None if some vectors are gone from the cache, then the file has to be vectorized as usual
+async fn records_from_marker(
+    marker: &FileVectorizedMarker,
+    vecdb_cache_arc: Arc<AMutex<VecDBCache>>,
+) -> Option<Vec<VecdbRecord>> {
+    let splits = marker.chunks.iter().map(|(start_line, end_line, window_text_hash)| SplitResult {
+        file_path: marker.file_path.clone().into(),
+        window_text: "".to_string(),
+        window_text_hash: window_text_hash.clone(),
+        start_line: *start_line,
+        end_line: *end_line,
+        symbol_path: "".to_string(),
+    }).collect::<Vec<_>>();
+    let vectors = vecdb_cache_arc.lock().await.fetch_vectors_from_cache(&splits).await.ok()?;
+    splits.iter().zip(vectors.into_iter()).map(|(split, vector)| {
+        Some(VecdbRecord {
+            vector: Some(vector?),
+            file_path: split.file_path.clone(),
+            start_line: split.start_line,
+            end_line: split.end_line,
+            distance: -1.0,
+            usefulness: 0.0,
+        })
+    }).collect()
+}
+
 async fn vectorize_thread(
     client: Arc<AMutex<reqwest::Client>>,
     vservice: Arc<AMutex<FileVectorizerService>>,
@@ -172,8 +262,10 @@ async fn vectorize_thread(
         vecdb_handler_arc,
         vstatus,
         vstatus_notify,
+        vecdb_paused,
         vecdb_cache_arc,
-        api_key
+        api_key,
+        splitter_fingerprint,
     ) = {
         let vservice_locked = vservice.lock().await;
         (
@@ -183,13 +275,36 @@ async fn vectorize_thread(
             vservice_locked.vecdb_handler.clone(),
             vservice_locked.vstatus.clone(),
             vservice_locked.vstatus_notify.clone(),
+            vservice_locked.vecdb_paused.clone(),
             vservice_locked.vecdb_cache.clone(),
-            vservice_locked.api_key.clone()
+            vservice_locked.api_key.clone(),
+            vservice_locked.splitter_fingerprint.clone(),
         )
     };
 
     let mut last_updated: HashMap<String, SystemTime> = HashMap::new();
+    let mut memories_flush = MemoriesFlushDebounce::new(Duration::from_millis(constants.memories_flush_interval_ms));
+    // files split but not yet in vecdb, they get a marker when ready_to_vecdb is flushed
+    let mut markers_pending: Vec<FileVectorizedMarker> = vec![];
     loop {
+        if vecdb_paused.load(Ordering::SeqCst) {
+            // queue stays untouched, it will be picked up after resume
+            let state_changed = {
+                let mut vstatus_locked = vstatus.lock().await;
+                let changed = vstatus_locked.state != "paused";
+                vstatus_locked.state = "paused".to_string();
+                changed
+            };
+            if state_changed {
+                info!("vectorizer paused");
+                vstatus_notify.notify_waiters();
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_millis(1_000)) => {},
+                _ = vstatus_notify.notified() => {},
+            }
+            continue;
+        }
         let mut work_on_one: Option<MessageToVecdbThread> = None;
         let current_time = SystemTime::now();
         let mut vstatus_changed = false;
@@ -200,12 +315,18 @@ async fn vectorize_thread(
                     MessageToVecdbThread::RegularDocument(cpath) => {
                         last_updated.insert(cpath, current_time);
                     }
-                    MessageToVecdbThread::ImmediatelyRegularDocument(_) | MessageToVecdbThread::MemoriesSomethingDirty() => {
+                    MessageToVecdbThread::MemoriesSomethingDirty() => {
+                        memories_flush.mark_dirty(Instant::now());
+                    }
+                    MessageToVecdbThread::ImmediatelyRegularDocument(_) | MessageToVecdbThread::UnchangedDocument(_) => {
                         work_on_one = Some(msg);
                         break;
                     }
                 }
             }
+            if work_on_one.is_none() && memories_flush.take_ready(Instant::now()) {
+                work_on_one = Some(MessageToVecdbThread::MemoriesSomethingDirty());
+            }
             if work_on_one.is_none() {
                 let doc_to_remove = last_updated.iter()
                     .find(|(_, time)| time.elapsed().unwrap_or_default().as_secs() > COOLDOWN_SECONDS)
@@ -223,7 +344,8 @@ async fn vectorize_thread(
                 let mut vstatus_locked = vstatus.lock().await;
                 vstatus_locked.files_unprocessed = files_unprocessed;
                 vstatus_locked.files_total = files_total;
-                vstatus_locked.queue_additions = false;
+                // memories waiting for the flush are not vectorized yet, memories_block_until_vectorized keeps waiting
+                vstatus_locked.queue_additions = memories_flush.is_pending();
                 if work_on_one.is_some() && vstatus_locked.state != "parsing" {
                     vstatus_locked.state = "parsing".to_string();
                     vstatus_changed = true;
@@ -266,6 +388,11 @@ async fn vectorize_thread(
             assert!(run_actual_model_on_these.len() == 0);
             // This function assumes it can delete records with the filenames mentioned, therefore assert above
             _send_to_vecdb(vecdb_handler_arc.clone(), &mut ready_to_vecdb).await;
+            if !markers_pending.is_empty() {
+                if let Err(err) = vecdb_cache_arc.lock().await.files_vectorized_set(markers_pending.drain(..).collect()).await {
+                    tracing::error!("cannot save vectorized files: {}", err);
+                }
+            }
         }
 
         if (files_unprocessed + 99).div(100) != (reported_unprocessed + 99).div(100) {
@@ -278,6 +405,20 @@ async fn vectorize_thread(
                 Some(MessageToVecdbThread::ImmediatelyRegularDocument(cpath)) => {
                     cpath
                 }
+                Some(MessageToVecdbThread::UnchangedDocument(marker)) => {
+                    match records_from_marker(&marker, vecdb_cache_arc.clone()).await {
+                        Some(records) => {
+                            {
+                                let mut vstatus_locked = vstatus.lock().await;
+                                vstatus_locked.files_skipped_by_cache += 1;
+                                vstatus_locked.cache_hits += records.len();
+                            }
+                            ready_to_vecdb.extend(records);
+                            continue;
+                        }
+                        None => marker.file_path,
+                    }
+                }
                 Some(MessageToVecdbThread::MemoriesSomethingDirty()) => {
                     info!("MEMDB VECTORIZER START");
                     let r = vectorize_dirty_memories(
@@ -291,6 +432,11 @@ async fn vectorize_thread(
                     info!("/MEMDB {:?}", r);
                     continue;
                 }
+                None if memories_flush.is_pending() => {
+                    let time_left = memories_flush.time_left(Instant::now()).unwrap_or_default();
+                    tokio::time::sleep(time_left.min(Duration::from_millis(1_000))).await;
+                    continue;
+                }
                 None if last_updated.is_empty() => {
                     // no more files
                     assert!(run_actual_model_on_these.is_empty());
@@ -343,16 +489,35 @@ async fn vectorize_thread(
         if let Err(_) = doc.update_text_from_disk(gcx.clone()).await {
             info!("{} cannot read, deleting from index", last_30_chars);  // don't care what the error is, trivial (or privacy)
             vecdb_handler_arc.lock().await.vecdb_records_remove(vec![doc.doc_path.to_string_lossy().to_string()]).await;
+            let _ = vecdb_cache_arc.lock().await.files_vectorized_remove(vec![cpath.clone()]).await;
             continue;
         }
+        let content_hash = crate::ast::chunk_utils::official_text_hashing_function(&doc.doc_text.as_ref().map(|t| t.to_string()).unwrap_or_default());
+        let mtime_ms = file_mtime_ms(&cpath).unwrap_or(0);
+
+        // touched but not changed
+        let marker_mb = vecdb_cache_arc.lock().await.files_vectorized_get(&vec![cpath.clone()]).await.unwrap_or_default().remove(&cpath);
+        if let Some(marker) = marker_mb.filter(|m| m.content_hash == content_hash && m.splitter_fingerprint == splitter_fingerprint) {
+            if let Some(records) = records_from_marker(&marker, vecdb_cache_arc.clone()).await {
+                {
+                    let mut vstatus_locked = vstatus.lock().await;
+                    vstatus_locked.files_skipped_by_cache += 1;
+                    vstatus_locked.cache_hits += records.len();
+                }
+                ready_to_vecdb.extend(records);
+                markers_pending.push(FileVectorizedMarker { mtime_ms, ..marker });
+                continue;
+            }
+        }
 
-        if let Err(err) = doc.does_text_look_good() {
+        // notebooks carry long lines of outputs (base64 images), only their cells get indexed
+        if let Err(err) = if is_notebook(&doc.doc_path) { Ok(()) } else { doc.does_text_look_good() } {
             info!("embeddings {} doesn't look good: {}", last_30_chars, err);
             continue;
         }
 
         let file_splitter = AstBasedFileSplitter::new(constants.splitter_window_size);
-        let mut splits = file_splitter.vectorization_split(&doc, None, gcx.clone(), constants.vectorizer_n_ctx).await.unwrap_or_else(|err| {
+        let mut splits = file_splitter.vectorization_split(&doc, constants.tokenizer.clone(), gcx.clone(), constants.vectorizer_n_ctx).await.unwrap_or_else(|err| {
             info!("{}", err);
             vec![]
         });
@@ -382,11 +547,19 @@ async fn vectorize_thread(
             }
         }
 
+        markers_pending.push(FileVectorizedMarker {
+            file_path: cpath.clone(),
+            content_hash,
+            mtime_ms,
+            splitter_fingerprint: splitter_fingerprint.clone(),
+            chunks: splits.iter().map(|s| (s.start_line, s.end_line, s.window_text_hash.clone())).collect(),
+        });
         from_splits_to_vecdb_records_applying_cache(
             &mut splits,
             &mut ready_to_vecdb,
             &mut run_actual_model_on_these,
             vecdb_cache_arc.clone(),
+            vstatus.clone(),
             1024,
         ).await;
     }
@@ -430,17 +603,25 @@ impl FileVectorizerService {
                 queue_additions: true,
                 vecdb_max_files_hit: false,
                 vecdb_errors: IndexMap::new(),
+                files_skipped_by_cache: 0,
+                cache_hits: 0,
+                cache_misses: 0,
+                embeddings_computed: 0,
+                other_models: IndexMap::new(),
             }
         ));
+        let splitter_fingerprint = splitter_fingerprint(&constants);
         FileVectorizerService {
             vecdb_handler: vecdb_handler.clone(),
             vecdb_cache: vecdb_cache_arc.clone(),
             vstatus: vstatus.clone(),
             vstatus_notify: Arc::new(ANotify::new()),
+            vecdb_paused: Arc::new(AtomicBool::new(false)),
             constants,
             api_key,
             memdb,
             vecdb_todo: Default::default(),
+            splitter_fingerprint,
         }
     }
 }
@@ -480,6 +661,44 @@ pub async fn vectorizer_enqueue_dirty_memory(
     vstatus_notify.notify_waiters();
 }
 
+pub async fn vectorizer_set_paused(
+    vservice: Arc<AMutex<FileVectorizerService>>,
+    paused: bool,
+) {
+    let (vecdb_paused, vstatus, vstatus_notify) = {
+        let service = vservice.lock().await;
+        (
+            service.vecdb_paused.clone(),
+            service.vstatus.clone(),
+            service.vstatus_notify.clone(),
+        )
+    };
+    vecdb_paused.store(paused, Ordering::SeqCst);
+    {
+        let mut vstatus_locked = vstatus.lock().await;
+        if paused {
+            vstatus_locked.state = "paused".to_string();
+        } else if vstatus_locked.state == "paused" {
+            // the thread will figure out the real state on the next iteration
+            vstatus_locked.state = "cooldown".to_string();
+            vstatus_locked.queue_additions = true;
+        }
+    }
+    info!("vectorizer {}", if paused { "pause requested" } else { "resumed" });
+    vstatus_notify.notify_waiters();
+}
+
+// A full reindex starts counting cache effectiveness from scratch
+pub async fn vectorizer_reset_cache_counters(
+    vservice: Arc<AMutex<FileVectorizerService>>,
+) {
+    let vstatus = vservice.lock().await.vstatus.clone();
+    let mut vstatus_locked = vstatus.lock().await;
+    vstatus_locked.cache_hits = 0;
+    vstatus_locked.cache_misses = 0;
+    vstatus_locked.embeddings_computed = 0;
+}
+
 fn _filter_docs_to_enqueue(docs: &Vec<String>) -> Vec<String> {
     let mut rejected_reasons = HashMap::new();
     let mut filtered_docs = vec![];
@@ -511,13 +730,15 @@ pub async fn vectorizer_enqueue_files(
 ) {
     info!("adding {} files", documents.len());
     let documents = _filter_docs_to_enqueue(documents);
-    let (vecdb_todo, vstatus, vstatus_notify, vecdb_max_files) = {
+    let (vecdb_todo, vstatus, vstatus_notify, vecdb_max_files, vecdb_cache, splitter_fingerprint) = {
         let service = vservice.lock().await;
         (
             service.vecdb_todo.clone(),
             service.vstatus.clone(),
             service.vstatus_notify.clone(),
-            service.constants.vecdb_max_files
+            service.constants.vecdb_max_files,
+            service.vecdb_cache.clone(),
+            service.splitter_fingerprint.clone(),
         )
     };
     let mut documents_my_copy = documents.clone();
@@ -526,12 +747,21 @@ pub async fn vectorizer_enqueue_files(
         documents_my_copy.truncate(vecdb_max_files);
         vstatus.lock().await.vecdb_max_files_hit = true;
     }
+    // vectorized before with the same mtime and splitter (previous run probably), no need to wait for cooldown or read the file
+    let mut markers = vecdb_cache.lock().await.files_vectorized_get(&documents).await.unwrap_or_else(|err| {
+        warn!("cannot read vectorized files: {}", err);
+        HashMap::new()
+    });
     {
         {
             // two locks in sequence, vecdb_todo.lock -> vstatus.lock
             let mut vecdb_todo_locked = vecdb_todo.lock().await;
             for doc in documents.iter() {
-                if process_immediately {
+                let unchanged_marker = markers.remove(doc)
+                    .filter(|m| Some(m.mtime_ms) == file_mtime_ms(doc) && m.splitter_fingerprint == splitter_fingerprint);
+                if let Some(marker) = unchanged_marker {
+                    vecdb_todo_locked.push_back(MessageToVecdbThread::UnchangedDocument(marker));
+                } else if process_immediately {
                     vecdb_todo_locked.push_back(MessageToVecdbThread::ImmediatelyRegularDocument(doc.clone()));
                 } else {
                     vecdb_todo_locked.push_back(MessageToVecdbThread::RegularDocument(doc.clone()));
@@ -544,3 +774,181 @@ pub async fn vectorizer_enqueue_files(
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vecdb::vdb_structs::DistanceMetric;
+
+    fn frog_constants(splitter_window_size: usize) -> VecdbConstants {
+        VecdbConstants {
+            embedding_model: "frog-embeddings".to_string(),
+            embedding_size: 4,
+            embedding_batch: 64,
+            tokenizer: None,
+            vectorizer_n_ctx: 512,
+            endpoint_embeddings_template: "".to_string(),
+            endpoint_embeddings_style: "".to_string(),
+            splitter_window_size,
+            vecdb_max_files: 10,
+            distance_reject: 0.5,
+            distance_metric: DistanceMetric::Cosine,
+            memories_flush_interval_ms: 300,
+        }
+    }
+
+    async fn frog_vectorizer_service(dir: &std::path::Path, constants: VecdbConstants) -> Arc<AMutex<FileVectorizerService>> {
+        let handler = Arc::new(AMutex::new(VecDBHandler::init(constants.embedding_size, constants.distance_metric).await.unwrap()));
+        let cache = Arc::new(AMutex::new(VecDBCache::init(&dir.to_path_buf(), &constants.embedding_model, constants.embedding_size).await.unwrap()));
+        let memdb = Arc::new(AMutex::new(MemoriesDatabase::init(&dir.to_path_buf(), &constants, false).await.unwrap()));
+        Arc::new(AMutex::new(FileVectorizerService::new(handler, cache, constants, "".to_string(), memdb).await))
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_file_skipped_only_with_the_same_splitter() {
+        let dir = tempfile::Builder::new().prefix("frog_vecdb").tempdir().unwrap();
+        let frog_py = dir.path().join("frog.py");
+        std::fs::write(&frog_py, "def jump():\n    return 42\n").unwrap();
+        let frog_py = frog_py.to_string_lossy().to_string();
+
+        let vservice = frog_vectorizer_service(dir.path(), frog_constants(256)).await;
+        let (vecdb_cache, fingerprint) = {
+            let service = vservice.lock().await;
+            (service.vecdb_cache.clone(), service.splitter_fingerprint.clone())
+        };
+        vecdb_cache.lock().await.files_vectorized_set(vec![FileVectorizedMarker {
+            file_path: frog_py.clone(),
+            content_hash: "abc".to_string(),
+            mtime_ms: file_mtime_ms(&frog_py).unwrap(),
+            splitter_fingerprint: fingerprint,
+            chunks: vec![(0, 1, "h1".to_string())],
+        }]).await.unwrap();
+
+        vectorizer_enqueue_files(vservice.clone(), &vec![frog_py.clone()], false).await;
+        let todo = vservice.lock().await.vecdb_todo.clone();
+        assert!(matches!(todo.lock().await.pop_front(), Some(MessageToVecdbThread::UnchangedDocument(m)) if m.file_path == frog_py));
+
+        // the same cache, files split with another window have other chunks
+        let vservice_resized = frog_vectorizer_service(dir.path(), frog_constants(128)).await;
+        vectorizer_enqueue_files(vservice_resized.clone(), &vec![frog_py.clone()], false).await;
+        let todo = vservice_resized.lock().await.vecdb_todo.clone();
+        assert!(matches!(todo.lock().await.pop_front(), Some(MessageToVecdbThread::RegularDocument(p)) if p == frog_py));
+    }
+
+    #[test]
+    fn test_memories_added_within_interval_flush_once() {
+        let mut memories_flush = MemoriesFlushDebounce::new(Duration::from_millis(300));
+        let t0 = Instant::now();
+        let mut flushes = 0;
+        for ms in [0, 50, 120, 250] {
+            memories_flush.mark_dirty(t0 + Duration::from_millis(ms));
+            if memories_flush.take_ready(t0 + Duration::from_millis(ms)) {
+                flushes += 1;
+            }
+        }
+        assert!(memories_flush.is_pending());
+        assert_eq!(memories_flush.time_left(t0 + Duration::from_millis(250)), Some(Duration::from_millis(50)));
+
+        for ms in [300, 310, 1000] {
+            if memories_flush.take_ready(t0 + Duration::from_millis(ms)) {
+                flushes += 1;
+            }
+        }
+        assert_eq!(flushes, 1);
+        assert!(!memories_flush.is_pending());
+
+        memories_flush.mark_dirty(t0 + Duration::from_millis(1500));
+        assert!(!memories_flush.take_ready(t0 + Duration::from_millis(1600)));
+        assert!(memories_flush.take_ready(t0 + Duration::from_millis(1800)));
+    }
+
+    #[tokio::test]
+    async fn test_memories_added_in_a_burst_are_embedded_by_one_flush() {
+        let dir = tempfile::Builder::new().prefix("frog_memories").tempdir().unwrap();
+        let gcx_dir = tempfile::Builder::new().prefix("frog_memories_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(gcx_dir.path(), vec![]).await;
+        let goals = ["frog jumps over the log", "frog croaks at night", "frog swims in the pond"];
+        let embeddings = mockito::mock("POST", "/v1/embeddings")
+            .match_body(mockito::Matcher::AllOf(goals.iter().map(|g| mockito::Matcher::Regex(g.to_string())).collect()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"data": (0..goals.len()).map(|i| serde_json::json!({"embedding": [1.0, 0.0, 0.0, i as f32], "index": i})).collect::<Vec<_>>()}).to_string())
+            .expect(1)
+            .create();
+
+        let mut constants = frog_constants(256);
+        constants.endpoint_embeddings_style = "openai".to_string();
+        constants.endpoint_embeddings_template = format!("{}/v1/embeddings", mockito::server_url());
+        constants.memories_flush_interval_ms = 500;
+        let vservice = frog_vectorizer_service(dir.path(), constants).await;
+        let memdb = {
+            let mut service = vservice.lock().await;
+            service.api_key = "frog-key".to_string();
+            service.memdb.clone()
+        };
+        let client = Arc::new(AMutex::new(reqwest::Client::builder().no_proxy().build().unwrap()));
+        let _handles = vecdb_start_background_tasks(client, vservice.clone(), gcx.clone()).await;
+
+        for goal in goals.iter() {
+            {
+                let mut memdb_locked = memdb.lock().await;
+                let memid = memdb_locked.permdb_add("proj-fact", goal, "pond", "ribbit", "local-committed").unwrap();
+                memdb_locked.dirty_memids.push(memid);
+            }
+            vectorizer_enqueue_dirty_memory(vservice.clone()).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let t0 = Instant::now();
+        crate::vecdb::vdb_highlev::memories_block_until_vectorized_from_vectorizer(vservice.clone(), 10_000).await.unwrap();
+        assert!(t0.elapsed() < Duration::from_millis(10_000), "memories_block_until_vectorized timed out instead of seeing the flush");
+        embeddings.assert();
+        assert!(memdb.lock().await.dirty_memids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nothing_embedded_while_paused() {
+        let dir = tempfile::Builder::new().prefix("frog_paused").tempdir().unwrap();
+        let gcx_dir = tempfile::Builder::new().prefix("frog_paused_gcx").tempdir().unwrap();
+        let gcx = crate::global_context::create_global_context_for_tests(gcx_dir.path(), vec![]).await;
+        let goal = "tadpole naps on a lily pad";
+        let embeddings = mockito::mock("POST", "/v1/embeddings")
+            .match_body(mockito::Matcher::Regex(goal.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"data": [{"embedding": [0.0, 1.0, 0.0, 0.0], "index": 0}]}).to_string())
+            .expect(1)
+            .create();
+
+        let mut constants = frog_constants(256);
+        constants.endpoint_embeddings_style = "openai".to_string();
+        constants.endpoint_embeddings_template = format!("{}/v1/embeddings", mockito::server_url());
+        let vservice = frog_vectorizer_service(dir.path(), constants).await;
+        let memdb = {
+            let mut service = vservice.lock().await;
+            service.api_key = "frog-key".to_string();
+            service.memdb.clone()
+        };
+        vectorizer_set_paused(vservice.clone(), true).await;
+        let client = Arc::new(AMutex::new(reqwest::Client::builder().no_proxy().build().unwrap()));
+        let _handles = vecdb_start_background_tasks(client, vservice.clone(), gcx.clone()).await;
+
+        {
+            let mut memdb_locked = memdb.lock().await;
+            let memid = memdb_locked.permdb_add("proj-fact", goal, "pond", "zzz", "local-committed").unwrap();
+            memdb_locked.dirty_memids.push(memid);
+        }
+        vectorizer_enqueue_dirty_memory(vservice.clone()).await;
+        // several flush intervals, a running vectorizer would have embedded the memory by now
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+        assert!(!embeddings.matched());
+        assert_eq!(memdb.lock().await.dirty_memids.len(), 1);
+        assert_eq!(vservice.lock().await.vstatus.lock().await.state, "paused");
+
+        vectorizer_set_paused(vservice.clone(), false).await;
+        crate::vecdb::vdb_highlev::memories_block_until_vectorized_from_vectorizer(vservice.clone(), 10_000).await.unwrap();
+        embeddings.assert();
+        assert!(memdb.lock().await.dirty_memids.is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+import os
+import time
+import requests
+import termcolor
+
+
+base_url = "http://127.0.0.1:8001/v1"
+frog_dir = os.path.join(os.path.dirname(__file__), "emergency_frog_situation")
+new_file = os.path.join(frog_dir, "frog_pause_test.py")
+
+
+def vdb_status():
+    response = requests.get(f"{base_url}/vdb-status")
+    assert response.status_code == 200, response.text
+    return response.json()
+
+
+def wait_for_state(accepted_states, timeout: float):
+    t0 = time.time()
+    while time.time() - t0 < timeout:
+        status = vdb_status()
+        if status["state"] in accepted_states:
+            return status
+        time.sleep(0.5)
+    raise AssertionError(f"vecdb state is {status['state']}, expected one of {accepted_states}")
+
+
+def test_vecdb_pause_resume():
+    wait_for_state(["done"], 120)
+
+    response = requests.post(f"{base_url}/vdb-pause")
+    assert response.status_code == 200, response.text
+    status = wait_for_state(["paused"], 5)
+    vectors_before = status["vectors_made_since_start"]
+
+    text = "def frog_pause_test():\n    print(\"the frog waits on a lily pad until the pond is quiet\")\n"
+    with open(new_file, "w") as f:
+        f.write(text)
+    requests.post(f"{base_url}/lsp-did-changed", json={"uri": f"file://{os.path.abspath(new_file)}", "text": text})
+
+    time.sleep(15)  # longer than vectorizer cooldown
+    status = vdb_status()
+    assert status["state"] == "paused", status
+    assert status["vectors_made_since_start"] == vectors_before, status
+    print(termcolor.colored("nothing vectorized while paused", "green"))
+
+    response = requests.post(f"{base_url}/vdb-resume")
+    assert response.status_code == 200, response.text
+    t0 = time.time()
+    while time.time() - t0 < 60:
+        status = vdb_status()
+        if status["vectors_made_since_start"] > vectors_before:
+            break
+        time.sleep(0.5)
+    assert status["vectors_made_since_start"] > vectors_before, status
+    print(termcolor.colored("vectorized after resume", "green"))
+
+
+if __name__ == "__main__":
+    try:
+        test_vecdb_pause_resume()
+    finally:
+        if os.path.exists(new_file):
+            os.remove(new_file)
+    print(termcolor.colored("PASS", "green"))